@@ -1,4 +1,4 @@
-use clap::{ArgAction, Args, Parser, Subcommand};
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
 
 #[derive(Debug, Args)]
 pub struct RequestReview {
@@ -24,7 +24,9 @@ pub struct BranchCmdOpts {
 
 #[derive(Debug, Args)]
 pub struct IntegrateCmdOpts {
-    pub patch_index_or_range: String,
+    /// Patch index, patch index range, or a space separated batch of them,
+    /// e.g. "0 2-4", integrated together in a single atomic push
+    pub patch_index_or_range_batch: String,
     /// Use the provided branch name instead of generating one
     #[arg(short = 'n')]
     pub branch_name: Option<String>,
@@ -35,6 +37,35 @@ pub struct IntegrateCmdOpts {
     /// Skip safety checks and publish
     #[arg(short = 'f', long = "force")]
     pub force: bool,
+    /// When the push fails because the upstream moved, fetch, rebuild &
+    /// verify again, then retry with backoff, up to
+    /// integrate.retry_max_attempts times
+    #[arg(long = "retry")]
+    pub retry: bool,
+    /// Sign the push certificate, as with git push --signed, also enabled
+    /// by integrate.sign_push
+    #[arg(long = "sign-push")]
+    pub sign_push: bool,
+    /// Create & push a signed tag, named after integrate.tag_name_template,
+    /// listing the ps-ids of each integrated patch series, also enabled by
+    /// integrate.tag
+    #[arg(long = "tag")]
+    pub tag: bool,
+    /// Run the safety checks & report what passed or failed without
+    /// integrating. Exits 0 when all pass, 2 when a patch series has no
+    /// single associated pushed branch, 3 when it doesn't match its remote
+    /// branch, 4 when the integrate_verify hook fails, 5 when isolation
+    /// fails, 6 when it doesn't integrate into the upstream, 7 when the
+    /// remote branch has commits not signed by someone trusted & 1 on error
+    #[arg(long = "check", conflicts_with_all = ["force", "retry", "keep_branch", "sign_push", "tag"])]
+    pub check: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ListCmdOpts {
+    /// Re-execute the list_additional_information hook rather than using its cached output
+    #[arg(long = "refresh")]
+    pub refresh: bool,
 }
 
 #[derive(Debug, Args)]
@@ -58,11 +89,37 @@ pub struct AppendCmdOpts {
     pub branch_name: String,
 }
 
+#[derive(Debug, Args)]
+pub struct TransferCmdOpts {
+    pub patch_index_or_range: String,
+    /// The local branch of the patch stack to move the patch(es) to
+    pub target_stack: String,
+}
+
+#[derive(Debug, Args)]
+pub struct GcCmdOpts {
+    /// Delete the branches without asking first
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ContinueCmdOpts {
+    /// Abort the interrupted operation instead, putting things back the way they were before it
+    #[arg(long)]
+    pub abort: bool,
+}
+
 #[derive(Debug, Args)]
 pub struct IsolateCmdOpts {
     pub patch_index_or_range: Option<String>,
 }
 
+#[derive(Debug, Args)]
+pub struct VerifySignaturesCmdOpts {
+    pub patch_index_or_range: Option<String>,
+}
+
 #[derive(Debug, Args)]
 pub struct CheckoutCmdOpts {
     pub patch_index: usize,
@@ -85,6 +142,156 @@ pub struct PushCmdOpts {
     pub branch_name: String,
 }
 
+#[derive(Debug, Subcommand)]
+pub enum HooksCommand {
+    /// (ls) - List the hooks Git Patch Stack knows about & where each resolves from
+    ///
+    /// Each hook is listed along with the command it belongs to, the phase of that command it
+    /// runs in & the arguments it is given. Pre hooks can veto the operation by exiting non-zero.
+    ///
+    /// Hooks are searched for in the communal repository hooks (.git-ps/hooks), the repository
+    /// hooks (.git/git-ps/hooks), & the user hooks (~/.config/git-ps/hooks), in that order. Each
+    /// location can provide a hook as a single file, e.g. pre_push, and/or as multiple files in a
+    /// directory, e.g. pre_push.d/, which run in lexical order.
+    ///
+    /// By default only the hooks from the first location that has any run. A location can set
+    /// chaining = "extend" or chaining = "replace" in the [hooks] section of its own config file
+    /// to have its hooks run in addition to, or instead of, those of the other locations. Chained
+    /// hooks run in the location order given by the order key of the [hooks] section.
+    #[command(name = "list", alias = "ls")]
+    List,
+}
+
+#[derive(Debug, Args)]
+pub struct HooksCmdOpts {
+    #[command(subcommand)]
+    pub command: HooksCommand,
+}
+
+#[derive(Debug, Args)]
+pub struct StackNewCmdOpts {
+    /// name of the branch to create for the stack
+    pub name: String,
+    /// remote tracking branch to base the stack on, e.g. origin/main, defaults to the upstream of
+    /// the current stack
+    #[arg(long)]
+    pub onto: Option<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct StackSwitchCmdOpts {
+    pub name: String,
+}
+
+#[derive(Debug, Args)]
+pub struct StackDeleteCmdOpts {
+    pub name: String,
+    /// delete the stack even though it has patches that haven't been integrated
+    #[arg(short = 'f', long = "force")]
+    pub force: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum StackCommand {
+    /// Create a new patch stack & switch to it
+    ///
+    /// A patch stack is a local branch based on a remote tracking branch, its upstream, the
+    /// patches being the commits on the branch that aren't on the upstream.
+    #[command(name = "new")]
+    New(StackNewCmdOpts),
+    /// (ls) - List the patch stacks along with their ahead/behind counts & review states
+    ///
+    /// The patches of each stack are counted as new when they aren't on any branch yet,
+    /// branched when on a local branch that hasn't been pushed, in review when pushed as they
+    /// are & changed when they have changed since being pushed.
+    #[command(name = "list", alias = "ls")]
+    List,
+    /// (sw) - Switch to the given patch stack
+    #[command(name = "switch", alias = "sw")]
+    Switch(StackSwitchCmdOpts),
+    /// Delete the given patch stack along with the request review branches of its patches
+    #[command(name = "delete")]
+    Delete(StackDeleteCmdOpts),
+}
+
+#[derive(Debug, Args)]
+pub struct StackCmdOpts {
+    #[command(subcommand)]
+    pub command: StackCommand,
+}
+
+/// The config files, from lowest to highest precedence
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ConfigLevel {
+    /// ~/.config/git-ps/config.toml
+    User,
+    /// .git/git-ps/config.toml
+    Personal,
+    /// .git-ps/config.toml, shared with everyone working on the repository
+    Communal,
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigListCmdOpts {
+    /// show the level & file, environment variable or option each value comes from
+    #[arg(long)]
+    pub show_origin: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigGetCmdOpts {
+    /// dotted key, e.g. list.reverse_order
+    pub key: String,
+    /// get the value from this level's file rather than the value in effect
+    #[arg(long, value_enum)]
+    pub level: Option<ConfigLevel>,
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigSetCmdOpts {
+    /// dotted key, e.g. list.reverse_order
+    pub key: String,
+    /// TOML value, e.g. true, 20 or { color = "Red" }, anything else is taken as a string
+    pub value: String,
+    /// the level whose file is changed
+    #[arg(long, value_enum, default_value = "personal")]
+    pub level: ConfigLevel,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// (ls) - List the configured values in effect
+    ///
+    /// Values are read from the user, personal & communal config files, in order of increasing
+    /// precedence, so a value in the communal config overrides the same value in the personal
+    /// config & so on. GPS_<SECTION>_<KEY> environment variables and -c section.key=value
+    /// options override all of the config files, the options taking precedence. Values that
+    /// aren't configured anywhere use their defaults & aren't listed.
+    ///
+    /// Sections within [profile."<upstream pattern>"], e.g. [profile."origin/release-*".integrate],
+    /// only apply to patch stacks whose upstream branch matches the pattern.
+    #[command(name = "list", alias = "ls")]
+    List(ConfigListCmdOpts),
+    /// Get a configured value, exits non-zero when it isn't configured
+    #[command(name = "get")]
+    Get(ConfigGetCmdOpts),
+    /// Set a value in one of the config files, keeping the rest of the file intact
+    #[command(name = "set")]
+    Set(ConfigSetCmdOpts),
+    /// Validate the config files & print the effective config
+    ///
+    /// Unknown keys, e.g. typos, and invalid values, e.g. bad colors, are reported for each
+    /// config file & the command exits non-zero.
+    #[command(name = "validate")]
+    Validate,
+}
+
+#[derive(Debug, Args)]
+pub struct ConfigCmdOpts {
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
 #[derive(Debug, Subcommand)]
 pub enum Command {
     /// (b) - Create a branch for a patch or patch series
@@ -93,6 +300,11 @@ pub enum Command {
 
     /// (int) - Integrate the specified patch into the patch stacks upstream
     /// remote
+    ///
+    /// How the patches land is configured with integrate.strategy: ff pushes
+    /// them as is, merge pushes a merge commit of them & squash a single
+    /// commit squashing them, with messages following
+    /// integrate.message_template.
     #[command(name = "integrate", alias = "int")]
     Integrate(IntegrateCmdOpts),
 
@@ -183,9 +395,14 @@ well as the remote tracking branch of `bar`. The `*` characters here indicate
 that both the copy of the patch in both the local `bar` branch and the remote
 tracking branch of `bar` have different diffs than the patch in the patch
 stack.
+
+If list.add_extra_patch_info is enabled the output of the
+list_additional_information hook is appended to the state information. That
+output can be cached, per patch & branch state, for list.extra_patch_info_cache_ttl
+seconds (default 0, caching disabled). Use --refresh to ignore the cache.
 "
     )]
-    List,
+    List(ListCmdOpts),
 
     /// Interactively rebase your stack of patches
     ///
@@ -204,6 +421,11 @@ stack.
     Rebase(RebaseCmdOpts),
 
     /// Pull changes down from upstream and rebase stack on top
+    ///
+    /// The stack is rebased in memory, dropping the patches that landed upstream, recognized by
+    /// their patch id or their diff, and reporting which patches landed, were rebased, or
+    /// conflict. Only when a patch conflicts is the rest of the stack rebased in the working copy,
+    /// from that patch on, so the conflict can be resolved.
     #[command(name = "pull")]
     Pull,
 
@@ -242,6 +464,44 @@ either a Git alias or a shell alias for that command.
     #[command(name = "append", alias = "a")]
     Append(AppendCmdOpts),
 
+    /// Move the specified patch(es) from the current patch stack to the end of another
+    ///
+    /// The patches keep their patch ids so any request review branches stay associated with them.
+    /// When the patches conflict with the target stack, or the remaining patches conflict once
+    /// they are removed, neither stack is changed.
+    #[command(name = "transfer")]
+    Transfer(TransferCmdOpts),
+
+    /// Show which patches depend on which earlier patches
+    ///
+    /// A patch depends on an earlier patch when it can't be applied to the patch stack base
+    /// without it, e.g. because it changes lines the earlier patch introduced. Only the direct
+    /// dependencies are listed. When requesting review of, branching or isolating patches that
+    /// depend on patches outside of the range, the command fails naming those patches, unless
+    /// branch.include_dependencies is set in which case they are included automatically.
+    #[command(name = "deps")]
+    Deps,
+
+    /// Continue the operation that stopped at a conflict
+    ///
+    /// With conflicts.resolve_in_worktree set, a `branch`, `request-review` or `append` that hits
+    /// a conflict cherry picking a patch checks the conflicting cherry pick out with conflict
+    /// markers in a temporary worktree within the gitdir, & a `pull` leaves the conflicting
+    /// rebase in place, rather than failing. Once the conflict is resolved & staged with `git
+    /// add`, this commits the resolution & resumes the interrupted operation.
+    #[command(name = "continue")]
+    Continue(ContinueCmdOpts),
+
+    /// Delete the request review branches of patches that landed upstream
+    ///
+    /// A request review branch landed once every one of its patches is in the upstream of the
+    /// patch stack, recognized by its patch id or its diff, e.g. because someone else integrated
+    /// it or it was merged through the forge's UI. The branches are listed & deleted locally and
+    /// remotely after confirmation. `pull` can do the same after rebasing by setting
+    /// pull.cleanup_landed_branches to "prompt" or "auto".
+    #[command(name = "gc")]
+    Gc(GcCmdOpts),
+
     /// Push the local patches of the named patch series up to it's remote tracking branch
     #[command(name = "push")]
     Push(PushCmdOpts),
@@ -261,6 +521,19 @@ either a Git alias or a shell alias for that command.
     #[command(name = "isolate", alias = "iso")]
     Isolate(IsolateCmdOpts),
 
+    /// Verify the signatures of patches & of the commits on their remote review branches
+    ///
+    /// Checks the signatures of the patches in the given patch index or range, all of the patches
+    /// in the stack by default, as well as of the commits collaborators pushed to the remote
+    /// request review branches of those patches. GPG, SSH & x509 signatures are verified according
+    /// to your git configuration, e.g. gpg.ssh.allowedSignersFile for SSH signatures. Exits 1 when
+    /// any of the commits isn't signed by someone trusted.
+    ///
+    /// Set integrate.verify_signatures to true to have integrate refuse to push a remote branch
+    /// with commits that aren't signed by someone trusted.
+    #[command(name = "verify-signatures")]
+    VerifySignatures(VerifySignaturesCmdOpts),
+
     /// (co) - Checkout the patch identified by the patch-index, leaving you
     /// in a headless state.
     #[command(name = "checkout", alias = "co")]
@@ -270,6 +543,18 @@ either a Git alias or a shell alias for that command.
     #[command(name = "fetch", alias = "f")]
     Fetch,
 
+    /// Manage multiple named patch stacks
+    #[command(name = "stack")]
+    Stack(StackCmdOpts),
+
+    /// Inspect the hooks available to Git Patch Stack
+    #[command(name = "hooks")]
+    Hooks(HooksCmdOpts),
+
+    /// Inspect, validate & edit the Git Patch Stack configuration
+    #[command(name = "config")]
+    Config(ConfigCmdOpts),
+
     /// (bs) backup your current patch stack to the given branch name
     #[cfg(feature = "backup_cmd")]
     #[command(name = "backup-stack", alias = "bs")]
@@ -301,6 +586,20 @@ pub struct Cli {
     #[arg(long = "no-color", global = true, action(ArgAction::SetFalse))]
     pub color: bool,
 
+    /// report the progress of long running operations, e.g. each replayed patch, hook & push
+    #[arg(long = "progress", global = true)]
+    pub progress: bool,
+
+    /// override a config value, taking precedence over the config files & GPS_<SECTION>_<KEY>
+    /// environment variables, e.g. -c integrate.prompt_for_reassurance=false
+    #[arg(
+        short = 'c',
+        global = true,
+        value_name = "SECTION.KEY=VALUE",
+        action(ArgAction::Append)
+    )]
+    pub config_overrides: Vec<String>,
+
     #[command(subcommand)]
     pub command: Command,
 }
@@ -1,14 +1,16 @@
 #[cfg(feature = "backup_cmd")]
+use super::utils::print_error_chain;
+#[cfg(feature = "backup_cmd")]
 use gps as ps;
 #[cfg(feature = "backup_cmd")]
 use std::string::String;
 
 #[cfg(feature = "backup_cmd")]
-pub fn backup_stack(branch_name: String) {
+pub fn backup_stack(branch_name: String, color: bool) {
     match ps::backup_stack(branch_name) {
         Ok(_) => {}
         Err(e) => {
-            eprintln!("Error: {:?}", e);
+            print_error_chain(color, e.into());
             std::process::exit(1);
         }
     }
@@ -1,11 +1,12 @@
+use super::utils::print_error_chain;
 use gps as ps;
 
-pub fn checkout(patch_index: usize) {
+pub fn checkout(patch_index: usize, color: bool) {
     let res = ps::checkout(patch_index);
     match res {
         Ok(_) => {}
         Err(e) => {
-            eprintln!("Error: {:?}", e);
+            print_error_chain(color, e.into());
             std::process::exit(1);
         }
     }
@@ -0,0 +1,95 @@
+use super::utils::{paint, print_err, print_error_chain, print_warn};
+use ansi_term::Colour::Green;
+use gps as ps;
+
+/// Set the command line config overrides & warn about the ignored environment overrides
+pub fn set_overrides(raw: &[String], color: bool) {
+    if let Err(e) = ps::config::set_overrides(raw) {
+        print_error_chain(color, e.into());
+        std::process::exit(1);
+    }
+    for config_override in ps::config::ignored_environment_overrides() {
+        print_warn(
+            color,
+            &format!(
+                "Ignoring {}, {} isn't a known config key",
+                config_override.source, config_override.key
+            ),
+        );
+    }
+}
+
+pub fn list(show_origin: bool, color: bool) {
+    match ps::config::list() {
+        Ok(entries) => {
+            for entry in entries {
+                if show_origin {
+                    println!("{}\t{}={}", entry.origin, entry.key, entry.value);
+                } else {
+                    println!("{}={}", entry.key, entry.value);
+                }
+            }
+        }
+        Err(e) => {
+            print_error_chain(color, e.into());
+            std::process::exit(1);
+        }
+    }
+}
+
+pub fn get(key: &str, level: Option<ps::config::ConfigLevel>, color: bool) {
+    match ps::config::get(key, level) {
+        // strings are printed raw so the value can be used as is in scripts
+        Ok(Some(entry)) => match entry.value.as_str() {
+            Some(value) => println!("{}", value),
+            None => println!("{}", entry.value),
+        },
+        Ok(None) => std::process::exit(1),
+        Err(e) => {
+            print_error_chain(color, e.into());
+            std::process::exit(1);
+        }
+    }
+}
+
+pub fn set(key: &str, value: &str, level: ps::config::ConfigLevel, color: bool) {
+    match ps::config::set(key, value, level) {
+        Ok(path) => println!("set {} in {}", key, path.display()),
+        Err(e) => {
+            print_error_chain(color, e.into());
+            std::process::exit(1);
+        }
+    }
+}
+
+pub fn validate(color: bool) {
+    match ps::config::validate() {
+        Ok(validation) => {
+            for level in validation.levels.iter() {
+                let status = if !level.exists {
+                    "not present".to_string()
+                } else if level.problems.is_empty() {
+                    paint(color, Green, "ok")
+                } else {
+                    "invalid".to_string()
+                };
+                println!("{} {} - {}", level.level, level.path.display(), status);
+                for line in level.problems.iter().flat_map(|p| p.lines()) {
+                    print_err(color, &format!("    {}", line));
+                }
+            }
+            for problem in validation.problems.iter() {
+                print_err(color, problem);
+            }
+
+            match validation.config {
+                Some(config) => println!("\n{:#?}", config),
+                None => std::process::exit(1),
+            }
+        }
+        Err(e) => {
+            print_error_chain(color, e.into());
+            std::process::exit(1);
+        }
+    }
+}
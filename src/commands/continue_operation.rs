@@ -0,0 +1,17 @@
+use super::pull;
+use super::utils::print_error_chain;
+use gps as ps;
+
+pub fn continue_operation(abort: bool, color: bool) {
+    match ps::continue_operation(abort) {
+        Ok(Some(pull_follow_up)) => {
+            println!();
+            pull::follow_up(&pull_follow_up, color)
+        }
+        Ok(None) => {}
+        Err(e) => {
+            print_error_chain(color, e.into());
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use super::utils::print_error_chain;
+use ansi_term::Colour::Yellow;
+use gps as ps;
+
+pub fn deps(color: bool) {
+    match ps::deps() {
+        Ok(patches) => {
+            for patch in patches.iter().rev() {
+                let sha = patch.oid.to_string();
+                let sha = &sha[..7];
+                let sha = if color {
+                    Yellow.paint(sha).to_string()
+                } else {
+                    sha.to_string()
+                };
+                if patch.depends_on.is_empty() {
+                    println!("{:<4} {} {}", patch.index, sha, patch.summary);
+                } else {
+                    let depends_on: Vec<String> =
+                        patch.depends_on.iter().map(|i| i.to_string()).collect();
+                    println!(
+                        "{:<4} {} {} <- {}",
+                        patch.index,
+                        sha,
+                        patch.summary,
+                        depends_on.join(", ")
+                    );
+                }
+            }
+        }
+        Err(e) => {
+            print_error_chain(color, e.into());
+            std::process::exit(1);
+        }
+    }
+}
@@ -1,11 +1,45 @@
+use super::utils::print_error_chain;
+use ansi_term::Colour::{Cyan, Yellow};
 use gps as ps;
 
 pub fn fetch(color: bool) {
-    match ps::fetch(color) {
-        Ok(_) => {}
+    match ps::fetch() {
+        Ok(Some(upstream_patches)) => print_upstream_patches(&upstream_patches, color),
+        Ok(None) => {}
         Err(e) => {
-            eprintln!("Error: {:?}", e);
+            print_error_chain(color, e.into());
             std::process::exit(1);
         }
     };
 }
+
+pub fn print_upstream_patches(upstream_patches: &[ps::UpstreamPatch], color: bool) {
+    println!();
+    if upstream_patches.is_empty() {
+        println!("None. You are already up to date!");
+    }
+    for patch in upstream_patches.iter() {
+        let sha = format!("{:.6}", patch.oid);
+        if color {
+            let display_name = patch
+                .author_name
+                .as_deref()
+                .or(patch.author_email.as_deref())
+                .unwrap_or("Unknown");
+            println!(
+                "* {} {} {}",
+                Yellow.paint(sha),
+                patch.summary,
+                Cyan.paint(display_name)
+            );
+        } else {
+            println!(
+                "* {} {} {} <{}>",
+                sha,
+                patch.summary,
+                patch.author_name.as_deref().unwrap_or(""),
+                patch.author_email.as_deref().unwrap_or("")
+            );
+        }
+    }
+}
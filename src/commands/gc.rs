@@ -0,0 +1,58 @@
+use super::utils::{get_confirmation, print_error_chain};
+use gps as ps;
+
+pub fn gc(yes: bool, color: bool) {
+    let landed = match ps::gc::landed_branches() {
+        Ok(landed) => landed,
+        Err(e) => {
+            print_error_chain(color, e.into());
+            std::process::exit(1);
+        }
+    };
+    if !delete_landed_branches(&landed, !yes, color) {
+        println!("No request review branches deleted.")
+    }
+}
+
+/// List the request review branches of landed patches & delete them, asking first when `prompt`,
+/// returning whether they were deleted
+pub fn delete_landed_branches(landed: &[String], prompt: bool, color: bool) -> bool {
+    if landed.is_empty() {
+        return false;
+    }
+
+    println!("Request review branches of patches that landed upstream:");
+    for name in landed.iter() {
+        println!("  {}", name);
+    }
+    if prompt {
+        match get_confirmation("\nDelete them locally & remotely?") {
+            Ok(true) => {}
+            Ok(false) => return false,
+            Err(e) => {
+                print_error_chain(color, e.into());
+                std::process::exit(1);
+            }
+        }
+    }
+
+    match ps::gc::delete_branches(landed) {
+        Ok(not_deleted) => {
+            print_remote_branches_not_deleted(&not_deleted);
+            true
+        }
+        Err(e) => {
+            print_error_chain(color, e.into());
+            std::process::exit(1);
+        }
+    }
+}
+
+pub fn print_remote_branches_not_deleted(not_deleted: &[ps::gc::RemoteBranchNotDeleted]) {
+    for remote_branch in not_deleted.iter() {
+        println!(
+            "Failed to delete remote branch {}, it may already be gone, {}",
+            remote_branch.name, remote_branch.reason
+        );
+    }
+}
@@ -0,0 +1,43 @@
+use super::utils::{paint, print_error_chain};
+use ansi_term::Colour::{Green, Red, Yellow};
+use gps as ps;
+
+pub fn list(color: bool) {
+    match ps::hooks::list_hooks() {
+        Ok(resolutions) => {
+            for resolution in resolutions {
+                let heading = format!(
+                    "{} ({}, {}) {}",
+                    resolution.name,
+                    resolution.phase,
+                    resolution.command,
+                    resolution.args.join(" ")
+                );
+                println!("{}", heading.trim_end());
+                if resolution.files.is_empty() {
+                    println!("    not found");
+                }
+                for file in resolution.files.iter() {
+                    let status = match (file.runs, file.executable) {
+                        (true, _) => paint(color, Green, "runs"),
+                        (false, false) => paint(color, Yellow, "not executable"),
+                        (false, true) => "skipped".to_string(),
+                    };
+                    println!(
+                        "    {} {} - {}",
+                        status,
+                        file.location,
+                        file.path.to_str().unwrap_or("some non utf-8 path")
+                    );
+                }
+                if let Some(e) = resolution.error {
+                    println!("    {}", paint(color, Red, &format!("unresolvable, {}", e)));
+                }
+            }
+        }
+        Err(e) => {
+            print_error_chain(color, e.into());
+            std::process::exit(1);
+        }
+    }
+}
@@ -1,26 +1,56 @@
-use super::patch_index_range::PatchIndexRange;
-use super::utils::{print_err, print_error_chain};
+use super::patch_index_range_batch::PatchIndexRangeBatch;
+use super::pull::print_pull;
+use super::utils::{get_confirmation, paint, print_err, print_error_chain, print_warn};
+use ansi_term::Colour::{Green, Red, Yellow};
 use gps as ps;
-use std::str::FromStr;
 
+#[allow(clippy::too_many_arguments)]
 pub fn integrate(
-    patch_index_or_range: String,
+    patch_index_or_range_batch: String,
     force: bool,
     keep_branch: bool,
+    retry: bool,
+    sign_push: bool,
+    tag: bool,
+    check: bool,
     branch_name: Option<String>,
     color: bool,
 ) {
-    match PatchIndexRange::from_str(&patch_index_or_range) {
-        Ok(patch_index_range) => {
+    match patch_index_or_range_batch.parse::<PatchIndexRangeBatch>() {
+        Ok(batch) => {
+            if batch.len() > 1 && branch_name.is_some() {
+                print_err(
+                    color,
+                    r#"
+  Specifying a branch name does not work when batching.
+
+  The integrate command uses the branch associated with each patch or patch
+  series of the batch respectively.
+"#,
+                );
+                std::process::exit(1);
+            }
+
+            let patch_index_ranges: Vec<(usize, Option<usize>)> =
+                batch.map(|r| (r.start_index, r.end_index)).collect();
+            if check {
+                check_integrate(&patch_index_ranges, branch_name, color);
+                return;
+            }
+            let _events =
+                ps::events::subscribe(move |event: &ps::events::Event| print_event(event, color));
             match ps::integrate::integrate(
-                patch_index_range.start_index,
-                patch_index_range.end_index,
+                &patch_index_ranges,
                 force,
                 keep_branch,
+                retry,
+                sign_push,
+                tag,
                 branch_name,
-                color,
+                |patch_index_ranges| reassure(patch_index_ranges, color),
             ) {
-                Ok(_) => {}
+                Ok(Some(pull_result)) => print_pull(&pull_result, color),
+                Ok(None) => {}
                 Err(ps::integrate::IntegrateError::MergeCommitDetected(oid)) => {
                     print_err(
                         color,
@@ -50,130 +80,164 @@ pub fn integrate(
                     );
                     std::process::exit(1);
                 }
-                Err(ps::integrate::IntegrateError::HasNoAssociatedBranch) => {
+                Err(ps::integrate::IntegrateError::CheckFailed(failed_check)) => {
+                    let mut details: Vec<&str> = failed_check.detail.lines().collect();
+                    if let Some(ref diff) = failed_check.diff {
+                        details.push("");
+                        details.extend(diff.lines());
+                    }
                     print_err(
                         color,
-                        r#"
-  Looks like the patch(es) haven't had a branch associated with them yet.
-
-  You can associate them with gps branch and gps request-review.
+                        &format!(
+                            r#"
+  The "{}" safety check failed, so we have aborted the integration.
 
-  Or you can skip saftey checks like this one with --force.
-        "#,
-                    );
-                    std::process::exit(1);
-                }
-                Err(ps::integrate::IntegrateError::AssociatedBranchAmbiguous) => {
-                    print_err(
-                        color,
-                        r#"
-  Looks like the patch(es) are associated to more than one branch.
+    {}
 
-  To integrate it needs to know which associated branch you want to work with. You can specify the branch with -n.
+  You can see the outcome of all the safety checks with gps integrate --check.
 
   Alternatively you can also skip saftey checks like this one with --force.
         "#,
+                            failed_check.kind.description(),
+                            details.join("\n    ")
+                        ),
                     );
                     std::process::exit(1);
                 }
-                Err(ps::integrate::IntegrateError::UpstreamBranchInfoMissing) => {
+                Err(ps::integrate::IntegrateError::UncommittedChangesExist) => {
                     print_err(
                         color,
                         r#"
-  Looks like the patch(es) have not been pushed yet.
-
-  Either push them with gps sync or gps request-review.
+  gps integrate command requires a clean working directory when verifying isolation, but it looks like yours is dirty.
 
-  Alternatively you can also skip saftey checks like this one with --force.
+  It is recommended that you create a WIP commit. But, you could also use git stash if you prefer.
         "#,
                     );
                     std::process::exit(1);
                 }
-                Err(ps::integrate::IntegrateError::CommitCountMissmatch(
-                    patch_series_count,
-                    remote_branch_commit_count,
-                )) => {
-                    print_err(
-                        color,
-                        &format!(
-                            r#"
-  Looks like the patch series consists of {} patches but the remote has {}.
-
-  Investigate this mismatch & resolve it before trying to integrate again.
-
-  Or you can skip saftey checks like this one with --force.
-        "#,
-                            patch_series_count, remote_branch_commit_count
-                        ),
-                    );
+                Err(e) => {
+                    print_error_chain(color, e.into());
                     std::process::exit(1);
                 }
-                Err(ps::integrate::IntegrateError::PatchAndRemotePatchIdMissmatch(patch_index)) => {
-                    print_err(
-                        color,
-                        &format!(
-                            r#"
-  The patch at index {} has a different patch identifier than the patch on the remote.
+            }
+        }
+        Err(e) => {
+            print_error_chain(color, e.into());
+            std::process::exit(1);
+        }
+    }
+}
 
-  It could be that you made a change locally to that patches id or that someone made a change to that patches id remotely.
+/// Print the retries & rebuilds happening while integrating
+fn print_event(event: &ps::events::Event, color: bool) {
+    match event {
+        ps::events::Event::PushRetryScheduled {
+            upstream,
+            delay_secs,
+            attempt,
+            max_attempts,
+        } => print_warn(
+            color,
+            &format!(
+                "\nPushing to {} failed, likely because it moved, retrying in {}s (attempt {} of {})\n",
+                upstream, delay_secs, attempt, max_attempts
+            ),
+        ),
+        ps::events::Event::BranchRebuilt { name, .. } => {
+            println!("Rebuilt {} on the latest upstream", name)
+        }
+        _ => {}
+    }
+}
 
-  If you made the change locally, you can update the remote with gps sync or gps request-review.
+/// Show the patches about to be integrated & ask whether to go ahead
+fn reassure(
+    patch_index_ranges: &[(usize, Option<usize>)],
+    color: bool,
+) -> Result<bool, Box<dyn std::error::Error>> {
+    for (start_patch_index, end_patch_index) in patch_index_ranges.iter() {
+        match ps::show(*start_patch_index, *end_patch_index) {
+            Err(ps::ShowError::ExitSignal(13)) => print_warn(
+                color,
+                r#"
+Warning: showing the patch exited with a SIGPIPE. This is likely because you
+exited the pager (e.g. less) without going to the last page.
+
+See https://github.com/uptech/git-ps-rs/issues/120 for details on why this
+happens.
+"#,
+            ),
+            Err(e) => return Err(e.into()),
+            Ok(_) => (),
+        }
+    }
+    Ok(get_confirmation(
+        "\n\nAre you sure you want to integrate this patch?",
+    )?)
+}
 
-  Alternatively you can also skip saftey checks like this one with --force.
-        "#,
-                            patch_index
-                        ),
-                    );
-                    std::process::exit(1);
+/// Print the report of the integrate safety checks, exiting with the status of the first failed
+/// check so CI bots can tell why the patches can't be integrated
+fn check_integrate(
+    patch_index_ranges: &[(usize, Option<usize>)],
+    branch_name: Option<String>,
+    color: bool,
+) {
+    use ps::integrate_check::{IntegrateCheckKind, IntegrateCheckStatus};
+
+    match ps::integrate_check::check(patch_index_ranges, branch_name) {
+        Ok(reports) => {
+            for report in reports.iter() {
+                let range = match report.end_patch_index {
+                    Some(end_patch_index) => {
+                        format!("{}-{}", report.start_patch_index, end_patch_index)
+                    }
+                    None => report.start_patch_index.to_string(),
+                };
+                match report.branch_name {
+                    Some(ref branch_name) => println!("\n[{}] {}", range, branch_name),
+                    None => println!("\n[{}]", range),
                 }
-                Err(ps::integrate::IntegrateError::PatchDiffHashMissmatch(patch_index)) => {
-                    print_err(
-                        color,
-                        &format!(
-                            r#"
-  The patch at index {} is different from that patch on the remote.
-
-  It could be that you made a change locally to that patch or that someone made a change to that patch remotely.
-
-  If you made a change locally, you can update the remote with gps sync or gps request-review.
-
-  Alternatively you can also skip saftey checks like this one with --force.
-        "#,
-                            patch_index
-                        ),
-                    );
-                    std::process::exit(1);
+                for check in report.checks.iter() {
+                    let status = match check.status {
+                        IntegrateCheckStatus::Passed => paint(color, Green, "pass"),
+                        IntegrateCheckStatus::Failed => paint(color, Red, "FAIL"),
+                        IntegrateCheckStatus::Skipped => paint(color, Yellow, "skip"),
+                    };
+                    println!("  {} {}", status, check.kind.description());
+                    for line in check.detail.lines() {
+                        println!("         {}", line);
+                    }
+                    if let Some(ref diff) = check.diff {
+                        println!();
+                        for line in diff.lines() {
+                            println!("         {}", line);
+                        }
+                    }
                 }
-                Err(ps::integrate::IntegrateError::VerifyHookExecutionFailed(e)) => {
-                    print_err(
-                        color,
-                        &format!(
-                            r#"
-  The integrate_verify hook execution failed with {:?}.
-
-  We have aborted the integration. For the integration to continue the integrate_verify hook must exit 0.
+            }
 
-  Alternatively you can also skip saftey checks like this one with --force.
-        "#,
-                            e
-                        ),
-                    );
-                    std::process::exit(1);
-                }
-                Err(ps::integrate::IntegrateError::UncommittedChangesExist) => {
+            let first_failure = reports
+                .iter()
+                .filter_map(|r| r.first_failure())
+                .min_by_key(|c| c.kind as u8);
+            match first_failure {
+                None => println!("\nAll checks passed, the patch(es) can be integrated."),
+                Some(failed_check) => {
                     print_err(
                         color,
-                        r#"
-  gps integrate command requires a clean working directory when verifying isolation, but it looks like yours is dirty.
-
-  It is recommended that you create a WIP commit. But, you could also use git stash if you prefer.
-        "#,
+                        "\nSome checks failed, the patch(es) can NOT be integrated.",
                     );
-                    std::process::exit(1);
-                }
-                Err(e) => {
-                    print_error_chain(color, e.into());
-                    std::process::exit(1);
+                    std::process::exit(match failed_check.kind {
+                        IntegrateCheckKind::AssociatedBranch | IntegrateCheckKind::Pushed => 2,
+                        IntegrateCheckKind::CommitCount
+                        | IntegrateCheckKind::PatchIds
+                        | IntegrateCheckKind::PatchDiffs => 3,
+                        IntegrateCheckKind::Signatures => 7,
+                        IntegrateCheckKind::VerifyHook => 4,
+                        IntegrateCheckKind::Isolation => 5,
+                        IntegrateCheckKind::Integrates => 6,
+                    });
                 }
             }
         }
@@ -9,7 +9,6 @@ pub fn isolate(patch_index_or_range: Option<String>, color: bool) {
             Ok(patch_index_range) => match ps::isolate(
                 Some(patch_index_range.start_index),
                 patch_index_range.end_index,
-                color,
             ) {
                 Ok(_) => {}
                 Err(ps::IsolateError::UncommittedChangesExist) => {
@@ -62,7 +61,7 @@ pub fn isolate(patch_index_or_range: Option<String>, color: bool) {
                 std::process::exit(1);
             }
         },
-        None => match ps::isolate(None, None, color) {
+        None => match ps::isolate(None, None) {
             Ok(_) => {}
             Err(ps::IsolateError::UncommittedChangesExist) => {
                 print_err(
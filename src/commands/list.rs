@@ -5,15 +5,61 @@
 // be strongly considered if they fit better in one of the other modules
 // inside the ps module and should be exposed via the library public interface.
 
-use super::utils::print_error_chain;
+use super::utils::{paint, print_error_chain};
+use ansi_term::Colour::{Red, Yellow};
 use gps as ps;
 
-pub fn list(color: bool) {
-    match ps::list(color) {
-        Ok(_) => {}
+pub fn list(color: bool, refresh: bool) {
+    match ps::list(refresh) {
+        Ok(listing) => print_listing(&listing, color),
         Err(e) => {
             print_error_chain(color, e.into());
             std::process::exit(1);
         }
     };
 }
+
+fn print_listing(listing: &ps::Listing, color: bool) {
+    if let Some(ref rebase) = listing.rebase {
+        println!(
+            "{} {}",
+            paint(
+                color,
+                Red,
+                &format!("rebase of '{}' in progress; onto", rebase.head_name)
+            ),
+            paint(color, Yellow, &format!("{:.7}", rebase.onto))
+        );
+        if !listing.reverse_order {
+            print_rebase_todos(rebase.todos.iter().rev(), color);
+        }
+    }
+
+    println!(
+        "{} tracking {} [ahead {}, behind {}]",
+        listing.status.branch_name,
+        listing.status.upstream_branch_name,
+        listing.status.ahead,
+        listing.status.behind,
+    );
+    for row in listing.rows.iter() {
+        println!("{}", row.render(color));
+    }
+
+    if let Some(ref rebase) = listing.rebase {
+        if listing.reverse_order {
+            println!();
+            print_rebase_todos(rebase.todos.iter(), color);
+        }
+    }
+}
+
+fn print_rebase_todos<'a>(todos: impl ExactSizeIterator<Item = &'a ps::ListingRow>, color: bool) {
+    println!("Next commands to do ({} remaining commands)", todos.len());
+    for todo in todos {
+        println!("{}", todo.render(color));
+    }
+    println!("(use \"git rebase --edit-todo\" to view and edit)");
+    println!("(use \"git rebase --continue\" once you are satisfied with your changes)");
+    println!();
+}
@@ -7,17 +7,27 @@ pub mod append;
 pub mod backup_stack;
 pub mod branch;
 pub mod checkout;
+pub mod config;
+pub mod continue_operation;
+pub mod deps;
 pub mod fetch;
+pub mod gc;
+pub mod hooks;
 pub mod id;
 pub mod integrate;
 pub mod isolate;
 pub mod list;
 pub mod patch_index_range;
 pub mod patch_index_range_batch;
+pub mod progress;
 pub mod pull;
 pub mod push;
 pub mod rebase;
 pub mod request_review;
 pub mod sha;
 pub mod show;
+pub mod stack;
+pub mod transfer;
 pub mod utils;
+pub mod verify_signatures;
+pub mod warnings;
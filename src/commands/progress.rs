@@ -0,0 +1,58 @@
+use super::utils::paint;
+use ansi_term::Colour::{Green, Red};
+use ansi_term::Style;
+use gps::events::{self, Event, Subscription};
+
+/// Render the events of the operations as progress lines on stderr, until the returned
+/// subscription is dropped
+pub fn subscribe(color: bool) -> Subscription {
+    events::subscribe(move |event: &Event| render(color, event))
+}
+
+fn render(color: bool, event: &Event) {
+    let line = match event {
+        Event::PatchReplayed {
+            oid,
+            new_oid,
+            summary,
+        } => format!("replayed {} -> {} {}", short(oid), short(new_oid), summary),
+        Event::HookStarted { name } => format!("running hook {}", name),
+        Event::HookFinished { name, success } => {
+            format!("hook {} {}", name, outcome(color, *success, "passed"))
+        }
+        Event::FetchStarted => "fetching".to_string(),
+        Event::FetchFinished { success } => format!("fetch {}", outcome(color, *success, "done")),
+        Event::PushStarted { remote, refspecs } => {
+            format!("pushing {} to {}", refspecs.join(" "), remote)
+        }
+        Event::PushFinished { remote, success } => {
+            format!("push to {} {}", remote, outcome(color, *success, "done"))
+        }
+        // the integrate command reports these itself, with or without --progress
+        Event::PushRetryScheduled { .. } | Event::BranchRebuilt { .. } => return,
+        // warnings are reported with or without --progress
+        Event::HookNotExecutable { .. } => return,
+        Event::BranchDeleted { name, remote } => match remote {
+            Some(remote) => format!("deleted remote branch {}/{}", remote, name),
+            None => format!("deleted branch {}", name),
+        },
+    };
+
+    if color {
+        eprintln!("  {}", Style::new().dimmed().paint(line))
+    } else {
+        eprintln!("  {}", line)
+    }
+}
+
+fn short(oid: &git2::Oid) -> String {
+    oid.to_string()[..7].to_string()
+}
+
+fn outcome(color: bool, success: bool, succeeded: &str) -> String {
+    if success {
+        paint(color, Green, succeeded)
+    } else {
+        paint(color, Red, "failed")
+    }
+}
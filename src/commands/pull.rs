@@ -1,11 +1,24 @@
+use super::fetch::print_upstream_patches;
+use super::gc::{delete_landed_branches, print_remote_branches_not_deleted};
+use super::list;
+use super::utils::print_error_chain;
+use ansi_term::Colour::{Green, Red, Yellow};
 use gps as ps;
 use std::thread;
 
 pub fn pull(color: bool) {
     let check_release_thread = thread::spawn(ps::newer_release_available);
 
-    match ps::pull(color) {
-        Ok(_) => {}
+    println!("Fetching upstream patches...");
+    match ps::pull() {
+        Ok(result) => print_pull(&result, color),
+        Err(e @ ps::PullError::StoppedForConflictResolution(_)) => {
+            if let ps::PullError::StoppedForConflictResolution(ref report) = e {
+                print_report(report, color);
+            }
+            print_error_chain(color, e.into());
+            std::process::exit(1);
+        }
         Err(e) => {
             eprintln!("Error: {:?}", e);
             std::process::exit(1);
@@ -19,3 +32,66 @@ pub fn pull(color: bool) {
         ps::notify_of_newer_release(newer_release, color);
     }
 }
+
+/// Print the report of the pull & carry out what follows it
+pub fn print_pull(result: &ps::PullResult, color: bool) {
+    print_report(&result.report, color);
+    println!();
+    follow_up(&result.follow_up, color);
+}
+
+/// Print the deleted request review branches of landed patches, confirm the deletion of the ones
+/// awaiting it & list the patch stack, as configured for after a pull
+pub fn follow_up(follow_up: &ps::PullFollowUp, color: bool) {
+    if !follow_up.deleted_branches.is_empty() {
+        println!("Deleted request review branches of patches that landed upstream:");
+        for name in follow_up.deleted_branches.iter() {
+            println!("  {}", name);
+        }
+        print_remote_branches_not_deleted(&follow_up.remote_branches_not_deleted);
+    }
+    delete_landed_branches(&follow_up.branches_to_confirm, true, color);
+
+    if follow_up.list_patch_stack {
+        println!("Listing patch stack...");
+        list::list(color, false);
+    }
+}
+
+fn print_report(report: &ps::PullReport, color: bool) {
+    if let Some(ref upstream_patches) = report.upstream_patches {
+        print_upstream_patches(upstream_patches, color);
+    }
+    println!();
+
+    println!("Rebasing...");
+    let print_patch = |status: &str, patch: &ps::PulledPatch| {
+        let sha = format!("{:.7}", patch.oid);
+        let padded_status = format!("{:<9}", status);
+        if color {
+            let padded_status = match status {
+                "landed" => Green.paint(padded_status),
+                "rebased" => Yellow.paint(padded_status),
+                _ => Red.paint(padded_status),
+            };
+            println!(
+                "  {} {} {}",
+                padded_status,
+                Yellow.paint(sha),
+                patch.summary
+            );
+        } else {
+            println!("  {} {} {}", padded_status, sha, patch.summary);
+        }
+    };
+
+    for patch in report.landed.iter() {
+        print_patch("landed", patch);
+    }
+    for patch in report.rebased.iter() {
+        print_patch("rebased", patch);
+    }
+    if let Some(patch) = report.conflict.as_ref() {
+        print_patch("conflict", patch);
+    }
+}
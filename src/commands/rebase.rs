@@ -5,13 +5,14 @@
 // functions and should be strongly considered if they fit better in one of
 // the other modules in the `ps` module.
 
+use super::utils::print_error_chain;
 use gps as ps;
 
-pub fn rebase(continue_rebase: bool) {
+pub fn rebase(continue_rebase: bool, color: bool) {
     match ps::rebase(continue_rebase) {
         Ok(_) => (),
         Err(e) => {
-            eprintln!("Error: {:?}", e);
+            print_error_chain(color, e.into());
             std::process::exit(1);
         }
     };
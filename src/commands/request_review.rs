@@ -56,7 +56,6 @@ pub fn request_review(
             patch_index_range.start_index,
             patch_index_range.end_index,
             branch_name.clone(),
-            color,
             isolation_verification_hook,
             post_sync_hook,
         ) {
@@ -121,6 +120,11 @@ pub fn request_review(
         "#,
                     );
                 }
+                ps::RequestReviewError::StoppedForConflictResolution(_, _) => {
+                    // the rest of the batch waits for the interrupted request for review
+                    print_error_chain(color, e.into());
+                    std::process::exit(1);
+                }
                 _ => {
                     print_error_chain(color, e.into());
                 }
@@ -0,0 +1,81 @@
+use super::utils::print_error_chain;
+use ansi_term::Colour::{Green, Yellow};
+use gps as ps;
+
+pub fn new(name: &str, onto: Option<String>, color: bool) {
+    match ps::stack::new(name, onto) {
+        Ok(upstream) => println!("created patch stack {} on {}", name, upstream),
+        Err(e) => {
+            print_error_chain(color, e.into());
+            std::process::exit(1);
+        }
+    }
+}
+
+pub fn list(color: bool) {
+    match ps::stack::list() {
+        Ok(stacks) => {
+            for stack in stacks {
+                let marker = if stack.is_current { "*" } else { " " };
+                let name = if stack.is_current && color {
+                    Green.paint(&stack.name).to_string()
+                } else {
+                    stack.name.clone()
+                };
+                match stack.upstream {
+                    Some(upstream) => {
+                        let review = &stack.review;
+                        println!(
+                            "{} {} tracking {} [ahead {}, behind {}] {} new, {} branched, {} in review, {}",
+                            marker,
+                            name,
+                            upstream,
+                            stack.ahead,
+                            stack.behind,
+                            review.new,
+                            review.branched,
+                            review.requested,
+                            paint_changed(color, review.changed)
+                        );
+                    }
+                    None => println!("{} {} (no upstream)", marker, name),
+                }
+            }
+        }
+        Err(e) => {
+            print_error_chain(color, e.into());
+            std::process::exit(1);
+        }
+    }
+}
+
+pub fn switch(name: &str, color: bool) {
+    if let Err(e) = ps::stack::switch(name) {
+        print_error_chain(color, e.into());
+        std::process::exit(1);
+    }
+}
+
+pub fn delete(name: &str, force: bool, color: bool) {
+    match ps::stack::delete(name, force) {
+        Ok(rr_branch_names) => {
+            for rr_branch_name in rr_branch_names {
+                println!("deleted request review branch {}", rr_branch_name);
+            }
+            println!("deleted patch stack {}", name);
+        }
+        Err(e) => {
+            print_error_chain(color, e.into());
+            std::process::exit(1);
+        }
+    }
+}
+
+fn paint_changed(color: bool, changed: usize) -> String {
+    let text = format!("{} changed", changed);
+    if color && changed > 0 {
+        Yellow.paint(text).to_string()
+    } else {
+        text
+    }
+}
@@ -0,0 +1,27 @@
+use super::patch_index_range::PatchIndexRange;
+use super::utils::print_error_chain;
+use gps as ps;
+use std::str::FromStr;
+
+pub fn transfer(patch_index_or_range: String, target_stack: &str, color: bool) {
+    match PatchIndexRange::from_str(&patch_index_or_range) {
+        Ok(patch_index_range) => {
+            let res = ps::transfer(
+                patch_index_range.start_index,
+                patch_index_range.end_index,
+                target_stack,
+            );
+            match res {
+                Ok(_) => {}
+                Err(e) => {
+                    print_error_chain(color, e.into());
+                    std::process::exit(1);
+                }
+            }
+        }
+        Err(e) => {
+            print_error_chain(color, e.into());
+            std::process::exit(1);
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use ansi_term::Colour::Red;
+use ansi_term::Colour::{Red, Yellow};
 
 pub fn print_err(color: bool, message: &str) {
     if color {
@@ -8,6 +8,14 @@ pub fn print_err(color: bool, message: &str) {
     }
 }
 
+pub fn print_warn(color: bool, message: &str) {
+    if color {
+        eprintln!("{}", Yellow.paint(message))
+    } else {
+        eprintln!("{}", message)
+    }
+}
+
 pub fn print_error_chain(color: bool, e: Box<dyn std::error::Error>) {
     print_err(color, &format!("\nError: {}\n", e));
     let mut err = e.source();
@@ -16,3 +24,21 @@ pub fn print_error_chain(color: bool, e: Box<dyn std::error::Error>) {
         err = e.source();
     }
 }
+
+/// Paint the text in the colour when color output is enabled
+pub fn paint(color: bool, colour: ansi_term::Colour, text: &str) -> String {
+    if color {
+        colour.paint(text).to_string()
+    } else {
+        text.to_string()
+    }
+}
+
+/// Ask the yes/no question on stdout & read the answer from stdin, anything but yes or y being a no
+pub fn get_confirmation(question: &str) -> std::io::Result<bool> {
+    let mut answer = String::new();
+    println!("{} (y/N)", question);
+    std::io::stdin().read_line(&mut answer)?;
+    let normalized_answer = answer.to_lowercase().trim().to_string();
+    Ok(normalized_answer == "yes" || normalized_answer == "y")
+}
@@ -0,0 +1,70 @@
+use super::patch_index_range::PatchIndexRange;
+use super::utils::{paint, print_err, print_error_chain};
+use ansi_term::Colour::{Green, Red};
+use gps as ps;
+use std::str::FromStr;
+
+pub fn verify_signatures(patch_index_or_range: Option<String>, color: bool) {
+    let (start_index, end_index) = match patch_index_or_range {
+        Some(pir) => match PatchIndexRange::from_str(&pir) {
+            Ok(patch_index_range) => (
+                Some(patch_index_range.start_index),
+                patch_index_range.end_index,
+            ),
+            Err(e) => {
+                print_error_chain(color, e.into());
+                std::process::exit(1);
+            }
+        },
+        None => (None, None),
+    };
+
+    match ps::verify_signatures::verify_signatures(start_index, end_index) {
+        Ok(verified_commits) => {
+            for verified_commit in verified_commits.iter() {
+                let status = if verified_commit.is_trusted() {
+                    paint(color, Green, "good")
+                } else {
+                    paint(color, Red, &verified_commit.status.to_string())
+                };
+                let location = match verified_commit.location {
+                    ps::verify_signatures::SignedCommitLocation::Patch(patch_index) => {
+                        format!("{:<3}", patch_index)
+                    }
+                    ps::verify_signatures::SignedCommitLocation::ReviewBranch(ref branch_name) => {
+                        branch_name.to_string()
+                    }
+                };
+                let short_sha: String = verified_commit.oid.to_string().chars().take(7).collect();
+                let signer = verified_commit
+                    .signer
+                    .as_ref()
+                    .map(|signer| format!(" ({})", signer))
+                    .unwrap_or_default();
+                println!(
+                    "{} {} {} {}{}",
+                    location, short_sha, verified_commit.summary, status, signer
+                );
+            }
+
+            let untrusted_count = verified_commits.iter().filter(|c| !c.is_trusted()).count();
+            if untrusted_count > 0 {
+                print_err(
+                    color,
+                    &format!(
+                        r#"
+  {} of the {} commit(s) aren't signed by someone trusted.
+        "#,
+                        untrusted_count,
+                        verified_commits.len()
+                    ),
+                );
+                std::process::exit(1);
+            }
+        }
+        Err(e) => {
+            print_error_chain(color, e.into());
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,31 @@
+use super::utils::print_warn;
+use gps::events::{self, Event, Subscription};
+
+/// Render the warnings the operations emit as events, e.g. hooks skipped because they aren't
+/// executable, on stderr until the returned subscription is dropped. Unlike the progress lines
+/// these are always shown.
+pub fn subscribe(color: bool) -> Subscription {
+    events::subscribe(move |event: &Event| render(color, event))
+}
+
+fn render(color: bool, event: &Event) {
+    if let Event::HookNotExecutable { name, path } = event {
+        let path_str = path.to_str().unwrap_or("unknow path");
+        print_warn(
+            color,
+            &format!(
+                r#"
+  The {} hook was found at
+
+    {}
+
+  but it is NOT executable. Due to this the hook is being skipped. Generally
+  this can be corrected with the following.
+
+    chmod u+x {}
+"#,
+                name, path_str, path_str
+            ),
+        );
+    }
+}
@@ -1,3 +1,13 @@
+//! Git Patch Stack as a library
+//!
+//! [`PatchStack`] is the entry point for tools embedding gps. It opens the patch stack checked out
+//! in the repository containing the current working directory, or in a given repository, & its
+//! methods return data, e.g. the patches, their branch states & the result of syncing them,
+//! leaving the printing to the caller.
+//!
+//! Long running operations, e.g. [`pull`] or [`integrate::integrate`], emit [`events::Event`]s as
+//! they progress, which [`events::subscribe`] notifies an observer of.
+
 #[macro_use]
 extern crate lazy_static;
 
@@ -7,18 +17,32 @@ pub use ps::public::append;
 pub use ps::public::backup_stack::backup_stack;
 pub use ps::public::branch::{branch, BranchError};
 pub use ps::public::checkout::checkout;
+pub use ps::public::config;
+pub use ps::public::continue_operation::{continue_operation, ContinueError};
+pub use ps::public::deps::{deps, DepsError, PatchDependencies};
+pub use ps::public::events;
 pub use ps::public::fetch::fetch;
+pub use ps::public::gc::{self, GcError};
+pub use ps::public::hooks;
 pub use ps::public::id::id;
 pub use ps::public::integrate;
+pub use ps::public::integrate_check;
 pub use ps::public::isolate::{isolate, IsolateError};
 pub use ps::public::latest_github_release::{newer_release_available, notify_of_newer_release};
-pub use ps::public::list::list;
-pub use ps::public::pull::{pull, PullError};
+pub use ps::public::list::{list, ListError, Listing, ListingRow, RebaseInProgress};
+pub use ps::public::patch_stack::{
+    BranchState, Patch, PatchStack, PatchStackError, PatchState, RemoteBranchState, StackStatus,
+    SyncResult,
+};
+pub use ps::public::pull::{pull, PullError, PullFollowUp, PullReport, PullResult, PulledPatch};
 pub use ps::public::push::push;
 pub use ps::public::rebase::rebase;
 pub use ps::public::request_review::{request_review, RequestReviewError};
 pub use ps::public::sha;
-pub use ps::public::show::show;
+pub use ps::public::show::{show, ShowError};
+pub use ps::public::stack;
 pub use ps::public::sync::{sync, SyncError};
-pub use ps::public::upstream_patches::upstream_patches;
+pub use ps::public::transfer::{transfer, TransferError};
+pub use ps::public::upstream_patches::{upstream_patches, UpstreamPatch};
 pub use ps::public::verify_isolation::{verify_isolation, VerifyIsolationError};
+pub use ps::public::verify_signatures;
@@ -17,20 +17,29 @@ use clap::Parser;
 
 fn main() {
     let cli = cli::Cli::parse();
+    commands::config::set_overrides(&cli.config_overrides, cli.color);
+    let _warnings = commands::warnings::subscribe(cli.color);
+    let _progress = cli
+        .progress
+        .then(|| commands::progress::subscribe(cli.color));
 
     match cli.command {
         cli::Command::Branch(opts) => {
             commands::branch::branch(opts.patch_index_or_range, opts.branch_name, cli.color)
         }
         cli::Command::Integrate(opts) => commands::integrate::integrate(
-            opts.patch_index_or_range,
+            opts.patch_index_or_range_batch,
             opts.force,
             opts.keep_branch,
+            opts.retry,
+            opts.sign_push,
+            opts.tag,
+            opts.check,
             opts.branch_name,
             cli.color,
         ),
-        cli::Command::List => commands::list::list(cli.color),
-        cli::Command::Rebase(opts) => commands::rebase::rebase(opts.r#continue),
+        cli::Command::List(opts) => commands::list::list(cli.color, opts.refresh),
+        cli::Command::Rebase(opts) => commands::rebase::rebase(opts.r#continue, cli.color),
         cli::Command::Pull => commands::pull::pull(cli.color),
         cli::Command::RequestReview(opts) => commands::request_review::request_review(
             opts.patch_index_or_range_batch,
@@ -51,9 +60,51 @@ fn main() {
         cli::Command::Isolate(opts) => {
             commands::isolate::isolate(opts.patch_index_or_range, cli.color)
         }
-        cli::Command::Checkout(opts) => commands::checkout::checkout(opts.patch_index),
+        cli::Command::VerifySignatures(opts) => {
+            commands::verify_signatures::verify_signatures(opts.patch_index_or_range, cli.color)
+        }
+        cli::Command::Checkout(opts) => commands::checkout::checkout(opts.patch_index, cli.color),
         cli::Command::Fetch => commands::fetch::fetch(cli.color),
+        cli::Command::Transfer(opts) => {
+            commands::transfer::transfer(opts.patch_index_or_range, &opts.target_stack, cli.color)
+        }
+        cli::Command::Deps => commands::deps::deps(cli.color),
+        cli::Command::Gc(opts) => commands::gc::gc(opts.yes, cli.color),
+        cli::Command::Continue(opts) => {
+            commands::continue_operation::continue_operation(opts.abort, cli.color)
+        }
+        cli::Command::Stack(opts) => match opts.command {
+            cli::StackCommand::New(opts) => commands::stack::new(&opts.name, opts.onto, cli.color),
+            cli::StackCommand::List => commands::stack::list(cli.color),
+            cli::StackCommand::Switch(opts) => commands::stack::switch(&opts.name, cli.color),
+            cli::StackCommand::Delete(opts) => {
+                commands::stack::delete(&opts.name, opts.force, cli.color)
+            }
+        },
+        cli::Command::Hooks(opts) => match opts.command {
+            cli::HooksCommand::List => commands::hooks::list(cli.color),
+        },
+        cli::Command::Config(opts) => match opts.command {
+            cli::ConfigCommand::List(opts) => commands::config::list(opts.show_origin, cli.color),
+            cli::ConfigCommand::Get(opts) => {
+                commands::config::get(&opts.key, opts.level.map(config_level), cli.color)
+            }
+            cli::ConfigCommand::Set(opts) => {
+                commands::config::set(&opts.key, &opts.value, config_level(opts.level), cli.color)
+            }
+            cli::ConfigCommand::Validate => commands::config::validate(cli.color),
+        },
         #[cfg(feature = "backup_cmd")]
-        cli::Command::BackupStack(opts) => commands::backup_stack::backup_stack(opts.branch_name),
+        cli::Command::BackupStack(opts) => {
+            commands::backup_stack::backup_stack(opts.branch_name, cli.color)
+        }
     };
 }
+
+fn config_level(level: cli::ConfigLevel) -> gps::config::ConfigLevel {
+    match level {
+        cli::ConfigLevel::User => gps::config::ConfigLevel::User,
+        cli::ConfigLevel::Personal => gps::config::ConfigLevel::Personal,
+        cli::ConfigLevel::Communal => gps::config::ConfigLevel::Communal,
+    }
+}
@@ -9,7 +9,7 @@ pub mod public;
 use std::collections::HashMap;
 use std::str::FromStr;
 
-use private::{cherry_picking, git, state_computation};
+use private::{cherry_picking, config, git, state_computation};
 // This is the `ps` module. It is responsible for housing functionality
 // specific to Patch Stack as a conceptual level.  It is responsible for
 // consuming functionality from other modules like the `git` and `utils`
@@ -175,17 +175,23 @@ pub fn extract_ps_id(message: &str) -> Option<Uuid> {
     }
 }
 
+/// Extract every ps-id of the message, e.g. of a squashed or merge commit integrating several
+/// patches
+pub fn extract_ps_ids(message: &str) -> Vec<Uuid> {
+    lazy_static! {
+        static ref RE: Regex = Regex::new(r"ps-id:\s(?P<patchStackId>[\w\d-]+)").unwrap();
+    }
+    RE.captures_iter(message)
+        .filter_map(|caps| Uuid::from_str(&caps["patchStackId"]).ok())
+        .collect()
+}
+
 pub fn slugify(summary: &str) -> String {
     summary
         .replace(|c: char| !c.is_alphanumeric(), "_")
         .to_lowercase()
 }
 
-pub fn generate_rr_branch_name(summary: &str) -> String {
-    let slug = slugify(summary);
-    format!("ps/rr/{}", slug)
-}
-
 #[derive(Debug)]
 pub enum AddPatchIdsError {
     GetCurrentBranch,
@@ -294,6 +300,7 @@ impl std::error::Error for AddPatchIdsError {
 pub fn add_patch_ids(
     repo: &git2::Repository,
     config: &git2::Config,
+    rewrite_config: &config::PsRewriteConfig,
 ) -> Result<(), AddPatchIdsError> {
     // Get currently checked out branch
     let branch_ref_name =
@@ -334,6 +341,7 @@ pub fn add_patch_ids(
     let last_cherry_picked_commit_oid = cherry_picking::cherry_pick(
         repo,
         config,
+        rewrite_config,
         upstream_branch_oid,
         Some(cur_branch_oid),
         add_id_rework_branch_ref_name,
@@ -395,7 +403,7 @@ impl std::error::Error for PatchRangeWithinStackBoundsError {
 pub fn patch_range_within_stack_bounds(
     start_patch_index: usize,
     end_patch_index: Option<usize>,
-    stack_patches: &Vec<ListPatch>,
+    stack_patches: &[ListPatch],
 ) -> Result<(), PatchRangeWithinStackBoundsError> {
     if start_patch_index > (stack_patches.len() - 1) {
         return Err(
@@ -469,18 +477,22 @@ mod tests {
     }
 
     #[test]
-    fn test_slugify() {
+    fn test_extract_ps_ids_with_several_ps_ids() {
+        let msg = "Merge ps/rr/foo into main\n\n<!-- ps-id: 2dce2a21-72b9-487a-b641-4a0b157b76e8 -->\n<!-- ps-id: 9c1d7f4e-08a3-4d7b-9e2a-5b6c3f1e0a27 -->\n";
         assert_eq!(
-            super::slugify("Hello & Goodbye - Purple %#@!()"),
-            "hello___goodbye___purple_______"
+            super::extract_ps_ids(msg),
+            vec![
+                Uuid::from_str("2dce2a21-72b9-487a-b641-4a0b157b76e8").unwrap(),
+                Uuid::from_str("9c1d7f4e-08a3-4d7b-9e2a-5b6c3f1e0a27").unwrap()
+            ]
         );
     }
 
     #[test]
-    fn test_generate_rr_branch_name() {
+    fn test_slugify() {
         assert_eq!(
-            super::generate_rr_branch_name("Hello & Goodbye"),
-            "ps/rr/hello___goodbye"
+            super::slugify("Hello & Goodbye - Purple %#@!()"),
+            "hello___goodbye___purple_______"
         );
     }
 }
@@ -1,10 +1,17 @@
 use super::super::super::ps;
 use super::super::private::cherry_picking;
+use super::super::private::config;
+use super::super::private::conflicts;
+use super::super::private::dependencies;
 use super::super::private::git;
+use super::super::private::paths;
+use super::super::private::rr_branch_name;
 use super::super::private::state_computation;
 use std::collections::HashMap;
 use std::fmt;
+use std::path::PathBuf;
 use std::result::Result;
+use std::time::SystemTime;
 use uuid::Uuid;
 
 #[derive(Debug)]
@@ -20,8 +27,15 @@ pub enum BranchError {
     RrBranchNameNotUtf8,
     MergeCommitDetected(String),
     ConflictsExist(String, String),
+    DependenciesMissing(Vec<usize>),
+    DependenciesFailed(Box<dyn std::error::Error>),
+    StoppedForConflictResolution(String, PathBuf),
+    StopForConflictResolutionFailed(Box<dyn std::error::Error>),
     GetPatchListFailed(Box<dyn std::error::Error>),
     OpenGitConfigFailed(Box<dyn std::error::Error>),
+    GetConfigFailed(Box<dyn std::error::Error>),
+    RrBranchNamingInvalid(Box<dyn std::error::Error>),
+    GenerateRrBranchNameFailed(Box<dyn std::error::Error>),
     PatchStackHeadNoName,
     GetListPatchInfoFailed(Box<dyn std::error::Error>),
     PatchBranchAmbiguous,
@@ -104,12 +118,43 @@ impl fmt::Display for BranchError {
                     src_oid, dst_oid
                 )
             }
+            BranchError::DependenciesMissing(indexes) => {
+                let indexes: Vec<String> = indexes.iter().map(|i| i.to_string()).collect();
+                write!(
+                    f,
+                    "The patches depend on earlier patches ({}) that aren't part of the branch. Request review of those first, include them in the patch range, or set branch.include_dependencies to include them automatically.",
+                    indexes.join(", ")
+                )
+            }
+            BranchError::DependenciesFailed(e) => {
+                write!(f, "Failed to include the patches depended on, {}", e)
+            }
+            BranchError::StoppedForConflictResolution(oid, path) => {
+                write!(
+                    f,
+                    "Stopped at conflicts playing commit ({}), resolve them in the worktree at {}, stage them with git add & run gps continue, or gps continue --abort",
+                    oid,
+                    path.display()
+                )
+            }
+            BranchError::StopForConflictResolutionFailed(e) => {
+                write!(f, "Failed to stop for conflict resolution, {}", e)
+            }
             BranchError::GetPatchListFailed(_patch_list_error) => {
                 write!(f, "Failed to get patch list")
             }
             BranchError::OpenGitConfigFailed(_) => {
                 write!(f, "Failed to open git config")
             }
+            BranchError::GetConfigFailed(e) => {
+                write!(f, "Failed to get config, {}", e)
+            }
+            BranchError::RrBranchNamingInvalid(e) => {
+                write!(f, "Invalid request-review branch name template, {}", e)
+            }
+            BranchError::GenerateRrBranchNameFailed(e) => {
+                write!(f, "Failed to generate request-review branch name, {}", e)
+            }
             BranchError::PatchStackHeadNoName => {
                 write!(f, "Patch Stack Head has no name")
             }
@@ -158,9 +203,16 @@ impl std::error::Error for BranchError {
             | Self::CreateRrBranchFailed
             | Self::RrBranchNameNotUtf8
             | Self::MergeCommitDetected(_)
-            | Self::ConflictsExist(_, _) => None,
+            | Self::ConflictsExist(_, _)
+            | Self::DependenciesMissing(_)
+            | Self::StoppedForConflictResolution(_, _) => None,
+            Self::DependenciesFailed(e) => Some(e.as_ref()),
+            Self::StopForConflictResolutionFailed(e) => Some(e.as_ref()),
             Self::GetPatchListFailed(e) => Some(e.as_ref()),
             Self::OpenGitConfigFailed(e) => Some(e.as_ref()),
+            Self::GetConfigFailed(e) => Some(e.as_ref()),
+            Self::RrBranchNamingInvalid(e) => Some(e.as_ref()),
+            Self::GenerateRrBranchNameFailed(e) => Some(e.as_ref()),
             Self::PatchStackHeadNoName => None,
             Self::GetListPatchInfoFailed(e) => Some(e.as_ref()),
             Self::PatchBranchAmbiguous => None,
@@ -173,16 +225,32 @@ impl std::error::Error for BranchError {
     }
 }
 
+/// Create or replace the branch with the patch or patch series cherry picked onto the patch stack
+/// base. When given the operation the branch is created for & conflict resolution in a worktree
+/// is enabled, a conflict stops the operation to be resumed by `gps continue` instead of failing
+/// it.
 pub fn branch(
     repo: &git2::Repository,
     start_patch_index: usize,
     end_patch_index: Option<usize>,
     given_branch_name_option: Option<String>,
+    interrupted_operation: Option<conflicts::InterruptedOperation>,
 ) -> Result<(git2::Branch<'_>, git2::Oid), BranchError> {
     let config =
         git2::Config::open_default().map_err(|e| BranchError::OpenGitConfigFailed(e.into()))?;
 
-    ps::add_patch_ids(repo, &config)?;
+    let repo_root_path =
+        paths::repo_root_path(repo).map_err(|e| BranchError::GetConfigFailed(e.into()))?;
+    let repo_root_str = repo_root_path.to_str().ok_or(BranchError::GetConfigFailed(
+        "repository path not utf-8".into(),
+    ))?;
+    let repo_gitdir_str = repo.path().to_str().ok_or(BranchError::GetConfigFailed(
+        "repository gitdir not utf-8".into(),
+    ))?;
+    let ps_config = config::get_config(repo, repo_root_str, repo_gitdir_str)
+        .map_err(|e| BranchError::GetConfigFailed(e.into()))?;
+
+    ps::add_patch_ids(repo, &config, &ps_config.rewrite)?;
 
     let patch_stack = ps::get_patch_stack(repo)?;
     let patches_vec = ps::get_patch_list(repo, &patch_stack)
@@ -203,9 +271,18 @@ pub fn branch(
         .shorthand()
         .ok_or(BranchError::PatchStackHeadNoName)?;
 
+    let rr_branch_naming = rr_branch_name::RrBranchNaming::new(&ps_config.request_review)
+        .map_err(|e| BranchError::RrBranchNamingInvalid(e.into()))?;
+
     let patch_info_collection: HashMap<Uuid, state_computation::PatchGitInfo> =
-        state_computation::get_list_patch_info(repo, patch_stack_base_commit.id(), head_ref_name)
-            .map_err(|e| BranchError::GetListPatchInfoFailed(e.into()))?;
+        state_computation::get_list_patch_info(
+            repo,
+            patch_stack_base_commit.id(),
+            head_ref_name,
+            &ps_config.branch_scan,
+            &rr_branch_naming,
+        )
+        .map_err(|e| BranchError::GetListPatchInfoFailed(e.into()))?;
 
     // collect vector of indexes
     let indexes_iter = match end_patch_index {
@@ -232,13 +309,36 @@ pub fn branch(
             let patch_oid = patches_vec.get(indexes_iter.last().unwrap()).unwrap().oid;
             let patch_commit = repo.find_commit(patch_oid).unwrap();
             let patch_summary = patch_commit.summary().expect("Patch Missing Summary");
-            new_branch_name = ps::generate_rr_branch_name(patch_summary);
+            let user_name = repo
+                .config()
+                .and_then(|c| c.get_string("user.name"))
+                .unwrap_or_default();
+            new_branch_name = rr_branch_naming
+                .generate(
+                    &rr_branch_name::RrBranchNameContext {
+                        user: &user_name,
+                        summary: patch_summary,
+                        message: patch_commit.message().unwrap_or_default(),
+                        ps_id: ps::commit_ps_id(&patch_commit),
+                        patch_index: start_patch_index,
+                        now: SystemTime::now(),
+                    },
+                    |name| {
+                        branch_name_taken(
+                            repo,
+                            name,
+                            ps::commit_ps_id(&patch_commit),
+                            patch_stack_base_commit.id(),
+                        )
+                    },
+                )
+                .map_err(|e| BranchError::GenerateRrBranchNameFailed(e.into()))?;
         } else {
             return Err(BranchError::PatchSeriesRequireBranchName);
         }
     } else if range_patch_branches.len() == 1 {
         new_branch_name = range_patch_branches.first().unwrap().to_string();
-        if new_branch_name.starts_with("ps/rr/") && end_patch_index.is_some() {
+        if rr_branch_naming.is_rr_branch_name(&new_branch_name) && end_patch_index.is_some() {
             return Err(BranchError::PatchSeriesRequireBranchName);
         }
     } else {
@@ -247,6 +347,11 @@ pub fn branch(
         ));
     }
 
+    let original_branch_oid = repo
+        .find_branch(new_branch_name.as_str(), git2::BranchType::Local)
+        .ok()
+        .and_then(|b| b.get().target());
+
     // create branch on top of the patch stack base
     let branch = repo
         .branch(new_branch_name.as_str(), &patch_stack_base_commit, true)
@@ -257,34 +362,151 @@ pub fn branch(
         .name()
         .ok_or(BranchError::RrBranchNameNotUtf8)?;
 
-    let start_patch_oid = patches_vec.get(start_patch_index).unwrap().oid;
+    // the earlier patches the range depends on, directly or indirectly
+    let range_prerequisites = || {
+        let patch_oids: Vec<git2::Oid> = patches_vec.iter().map(|p| p.oid).collect();
+        dependencies::range_prerequisites(
+            repo,
+            patch_stack_base_commit.id(),
+            &patch_oids,
+            start_patch_index,
+            end_patch_index,
+        )
+        .map_err(|e| BranchError::DependenciesFailed(e.into()))
+    };
 
-    let last_commit_oid_cherry_picked = match end_patch_index {
-        Some(end_index) => {
-            let end_patch_oid = patches_vec.get(end_index).unwrap().oid;
-            cherry_picking::cherry_pick(
+    let include_dependencies = ps_config.branch.include_dependencies;
+    if include_dependencies {
+        for prerequisite_index in range_prerequisites()? {
+            dependencies::cherry_pick_prerequisite(
                 repo,
                 &config,
-                start_patch_oid,
-                Some(end_patch_oid),
+                &ps_config.rewrite,
+                patches_vec[prerequisite_index].oid,
                 branch_ref_name,
-                1,
-                false,
-                true,
             )
+            .map_err(|e| BranchError::DependenciesFailed(e.into()))?;
         }
-        None => cherry_picking::cherry_pick(
-            repo,
-            &config,
-            start_patch_oid,
-            Some(start_patch_oid),
-            branch_ref_name,
-            1,
-            false,
-            true,
-        ),
+    }
+
+    let start_patch_oid = patches_vec.get(start_patch_index).unwrap().oid;
+    let end_patch_oid = patches_vec
+        .get(end_patch_index.unwrap_or(start_patch_index))
+        .unwrap()
+        .oid;
+
+    let last_commit_oid_cherry_picked = match cherry_picking::cherry_pick(
+        repo,
+        &config,
+        &ps_config.rewrite,
+        start_patch_oid,
+        Some(end_patch_oid),
+        branch_ref_name,
+        1,
+        false,
+        true,
+    ) {
+        Err(cherry_picking::CherryPickError::ConflictsExist(src_oid, dst_oid))
+            if ps_config.conflicts.resolve_in_worktree && interrupted_operation.is_some() =>
+        {
+            let target = conflicts::CherryPickTarget {
+                dest_ref_name: branch_ref_name,
+                leaf_oid: end_patch_oid,
+                committer_time_offset: 1,
+                add_missing_patch_ids: false,
+                original_oid: original_branch_oid,
+            };
+            let worktree_path = conflicts::stop_for_resolution(
+                repo,
+                interrupted_operation.unwrap(),
+                &target,
+                &src_oid,
+                &dst_oid,
+            )
+            .map_err(|e| BranchError::StopForConflictResolutionFailed(e.into()))?;
+            Err(BranchError::StoppedForConflictResolution(
+                src_oid,
+                worktree_path,
+            ))
+        }
+        // explain the conflict when it is due to patches missing from the branch
+        Err(cherry_picking::CherryPickError::ConflictsExist(src_oid, dst_oid))
+            if !include_dependencies =>
+        {
+            match range_prerequisites() {
+                Ok(prerequisites) if !prerequisites.is_empty() => {
+                    // don't leave a branch around that can't be completed as is
+                    if original_branch_oid.is_none() {
+                        let _ = repo
+                            .find_branch(new_branch_name.as_str(), git2::BranchType::Local)
+                            .and_then(|mut b| b.delete());
+                    }
+                    Err(BranchError::DependenciesMissing(prerequisites))
+                }
+                _ => Err(BranchError::ConflictsExist(src_oid, dst_oid)),
+            }
+        }
+        result => result.map_err(BranchError::from),
     }?
     .expect("No commits cherry picked, when we expected at least one");
 
     Ok((branch, last_commit_oid_cherry_picked))
 }
+
+/// Whether a local branch, or a remote tracking branch of any remote that isn't associated with
+/// the patch, already has the given name. A remote branch left over from an earlier review of the
+/// same patch is reused, so that the existing review gets updated rather than duplicated.
+fn branch_name_taken(
+    repo: &git2::Repository,
+    branch_name: &str,
+    ps_id: Option<Uuid>,
+    base_oid: git2::Oid,
+) -> bool {
+    if repo
+        .find_branch(branch_name, git2::BranchType::Local)
+        .is_ok()
+    {
+        return true;
+    }
+
+    repo.remotes()
+        .map(|remotes| {
+            remotes.iter().flatten().any(|remote| {
+                match repo.find_branch(
+                    &format!("{}/{}", remote, branch_name),
+                    git2::BranchType::Remote,
+                ) {
+                    Ok(remote_branch) => match remote_branch.get().target() {
+                        Some(head_oid) => !branch_carries_patch(repo, head_oid, base_oid, ps_id),
+                        None => true,
+                    },
+                    Err(_) => false,
+                }
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// Whether any of the commits between the patch stack base & the branch head is the patch
+fn branch_carries_patch(
+    repo: &git2::Repository,
+    head_oid: git2::Oid,
+    base_oid: git2::Oid,
+    ps_id: Option<Uuid>,
+) -> bool {
+    let ps_id = match ps_id {
+        Some(ps_id) => ps_id,
+        None => return false,
+    };
+    let mut revwalk = match repo.revwalk() {
+        Ok(revwalk) => revwalk,
+        Err(_) => return false,
+    };
+    if revwalk.push(head_oid).is_err() || revwalk.hide(base_oid).is_err() {
+        return false;
+    }
+    revwalk
+        .flatten()
+        .filter_map(|oid| repo.find_commit(oid).ok())
+        .any(|commit| ps::commit_ps_id(&commit) == Some(ps_id))
+}
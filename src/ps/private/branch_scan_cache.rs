@@ -0,0 +1,164 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+pub const BRANCH_SCAN_CACHE_NAME: &str = "branch_scan";
+
+#[derive(Debug)]
+pub enum SaveBranchScanCacheError {
+    CreateDir(io::Error),
+    Serialize(serde_json::Error),
+    Write(io::Error),
+}
+
+impl std::fmt::Display for SaveBranchScanCacheError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CreateDir(e) => write!(f, "failed to create cache directory, {}", e),
+            Self::Serialize(e) => write!(f, "failed to serialize cache, {}", e),
+            Self::Write(e) => write!(f, "failed to write cache, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SaveBranchScanCacheError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::CreateDir(e) => Some(e),
+            Self::Serialize(e) => Some(e),
+            Self::Write(e) => Some(e),
+        }
+    }
+}
+
+/// A patch found while scanning a branch
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct CachedPatch {
+    pub commit_oid: String,
+    pub patch_id: uuid::Uuid,
+    pub commit_diff_id: String,
+}
+
+/// The result of scanning the commits between a branch head & the patch stack base
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct CachedBranchScan {
+    pub commit_count: usize,
+    pub patches: Vec<CachedPatch>,
+}
+
+/// Cache of the branch scans done to compute the patch stack state.
+///
+/// Scans are keyed by the oids of the branch head & the patch stack base, see
+/// `branch_scan_key()`, so they are naturally invalidated when either of those refs move. The
+/// commit diff patch ids are keyed by commit oid as they never change for a given commit, which
+/// means they survive the base moving, e.g. after a pull.
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct BranchScanCache {
+    patch_ids: HashMap<String, String>,
+    scans: HashMap<String, CachedBranchScan>,
+}
+
+pub fn branch_scan_key(branch_head_oid: &git2::Oid, base_oid: &git2::Oid) -> String {
+    format!("{}..{}", base_oid, branch_head_oid)
+}
+
+impl BranchScanCache {
+    /// Load the cache from the given path. A missing or unreadable cache is simply empty.
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn scan(&self, key: &str) -> Option<&CachedBranchScan> {
+        self.scans.get(key)
+    }
+
+    pub fn insert_scan(&mut self, key: String, scan: CachedBranchScan) {
+        for patch in scan.patches.iter() {
+            self.patch_ids
+                .insert(patch.commit_oid.clone(), patch.commit_diff_id.clone());
+        }
+        self.scans.insert(key, scan);
+    }
+
+    pub fn patch_ids(&self) -> &HashMap<String, String> {
+        &self.patch_ids
+    }
+
+    /// Drop the scans that weren't used, along with the patch ids of commits no longer part of
+    /// any scan, so the cache only covers branches that still exist
+    pub fn retain_scans(&mut self, used_keys: &HashSet<String>) {
+        self.scans.retain(|key, _| used_keys.contains(key));
+        let used_commits: HashSet<&String> = self
+            .scans
+            .values()
+            .flat_map(|scan| scan.patches.iter().map(|p| &p.commit_oid))
+            .collect();
+        self.patch_ids
+            .retain(|commit_oid, _| used_commits.contains(commit_oid));
+    }
+
+    /// Save the cache to the given path, replacing it atomically so concurrent readers never
+    /// see a partially written cache
+    pub fn save(&self, path: &Path) -> Result<(), SaveBranchScanCacheError> {
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        fs::create_dir_all(dir).map_err(SaveBranchScanCacheError::CreateDir)?;
+        let content = serde_json::to_string(self).map_err(SaveBranchScanCacheError::Serialize)?;
+        let mut file =
+            tempfile::NamedTempFile::new_in(dir).map_err(SaveBranchScanCacheError::Write)?;
+        file.write_all(content.as_bytes())
+            .map_err(SaveBranchScanCacheError::Write)?;
+        file.persist(path)
+            .map_err(|e| SaveBranchScanCacheError::Write(e.error))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn scan_with_commit(commit_oid: &str) -> CachedBranchScan {
+        CachedBranchScan {
+            commit_count: 1,
+            patches: vec![CachedPatch {
+                commit_oid: commit_oid.to_string(),
+                patch_id: uuid::Uuid::new_v4(),
+                commit_diff_id: format!("diff-{}", commit_oid),
+            }],
+        }
+    }
+
+    #[test]
+    fn test_branch_scan_cache_retain_scans_drops_unused_scans_and_patch_ids() {
+        let mut cache = BranchScanCache::default();
+        cache.insert_scan("a".to_string(), scan_with_commit("1"));
+        cache.insert_scan("b".to_string(), scan_with_commit("2"));
+
+        cache.retain_scans(&HashSet::from(["a".to_string()]));
+
+        assert!(cache.scan("a").is_some());
+        assert!(cache.scan("b").is_none());
+        assert_eq!(cache.patch_ids().get("1"), Some(&"diff-1".to_string()));
+        assert_eq!(cache.patch_ids().get("2"), None);
+    }
+
+    #[test]
+    fn test_branch_scan_cache_round_trips_through_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("cache").join("branch_scan.json");
+        let scan = scan_with_commit("1");
+        let mut cache = BranchScanCache::default();
+        cache.insert_scan("a".to_string(), scan.clone());
+        cache.save(&path).unwrap();
+
+        let loaded = BranchScanCache::load(&path);
+        assert_eq!(loaded.scan("a"), Some(&scan));
+        assert_eq!(loaded.patch_ids().get("1"), Some(&"diff-1".to_string()));
+    }
+}
@@ -10,6 +10,30 @@ pub enum CheckoutError {
     FailedToCheckout(utils::ExecuteError),
 }
 
+impl std::fmt::Display for CheckoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::GetPatchStackFailed(e) => write!(f, "get patch stack failed, {}", e),
+            Self::GetPatchListFailed(e) => {
+                write!(f, "get patch stack list of patches failed, {}", e)
+            }
+            Self::PatchIndexNotFound => write!(f, "patch with patch index not found"),
+            Self::FailedToCheckout(e) => write!(f, "checkout failed, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CheckoutError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::GetPatchStackFailed(e) => Some(e),
+            Self::GetPatchListFailed(e) => Some(e),
+            Self::PatchIndexNotFound => None,
+            Self::FailedToCheckout(e) => Some(e),
+        }
+    }
+}
+
 pub fn checkout(repo: &git2::Repository, patch_index: usize) -> Result<(), CheckoutError> {
     // get the sha of the commit referenced by the patch index
     let patch_stack = ps::get_patch_stack(repo).map_err(CheckoutError::GetPatchStackFailed)?;
@@ -1,4 +1,6 @@
 use super::super::super::ps;
+use super::config::PsRewriteConfig;
+use super::events::{self, Event};
 use super::git;
 use std::result::Result;
 
@@ -131,7 +133,8 @@ impl std::error::Error for CherryPickError {
 ///
 /// The given `repo` is the repository that you want to cherry pick the range of commits within.
 /// The `config` is the config used to facilitate commit creation, providing things like the
-/// author, email, etc.
+/// author, email, etc. The `rewrite_config` decides the committer of the new commits & whether
+/// they are signed.
 ///
 /// The `root_oid` specifies the commit to start the ranged cherry picking process from,
 /// inclusively. Meaning this commit WILL be included in the cherry picked commits, as will all its
@@ -145,7 +148,8 @@ impl std::error::Error for CherryPickError {
 /// The `dest_ref_name` specifies the reference (e.g. branch) to cherry pick the range of commits
 /// into.
 ///
-/// The `committer_time_offset` specifies how much to offset the commiter time by in seconds.
+/// The `committer_time_offset` specifies how much to offset the commiter time by in seconds, when
+/// the committer is reset rather than preserved according to the rewrite config.
 ///
 /// The `add_missing_patch_ids` boolean specifies if it should add patch ids to commits missing
 /// them that are involved in the cherry pick.
@@ -155,9 +159,11 @@ impl std::error::Error for CherryPickError {
 ///
 /// It returns an Ok(Option(last_cherry_picked_commit_oid)) result in the case of success and an
 /// error result of GitError in the case of failure.
+#[allow(clippy::too_many_arguments)]
 pub fn cherry_pick(
     repo: &'_ git2::Repository,
     config: &git2::Config,
+    rewrite_config: &PsRewriteConfig,
     root_oid: git2::Oid,
     leaf_oid: Option<git2::Oid>,
     dest_ref_name: &str,
@@ -174,6 +180,7 @@ pub fn cherry_pick(
                 cherry_pick_no_working_copy_range(
                     repo,
                     config,
+                    rewrite_config,
                     root_commit_parent_commit_oid,
                     leaf_oid,
                     dest_ref_name,
@@ -184,6 +191,7 @@ pub fn cherry_pick(
                 cherry_pick_no_working_copy_range(
                     repo,
                     config,
+                    rewrite_config,
                     root_oid,
                     leaf_oid,
                     dest_ref_name,
@@ -195,6 +203,7 @@ pub fn cherry_pick(
         None => Some(cherry_pick_no_working_copy(
             repo,
             config,
+            rewrite_config,
             root_oid,
             dest_ref_name,
             committer_time_offset,
@@ -223,9 +232,11 @@ pub fn cherry_pick(
 ///
 /// It returns an Ok(Option(last_cherry_picked_commit_oid)) result in the case of success and an
 /// error result of GitError in the case of failure.
+#[allow(clippy::too_many_arguments)]
 fn cherry_pick_no_working_copy_range(
     repo: &'_ git2::Repository,
     config: &git2::Config,
+    rewrite_config: &PsRewriteConfig,
     root_oid: git2::Oid,
     leaf_oid: git2::Oid,
     dest_ref_name: &str,
@@ -246,6 +257,7 @@ fn cherry_pick_no_working_copy_range(
         last_cherry_picked_oid = Some(cherry_pick_no_working_copy(
             repo,
             config,
+            rewrite_config,
             rev,
             dest_ref_name,
             committer_time_offset,
@@ -267,6 +279,7 @@ fn cherry_pick_no_working_copy_range(
 fn cherry_pick_no_working_copy<'a>(
     repo: &'a git2::Repository,
     config: &'a git2::Config,
+    rewrite_config: &PsRewriteConfig,
     oid: git2::Oid,
     dest_ref_name: &str,
     committer_time_offset: i64,
@@ -305,24 +318,47 @@ fn cherry_pick_no_working_copy<'a>(
     let tree_oid = index.write_tree_to(repo)?;
     let tree = repo.find_tree(tree_oid)?;
 
+    commit_cherry_picked_tree(
+        repo,
+        config,
+        rewrite_config,
+        &commit,
+        &tree,
+        &destination_commit,
+        dest_ref_name,
+        committer_time_offset,
+        add_missing_patch_id,
+    )
+}
+
+/// Commit the tree resulting from cherry picking the commit onto the destination commit, as the
+/// new commit of the dest_ref_name, keeping the author & message of the cherry picked commit & the
+/// committer according to the configured committer policy
+#[allow(clippy::too_many_arguments)]
+pub fn commit_cherry_picked_tree(
+    repo: &git2::Repository,
+    config: &git2::Config,
+    rewrite_config: &PsRewriteConfig,
+    commit: &git2::Commit,
+    tree: &git2::Tree,
+    destination_commit: &git2::Commit,
+    dest_ref_name: &str,
+    committer_time_offset: i64,
+    add_missing_patch_id: bool,
+) -> Result<git2::Oid, CherryPickError> {
     let author = commit.author();
-    let committer = repo.signature().unwrap();
+    let new_committer = git::rewritten_committer(
+        repo,
+        rewrite_config.committer,
+        commit,
+        committer_time_offset,
+    )
+    .map_err(|e| CherryPickError::UnhandledError(e.into()))?;
 
     let message = commit.message().unwrap();
 
-    let new_time = git2::Time::new(
-        committer.when().seconds() + committer_time_offset,
-        committer.when().offset_minutes(),
-    );
-    let new_committer = git2::Signature::new(
-        committer.name().unwrap(),
-        committer.email().unwrap(),
-        &new_time,
-    )
-    .unwrap();
-
     let possibly_amended_mesesage = match add_missing_patch_id {
-        true => match ps::commit_ps_id(&commit) {
+        true => match ps::commit_ps_id(commit) {
             Some(_) => message.to_string(),
             None => {
                 let patch_id: uuid::Uuid = uuid::Uuid::new_v4();
@@ -336,14 +372,21 @@ fn cherry_pick_no_working_copy<'a>(
     let new_commit_oid = git::create_commit(
         repo,
         config,
+        rewrite_config.sign,
         dest_ref_name,
         &author,
         &new_committer,
         &possibly_amended_mesesage,
-        &tree,
-        &[&destination_commit],
+        tree,
+        &[destination_commit],
     )
     .unwrap();
 
+    events::emit(Event::PatchReplayed {
+        oid: commit.id(),
+        new_oid: new_commit_oid,
+        summary: commit.summary().unwrap_or_default().to_string(),
+    });
+
     Ok(new_commit_oid)
 }
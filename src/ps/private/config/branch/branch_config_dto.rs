@@ -6,6 +6,7 @@ use std::option::Option;
 pub struct BranchConfigDto {
     pub verify_isolation: Option<bool>,
     pub push_to_remote: Option<bool>,
+    pub include_dependencies: Option<bool>,
 }
 
 impl utils::Mergable for BranchConfigDto {
@@ -14,6 +15,7 @@ impl utils::Mergable for BranchConfigDto {
         BranchConfigDto {
             verify_isolation: b.verify_isolation.or(self.verify_isolation),
             push_to_remote: b.push_to_remote.or(self.push_to_remote),
+            include_dependencies: b.include_dependencies.or(self.include_dependencies),
         }
     }
 }
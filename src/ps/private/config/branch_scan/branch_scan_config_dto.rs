@@ -0,0 +1,21 @@
+use super::super::super::utils;
+use serde::Deserialize;
+use std::option::Option;
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct BranchScanConfigDto {
+    pub review_branches_only: Option<bool>,
+    pub cache: Option<bool>,
+    pub jobs: Option<usize>,
+}
+
+impl utils::Mergable for BranchScanConfigDto {
+    /// Merge the provided b with self overriding with any present values
+    fn merge(&self, b: &Self) -> Self {
+        BranchScanConfigDto {
+            review_branches_only: b.review_branches_only.or(self.review_branches_only),
+            cache: b.cache.or(self.cache),
+            jobs: b.jobs.or(self.jobs),
+        }
+    }
+}
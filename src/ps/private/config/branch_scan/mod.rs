@@ -0,0 +1,3 @@
+mod branch_scan_config_dto;
+
+pub use branch_scan_config_dto::*;
@@ -1,12 +1,31 @@
 use super::super::utils;
 use super::branch::BranchConfigDto;
+use super::branch_scan::BranchScanConfigDto;
+use super::conflicts::ConflictsConfigDto;
 use super::fetch::FetchConfigDto;
+use super::hooks::HooksConfigDto;
 use super::integrate::IntegrateConfigDto;
 use super::list::ListConfigDto;
+use super::profile::{ProfileConfigDto, ProfilesConfigDto};
 use super::pull::PullConfigDto;
 use super::request_review::RequestReviewConfigDto;
+use super::rewrite::RewriteConfigDto;
 use serde::Deserialize;
 
+/// The names of the config sections, i.e. the tables of `ConfigDto`
+pub const CONFIG_SECTIONS: &[&str] = &[
+    "request_review",
+    "pull",
+    "integrate",
+    "fetch",
+    "list",
+    "branch",
+    "hooks",
+    "branch_scan",
+    "conflicts",
+    "rewrite",
+];
+
 #[derive(Debug, Deserialize, Default)]
 pub struct ConfigDto {
     pub request_review: Option<RequestReviewConfigDto>,
@@ -15,6 +34,11 @@ pub struct ConfigDto {
     pub fetch: Option<FetchConfigDto>,
     pub list: Option<ListConfigDto>,
     pub branch: Option<BranchConfigDto>,
+    pub hooks: Option<HooksConfigDto>,
+    pub branch_scan: Option<BranchScanConfigDto>,
+    pub conflicts: Option<ConflictsConfigDto>,
+    pub rewrite: Option<RewriteConfigDto>,
+    pub profile: Option<ProfilesConfigDto>,
 }
 
 impl utils::Mergable for ConfigDto {
@@ -26,6 +50,29 @@ impl utils::Mergable for ConfigDto {
             fetch: utils::merge_option(&self.fetch, &b.fetch),
             list: utils::merge_option(&self.list, &b.list),
             branch: utils::merge_option(&self.branch, &b.branch),
+            hooks: utils::merge_option(&self.hooks, &b.hooks),
+            branch_scan: utils::merge_option(&self.branch_scan, &b.branch_scan),
+            conflicts: utils::merge_option(&self.conflicts, &b.conflicts),
+            rewrite: utils::merge_option(&self.rewrite, &b.rewrite),
+            profile: utils::merge_option(&self.profile, &b.profile),
+        }
+    }
+}
+
+impl From<&ProfileConfigDto> for ConfigDto {
+    fn from(profile: &ProfileConfigDto) -> Self {
+        ConfigDto {
+            request_review: profile.request_review.clone(),
+            pull: profile.pull.clone(),
+            integrate: profile.integrate.clone(),
+            fetch: profile.fetch.clone(),
+            list: profile.list.clone(),
+            branch: profile.branch.clone(),
+            hooks: profile.hooks.clone(),
+            branch_scan: profile.branch_scan.clone(),
+            conflicts: profile.conflicts.clone(),
+            rewrite: profile.rewrite.clone(),
+            profile: None,
         }
     }
 }
@@ -0,0 +1,43 @@
+use super::super::paths;
+use std::path::PathBuf;
+
+/// The levels configuration is read from, see `CONFIG_LEVELS` for their precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ConfigLevel {
+    /// `~/.config/git-ps/config.toml`, personal across all repositories
+    User,
+    /// `git-ps/config.toml` in the repository's gitdir, personal to this repository
+    Personal,
+    /// `.git-ps/config.toml` in the repository's working tree, shared with the team
+    Communal,
+}
+
+/// The config levels in order of increasing precedence, later levels override earlier ones
+pub const CONFIG_LEVELS: [ConfigLevel; 3] = [
+    ConfigLevel::User,
+    ConfigLevel::Personal,
+    ConfigLevel::Communal,
+];
+
+impl std::fmt::Display for ConfigLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::User => write!(f, "user"),
+            Self::Personal => write!(f, "personal"),
+            Self::Communal => write!(f, "communal"),
+        }
+    }
+}
+
+/// Get the path of the config file of the given level
+pub fn config_level_path(
+    level: ConfigLevel,
+    repo_root: &str,
+    repo_gitdir: &str,
+) -> Result<PathBuf, paths::UserLevelConfigPathError> {
+    match level {
+        ConfigLevel::User => paths::user_level_config_path(),
+        ConfigLevel::Personal => Ok(paths::personal_repository_level_config_path(repo_gitdir)),
+        ConfigLevel::Communal => Ok(paths::communal_repository_level_config_path(repo_root)),
+    }
+}
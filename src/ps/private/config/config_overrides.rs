@@ -0,0 +1,314 @@
+use super::config_dto::{ConfigDto, CONFIG_SECTIONS};
+use super::config_values::{
+    set_document_value, validate_config_content, SetConfigValueError, MERGED_TABLES,
+};
+use std::sync::Mutex;
+
+/// Prefix of the environment variables that override config values
+pub const ENV_OVERRIDE_PREFIX: &str = "GPS_";
+
+lazy_static! {
+    static ref COMMAND_LINE_OVERRIDES: Mutex<Vec<ConfigOverride>> = Mutex::new(vec![]);
+    // the known environment overrides & the ignored ones, whose keys aren't known config keys
+    static ref ENVIRONMENT_OVERRIDES: (Vec<ConfigOverride>, Vec<ConfigOverride>) =
+        partition_unknown_keys(env_overrides(std::env::vars()));
+}
+
+/// Where a config override comes from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigOverrideSource {
+    /// The environment variable with the given name
+    Environment(String),
+    /// A `-c section.key=value` option
+    CommandLine,
+}
+
+impl std::fmt::Display for ConfigOverrideSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Environment(name) => write!(f, "env:{}", name),
+            Self::CommandLine => write!(f, "command line"),
+        }
+    }
+}
+
+/// A config value overridden outside of the config files, taking precedence over all of them
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigOverride {
+    pub key: String,
+    pub value: String,
+    pub source: ConfigOverrideSource,
+}
+
+#[derive(Debug)]
+pub enum ConfigOverrideError {
+    Malformed(String),
+    KeyInvalid(ConfigOverrideSource, Box<SetConfigValueError>),
+    ValueInvalid(toml::de::Error),
+    KeyUnknown(ConfigOverrideSource, String),
+}
+
+impl std::fmt::Display for ConfigOverrideError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Malformed(raw) => write!(
+                f,
+                "config override {} malformed, expected section.key=value",
+                raw
+            ),
+            Self::KeyInvalid(source, e) => write!(f, "config override from {}, {}", source, e),
+            Self::ValueInvalid(e) => write!(f, "config override value invalid, {}", e),
+            Self::KeyUnknown(source, key) => {
+                write!(f, "config override from {}, unknown key {}", source, key)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfigOverrideError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Malformed(_) => None,
+            Self::KeyInvalid(_, e) => Some(e.as_ref()),
+            Self::ValueInvalid(e) => Some(e),
+            Self::KeyUnknown(_, _) => None,
+        }
+    }
+}
+
+/// Parse the `section.key=value` overrides given on the command line
+pub fn command_line_overrides(raw: &[String]) -> Result<Vec<ConfigOverride>, ConfigOverrideError> {
+    raw.iter()
+        .map(|raw_override| match raw_override.split_once('=') {
+            Some((key, value)) if key.contains('.') => Ok(ConfigOverride {
+                key: key.trim().to_string(),
+                value: value.to_string(),
+                source: ConfigOverrideSource::CommandLine,
+            }),
+            _ => Err(ConfigOverrideError::Malformed(raw_override.clone())),
+        })
+        .collect()
+}
+
+/// Set the overrides given on the command line so every subsequent `get_config()` applies them
+pub fn set_command_line_overrides(raw: &[String]) -> Result<(), ConfigOverrideError> {
+    let overrides = command_line_overrides(raw)?;
+    *COMMAND_LINE_OVERRIDES.lock().unwrap() = overrides;
+    Ok(())
+}
+
+/// Map the `GPS_<SECTION>_<KEY>` environment variables to config overrides, e.g.
+/// `GPS_BRANCH_SCAN_JOBS` to `branch_scan.jobs` or `GPS_HOOKS_TIMEOUTS_PRE_PUSH` to
+/// `hooks.timeouts.pre_push`. Variables that don't name a config section are ignored.
+pub fn env_overrides<I: IntoIterator<Item = (String, String)>>(vars: I) -> Vec<ConfigOverride> {
+    // the longest prefixes are matched first so branch_scan isn't taken for branch
+    let mut prefixes: Vec<String> = MERGED_TABLES
+        .iter()
+        .chain(CONFIG_SECTIONS.iter())
+        .map(|table| table.to_string())
+        .collect();
+    prefixes.sort_by_key(|p| std::cmp::Reverse(p.len()));
+
+    let mut overrides: Vec<ConfigOverride> = vars
+        .into_iter()
+        .filter_map(|(name, value)| {
+            let rest = name.strip_prefix(ENV_OVERRIDE_PREFIX)?;
+            let rest_lower = rest.to_lowercase();
+            let table = prefixes.iter().find(|p| {
+                rest_lower
+                    .strip_prefix(&p.replace('.', "_"))
+                    .is_some_and(|key| key.starts_with('_') && key.len() > 1)
+            })?;
+            let key = &rest_lower[table.len() + 1..];
+            Some(ConfigOverride {
+                key: format!("{}.{}", table, key),
+                value,
+                source: ConfigOverrideSource::Environment(name),
+            })
+        })
+        .collect();
+    // the environment is unordered, so the overrides are sorted to be deterministic
+    overrides.sort_by(|a, b| a.key.cmp(&b.key));
+    overrides
+}
+
+/// Split the overrides into the ones setting known config keys & the ones that don't. The
+/// environment may hold unrelated `GPS_` variables, e.g. in CI, so rather than failing every
+/// command, environment overrides of unknown keys are skipped with a warning.
+pub fn partition_unknown_keys(
+    overrides: Vec<ConfigOverride>,
+) -> (Vec<ConfigOverride>, Vec<ConfigOverride>) {
+    overrides.into_iter().partition(|config_override| {
+        // overrides that fail for other reasons, e.g. an invalid value, are kept to be reported
+        match overrides_document(std::slice::from_ref(config_override)) {
+            Ok(document) => validate_config_content(&document.to_string())
+                .map_or(true, |unknown_keys| unknown_keys.is_empty()),
+            Err(_) => true,
+        }
+    })
+}
+
+/// The overrides in effect in order of increasing precedence, the environment variables followed
+/// by the command line options
+pub fn config_overrides() -> Vec<ConfigOverride> {
+    let mut overrides = ENVIRONMENT_OVERRIDES.0.clone();
+    overrides.extend(COMMAND_LINE_OVERRIDES.lock().unwrap().iter().cloned());
+    overrides
+}
+
+/// The environment variable overrides that are ignored because their keys aren't known config
+/// keys, e.g. because of a typo, so the caller can warn about them
+pub fn ignored_environment_overrides() -> Vec<ConfigOverride> {
+    ENVIRONMENT_OVERRIDES.1.clone()
+}
+
+fn overrides_document(
+    overrides: &[ConfigOverride],
+) -> Result<toml_edit::DocumentMut, ConfigOverrideError> {
+    let mut document = toml_edit::DocumentMut::new();
+    for config_override in overrides {
+        set_document_value(&mut document, &config_override.key, &config_override.value).map_err(
+            |e| ConfigOverrideError::KeyInvalid(config_override.source.clone(), e.into()),
+        )?;
+    }
+    Ok(document)
+}
+
+/// Build the config layer the overrides make up, later overrides taking precedence over earlier
+/// ones. Unknown keys are rejected as an override is always set deliberately.
+pub fn overrides_config_dto(
+    overrides: &[ConfigOverride],
+) -> Result<ConfigDto, ConfigOverrideError> {
+    let content = overrides_document(overrides)?.to_string();
+    let unknown_keys =
+        validate_config_content(&content).map_err(ConfigOverrideError::ValueInvalid)?;
+    if let Some(unknown_key) = unknown_keys.into_iter().next() {
+        let source = overrides
+            .iter()
+            .rev()
+            .find(|o| o.key == unknown_key || unknown_key.starts_with(&format!("{}.", o.key)))
+            .map(|o| o.source.clone())
+            .unwrap_or(ConfigOverrideSource::CommandLine);
+        return Err(ConfigOverrideError::KeyUnknown(source, unknown_key));
+    }
+
+    toml::from_str(&content).map_err(ConfigOverrideError::ValueInvalid)
+}
+
+/// Build the table of overridden values, e.g. to list them along with the configured ones
+pub fn overrides_config_table(
+    overrides: &[ConfigOverride],
+) -> Result<toml::Table, ConfigOverrideError> {
+    overrides_document(overrides)?
+        .to_string()
+        .parse::<toml::Table>()
+        .map_err(ConfigOverrideError::ValueInvalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::rewrite::{CommitterPolicy, SignPolicy};
+    use super::*;
+    use crate::ps::private::utils::Mergable;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(n, v)| (n.to_string(), v.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_env_overrides_maps_sections_and_keys() {
+        let overrides = env_overrides(vars(&[
+            ("GPS_BRANCH_SCAN_JOBS", "4"),
+            ("GPS_INTEGRATE_PROMPT_FOR_REASSURANCE", "false"),
+            ("GPS_HOOKS_TIMEOUTS_PRE_PUSH", "10"),
+            ("GPS_DEVICE", "/dev/ttyUSB0"),
+            ("GPS_LIST_", "true"),
+            ("HOME", "/home/me"),
+        ]));
+
+        let keys: Vec<&str> = overrides.iter().map(|o| o.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "branch_scan.jobs",
+                "hooks.timeouts.pre_push",
+                "integrate.prompt_for_reassurance"
+            ]
+        );
+        assert_eq!(
+            overrides[0].source,
+            ConfigOverrideSource::Environment("GPS_BRANCH_SCAN_JOBS".to_string())
+        );
+    }
+
+    #[test]
+    fn test_command_line_overrides_rejects_malformed() {
+        let overrides =
+            command_line_overrides(&["pull.show_list_post_pull=true".to_string()]).unwrap();
+        assert_eq!(overrides[0].key, "pull.show_list_post_pull");
+        assert_eq!(overrides[0].value, "true");
+
+        assert!(command_line_overrides(&["pull=true".to_string()]).is_err());
+        assert!(command_line_overrides(&["pull.show_list_post_pull".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_overrides_config_dto_takes_precedence_over_files() {
+        let file_config: ConfigDto =
+            toml::from_str("[list]\nreverse_order = false\nextra_patch_info_length = 5\n").unwrap();
+        let mut overrides = env_overrides(vars(&[("GPS_LIST_REVERSE_ORDER", "false")]));
+        overrides.extend(command_line_overrides(&["list.reverse_order=true".to_string()]).unwrap());
+
+        let config = file_config.merge(&overrides_config_dto(&overrides).unwrap());
+
+        let list = config.list.unwrap();
+        assert_eq!(list.reverse_order, Some(true));
+        assert_eq!(list.extra_patch_info_length, Some(5));
+    }
+
+    #[test]
+    fn test_partition_unknown_keys_splits_off_unknown_environment_keys() {
+        let (known, unknown) = partition_unknown_keys(env_overrides(vars(&[
+            ("GPS_LIST_REVERSE_ORDER", "true"),
+            ("GPS_LIST_REVERSE_ORDR", "true"),
+            ("GPS_BRANCH_SCAN_JOBS", "not a number"),
+        ])));
+
+        let known_keys: Vec<&str> = known.iter().map(|o| o.key.as_str()).collect();
+        assert_eq!(known_keys, vec!["branch_scan.jobs", "list.reverse_order"]);
+        assert_eq!(unknown.len(), 1);
+        assert_eq!(unknown[0].key, "list.reverse_ordr");
+    }
+
+    #[test]
+    fn test_overrides_config_dto_rejects_unknown_keys() {
+        let overrides = env_overrides(vars(&[("GPS_LIST_REVERSE_ORDR", "true")]));
+        match overrides_config_dto(&overrides) {
+            Err(ConfigOverrideError::KeyUnknown(source, key)) => {
+                assert_eq!(
+                    source,
+                    ConfigOverrideSource::Environment("GPS_LIST_REVERSE_ORDR".to_string())
+                );
+                assert_eq!(key, "list.reverse_ordr");
+            }
+            other => panic!("expected unknown key, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_overrides_config_dto_sets_rewrite_policies() {
+        let mut overrides = env_overrides(vars(&[("GPS_REWRITE_SIGN", "always")]));
+        overrides
+            .extend(command_line_overrides(&["rewrite.committer=preserve".to_string()]).unwrap());
+
+        let rewrite = overrides_config_dto(&overrides).unwrap().rewrite.unwrap();
+        assert_eq!(rewrite.sign, Some(SignPolicy::Always));
+        assert_eq!(rewrite.committer, Some(CommitterPolicy::Preserve));
+
+        let overrides = command_line_overrides(&["rewrite.committer=keep".to_string()]).unwrap();
+        assert!(overrides_config_dto(&overrides).is_err());
+    }
+}
@@ -0,0 +1,290 @@
+use super::config_dto::ConfigDto;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Tables whose keys are merged individually across the levels rather than the table being
+/// overridden as a whole
+pub const MERGED_TABLES: &[&str] = &["hooks.timeouts"];
+
+#[derive(Debug)]
+pub enum ReadConfigTableError {
+    ReadFailed(io::Error),
+    ParseFailed(toml::de::Error),
+}
+
+impl std::fmt::Display for ReadConfigTableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ReadFailed(e) => write!(f, "failed to read the patch stack config, {}", e),
+            Self::ParseFailed(e) => write!(f, "failed to parse the patch stack config, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ReadConfigTableError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ReadFailed(e) => Some(e),
+            Self::ParseFailed(e) => Some(e),
+        }
+    }
+}
+
+/// Read the config file at the given path as a plain TOML table, None if it doesn't exist
+pub fn read_config_table(path: &Path) -> Result<Option<toml::Table>, ReadConfigTableError> {
+    match fs::read_to_string(path) {
+        Ok(content) => content
+            .parse::<toml::Table>()
+            .map(Some)
+            .map_err(ReadConfigTableError::ParseFailed),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(ReadConfigTableError::ReadFailed(e)),
+    }
+}
+
+/// Flatten the config table into the keys a level overrides the levels below it at, e.g.
+/// `list.reverse_order`, `hooks.timeouts.pre_push` or `profile.origin/release-*.integrate.keep_branch`,
+/// along with their values
+pub fn config_entries(table: &toml::Table) -> Vec<(String, toml::Value)> {
+    let mut entries: Vec<(String, toml::Value)> = vec![];
+    for (section_name, section) in table.iter() {
+        match section.as_table() {
+            // each profile is a config of its own, keyed by the upstream branch pattern
+            Some(profiles) if section_name == "profile" => {
+                for (pattern, profile) in profiles.iter() {
+                    match profile.as_table() {
+                        Some(profile_table) => {
+                            for (key, value) in config_entries(profile_table) {
+                                entries.push((format!("profile.{}.{}", pattern, key), value));
+                            }
+                        }
+                        None => entries.push((format!("profile.{}", pattern), profile.clone())),
+                    }
+                }
+            }
+            Some(section_table) => {
+                for (key, value) in section_table.iter() {
+                    let full_key = format!("{}.{}", section_name, key);
+                    match value.as_table() {
+                        Some(merged_table) if MERGED_TABLES.contains(&full_key.as_str()) => {
+                            for (sub_key, sub_value) in merged_table.iter() {
+                                entries
+                                    .push((format!("{}.{}", full_key, sub_key), sub_value.clone()));
+                            }
+                        }
+                        _ => entries.push((full_key, value.clone())),
+                    }
+                }
+            }
+            None => entries.push((section_name.clone(), section.clone())),
+        }
+    }
+    entries
+}
+
+/// Look up the value at the given dotted key, e.g. `list.patch_sha.color`
+pub fn config_value<'a>(table: &'a toml::Table, key: &str) -> Option<&'a toml::Value> {
+    let mut parts = key.split('.');
+    let mut value = table.get(parts.next()?)?;
+    for part in parts {
+        value = value.as_table()?.get(part)?;
+    }
+    Some(value)
+}
+
+/// Check the config content deserializes into the config, returning the keys that aren't part
+/// of the config, e.g. due to a typo, as those are otherwise silently ignored
+pub fn validate_config_content(content: &str) -> Result<Vec<String>, toml::de::Error> {
+    let mut unknown_keys: Vec<String> = vec![];
+    let deserializer = toml::Deserializer::new(content);
+    let _config: ConfigDto = serde_ignored::deserialize(deserializer, |path| {
+        // the optional sections & values show up as ? components of the path
+        unknown_keys.push(path.to_string().replace("?.", ""))
+    })?;
+    Ok(unknown_keys)
+}
+
+#[derive(Debug)]
+pub enum SetConfigValueError {
+    ReadFailed(io::Error),
+    ParseFailed(toml_edit::TomlError),
+    KeyInvalid(String),
+    ValueInvalid(toml::de::Error),
+    KeyUnknown(String),
+    CreateDirFailed(io::Error),
+    WriteFailed(io::Error),
+}
+
+impl std::fmt::Display for SetConfigValueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ReadFailed(e) => write!(f, "failed to read the patch stack config, {}", e),
+            Self::ParseFailed(e) => write!(f, "failed to parse the patch stack config, {}", e),
+            Self::KeyInvalid(key) => write!(f, "invalid key {}", key),
+            Self::ValueInvalid(e) => write!(f, "invalid value, {}", e),
+            Self::KeyUnknown(key) => write!(f, "unknown key {}", key),
+            Self::CreateDirFailed(e) => write!(f, "failed to create config directory, {}", e),
+            Self::WriteFailed(e) => write!(f, "failed to write the patch stack config, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SetConfigValueError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ReadFailed(e) => Some(e),
+            Self::ParseFailed(e) => Some(e),
+            Self::KeyInvalid(_) => None,
+            Self::ValueInvalid(e) => Some(e),
+            Self::KeyUnknown(_) => None,
+            Self::CreateDirFailed(e) => Some(e),
+            Self::WriteFailed(e) => Some(e),
+        }
+    }
+}
+
+/// Parse a value given on the command line or in the environment as a TOML value, e.g. `true`
+/// or `{ color = "Red" }`, falling back to a plain string
+fn parse_config_value(value: &str) -> toml_edit::Value {
+    value
+        .parse::<toml_edit::Value>()
+        .unwrap_or_else(|_| toml_edit::Value::from(value))
+}
+
+/// Set the dotted key to the value in the document, creating any missing tables along the way
+pub fn set_document_value(
+    document: &mut toml_edit::DocumentMut,
+    key: &str,
+    value: &str,
+) -> Result<(), SetConfigValueError> {
+    let parts: Vec<&str> = key.split('.').collect();
+    if parts.len() < 2 || parts.iter().any(|part| part.is_empty()) {
+        return Err(SetConfigValueError::KeyInvalid(key.to_string()));
+    }
+    let (value_key, table_keys) = parts.split_last().unwrap();
+
+    let mut table: &mut dyn toml_edit::TableLike = document.as_table_mut();
+    for table_key in table_keys {
+        table = table
+            .entry(table_key)
+            .or_insert_with(|| {
+                // implicit so intermediate tables don't get an empty header of their own
+                let mut new_table = toml_edit::Table::new();
+                new_table.set_implicit(true);
+                toml_edit::Item::Table(new_table)
+            })
+            .as_table_like_mut()
+            .ok_or_else(|| SetConfigValueError::KeyInvalid(key.to_string()))?;
+    }
+    table.insert(value_key, toml_edit::value(parse_config_value(value)));
+    Ok(())
+}
+
+/// Set the dotted key to the value in the config file at the given path, keeping the rest of
+/// the file, comments included, intact. The value is parsed as a TOML value, e.g. `true` or
+/// `{ color = "Red" }`, falling back to a plain string. The file is only written if the result
+/// is a valid config.
+pub fn set_config_value(path: &Path, key: &str, value: &str) -> Result<(), SetConfigValueError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(SetConfigValueError::ReadFailed(e)),
+    };
+    let mut document = content
+        .parse::<toml_edit::DocumentMut>()
+        .map_err(SetConfigValueError::ParseFailed)?;
+
+    set_document_value(&mut document, key, value)?;
+
+    let new_content = document.to_string();
+    let unknown_keys =
+        validate_config_content(&new_content).map_err(SetConfigValueError::ValueInvalid)?;
+    if let Some(unknown_key) = unknown_keys.into_iter().next() {
+        return Err(SetConfigValueError::KeyUnknown(unknown_key));
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(SetConfigValueError::CreateDirFailed)?;
+    }
+    fs::write(path, new_content).map_err(SetConfigValueError::WriteFailed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_config_entries_flattens_to_override_keys() {
+        let table: toml::Table = r#"
+[list]
+reverse_order = true
+patch_sha = { color = "Red" }
+
+[hooks.timeouts]
+pre_push = 10
+
+[profile."origin/release-*".integrate]
+keep_branch = true
+"#
+        .parse()
+        .unwrap();
+
+        let keys: Vec<String> = config_entries(&table).into_iter().map(|(k, _)| k).collect();
+        assert_eq!(
+            keys,
+            vec![
+                "hooks.timeouts.pre_push",
+                "list.patch_sha",
+                "list.reverse_order",
+                "profile.origin/release-*.integrate.keep_branch"
+            ]
+        );
+        assert_eq!(
+            config_value(&table, "list.patch_sha.color"),
+            Some(&toml::Value::String("Red".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_validate_config_content_reports_unknown_keys_and_bad_colors() {
+        let unknown_keys =
+            validate_config_content("[list]\nreverse_ordr = true\n[lsit]\nx = 1\n").unwrap();
+        assert_eq!(unknown_keys, vec!["list.reverse_ordr", "lsit"]);
+
+        assert!(
+            validate_config_content("[list]\npatch_sha = { color = \"Purpleish\" }\n").is_err()
+        );
+    }
+
+    #[test]
+    fn test_set_config_value_keeps_comments_and_rejects_unknown_keys() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("git-ps").join("config.toml");
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "# my settings\n[list]\nreverse_order = true\n").unwrap();
+
+        set_config_value(&path, "list.extra_patch_info_length", "20").unwrap();
+        set_config_value(
+            &path,
+            "request_review.branch_name_template",
+            "{user}/{slug}",
+        )
+        .unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# my settings\n"));
+        assert!(content.contains("extra_patch_info_length = 20"));
+        assert!(content.contains(r#"branch_name_template = "{user}/{slug}""#));
+
+        assert!(matches!(
+            set_config_value(&path, "list.revers_order", "false"),
+            Err(SetConfigValueError::KeyUnknown(_))
+        ));
+        assert!(matches!(
+            set_config_value(&path, "list.reverse_order", "maybe"),
+            Err(SetConfigValueError::ValueInvalid(_))
+        ));
+        assert_eq!(fs::read_to_string(&path).unwrap(), content);
+    }
+}
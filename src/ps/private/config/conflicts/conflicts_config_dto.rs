@@ -0,0 +1,17 @@
+use super::super::super::utils;
+use serde::Deserialize;
+use std::option::Option;
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ConflictsConfigDto {
+    pub resolve_in_worktree: Option<bool>,
+}
+
+impl utils::Mergable for ConflictsConfigDto {
+    /// Merge the provided b with self overriding with any present values
+    fn merge(&self, b: &Self) -> Self {
+        ConflictsConfigDto {
+            resolve_in_worktree: b.resolve_in_worktree.or(self.resolve_in_worktree),
+        }
+    }
+}
@@ -0,0 +1,3 @@
+mod conflicts_config_dto;
+
+pub use conflicts_config_dto::*;
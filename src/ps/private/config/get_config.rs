@@ -1,21 +1,32 @@
+use super::super::super::super::ps;
 use super::super::paths;
 use super::super::utils::*;
+use super::branch::BranchConfigDto;
+use super::branch_scan::BranchScanConfigDto;
 use super::config_dto::ConfigDto;
+use super::config_overrides::{config_overrides, overrides_config_dto, ConfigOverrideError};
+use super::conflicts::ConflictsConfigDto;
 use super::fetch::FetchConfigDto;
-use super::integrate::IntegrateConfigDto;
+use super::hooks::{HookLocation, HooksConfigDto};
+use super::integrate::{IntegrateConfigDto, IntegrateStrategy};
 use super::list::{ColorWithAlternate, ListConfigDto};
+use super::profile::matching_profiles;
 use super::ps_config::{
-    PsConfig, PsFetchConfig, PsIntegrateConfig, PsListConfig, PsPullConfig, PsRequestReviewConfig,
+    PsBranchConfig, PsBranchScanConfig, PsConfig, PsConflictsConfig, PsFetchConfig, PsHooksConfig,
+    PsIntegrateConfig, PsListConfig, PsPullConfig, PsRequestReviewConfig, PsRewriteConfig,
 };
-use super::pull::PullConfigDto;
+use super::pull::{LandedBranchesCleanup, PullConfigDto};
 use super::read_config_or_default::*;
 use super::request_review::RequestReviewConfigDto;
+use super::rewrite::{CommitterPolicy, RewriteConfigDto, SignPolicy};
 use ansi_term::Color;
+use std::collections::HashMap;
 
 #[derive(Debug)]
 pub enum GetConfigError {
     FailedToGetUserLevelConfigPath(paths::UserLevelConfigPathError),
     ReadConfigFailed(ReadConfigDtoOrDefaultError),
+    ConfigOverrideInvalid(ConfigOverrideError),
 }
 
 impl std::fmt::Display for GetConfigError {
@@ -25,6 +36,7 @@ impl std::fmt::Display for GetConfigError {
             Self::FailedToGetUserLevelConfigPath(e) => {
                 write!(f, "failed to get user level config path, {}", e)
             }
+            Self::ConfigOverrideInvalid(e) => write!(f, "invalid config override, {}", e),
         }
     }
 }
@@ -34,11 +46,37 @@ impl std::error::Error for GetConfigError {
         match self {
             Self::FailedToGetUserLevelConfigPath(e) => Some(e),
             Self::ReadConfigFailed(e) => Some(e),
+            Self::ConfigOverrideInvalid(e) => Some(e),
         }
     }
 }
 
-pub fn get_config(repo_root: &str, repo_gitdir: &str) -> Result<PsConfig, GetConfigError> {
+/// Get the config for the patch stack checked out in the repository, applying the profiles
+/// matching its upstream branch. When there is no patch stack, e.g. on a detached HEAD or a branch
+/// without an upstream, no profile applies.
+pub fn get_config(
+    repo: &git2::Repository,
+    repo_root: &str,
+    repo_gitdir: &str,
+) -> Result<PsConfig, GetConfigError> {
+    let upstream_branch_name = patch_stack_upstream_branch_name(repo);
+    get_config_for_upstream(repo_root, repo_gitdir, upstream_branch_name.as_deref())
+}
+
+/// Get the upstream branch of the checked out patch stack, e.g. `origin/main`, if there is one
+fn patch_stack_upstream_branch_name(repo: &git2::Repository) -> Option<String> {
+    let patch_stack = ps::get_patch_stack(repo).ok()?;
+    patch_stack.base.shorthand().map(|name| name.to_string())
+}
+
+/// Get the config for a patch stack with the given upstream branch, e.g. `origin/release-1.2`,
+/// applying the profiles whose pattern matches it on top of the config files but beneath the
+/// overrides
+pub fn get_config_for_upstream(
+    repo_root: &str,
+    repo_gitdir: &str,
+    upstream_branch_name: Option<&str>,
+) -> Result<PsConfig, GetConfigError> {
     // get the three different configs or their defaults
     let user_level_config_path =
         paths::user_level_config_path().map_err(GetConfigError::FailedToGetUserLevelConfigPath)?;
@@ -53,12 +91,37 @@ pub fn get_config(repo_root: &str, repo_gitdir: &str) -> Result<PsConfig, GetCon
     let communal_repo_config = read_config_dto_or_default(&communal_repo_level_config_path)
         .map_err(GetConfigError::ReadConfigFailed)?;
 
-    // merge the three configs appropriately into the final config
-    let config_dto = user_level_config
+    // the environment variable & command line overrides make up a fourth level on top
+    let overrides_config =
+        overrides_config_dto(&config_overrides()).map_err(GetConfigError::ConfigOverrideInvalid)?;
+
+    // merge the configs appropriately into the final config
+    let mut config_dto = user_level_config
         .merge(&personal_repo_config)
         .merge(&communal_repo_config);
+    let profiles = merge_option(&config_dto.profile, &overrides_config.profile);
+    if let (Some(profiles), Some(upstream_branch_name)) = (&profiles, upstream_branch_name) {
+        for profile in matching_profiles(profiles, upstream_branch_name) {
+            config_dto = config_dto.merge(&ConfigDto::from(profile));
+        }
+    }
+    let config_dto = config_dto.merge(&overrides_config);
 
-    Ok(apply_config_defaults(&config_dto))
+    let mut config = apply_config_defaults(&config_dto);
+
+    // hook chaining is declared by each level for its own hooks, so it is taken from the
+    // individual configs rather than the merged one
+    for (location, level_config) in [
+        (HookLocation::Communal, &communal_repo_config),
+        (HookLocation::Repository, &personal_repo_config),
+        (HookLocation::User, &user_level_config),
+    ] {
+        if let Some(chaining) = level_config.hooks.as_ref().and_then(|h| h.chaining) {
+            config.hooks.chaining.insert(location, chaining);
+        }
+    }
+
+    Ok(config)
 }
 
 fn apply_config_defaults(config_dto: &ConfigDto) -> PsConfig {
@@ -68,6 +131,12 @@ fn apply_config_defaults(config_dto: &ConfigDto) -> PsConfig {
     let default_integrate_config = apply_integrate_config_defaults(&IntegrateConfigDto::default());
     let default_fetch_config = apply_fetch_config_defaults(&FetchConfigDto::default());
     let default_list_config = apply_list_config_defaults(&ListConfigDto::default());
+    let default_hooks_config = apply_hooks_config_defaults(&HooksConfigDto::default());
+    let default_branch_scan_config =
+        apply_branch_scan_config_defaults(&BranchScanConfigDto::default());
+    let default_branch_config = apply_branch_config_defaults(&BranchConfigDto::default());
+    let default_conflicts_config = apply_conflicts_config_defaults(&ConflictsConfigDto::default());
+    let default_rewrite_config = apply_rewrite_config_defaults(&RewriteConfigDto::default());
     PsConfig {
         request_review: config_dto
             .request_review
@@ -94,6 +163,31 @@ fn apply_config_defaults(config_dto: &ConfigDto) -> PsConfig {
             .as_ref()
             .map(apply_list_config_defaults)
             .unwrap_or(default_list_config),
+        hooks: config_dto
+            .hooks
+            .as_ref()
+            .map(apply_hooks_config_defaults)
+            .unwrap_or(default_hooks_config),
+        branch_scan: config_dto
+            .branch_scan
+            .as_ref()
+            .map(apply_branch_scan_config_defaults)
+            .unwrap_or(default_branch_scan_config),
+        branch: config_dto
+            .branch
+            .as_ref()
+            .map(apply_branch_config_defaults)
+            .unwrap_or(default_branch_config),
+        conflicts: config_dto
+            .conflicts
+            .as_ref()
+            .map(apply_conflicts_config_defaults)
+            .unwrap_or(default_conflicts_config),
+        rewrite: config_dto
+            .rewrite
+            .as_ref()
+            .map(apply_rewrite_config_defaults)
+            .unwrap_or(default_rewrite_config),
     }
 }
 
@@ -102,12 +196,22 @@ fn apply_request_review_config_defaults(
 ) -> PsRequestReviewConfig {
     PsRequestReviewConfig {
         verify_isolation: rr_config_dto.verify_isolation.unwrap_or(true),
+        branch_name_template: rr_config_dto
+            .branch_name_template
+            .clone()
+            .unwrap_or_else(|| "ps/rr/{slug}".to_string()),
+        branch_name_max_length: rr_config_dto.branch_name_max_length,
+        branch_name_slug_separator: rr_config_dto.branch_name_slug_separator.clone(),
+        ticket_pattern: rr_config_dto.ticket_pattern.clone(),
     }
 }
 
 fn apply_pull_config_defaults(pull_config_dto: &PullConfigDto) -> PsPullConfig {
     PsPullConfig {
         show_list_post_pull: pull_config_dto.show_list_post_pull.unwrap_or(false),
+        cleanup_landed_branches: pull_config_dto
+            .cleanup_landed_branches
+            .unwrap_or(LandedBranchesCleanup::Off),
     }
 }
 
@@ -116,6 +220,42 @@ fn apply_integrate_config_defaults(integrate_config_dto: &IntegrateConfigDto) ->
         prompt_for_reassurance: integrate_config_dto.prompt_for_reassurance.unwrap_or(true),
         verify_isolation: integrate_config_dto.verify_isolation.unwrap_or(true),
         pull_after_integrate: integrate_config_dto.pull_after_integrate.unwrap_or(false),
+        keep_branch: integrate_config_dto.keep_branch.unwrap_or(false),
+        require_verify: integrate_config_dto.require_verify.unwrap_or(false),
+        strategy: integrate_config_dto
+            .strategy
+            .unwrap_or(IntegrateStrategy::FastForward),
+        message_template: integrate_config_dto.message_template.clone(),
+        retry_max_attempts: integrate_config_dto.retry_max_attempts.unwrap_or(5),
+        retry_backoff: integrate_config_dto.retry_backoff.unwrap_or(2),
+        sign_push: integrate_config_dto.sign_push.unwrap_or(false),
+        tag: integrate_config_dto.tag.unwrap_or(false),
+        tag_name_template: integrate_config_dto
+            .tag_name_template
+            .clone()
+            .unwrap_or_else(|| "ps/integrated/{ps_id}".to_string()),
+        verify_signatures: integrate_config_dto.verify_signatures.unwrap_or(false),
+    }
+}
+
+fn apply_branch_config_defaults(branch_config_dto: &BranchConfigDto) -> PsBranchConfig {
+    PsBranchConfig {
+        include_dependencies: branch_config_dto.include_dependencies.unwrap_or(false),
+    }
+}
+
+fn apply_conflicts_config_defaults(conflicts_config_dto: &ConflictsConfigDto) -> PsConflictsConfig {
+    PsConflictsConfig {
+        resolve_in_worktree: conflicts_config_dto.resolve_in_worktree.unwrap_or(false),
+    }
+}
+
+fn apply_rewrite_config_defaults(rewrite_config_dto: &RewriteConfigDto) -> PsRewriteConfig {
+    PsRewriteConfig {
+        committer: rewrite_config_dto
+            .committer
+            .unwrap_or(CommitterPolicy::Reset),
+        sign: rewrite_config_dto.sign.unwrap_or(SignPolicy::Config),
     }
 }
 
@@ -127,6 +267,36 @@ fn apply_fetch_config_defaults(fetch_config_dto: &FetchConfigDto) -> PsFetchConf
     }
 }
 
+fn apply_hooks_config_defaults(hooks_config_dto: &HooksConfigDto) -> PsHooksConfig {
+    PsHooksConfig {
+        timeout: hooks_config_dto.timeout,
+        timeouts: hooks_config_dto.timeouts.clone().unwrap_or_default(),
+        order: hooks_config_dto.order.clone().unwrap_or(vec![
+            HookLocation::Communal,
+            HookLocation::Repository,
+            HookLocation::User,
+        ]),
+        chaining: HashMap::new(),
+    }
+}
+
+fn apply_branch_scan_config_defaults(
+    branch_scan_config_dto: &BranchScanConfigDto,
+) -> PsBranchScanConfig {
+    PsBranchScanConfig {
+        review_branches_only: branch_scan_config_dto.review_branches_only.unwrap_or(false),
+        cache: branch_scan_config_dto.cache.unwrap_or(true),
+        jobs: branch_scan_config_dto
+            .jobs
+            .unwrap_or_else(|| {
+                std::thread::available_parallelism()
+                    .map(|n| n.get())
+                    .unwrap_or(1)
+            })
+            .max(1),
+    }
+}
+
 fn apply_list_config_defaults(list_config_dto: &ListConfigDto) -> PsListConfig {
     PsListConfig {
         add_extra_patch_info: list_config_dto.add_extra_patch_info.unwrap_or(false),
@@ -177,5 +347,6 @@ fn apply_list_config_defaults(list_config_dto: &ListConfigDto) -> PsListConfig {
                 color: Some(Color::RGB(109, 202, 231)),
                 alternate_color: None,
             }),
+        extra_patch_info_cache_ttl: list_config_dto.extra_patch_info_cache_ttl.unwrap_or(0),
     }
 }
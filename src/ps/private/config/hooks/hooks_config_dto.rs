@@ -0,0 +1,65 @@
+use super::super::super::utils;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::option::Option;
+
+/// The levels hooks are searched for in, in order of precedence
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum HookLocation {
+    /// `.git-ps/hooks` in the repository's working tree, shared with the team
+    Communal,
+    /// `git-ps/hooks` in the repository's gitdir, personal to this repository
+    Repository,
+    /// `~/.config/git-ps/hooks`, personal across all repositories
+    User,
+}
+
+impl std::fmt::Display for HookLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Communal => write!(f, "communal"),
+            Self::Repository => write!(f, "repository"),
+            Self::User => write!(f, "user"),
+        }
+    }
+}
+
+/// How the hooks of a level combine with the hooks of the other levels
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum HookChaining {
+    /// The level's hooks run in addition to the hooks of the other levels
+    Extend,
+    /// The level's hooks run instead of the hooks of the other levels
+    Replace,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct HooksConfigDto {
+    pub timeout: Option<u64>,
+    pub timeouts: Option<HashMap<String, u64>>,
+    pub order: Option<Vec<HookLocation>>,
+    pub chaining: Option<HookChaining>,
+}
+
+impl utils::Mergable for HooksConfigDto {
+    /// Merge the provided b with self overriding with any present values
+    fn merge(&self, b: &Self) -> Self {
+        let timeouts = match (&self.timeouts, &b.timeouts) {
+            (Some(a_timeouts), Some(b_timeouts)) => {
+                let mut merged = a_timeouts.clone();
+                merged.extend(b_timeouts.clone());
+                Some(merged)
+            }
+            (a_timeouts, b_timeouts) => b_timeouts.clone().or(a_timeouts.clone()),
+        };
+
+        HooksConfigDto {
+            timeout: b.timeout.or(self.timeout),
+            timeouts,
+            order: b.order.clone().or(self.order.clone()),
+            chaining: b.chaining.or(self.chaining),
+        }
+    }
+}
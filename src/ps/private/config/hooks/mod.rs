@@ -0,0 +1,3 @@
+mod hooks_config_dto;
+
+pub use hooks_config_dto::*;
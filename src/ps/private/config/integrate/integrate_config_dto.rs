@@ -2,11 +2,34 @@ use super::super::super::utils;
 use serde::Deserialize;
 use std::option::Option;
 
+/// How integrate publishes the patches of the request review branch to the upstream
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum IntegrateStrategy {
+    /// Push the patches onto the upstream as is
+    #[serde(rename = "ff", alias = "fast-forward")]
+    FastForward,
+    /// Push a merge commit of the patches into the upstream
+    Merge,
+    /// Push a single commit squashing the patches onto the upstream
+    Squash,
+}
+
 #[derive(Debug, Deserialize, Clone, Default)]
 pub struct IntegrateConfigDto {
     pub prompt_for_reassurance: Option<bool>,
     pub verify_isolation: Option<bool>,
     pub pull_after_integrate: Option<bool>,
+    pub keep_branch: Option<bool>,
+    pub require_verify: Option<bool>,
+    pub strategy: Option<IntegrateStrategy>,
+    pub message_template: Option<String>,
+    pub retry_max_attempts: Option<u32>,
+    pub retry_backoff: Option<u64>,
+    pub sign_push: Option<bool>,
+    pub tag: Option<bool>,
+    pub tag_name_template: Option<String>,
+    pub verify_signatures: Option<bool>,
 }
 
 impl utils::Mergable for IntegrateConfigDto {
@@ -16,6 +39,19 @@ impl utils::Mergable for IntegrateConfigDto {
             prompt_for_reassurance: b.prompt_for_reassurance.or(self.prompt_for_reassurance),
             verify_isolation: b.verify_isolation.or(self.verify_isolation),
             pull_after_integrate: b.pull_after_integrate.or(self.pull_after_integrate),
+            keep_branch: b.keep_branch.or(self.keep_branch),
+            require_verify: b.require_verify.or(self.require_verify),
+            strategy: b.strategy.or(self.strategy),
+            message_template: b.message_template.clone().or(self.message_template.clone()),
+            retry_max_attempts: b.retry_max_attempts.or(self.retry_max_attempts),
+            retry_backoff: b.retry_backoff.or(self.retry_backoff),
+            sign_push: b.sign_push.or(self.sign_push),
+            tag: b.tag.or(self.tag),
+            tag_name_template: b
+                .tag_name_template
+                .clone()
+                .or(self.tag_name_template.clone()),
+            verify_signatures: b.verify_signatures.or(self.verify_signatures),
         }
     }
 }
@@ -37,6 +37,7 @@ pub struct ListConfigDto {
     pub patch_sha: Option<ColorWithAlternate>,
     pub patch_summary: Option<ColorWithAlternate>,
     pub patch_extra_info: Option<ColorWithAlternate>,
+    pub extra_patch_info_cache_ttl: Option<u64>,
 }
 
 impl utils::Mergable for ListConfigDto {
@@ -55,6 +56,9 @@ impl utils::Mergable for ListConfigDto {
             patch_sha: b.patch_sha.clone().or(self.patch_sha.clone()),
             patch_summary: b.patch_summary.clone().or(self.patch_summary.clone()),
             patch_extra_info: b.patch_extra_info.clone().or(self.patch_extra_info.clone()),
+            extra_patch_info_cache_ttl: b
+                .extra_patch_info_cache_ttl
+                .or(self.extra_patch_info_cache_ttl),
         }
     }
 }
@@ -1,15 +1,27 @@
 pub mod branch;
+pub mod branch_scan;
+pub mod conflicts;
 pub mod fetch;
+pub mod hooks;
 pub mod integrate;
 pub mod list;
+pub mod profile;
 pub mod pull;
 pub mod request_review;
+pub mod rewrite;
 
 mod config_dto;
+mod config_level;
+mod config_overrides;
+mod config_values;
 mod get_config;
 mod ps_config;
 mod read_config;
 mod read_config_or_default;
 
 pub use config_dto::*;
+pub use config_level::*;
+pub use config_overrides::*;
+pub use config_values::*;
 pub use get_config::*;
+pub use ps_config::*;
@@ -0,0 +1,3 @@
+mod profile_config_dto;
+
+pub use profile_config_dto::*;
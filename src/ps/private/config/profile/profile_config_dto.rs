@@ -0,0 +1,145 @@
+use super::super::super::utils;
+use super::super::branch::BranchConfigDto;
+use super::super::branch_scan::BranchScanConfigDto;
+use super::super::conflicts::ConflictsConfigDto;
+use super::super::fetch::FetchConfigDto;
+use super::super::hooks::HooksConfigDto;
+use super::super::integrate::IntegrateConfigDto;
+use super::super::list::ListConfigDto;
+use super::super::pull::PullConfigDto;
+use super::super::request_review::RequestReviewConfigDto;
+use super::super::rewrite::RewriteConfigDto;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+/// Config sections that only apply to patch stacks whose upstream branch matches the pattern the
+/// profile is keyed by, e.g. `[profile."origin/release-*".integrate]`
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ProfileConfigDto {
+    pub request_review: Option<RequestReviewConfigDto>,
+    pub pull: Option<PullConfigDto>,
+    pub integrate: Option<IntegrateConfigDto>,
+    pub fetch: Option<FetchConfigDto>,
+    pub list: Option<ListConfigDto>,
+    pub branch: Option<BranchConfigDto>,
+    pub hooks: Option<HooksConfigDto>,
+    pub branch_scan: Option<BranchScanConfigDto>,
+    pub conflicts: Option<ConflictsConfigDto>,
+    pub rewrite: Option<RewriteConfigDto>,
+}
+
+impl utils::Mergable for ProfileConfigDto {
+    /// Merge the provided b with self overriding with any present values
+    fn merge(&self, b: &Self) -> Self {
+        ProfileConfigDto {
+            request_review: utils::merge_option(&self.request_review, &b.request_review),
+            pull: utils::merge_option(&self.pull, &b.pull),
+            integrate: utils::merge_option(&self.integrate, &b.integrate),
+            fetch: utils::merge_option(&self.fetch, &b.fetch),
+            list: utils::merge_option(&self.list, &b.list),
+            branch: utils::merge_option(&self.branch, &b.branch),
+            hooks: utils::merge_option(&self.hooks, &b.hooks),
+            branch_scan: utils::merge_option(&self.branch_scan, &b.branch_scan),
+            conflicts: utils::merge_option(&self.conflicts, &b.conflicts),
+            rewrite: utils::merge_option(&self.rewrite, &b.rewrite),
+        }
+    }
+}
+
+pub type ProfilesConfigDto = BTreeMap<String, ProfileConfigDto>;
+
+impl utils::Mergable for ProfilesConfigDto {
+    /// Merge the provided b with self, merging the profiles keyed by the same pattern
+    fn merge(&self, b: &Self) -> Self {
+        let mut profiles = self.clone();
+        for (pattern, profile) in b.iter() {
+            let merged = match profiles.get(pattern) {
+                Some(existing) => existing.merge(profile),
+                None => profile.clone(),
+            };
+            profiles.insert(pattern.clone(), merged);
+        }
+        profiles
+    }
+}
+
+/// Check if the upstream branch, e.g. `origin/release-1.2`, matches the profile pattern, where
+/// `*` matches any sequence of characters
+pub fn profile_pattern_matches(pattern: &str, upstream_branch_name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let mut rest = match upstream_branch_name.strip_prefix(first) {
+        Some(rest) => rest,
+        None => return false,
+    };
+    let remaining: Vec<&str> = parts.collect();
+    match remaining.split_last() {
+        None => rest.is_empty(),
+        Some((last, middle)) => {
+            for part in middle {
+                match rest.find(part) {
+                    Some(index) => rest = &rest[index + part.len()..],
+                    None => return false,
+                }
+            }
+            rest.len() >= last.len() && rest.ends_with(last)
+        }
+    }
+}
+
+/// The profiles matching the upstream branch, ordered so the more specific patterns, those with
+/// more literal characters, come last and therefore take precedence
+pub fn matching_profiles<'a>(
+    profiles: &'a ProfilesConfigDto,
+    upstream_branch_name: &str,
+) -> Vec<&'a ProfileConfigDto> {
+    let mut matching: Vec<(&String, &ProfileConfigDto)> = profiles
+        .iter()
+        .filter(|(pattern, _)| profile_pattern_matches(pattern, upstream_branch_name))
+        .collect();
+    matching.sort_by_key(|(pattern, _)| pattern.replace('*', "").len());
+    matching.into_iter().map(|(_, profile)| profile).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_pattern_matches() {
+        assert!(profile_pattern_matches(
+            "origin/release-*",
+            "origin/release-1.2"
+        ));
+        assert!(profile_pattern_matches("origin/main", "origin/main"));
+        assert!(profile_pattern_matches("*/release-*", "upstream/release-3"));
+        assert!(profile_pattern_matches("*", "origin/main"));
+        assert!(!profile_pattern_matches("origin/release-*", "origin/main"));
+        assert!(!profile_pattern_matches("origin/main", "origin/main-2"));
+        assert!(!profile_pattern_matches("origin/*-x-*x", "origin/a-x-"));
+    }
+
+    #[test]
+    fn test_matching_profiles_orders_specific_patterns_last() {
+        let profiles: ProfilesConfigDto = toml::from_str(
+            r#"
+["origin/release-1.*".integrate]
+verify_isolation = true
+
+["*".integrate]
+verify_isolation = false
+
+["origin/main".integrate]
+verify_isolation = false
+"#,
+        )
+        .unwrap();
+
+        let matching = matching_profiles(&profiles, "origin/release-1.2");
+        assert_eq!(matching.len(), 2);
+        assert_eq!(
+            matching[1].integrate.as_ref().unwrap().verify_isolation,
+            Some(true)
+        );
+    }
+}
@@ -1,4 +1,10 @@
+use super::hooks::{HookChaining, HookLocation};
+use super::integrate::IntegrateStrategy;
 use super::list::ColorWithAlternate;
+use super::pull::LandedBranchesCleanup;
+use super::rewrite::{CommitterPolicy, SignPolicy};
+use std::collections::HashMap;
+use std::time::Duration;
 
 #[derive(Debug)]
 pub struct PsConfig {
@@ -7,16 +13,30 @@ pub struct PsConfig {
     pub integrate: PsIntegrateConfig,
     pub fetch: PsFetchConfig,
     pub list: PsListConfig,
+    pub hooks: PsHooksConfig,
+    pub branch_scan: PsBranchScanConfig,
+    pub branch: PsBranchConfig,
+    pub conflicts: PsConflictsConfig,
+    pub rewrite: PsRewriteConfig,
 }
 
 #[derive(Debug)]
 pub struct PsRequestReviewConfig {
     pub verify_isolation: bool,
+    /// Template request review branch names are generated from, e.g. ps/rr/{slug}
+    pub branch_name_template: String,
+    pub branch_name_max_length: Option<usize>,
+    /// Separator used when slugifying the summary, None keeps the original slugification
+    pub branch_name_slug_separator: Option<String>,
+    /// Regex used to extract the ticket number from the commit message
+    pub ticket_pattern: Option<String>,
 }
 
 #[derive(Debug)]
 pub struct PsPullConfig {
     pub show_list_post_pull: bool,
+    /// What to do with the request review branches of patches that landed upstream
+    pub cleanup_landed_branches: LandedBranchesCleanup,
 }
 
 #[derive(Debug)]
@@ -24,6 +44,27 @@ pub struct PsIntegrateConfig {
     pub prompt_for_reassurance: bool,
     pub verify_isolation: bool,
     pub pull_after_integrate: bool,
+    /// Keep the request review branches around after integrating, as if `-k` was given
+    pub keep_branch: bool,
+    /// Refuse to integrate with `--force` & require the integrate_verify hook to exist
+    pub require_verify: bool,
+    pub strategy: IntegrateStrategy,
+    /// Template of the message of the merge or squashed commit, None for the default one
+    pub message_template: Option<String>,
+    /// How many times `integrate --retry` attempts the push before giving up
+    pub retry_max_attempts: u32,
+    /// How long, in seconds, `integrate --retry` waits before its first retry, doubling for each
+    /// following one
+    pub retry_backoff: u64,
+    /// Sign the push certificate when pushing to the upstream, as if `--sign-push` was given
+    pub sign_push: bool,
+    /// Create & push a signed tag per integrated patch series, as if `--tag` was given
+    pub tag: bool,
+    /// Template the tag names are generated from, e.g. ps/integrated/{ps_id}
+    pub tag_name_template: String,
+    /// Refuse to integrate request review branches with commits that aren't signed by someone
+    /// trusted
+    pub verify_signatures: bool,
 }
 
 #[derive(Debug)]
@@ -43,4 +84,61 @@ pub struct PsListConfig {
     pub patch_sha: ColorWithAlternate,
     pub patch_summary: ColorWithAlternate,
     pub patch_extra_info: ColorWithAlternate,
+    /// How long, in seconds, list_additional_information hook output is cached for
+    pub extra_patch_info_cache_ttl: u64,
+}
+
+#[derive(Debug)]
+pub struct PsHooksConfig {
+    pub timeout: Option<u64>,
+    pub timeouts: HashMap<String, u64>,
+    /// The order in which the hooks of each level run when they are chained together
+    pub order: Vec<HookLocation>,
+    /// The chaining each level declared in its own config file, as opposed to the merged config
+    pub chaining: HashMap<HookLocation, HookChaining>,
+}
+
+impl PsHooksConfig {
+    /// Get the timeout to apply to the named hook, preferring a hook specific timeout over the
+    /// general one. A timeout of zero seconds means the hook is allowed to run forever.
+    pub fn timeout_for(&self, hook_name: &str) -> Option<Duration> {
+        self.timeouts
+            .get(hook_name)
+            .copied()
+            .or(self.timeout)
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+    }
+}
+
+#[derive(Debug)]
+pub struct PsBranchScanConfig {
+    /// Only scan the request review branches, e.g. ps/rr/*, rather than every local branch
+    pub review_branches_only: bool,
+    /// Persist the scan results & patch ids so they are only recomputed when refs move
+    pub cache: bool,
+    /// How many branches to scan in parallel
+    pub jobs: usize,
+}
+
+#[derive(Debug)]
+pub struct PsBranchConfig {
+    /// Include the earlier patches the requested ones depend on in the branch, rather than
+    /// refusing to create it
+    pub include_dependencies: bool,
+}
+
+#[derive(Debug)]
+pub struct PsConflictsConfig {
+    /// Stop at a cherry pick conflict with it checked out in a temporary worktree to be resolved &
+    /// resumed with `gps continue`, rather than aborting
+    pub resolve_in_worktree: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PsRewriteConfig {
+    /// How the committer of the commits gps rewrites, e.g. by cherry picking them, is chosen
+    pub committer: CommitterPolicy,
+    /// When the commits gps creates or rewrites are signed
+    pub sign: SignPolicy,
 }
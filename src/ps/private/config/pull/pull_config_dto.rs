@@ -2,9 +2,22 @@ use super::super::super::utils;
 use serde::Deserialize;
 use std::option::Option;
 
+/// What pull does with the request review branches of patches that landed upstream
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LandedBranchesCleanup {
+    /// Leave them alone, they can still be cleaned up with `gps gc`
+    Off,
+    /// List them & ask before deleting them locally & remotely
+    Prompt,
+    /// Delete them locally & remotely without asking
+    Auto,
+}
+
 #[derive(Debug, Deserialize, Clone, Default)]
 pub struct PullConfigDto {
     pub show_list_post_pull: Option<bool>,
+    pub cleanup_landed_branches: Option<LandedBranchesCleanup>,
 }
 
 impl utils::Mergable for PullConfigDto {
@@ -12,6 +25,7 @@ impl utils::Mergable for PullConfigDto {
     fn merge(&self, b: &Self) -> Self {
         PullConfigDto {
             show_list_post_pull: b.show_list_post_pull.or(self.show_list_post_pull),
+            cleanup_landed_branches: b.cleanup_landed_branches.or(self.cleanup_landed_branches),
         }
     }
 }
@@ -5,6 +5,10 @@ use std::option::Option;
 #[derive(Debug, Deserialize, Clone, Default)]
 pub struct RequestReviewConfigDto {
     pub verify_isolation: Option<bool>,
+    pub branch_name_template: Option<String>,
+    pub branch_name_max_length: Option<usize>,
+    pub branch_name_slug_separator: Option<String>,
+    pub ticket_pattern: Option<String>,
 }
 
 impl utils::Mergable for RequestReviewConfigDto {
@@ -12,6 +16,16 @@ impl utils::Mergable for RequestReviewConfigDto {
     fn merge(&self, b: &Self) -> Self {
         RequestReviewConfigDto {
             verify_isolation: b.verify_isolation.or(self.verify_isolation),
+            branch_name_template: b
+                .branch_name_template
+                .clone()
+                .or(self.branch_name_template.clone()),
+            branch_name_max_length: b.branch_name_max_length.or(self.branch_name_max_length),
+            branch_name_slug_separator: b
+                .branch_name_slug_separator
+                .clone()
+                .or(self.branch_name_slug_separator.clone()),
+            ticket_pattern: b.ticket_pattern.clone().or(self.ticket_pattern.clone()),
         }
     }
 }
@@ -0,0 +1,3 @@
+mod rewrite_config_dto;
+
+pub use rewrite_config_dto::*;
@@ -0,0 +1,39 @@
+use super::super::super::utils;
+use serde::Deserialize;
+use std::option::Option;
+
+/// How the committer of a commit gps rewrites, e.g. by cherry picking it, is chosen
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum CommitterPolicy {
+    /// Use the current user & time as the committer, like `git rebase` does
+    Reset,
+    /// Keep the committer identity & date of the original commit
+    Preserve,
+}
+
+/// When the commits gps creates or rewrites are signed
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SignPolicy {
+    /// Sign them when `commit.gpgsign` is true in git config
+    Config,
+    /// Sign them regardless of how git is configured to sign the commits you make
+    Always,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct RewriteConfigDto {
+    pub committer: Option<CommitterPolicy>,
+    pub sign: Option<SignPolicy>,
+}
+
+impl utils::Mergable for RewriteConfigDto {
+    /// Merge the provided b with self overriding with any present values
+    fn merge(&self, b: &Self) -> Self {
+        RewriteConfigDto {
+            committer: b.committer.or(self.committer),
+            sign: b.sign.or(self.sign),
+        }
+    }
+}
@@ -0,0 +1,596 @@
+// This module lets an operation stop at a cherry pick conflict, rather than aborting, so the
+// conflict can be resolved & the operation resumed with `gps continue`. The conflicting cherry
+// pick is checked out with conflict markers in a temporary worktree within the gitdir, so the
+// working copy of the patch stack is left untouched, & what the operation still has to do is
+// persisted in the gitdir, much like git persists the state of a rebase.
+
+use super::cherry_picking;
+use super::config;
+use super::paths;
+use super::utils;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::result::Result;
+
+/// The operation waiting to be continued, along with what it needs to finish up once the
+/// conflict is resolved
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum InterruptedOperation {
+    Branch,
+    Append,
+    RequestReview { post_sync_hook: bool },
+    Pull { upstream_branch_name: String },
+}
+
+impl std::fmt::Display for InterruptedOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Branch => write!(f, "branch"),
+            Self::Append => write!(f, "append"),
+            Self::RequestReview { .. } => write!(f, "request-review"),
+            Self::Pull { .. } => write!(f, "pull"),
+        }
+    }
+}
+
+/// A cherry pick of a range of patches stopped at a conflict
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct PendingCherryPick {
+    pub dest_ref_name: String,
+    /// What the destination ref pointed at before the operation, None if it didn't exist
+    pub original_oid: Option<String>,
+    pub conflicting_oid: String,
+    /// The commit the conflicting patch was being played onto
+    pub destination_oid: String,
+    /// The patches still to be cherry picked once the conflict is resolved
+    pub remaining_oids: Vec<String>,
+    pub committer_time_offset: i64,
+    pub add_missing_patch_ids: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ContinueState {
+    pub operation: InterruptedOperation,
+    /// The cherry pick to resume, None once it is done or when the operation stopped in the
+    /// working copy itself, e.g. in the middle of a rebase
+    pub cherry_pick: Option<PendingCherryPick>,
+    /// The ref the cherry pick finished on, so the rest of the operation can still be run from it
+    /// when that failed the first time round
+    #[serde(default)]
+    pub cherry_picked_ref_name: Option<String>,
+}
+
+/// Where the cherry pick of a range of patches is going
+pub struct CherryPickTarget<'a> {
+    pub dest_ref_name: &'a str,
+    pub leaf_oid: git2::Oid,
+    pub committer_time_offset: i64,
+    pub add_missing_patch_ids: bool,
+    /// What the destination ref pointed at before the operation, None if it didn't exist
+    pub original_oid: Option<git2::Oid>,
+}
+
+/// How resuming a cherry pick went
+pub enum ResumeOutcome {
+    Completed,
+    /// Stopped at another conflict, checked out in the worktree at the path
+    Stopped(String, PathBuf),
+}
+
+#[derive(Debug)]
+pub enum ConflictsError {
+    OperationInProgress(InterruptedOperation),
+    ReadStateFailed(io::Error),
+    ParseStateFailed(serde_json::Error),
+    WriteStateFailed(Box<dyn std::error::Error>),
+    CreateWorktreeFailed(Box<dyn std::error::Error>),
+    RemoveWorktreeFailed(Box<dyn std::error::Error>),
+    WorktreeMissing(PathBuf),
+    UnresolvedConflicts(PathBuf),
+    DestinationMoved(String),
+    CherryPickFailed(cherry_picking::CherryPickError),
+    Git(git2::Error),
+}
+
+impl From<git2::Error> for ConflictsError {
+    fn from(e: git2::Error) -> Self {
+        Self::Git(e)
+    }
+}
+
+impl std::fmt::Display for ConflictsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OperationInProgress(operation) => write!(
+                f,
+                "a {} is waiting to be continued, run gps continue or gps continue --abort first",
+                operation
+            ),
+            Self::ReadStateFailed(e) => write!(f, "failed to read the continue state, {}", e),
+            Self::ParseStateFailed(e) => write!(f, "failed to parse the continue state, {}", e),
+            Self::WriteStateFailed(e) => write!(f, "failed to write the continue state, {}", e),
+            Self::CreateWorktreeFailed(e) => {
+                write!(f, "failed to create the conflict worktree, {}", e)
+            }
+            Self::RemoveWorktreeFailed(e) => {
+                write!(f, "failed to remove the conflict worktree, {}", e)
+            }
+            Self::WorktreeMissing(path) => {
+                write!(f, "conflict worktree missing at {}", path.display())
+            }
+            Self::UnresolvedConflicts(path) => write!(
+                f,
+                "unresolved conflicts remain in {}, resolve them & stage the result with git add",
+                path.display()
+            ),
+            Self::DestinationMoved(ref_name) => write!(
+                f,
+                "{} moved since the operation stopped, run gps continue --abort",
+                ref_name
+            ),
+            Self::CherryPickFailed(e) => write!(f, "failed to cherry pick, {}", e),
+            Self::Git(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConflictsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::OperationInProgress(_) => None,
+            Self::ReadStateFailed(e) => Some(e),
+            Self::ParseStateFailed(e) => Some(e),
+            Self::WriteStateFailed(e) => Some(e.as_ref()),
+            Self::CreateWorktreeFailed(e) => Some(e.as_ref()),
+            Self::RemoveWorktreeFailed(e) => Some(e.as_ref()),
+            Self::WorktreeMissing(_) => None,
+            Self::UnresolvedConflicts(_) => None,
+            Self::DestinationMoved(_) => None,
+            Self::CherryPickFailed(e) => Some(e),
+            Self::Git(e) => Some(e),
+        }
+    }
+}
+
+/// Read the state of the operation waiting to be continued, None if there isn't one
+pub fn read_state(repo: &git2::Repository) -> Result<Option<ContinueState>, ConflictsError> {
+    match fs::read_to_string(paths::continue_state_path(repo)) {
+        Ok(content) => serde_json::from_str(&content)
+            .map(Some)
+            .map_err(ConflictsError::ParseStateFailed),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(ConflictsError::ReadStateFailed(e)),
+    }
+}
+
+/// Persist the state of the operation waiting to be continued
+pub fn write_state(repo: &git2::Repository, state: &ContinueState) -> Result<(), ConflictsError> {
+    let path = paths::continue_state_path(repo);
+    let dir = path.parent().unwrap_or_else(|| Path::new("."));
+    fs::create_dir_all(dir).map_err(|e| ConflictsError::WriteStateFailed(e.into()))?;
+    let content = serde_json::to_string_pretty(state)
+        .map_err(|e| ConflictsError::WriteStateFailed(e.into()))?;
+    let mut file = tempfile::NamedTempFile::new_in(dir)
+        .map_err(|e| ConflictsError::WriteStateFailed(e.into()))?;
+    file.write_all(content.as_bytes())
+        .map_err(|e| ConflictsError::WriteStateFailed(e.into()))?;
+    file.persist(&path)
+        .map_err(|e| ConflictsError::WriteStateFailed(e.into()))?;
+    Ok(())
+}
+
+/// Forget the operation waiting to be continued, removing the conflict worktree along with it
+pub fn clear_state(repo: &git2::Repository) -> Result<(), ConflictsError> {
+    remove_worktree(repo)?;
+    match fs::remove_file(paths::continue_state_path(repo)) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => {
+            Err(ConflictsError::WriteStateFailed(e.into()))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Fail when another operation is already waiting to be continued
+pub fn ensure_no_operation_in_progress(repo: &git2::Repository) -> Result<(), ConflictsError> {
+    match read_state(repo)? {
+        Some(state) => Err(ConflictsError::OperationInProgress(state.operation)),
+        None => Ok(()),
+    }
+}
+
+fn git_common_dir_arg(repo: &git2::Repository) -> String {
+    format!("--git-dir={}", paths::repo_common_dir(repo).display())
+}
+
+fn remove_worktree(repo: &git2::Repository) -> Result<(), ConflictsError> {
+    let worktree_path = paths::conflict_worktree_path(repo);
+    let git_dir_arg = git_common_dir_arg(repo);
+    if worktree_path.exists() {
+        let worktree_path_str = worktree_path.to_string_lossy().to_string();
+        let output = utils::execute_with_output(
+            "git",
+            &[
+                &git_dir_arg,
+                "worktree",
+                "remove",
+                "--force",
+                &worktree_path_str,
+            ],
+        )
+        .map_err(|e| ConflictsError::RemoveWorktreeFailed(e.into()))?;
+        utils::check_exit_status(output.status)
+            .map_err(|e| ConflictsError::RemoveWorktreeFailed(e.into()))?;
+    }
+    // drop the administrative files of a worktree removed by hand
+    utils::execute_with_output("git", &[&git_dir_arg, "worktree", "prune"])
+        .map_err(|e| ConflictsError::RemoveWorktreeFailed(e.into()))?;
+    Ok(())
+}
+
+/// Check the conflicting cherry pick out in the conflict worktree, leaving the conflict markers in
+/// the files for them to be resolved
+fn materialize(
+    repo: &git2::Repository,
+    pending: &PendingCherryPick,
+) -> Result<PathBuf, ConflictsError> {
+    remove_worktree(repo)?;
+    let worktree_path = paths::conflict_worktree_path(repo);
+    let worktree_path_str = worktree_path.to_string_lossy().to_string();
+    let git_dir_arg = git_common_dir_arg(repo);
+    let output = utils::execute_with_output(
+        "git",
+        &[
+            &git_dir_arg,
+            "worktree",
+            "add",
+            "--detach",
+            &worktree_path_str,
+            &pending.destination_oid,
+        ],
+    )
+    .map_err(|e| ConflictsError::CreateWorktreeFailed(e.into()))?;
+    utils::check_exit_status(output.status)
+        .map_err(|e| ConflictsError::CreateWorktreeFailed(e.into()))?;
+
+    // the cherry pick is expected to fail due to the conflicts, leaving them to be resolved
+    utils::execute_with_output(
+        "git",
+        &[
+            "-C",
+            &worktree_path_str,
+            "cherry-pick",
+            "--no-commit",
+            &pending.conflicting_oid,
+        ],
+    )
+    .map_err(|e| ConflictsError::CreateWorktreeFailed(e.into()))?;
+
+    Ok(worktree_path)
+}
+
+/// The patches after the conflicting one, up to & including the leaf, in the order they are to be
+/// cherry picked
+fn remaining_oids(
+    repo: &git2::Repository,
+    conflicting_oid: git2::Oid,
+    leaf_oid: git2::Oid,
+) -> Result<Vec<String>, ConflictsError> {
+    if conflicting_oid == leaf_oid {
+        return Ok(vec![]);
+    }
+    let mut rev_walk = repo.revwalk()?;
+    rev_walk.push(leaf_oid)?;
+    rev_walk.hide(conflicting_oid)?;
+    rev_walk.set_sorting(git2::Sort::REVERSE)?;
+    Ok(rev_walk.flatten().map(|oid| oid.to_string()).collect())
+}
+
+/// Stop the operation at the cherry pick conflict, checking it out in the conflict worktree &
+/// persisting what is left to do, returning the path of the worktree
+pub fn stop_for_resolution(
+    repo: &git2::Repository,
+    operation: InterruptedOperation,
+    target: &CherryPickTarget,
+    conflicting_oid: &str,
+    destination_oid: &str,
+) -> Result<PathBuf, ConflictsError> {
+    ensure_no_operation_in_progress(repo)?;
+    let pending = PendingCherryPick {
+        dest_ref_name: target.dest_ref_name.to_string(),
+        original_oid: target.original_oid.map(|oid| oid.to_string()),
+        conflicting_oid: conflicting_oid.to_string(),
+        destination_oid: destination_oid.to_string(),
+        remaining_oids: remaining_oids(
+            repo,
+            git2::Oid::from_str(conflicting_oid)?,
+            target.leaf_oid,
+        )?,
+        committer_time_offset: target.committer_time_offset,
+        add_missing_patch_ids: target.add_missing_patch_ids,
+    };
+    let worktree_path = materialize(repo, &pending)?;
+    write_state(
+        repo,
+        &ContinueState {
+            operation,
+            cherry_pick: Some(pending),
+            cherry_picked_ref_name: None,
+        },
+    )?;
+    Ok(worktree_path)
+}
+
+/// Stop the operation at a conflict git left in the working copy itself, e.g. during a rebase
+pub fn stop_in_working_copy(
+    repo: &git2::Repository,
+    operation: InterruptedOperation,
+) -> Result<(), ConflictsError> {
+    ensure_no_operation_in_progress(repo)?;
+    write_state(
+        repo,
+        &ContinueState {
+            operation,
+            cherry_pick: None,
+            cherry_picked_ref_name: None,
+        },
+    )
+}
+
+/// Commit the resolution staged in the conflict worktree & cherry pick the remaining patches,
+/// stopping again at the next conflict
+pub fn resume_cherry_pick(
+    repo: &git2::Repository,
+    config: &git2::Config,
+    rewrite_config: &config::PsRewriteConfig,
+    operation: &InterruptedOperation,
+    pending: &PendingCherryPick,
+) -> Result<ResumeOutcome, ConflictsError> {
+    let worktree_path = paths::conflict_worktree_path(repo);
+    let worktree_repo = git2::Repository::open(&worktree_path)
+        .map_err(|_| ConflictsError::WorktreeMissing(worktree_path.clone()))?;
+    let mut worktree_index = worktree_repo.index()?;
+    if worktree_index.has_conflicts() {
+        return Err(ConflictsError::UnresolvedConflicts(worktree_path));
+    }
+    let tree = repo.find_tree(worktree_index.write_tree()?)?;
+
+    let destination_oid = git2::Oid::from_str(&pending.destination_oid)?;
+    if repo.refname_to_id(&pending.dest_ref_name)? != destination_oid {
+        return Err(ConflictsError::DestinationMoved(
+            pending.dest_ref_name.clone(),
+        ));
+    }
+    let commit = repo.find_commit(git2::Oid::from_str(&pending.conflicting_oid)?)?;
+    let destination_commit = repo.find_commit(destination_oid)?;
+    cherry_picking::commit_cherry_picked_tree(
+        repo,
+        config,
+        rewrite_config,
+        &commit,
+        &tree,
+        &destination_commit,
+        &pending.dest_ref_name,
+        pending.committer_time_offset,
+        pending.add_missing_patch_ids,
+    )
+    .map_err(ConflictsError::CherryPickFailed)?;
+    remove_worktree(repo)?;
+
+    for (i, oid) in pending.remaining_oids.iter().enumerate() {
+        match cherry_picking::cherry_pick(
+            repo,
+            config,
+            rewrite_config,
+            git2::Oid::from_str(oid)?,
+            None,
+            &pending.dest_ref_name,
+            pending.committer_time_offset,
+            pending.add_missing_patch_ids,
+            true,
+        ) {
+            Ok(_) => {}
+            Err(cherry_picking::CherryPickError::ConflictsExist(src_oid, dst_oid)) => {
+                let next_pending = PendingCherryPick {
+                    conflicting_oid: src_oid.clone(),
+                    destination_oid: dst_oid,
+                    remaining_oids: pending.remaining_oids[i + 1..].to_vec(),
+                    ..pending.clone()
+                };
+                let worktree_path = materialize(repo, &next_pending)?;
+                write_state(
+                    repo,
+                    &ContinueState {
+                        operation: operation.clone(),
+                        cherry_pick: Some(next_pending),
+                        cherry_picked_ref_name: None,
+                    },
+                )?;
+                return Ok(ResumeOutcome::Stopped(src_oid, worktree_path));
+            }
+            Err(e) => return Err(ConflictsError::CherryPickFailed(e)),
+        }
+    }
+    Ok(ResumeOutcome::Completed)
+}
+
+/// Put the destination ref of the cherry pick back to what it was before the operation, deleting
+/// it if the operation created it
+pub fn restore_destination(
+    repo: &git2::Repository,
+    pending: &PendingCherryPick,
+) -> Result<(), ConflictsError> {
+    let mut reference = repo.find_reference(&pending.dest_ref_name)?;
+    match &pending.original_oid {
+        Some(original_oid) => {
+            reference.set_target(
+                git2::Oid::from_str(original_oid)?,
+                "gps continue --abort: restore",
+            )?;
+        }
+        None => reference.delete()?,
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::git::test_utils;
+    use super::*;
+
+    #[test]
+    fn test_state_round_trips_through_the_gitdir() {
+        let (_dir, repo) = test_utils::repo_init();
+        assert!(read_state(&repo).unwrap().is_none());
+
+        let state = ContinueState {
+            operation: InterruptedOperation::RequestReview {
+                post_sync_hook: true,
+            },
+            cherry_pick: Some(PendingCherryPick {
+                dest_ref_name: "refs/heads/ps/rr/foo".to_string(),
+                original_oid: None,
+                conflicting_oid: "a".repeat(40),
+                destination_oid: "b".repeat(40),
+                remaining_oids: vec!["c".repeat(40)],
+                committer_time_offset: 1,
+                add_missing_patch_ids: false,
+            }),
+            cherry_picked_ref_name: None,
+        };
+        write_state(&repo, &state).unwrap();
+        assert_eq!(read_state(&repo).unwrap(), Some(state));
+        assert!(matches!(
+            ensure_no_operation_in_progress(&repo),
+            Err(ConflictsError::OperationInProgress(_))
+        ));
+
+        clear_state(&repo).unwrap();
+        assert!(read_state(&repo).unwrap().is_none());
+    }
+
+    /// A repository with a patch stack, whose second patch conflicts with the destination branch,
+    /// stopped for resolution while cherry picking the stack onto the destination. Returns the
+    /// oid of the conflicting patch & of the destination before the cherry pick.
+    fn stopped_at_conflict(repo: &git2::Repository) -> (git2::Oid, git2::Oid, PendingCherryPick) {
+        let base_oid = test_utils::commit_files(repo, &[("conflict.txt", "base\n")], "base");
+        repo.reference("refs/heads/dest", base_oid, true, "test")
+            .unwrap();
+        let first_oid = test_utils::commit_files(repo, &[("first.txt", "first\n")], "first");
+        let second_oid = test_utils::commit_files(repo, &[("conflict.txt", "patch\n")], "second");
+        let third_oid = test_utils::commit_files(repo, &[("third.txt", "third\n")], "third");
+        repo.set_head("refs/heads/dest").unwrap();
+        let dest_oid = test_utils::commit_files(repo, &[("conflict.txt", "dest\n")], "dest");
+        repo.set_head("refs/heads/main").unwrap();
+
+        let config = repo.config().unwrap();
+        let (src_oid, dst_oid) = match cherry_picking::cherry_pick(
+            repo,
+            &config,
+            &test_utils::rewrite_config(),
+            first_oid,
+            Some(third_oid),
+            "refs/heads/dest",
+            0,
+            false,
+            true,
+        ) {
+            Err(cherry_picking::CherryPickError::ConflictsExist(src_oid, dst_oid)) => {
+                (src_oid, dst_oid)
+            }
+            other => panic!("expected a conflict, got {:?}", other),
+        };
+        let target = CherryPickTarget {
+            dest_ref_name: "refs/heads/dest",
+            leaf_oid: third_oid,
+            committer_time_offset: 0,
+            add_missing_patch_ids: false,
+            original_oid: Some(dest_oid),
+        };
+        stop_for_resolution(
+            repo,
+            InterruptedOperation::Branch,
+            &target,
+            &src_oid,
+            &dst_oid,
+        )
+        .unwrap();
+        let pending = read_state(repo).unwrap().unwrap().cherry_pick.unwrap();
+        (second_oid, dest_oid, pending)
+    }
+
+    #[test]
+    fn test_resume_cherry_pick_commits_the_staged_resolution() {
+        let (_dir, repo) = test_utils::repo_init();
+        let (second_oid, dest_oid, pending) = stopped_at_conflict(&repo);
+        assert_eq!(pending.conflicting_oid, second_oid.to_string());
+        assert_eq!(pending.remaining_oids.len(), 1);
+
+        let worktree_path = paths::conflict_worktree_path(&repo);
+        let config = repo.config().unwrap();
+        assert!(matches!(
+            resume_cherry_pick(
+                &repo,
+                &config,
+                &test_utils::rewrite_config(),
+                &InterruptedOperation::Branch,
+                &pending,
+            ),
+            Err(ConflictsError::UnresolvedConflicts(_))
+        ));
+
+        fs::write(worktree_path.join("conflict.txt"), "resolved\n").unwrap();
+        let worktree_path_str = worktree_path.to_string_lossy().to_string();
+        utils::execute_with_output("git", &["-C", &worktree_path_str, "add", "conflict.txt"])
+            .unwrap();
+        assert!(matches!(
+            resume_cherry_pick(
+                &repo,
+                &config,
+                &test_utils::rewrite_config(),
+                &InterruptedOperation::Branch,
+                &pending,
+            ),
+            Ok(ResumeOutcome::Completed)
+        ));
+
+        let third_commit = repo
+            .find_reference("refs/heads/dest")
+            .unwrap()
+            .peel_to_commit()
+            .unwrap();
+        assert_eq!(third_commit.summary(), Some("third"));
+        let second_commit = third_commit.parent(0).unwrap();
+        assert_eq!(second_commit.summary(), Some("second"));
+        let first_commit = second_commit.parent(0).unwrap();
+        assert_eq!(first_commit.summary(), Some("first"));
+        assert_eq!(first_commit.parent_id(0).unwrap(), dest_oid);
+        let resolved = second_commit
+            .tree()
+            .unwrap()
+            .get_path(Path::new("conflict.txt"))
+            .unwrap()
+            .to_object(&repo)
+            .unwrap()
+            .peel_to_blob()
+            .unwrap();
+        assert_eq!(resolved.content(), b"resolved\n");
+        assert!(!worktree_path.exists());
+    }
+
+    #[test]
+    fn test_restore_destination_aborts_the_cherry_pick() {
+        let (_dir, repo) = test_utils::repo_init();
+        let (_, dest_oid, pending) = stopped_at_conflict(&repo);
+        // the patch before the conflicting one was already cherry picked onto the destination
+        assert_ne!(repo.refname_to_id("refs/heads/dest").unwrap(), dest_oid);
+
+        restore_destination(&repo, &pending).unwrap();
+        clear_state(&repo).unwrap();
+
+        assert_eq!(repo.refname_to_id("refs/heads/dest").unwrap(), dest_oid);
+        assert!(read_state(&repo).unwrap().is_none());
+        assert!(!paths::conflict_worktree_path(&repo).exists());
+    }
+}
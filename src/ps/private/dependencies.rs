@@ -0,0 +1,370 @@
+// This module figures out which patches of a patch stack depend on which earlier patches. A patch
+// depends on an earlier patch when it can't be applied to the patch stack base without it. The
+// patches with hunks overlapping its own are tried as the prerequisites first, falling back to all
+// the earlier patches, & the prerequisites are then narrowed down to the minimal set it still
+// applies with. All the trial cherry picks happen in memory, nothing in the repository changes.
+
+use super::config;
+use super::events::{self, Event};
+use super::git;
+use std::collections::BTreeSet;
+use std::result::Result;
+
+#[derive(Debug)]
+pub enum DependenciesError {
+    MergeCommitDetected(String),
+    ConflictsExist(String, String),
+    CreateCommitFailed(git::CreateCommitError),
+    RewriteCommitterFailed(git::RewrittenCommitterError),
+    Git(git2::Error),
+}
+
+impl From<git2::Error> for DependenciesError {
+    fn from(e: git2::Error) -> Self {
+        Self::Git(e)
+    }
+}
+
+impl std::fmt::Display for DependenciesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::MergeCommitDetected(oid) => write!(f, "merge commit detected with sha {}", oid),
+            Self::ConflictsExist(oid_a, oid_b) => {
+                write!(f, "conflicts exist between shas {} and {}", oid_a, oid_b)
+            }
+            Self::CreateCommitFailed(e) => write!(f, "failed to create commit, {:?}", e),
+            Self::RewriteCommitterFailed(e) => write!(f, "failed to get committer, {}", e),
+            Self::Git(e) => write!(f, "failed to compute patch dependencies, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DependenciesError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::MergeCommitDetected(_) => None,
+            Self::ConflictsExist(_, _) => None,
+            Self::CreateCommitFailed(_) => None,
+            Self::RewriteCommitterFailed(e) => Some(e),
+            Self::Git(e) => Some(e),
+        }
+    }
+}
+
+/// The lines a hunk covers in a file, before or after the change
+#[derive(Debug, Clone)]
+struct HunkLines {
+    path: String,
+    start: u32,
+    end: u32,
+}
+
+impl HunkLines {
+    fn overlaps(&self, other: &HunkLines) -> bool {
+        self.path == other.path && self.start <= other.end && other.start <= self.end
+    }
+}
+
+/// The lines the patch covers in the files it changes, both before and after the change, context
+/// included
+fn patch_hunks(
+    repo: &git2::Repository,
+    commit: &git2::Commit,
+) -> Result<(Vec<HunkLines>, Vec<HunkLines>), DependenciesError> {
+    let parent_tree = commit.parent(0)?.tree()?;
+    let diff = repo.diff_tree_to_tree(Some(&parent_tree), Some(&commit.tree()?), None)?;
+
+    let mut old_hunks: Vec<HunkLines> = vec![];
+    let mut new_hunks: Vec<HunkLines> = vec![];
+    diff.foreach(
+        &mut |_, _| true,
+        None,
+        Some(&mut |delta, hunk| {
+            let path = |file: git2::DiffFile| {
+                file.path()
+                    .map(|p| p.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            };
+            old_hunks.push(HunkLines {
+                path: path(delta.old_file()),
+                start: hunk.old_start(),
+                end: hunk.old_start() + hunk.old_lines(),
+            });
+            new_hunks.push(HunkLines {
+                path: path(delta.new_file()),
+                start: hunk.new_start(),
+                end: hunk.new_start() + hunk.new_lines(),
+            });
+            true
+        }),
+        None,
+    )?;
+    Ok((old_hunks, new_hunks))
+}
+
+/// Whether the patches apply one after the other onto the tree without conflicts
+fn patches_apply(
+    repo: &git2::Repository,
+    base_tree: &git2::Tree,
+    commits: &[&git2::Commit],
+) -> Result<bool, DependenciesError> {
+    let mut tree_oid = base_tree.id();
+    for commit in commits {
+        let destination_tree = repo.find_tree(tree_oid)?;
+        let mut index = repo.merge_trees(
+            &commit.parent(0)?.tree()?,
+            &destination_tree,
+            &commit.tree()?,
+            None,
+        )?;
+        if index.has_conflicts() {
+            return Ok(false);
+        }
+        tree_oid = index.write_tree_to(repo)?;
+    }
+    Ok(true)
+}
+
+/// The given patch indexes along with all the patches they depend on, directly or indirectly,
+/// sorted in patch stack order
+fn with_prerequisites(dependencies: &[Vec<usize>], indexes: &BTreeSet<usize>) -> BTreeSet<usize> {
+    let mut closure: BTreeSet<usize> = BTreeSet::new();
+    let mut pending: Vec<usize> = indexes.iter().copied().collect();
+    while let Some(index) = pending.pop() {
+        if closure.insert(index) {
+            if let Some(index_dependencies) = dependencies.get(index) {
+                pending.extend(index_dependencies.iter().copied());
+            }
+        }
+    }
+    closure
+}
+
+/// Compute the patches each of the given patches directly depends on, as indexes into the given
+/// patches. The patches are expected in patch stack order, starting right on top of the base.
+pub fn patch_dependencies(
+    repo: &git2::Repository,
+    base_oid: git2::Oid,
+    patch_oids: &[git2::Oid],
+) -> Result<Vec<Vec<usize>>, DependenciesError> {
+    let base_tree = repo.find_commit(base_oid)?.tree()?;
+    let commits = patch_oids
+        .iter()
+        .map(|oid| repo.find_commit(*oid))
+        .collect::<Result<Vec<git2::Commit>, git2::Error>>()?;
+    if let Some(merge_commit) = commits.iter().find(|c| c.parent_count() > 1) {
+        return Err(DependenciesError::MergeCommitDetected(
+            merge_commit.id().to_string(),
+        ));
+    }
+    let hunks = commits
+        .iter()
+        .map(|c| patch_hunks(repo, c))
+        .collect::<Result<Vec<_>, DependenciesError>>()?;
+
+    let mut dependencies: Vec<Vec<usize>> = vec![];
+    for (index, commit) in commits.iter().enumerate() {
+        // the patch applies onto the trial of its prerequisites, in patch stack order
+        let applies_with = |prerequisites: &BTreeSet<usize>| {
+            let trial: Vec<&git2::Commit> = with_prerequisites(&dependencies, prerequisites)
+                .into_iter()
+                .map(|i| &commits[i])
+                .chain(std::iter::once(commit))
+                .collect();
+            patches_apply(repo, &base_tree, &trial)
+        };
+
+        if applies_with(&BTreeSet::new())? {
+            dependencies.push(vec![]);
+            continue;
+        }
+
+        let (old_hunks, _) = &hunks[index];
+        let overlapping: BTreeSet<usize> = (0..index)
+            .filter(|i| {
+                let (_, earlier_new_hunks) = &hunks[*i];
+                old_hunks
+                    .iter()
+                    .any(|h| earlier_new_hunks.iter().any(|e| h.overlaps(e)))
+            })
+            .collect();
+        let mut candidates = if applies_with(&overlapping)? {
+            overlapping
+        } else {
+            (0..index).collect()
+        };
+
+        // drop the candidates it still applies without
+        for candidate in candidates.clone().into_iter().rev() {
+            let mut without_candidate = candidates.clone();
+            without_candidate.remove(&candidate);
+            if applies_with(&without_candidate)? {
+                candidates = without_candidate;
+            }
+        }
+
+        // keep only the direct dependencies, the ones not already implied by another
+        let direct: Vec<usize> = candidates
+            .iter()
+            .copied()
+            .filter(|candidate| {
+                !candidates.iter().any(|other| {
+                    other != candidate
+                        && with_prerequisites(&dependencies, &BTreeSet::from([*other]))
+                            .contains(candidate)
+                })
+            })
+            .collect();
+        dependencies.push(direct);
+    }
+    Ok(dependencies)
+}
+
+/// The patches outside of the given range of patch indexes the patches in the range depend on,
+/// directly or indirectly, sorted in patch stack order
+pub fn prerequisites(
+    dependencies: &[Vec<usize>],
+    start_patch_index: usize,
+    end_patch_index: Option<usize>,
+) -> Vec<usize> {
+    let end_patch_index = end_patch_index.unwrap_or(start_patch_index);
+    let range: BTreeSet<usize> = (start_patch_index..=end_patch_index).collect();
+    with_prerequisites(dependencies, &range)
+        .into_iter()
+        .filter(|i| !range.contains(i))
+        .collect()
+}
+
+/// The patches outside of the given range of patch indexes the patches in the range depend on,
+/// computing the dependencies of the patches up to the end of the range
+pub fn range_prerequisites(
+    repo: &git2::Repository,
+    base_oid: git2::Oid,
+    patch_oids: &[git2::Oid],
+    start_patch_index: usize,
+    end_patch_index: Option<usize>,
+) -> Result<Vec<usize>, DependenciesError> {
+    let end_index = end_patch_index
+        .unwrap_or(start_patch_index)
+        .min(patch_oids.len().saturating_sub(1));
+    let dependencies = patch_dependencies(repo, base_oid, &patch_oids[..=end_index])?;
+    Ok(prerequisites(
+        &dependencies,
+        start_patch_index,
+        end_patch_index,
+    ))
+}
+
+/// Cherry pick the prerequisite patch onto the destination ref without its patch id, so that the
+/// branch stays associated with just the patches it was created for
+pub fn cherry_pick_prerequisite(
+    repo: &git2::Repository,
+    config: &git2::Config,
+    rewrite_config: &config::PsRewriteConfig,
+    oid: git2::Oid,
+    dest_ref_name: &str,
+) -> Result<git2::Oid, DependenciesError> {
+    let commit = repo.find_commit(oid)?;
+    if commit.parent_count() > 1 {
+        return Err(DependenciesError::MergeCommitDetected(oid.to_string()));
+    }
+    let destination_commit = repo.find_reference(dest_ref_name)?.peel_to_commit()?;
+    let mut index = repo.merge_trees(
+        &commit.parent(0)?.tree()?,
+        &destination_commit.tree()?,
+        &commit.tree()?,
+        None,
+    )?;
+    if index.has_conflicts() {
+        return Err(DependenciesError::ConflictsExist(
+            oid.to_string(),
+            destination_commit.id().to_string(),
+        ));
+    }
+    let tree = repo.find_tree(index.write_tree_to(repo)?)?;
+
+    let message: String = commit
+        .message()
+        .unwrap_or_default()
+        .lines()
+        .filter(|line| !line.contains("<!-- ps-id:"))
+        .collect::<Vec<&str>>()
+        .join("\n");
+    let author = commit.author();
+    let committer = git::rewritten_committer(repo, rewrite_config.committer, &commit, 0)
+        .map_err(DependenciesError::RewriteCommitterFailed)?;
+    let new_commit_oid = git::create_commit(
+        repo,
+        config,
+        rewrite_config.sign,
+        dest_ref_name,
+        &author,
+        &committer,
+        message.trim_end(),
+        &tree,
+        &[&destination_commit],
+    )
+    .map_err(DependenciesError::CreateCommitFailed)?;
+    events::emit(Event::PatchReplayed {
+        oid,
+        new_oid: new_commit_oid,
+        summary: commit.summary().unwrap_or_default().to_string(),
+    });
+    Ok(new_commit_oid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::git::test_utils;
+    use super::*;
+
+    #[test]
+    fn test_patch_dependencies_finds_minimal_prerequisites() {
+        let (_dir, repo) = test_utils::repo_init();
+        let base_oid = test_utils::commit_files(
+            &repo,
+            &[("a.txt", "a1\na2\na3\n"), ("b.txt", "b1\nb2\nb3\n")],
+            "base",
+        );
+        let patch_oids = vec![
+            test_utils::commit_files(&repo, &[("a.txt", "a1 changed\na2\na3\n")], "change a1"),
+            test_utils::commit_files(&repo, &[("b.txt", "b1 changed\nb2\nb3\n")], "change b1"),
+            test_utils::commit_files(
+                &repo,
+                &[("a.txt", "a1 changed again\na2\na3\n")],
+                "change a1 again",
+            ),
+            test_utils::commit_files(&repo, &[("c.txt", "c1\n")], "add c"),
+            test_utils::commit_files(
+                &repo,
+                &[
+                    ("a.txt", "a1 changed again\na2\na3\n"),
+                    ("c.txt", "c1 changed\n"),
+                ],
+                "change c1",
+            ),
+        ];
+
+        let dependencies = patch_dependencies(&repo, base_oid, &patch_oids).unwrap();
+        assert_eq!(dependencies, vec![vec![], vec![], vec![0], vec![], vec![3]]);
+
+        assert_eq!(prerequisites(&dependencies, 2, None), vec![0]);
+        assert_eq!(prerequisites(&dependencies, 2, Some(4)), vec![0]);
+        assert!(prerequisites(&dependencies, 0, Some(1)).is_empty());
+    }
+
+    #[test]
+    fn test_patch_dependencies_follows_indirect_dependencies() {
+        let (_dir, repo) = test_utils::repo_init();
+        let base_oid = test_utils::commit_files(&repo, &[("a.txt", "1\n")], "base");
+        let patch_oids = vec![
+            test_utils::commit_files(&repo, &[("a.txt", "2\n")], "second"),
+            test_utils::commit_files(&repo, &[("a.txt", "3\n")], "third"),
+            test_utils::commit_files(&repo, &[("a.txt", "4\n")], "fourth"),
+        ];
+
+        let dependencies = patch_dependencies(&repo, base_oid, &patch_oids).unwrap();
+        assert_eq!(dependencies, vec![vec![], vec![0], vec![1]]);
+        assert_eq!(prerequisites(&dependencies, 2, None), vec![0, 1]);
+    }
+}
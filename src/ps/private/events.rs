@@ -0,0 +1,157 @@
+// This module is the event bus of Git Patch Stack. Long running operations, e.g. replaying patches,
+// executing hooks or pushing, emit events as they progress & every subscribed observer is notified
+// of them, so that the CLI can render a progress display & library users their own UIs, without
+// the operations having to thread an observer through all of their functions.
+
+use std::sync::{Arc, Mutex};
+
+/// Something that happened during an operation
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    /// A patch was replayed onto a new base as a new commit, e.g. when cherry picking, rebasing or
+    /// adding patch ids
+    PatchReplayed {
+        oid: git2::Oid,
+        new_oid: git2::Oid,
+        summary: String,
+    },
+    HookStarted {
+        name: String,
+    },
+    /// The hook exited, successfully or not, or couldn't be executed
+    HookFinished {
+        name: String,
+        success: bool,
+    },
+    /// The hook was found at the path but isn't executable, so it was skipped
+    HookNotExecutable {
+        name: String,
+        path: std::path::PathBuf,
+    },
+    FetchStarted,
+    FetchFinished {
+        success: bool,
+    },
+    /// Pushing the refspecs, e.g. `ps/rr/foo:ps/rr/foo`, to the remote started
+    PushStarted {
+        remote: String,
+        refspecs: Vec<String>,
+    },
+    PushFinished {
+        remote: String,
+        success: bool,
+    },
+    /// The push to the upstream was rejected because it moved & is retried after the delay, as
+    /// the given attempt out of the maximum attempts
+    PushRetryScheduled {
+        upstream: String,
+        delay_secs: u64,
+        attempt: u32,
+        max_attempts: u32,
+    },
+    /// The patch branch was rebuilt on top of the latest upstream as the new commit, e.g. when
+    /// integrate retries after the upstream moved
+    BranchRebuilt {
+        name: String,
+        new_oid: git2::Oid,
+    },
+    /// A branch was deleted, from the given remote or locally when there is none
+    BranchDeleted {
+        name: String,
+        remote: Option<String>,
+    },
+}
+
+/// Something notified of the events of operations, any `Fn(&Event)` closure for example
+pub trait Observer: Send + Sync {
+    fn on_event(&self, event: &Event);
+}
+
+impl<F> Observer for F
+where
+    F: Fn(&Event) + Send + Sync,
+{
+    fn on_event(&self, event: &Event) {
+        self(event)
+    }
+}
+
+lazy_static! {
+    static ref OBSERVERS: Mutex<Vec<(usize, Arc<dyn Observer>)>> = Mutex::new(vec![]);
+    static ref NEXT_SUBSCRIPTION_ID: Mutex<usize> = Mutex::new(0);
+}
+
+/// The subscription of an observer, which stays subscribed until the subscription is dropped
+#[must_use = "the observer is unsubscribed as soon as the subscription is dropped"]
+pub struct Subscription {
+    id: usize,
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Ok(mut observers) = OBSERVERS.lock() {
+            observers.retain(|(id, _)| *id != self.id);
+        }
+    }
+}
+
+/// Subscribe the observer to the events of all operations until the returned subscription is
+/// dropped
+pub fn subscribe<O>(observer: O) -> Subscription
+where
+    O: Observer + 'static,
+{
+    let id = {
+        let mut next_id = NEXT_SUBSCRIPTION_ID.lock().unwrap();
+        *next_id += 1;
+        *next_id
+    };
+    OBSERVERS.lock().unwrap().push((id, Arc::new(observer)));
+    Subscription { id }
+}
+
+/// Notify the subscribed observers of the event
+pub fn emit(event: Event) {
+    // the observers are notified outside of the lock so they can subscribe & unsubscribe
+    let observers: Vec<Arc<dyn Observer>> = match OBSERVERS.lock() {
+        Ok(observers) => observers.iter().map(|(_, o)| o.clone()).collect(),
+        Err(_) => return,
+    };
+    for observer in observers.iter() {
+        observer.on_event(&event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{emit, subscribe, Event};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn subscribed_observers_are_notified_until_unsubscribed() {
+        let received: Arc<Mutex<Vec<Event>>> = Arc::new(Mutex::new(vec![]));
+        let received_by_observer = received.clone();
+        let subscription = subscribe(move |event: &Event| {
+            if let Event::HookStarted { ref name } = event {
+                if name.starts_with("events_test_") {
+                    received_by_observer.lock().unwrap().push(event.clone());
+                }
+            }
+        });
+
+        emit(Event::HookStarted {
+            name: "events_test_first".to_string(),
+        });
+        drop(subscription);
+        emit(Event::HookStarted {
+            name: "events_test_second".to_string(),
+        });
+
+        assert_eq!(
+            *received.lock().unwrap(),
+            vec![Event::HookStarted {
+                name: "events_test_first".to_string()
+            }]
+        );
+    }
+}
@@ -30,16 +30,21 @@
 mod branch_upstream_name;
 mod commit_diff;
 mod commit_diff_patch_id;
+mod commit_diff_text;
 mod common_ancestor;
 pub mod config;
+mod configured_signer;
 mod count_commits;
 mod create_commit;
 mod create_cwd_repo;
 mod create_signed_commit;
+mod create_signed_tag;
 mod create_unsigned_commit;
+mod ext_commit_signatures;
 mod ext_delete_remote_branch;
 mod ext_fetch;
 mod ext_push;
+mod ext_push_atomic;
 mod get_current_branch;
 mod get_current_branch_shorthand;
 mod get_revs;
@@ -55,22 +60,28 @@ mod line_to_rebase_todo;
 #[cfg(feature = "backup_cmd")]
 mod read_hashed_object;
 mod rebase_todo;
+mod rewritten_committer;
 pub mod signers;
 mod str_to_rebase_todos;
 #[cfg(test)]
-mod test_utils;
+pub mod test_utils;
 mod uncommited_changes_exist;
 
 pub use branch_upstream_name::*;
 pub use commit_diff::*;
 pub use commit_diff_patch_id::*;
+pub use commit_diff_text::*;
 pub use common_ancestor::*;
+pub use configured_signer::*;
 pub use count_commits::*;
 pub use create_commit::*;
 pub use create_cwd_repo::*;
+pub use create_signed_tag::*;
+pub use ext_commit_signatures::*;
 pub use ext_delete_remote_branch::*;
 pub use ext_fetch::*;
 pub use ext_push::*;
+pub use ext_push_atomic::*;
 pub use get_current_branch::*;
 pub use get_current_branch_shorthand::*;
 pub use get_revs::*;
@@ -86,5 +97,6 @@ pub use line_to_rebase_todo::*;
 #[cfg(feature = "backup_cmd")]
 pub use read_hashed_object::*;
 pub use rebase_todo::*;
+pub use rewritten_committer::*;
 pub use str_to_rebase_todos::*;
 pub use uncommited_changes_exist::*;
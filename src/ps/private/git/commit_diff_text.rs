@@ -0,0 +1,96 @@
+use super::commit_diff::{commit_diff, CommitDiffError};
+use git2;
+use std::result::Result;
+
+#[derive(Debug)]
+pub enum CommitDiffTextError {
+    GetDiff(CommitDiffError),
+    PrintDiff(git2::Error),
+    DiffText(git2::Error),
+}
+
+impl std::fmt::Display for CommitDiffTextError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::GetDiff(e) => write!(f, "get diff failed, {}", e),
+            Self::PrintDiff(e) => write!(f, "print diff failed, {}", e),
+            Self::DiffText(e) => write!(f, "diff the diffs failed, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CommitDiffTextError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::GetDiff(e) => Some(e),
+            Self::PrintDiff(e) => Some(e),
+            Self::DiffText(e) => Some(e),
+        }
+    }
+}
+
+/// Get the diff the commit introduces as unified diff text
+pub fn commit_diff_text(
+    repo: &git2::Repository,
+    commit: &git2::Commit,
+) -> Result<String, CommitDiffTextError> {
+    let diff = commit_diff(repo, commit).map_err(CommitDiffTextError::GetDiff)?;
+    let mut text = String::new();
+    diff.print(git2::DiffFormat::Patch, |_delta, _hunk, line| {
+        if let '+' | '-' | ' ' = line.origin() {
+            text.push(line.origin());
+        }
+        text.push_str(&String::from_utf8_lossy(line.content()));
+        true
+    })
+    .map_err(CommitDiffTextError::PrintDiff)?;
+    Ok(text)
+}
+
+/// Get the unified diff between the diffs two commits introduce, e.g. between a patch & its
+/// counterpart on a request review branch, None when they introduce the same diff
+pub fn commit_diffs_diff(
+    repo: &git2::Repository,
+    old_commit: &git2::Commit,
+    new_commit: &git2::Commit,
+) -> Result<Option<String>, CommitDiffTextError> {
+    let old_text = commit_diff_text(repo, old_commit)?;
+    let new_text = commit_diff_text(repo, new_commit)?;
+    if old_text == new_text {
+        return Ok(None);
+    }
+
+    let mut patch = git2::Patch::from_buffers(
+        old_text.as_bytes(),
+        Some(std::path::Path::new(&old_commit.id().to_string())),
+        new_text.as_bytes(),
+        Some(std::path::Path::new(&new_commit.id().to_string())),
+        None,
+    )
+    .map_err(CommitDiffTextError::DiffText)?;
+    let buf = patch.to_buf().map_err(CommitDiffTextError::DiffText)?;
+    Ok(Some(String::from_utf8_lossy(&buf).to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_utils::{create_commit, repo_init};
+
+    #[test]
+    fn commit_diffs_diff_reports_only_differing_patches() {
+        let (_td, repo) = repo_init();
+
+        let a_oid = create_commit(&repo, "fileA.txt", b"one\n", "add one");
+        let b_oid = create_commit(&repo, "fileB.txt", b"one\n", "add one elsewhere");
+        let c_oid = create_commit(&repo, "fileB.txt", b"two\n", "change to two");
+        let a = repo.find_commit(a_oid).unwrap();
+        let b = repo.find_commit(b_oid).unwrap();
+        let c = repo.find_commit(c_oid).unwrap();
+
+        assert!(super::commit_diffs_diff(&repo, &a, &a).unwrap().is_none());
+
+        let diff = super::commit_diffs_diff(&repo, &b, &c).unwrap().unwrap();
+        assert!(diff.contains("-+one"));
+        assert!(diff.contains("++two"));
+    }
+}
@@ -3,13 +3,21 @@ use std::result::Result;
 
 #[derive(Debug)]
 pub enum CommonAncestorError {
-    MergeBase { one: git2::Oid, two: git2::Oid, reason: git2::Error }
+    MergeBase {
+        one: git2::Oid,
+        two: git2::Oid,
+        reason: git2::Error,
+    },
 }
 
 impl std::fmt::Display for CommonAncestorError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::MergeBase { one, two, reason } => write!(f, "failed to get merge base for {} and {}, {}", one, two, reason),
+            Self::MergeBase { one, two, reason } => write!(
+                f,
+                "failed to get merge base for {} and {}, {}",
+                one, two, reason
+            ),
         }
     }
 }
@@ -17,7 +25,11 @@ impl std::fmt::Display for CommonAncestorError {
 impl std::error::Error for CommonAncestorError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            Self::MergeBase { one: _, two: _, reason } => Some(reason),
+            Self::MergeBase {
+                one: _,
+                two: _,
+                reason,
+            } => Some(reason),
         }
     }
 }
@@ -29,6 +41,10 @@ pub fn common_ancestor(
 ) -> Result<git2::Oid, CommonAncestorError> {
     let merge_base_oid = repo
         .merge_base(one, two)
-        .map_err(|e| CommonAncestorError::MergeBase { one: one, two: two, reason: e })?;
+        .map_err(|e| CommonAncestorError::MergeBase {
+            one,
+            two,
+            reason: e,
+        })?;
     Ok(merge_base_oid)
 }
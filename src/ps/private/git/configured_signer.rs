@@ -0,0 +1,143 @@
+use super::super::utils;
+use super::config::{config_get_string, ConfigGetError};
+use super::signers;
+use git2;
+use std::result::Result;
+
+/// Signs the given content, returning the signature to embed in the signed object
+pub type Signer = Box<dyn Fn(String) -> Result<String, signers::SignerError>>;
+
+#[derive(Debug)]
+pub enum ConfiguredSignerError {
+    GetGpgFormatFailed(ConfigGetError),
+    GetGpgProgramFailed(ConfigGetError),
+    GetUserSigningKeyFailed(ConfigGetError),
+    UserSigningKeyNotFoundInGitConfig,
+    GetDefaultKeyCommandFailed(ConfigGetError),
+    DefaultKeyCommandFailed(String),
+    UnsupportedGpgFormat(String),
+}
+
+impl std::fmt::Display for ConfiguredSignerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::GetGpgFormatFailed(e) => write!(f, "get gpg.format failed, {}", e),
+            Self::GetGpgProgramFailed(e) => write!(f, "get gpg program failed, {}", e),
+            Self::GetUserSigningKeyFailed(e) => write!(f, "get user.signingkey failed, {}", e),
+            Self::UserSigningKeyNotFoundInGitConfig => {
+                write!(f, "user.signingkey not found in git config")
+            }
+            Self::GetDefaultKeyCommandFailed(e) => {
+                write!(f, "get gpg.ssh.defaultKeyCommand failed, {}", e)
+            }
+            Self::DefaultKeyCommandFailed(reason) => {
+                write!(f, "gpg.ssh.defaultKeyCommand failed, {}", reason)
+            }
+            Self::UnsupportedGpgFormat(format) => {
+                write!(f, "gpg.format {} is not supported", format)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ConfiguredSignerError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::GetGpgFormatFailed(e) => Some(e),
+            Self::GetGpgProgramFailed(e) => Some(e),
+            Self::GetUserSigningKeyFailed(e) => Some(e),
+            Self::UserSigningKeyNotFoundInGitConfig => None,
+            Self::GetDefaultKeyCommandFailed(e) => Some(e),
+            Self::DefaultKeyCommandFailed(_) => None,
+            Self::UnsupportedGpgFormat(_) => None,
+        }
+    }
+}
+
+/// Get the signer for the `gpg.format` configured in git, openpgp by default, using the
+/// `user.signingkey` & the format specific or general gpg program. For ssh the key output by
+/// `gpg.ssh.defaultKeyCommand` is used when no `user.signingkey` is configured, e.g. a key held by
+/// `ssh-agent` listed with `ssh-add -L`, so signing doesn't need a key file or passphrase prompts.
+pub fn configured_signer(config: &git2::Config) -> Result<Signer, ConfiguredSignerError> {
+    let gpg_format = config_get_string(config, "gpg.format")
+        .map_err(ConfiguredSignerError::GetGpgFormatFailed)?
+        .unwrap_or(("openpgp").to_string());
+
+    // If program is specified for the desired format, use that program to sign the commit.
+    // Otherwise, fallback to the general program (legacy for opengpg).
+    let gpg_program_option = config_get_string(config, &format!("gpg.{}.program", gpg_format))
+        .and_then(|opt| {
+            opt.map_or_else(|| config_get_string(config, "gpg.program"), |v| Ok(Some(v)))
+        })
+        .map_err(ConfiguredSignerError::GetGpgProgramFailed)?;
+
+    let signing_key_option = config_get_string(config, "user.signingkey")
+        .map_err(ConfiguredSignerError::GetUserSigningKeyFailed)?;
+    let signing_key_config = match signing_key_option {
+        Some(signing_key) => signing_key,
+        None if gpg_format == "ssh" => {
+            let default_key_command = config_get_string(config, "gpg.ssh.defaultKeyCommand")
+                .map_err(ConfiguredSignerError::GetDefaultKeyCommandFailed)?
+                .ok_or(ConfiguredSignerError::UserSigningKeyNotFoundInGitConfig)?;
+            ssh_default_key(&default_key_command)?
+        }
+        None => return Err(ConfiguredSignerError::UserSigningKeyNotFoundInGitConfig),
+    };
+
+    match gpg_format.as_str() {
+        "openpgp" => Ok(Box::new(signers::gpg_signer(
+            signing_key_config,
+            gpg_program_option,
+        ))),
+        "ssh" => Ok(Box::new(signers::ssh_signer(
+            signing_key_config,
+            gpg_program_option,
+        ))),
+        "x509" => Ok(Box::new(signers::x509_signer(
+            signing_key_config,
+            gpg_program_option,
+        ))),
+        _ => Err(ConfiguredSignerError::UnsupportedGpgFormat(gpg_format)),
+    }
+}
+
+/// Run the `gpg.ssh.defaultKeyCommand` the way git does, using the first line it outputs as the
+/// literal ssh public key to sign with
+fn ssh_default_key(default_key_command: &str) -> Result<String, ConfiguredSignerError> {
+    let output = utils::execute_with_output("sh", &["-c", default_key_command])
+        .map_err(|e| ConfiguredSignerError::DefaultKeyCommandFailed(e.to_string()))?;
+    if !output.status.success() {
+        return Err(ConfiguredSignerError::DefaultKeyCommandFailed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let key = stdout.lines().next().unwrap_or_default().trim();
+    if key.starts_with("ssh-") || key.starts_with("key::") {
+        Ok(key.to_string())
+    } else {
+        Err(ConfiguredSignerError::DefaultKeyCommandFailed(format!(
+            "it returned no ssh key, {}",
+            key
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn ssh_default_key_uses_first_key_output_by_command() {
+        let key = super::ssh_default_key(
+            "printf 'ssh-ed25519 AAAAC3Nz first@example.com\\nssh-rsa AAAAB3Nz second\\n'",
+        )
+        .unwrap();
+        assert_eq!(key, "ssh-ed25519 AAAAC3Nz first@example.com");
+    }
+
+    #[test]
+    fn ssh_default_key_fails_without_key() {
+        assert!(super::ssh_default_key("echo 'The agent has no identities.'").is_err());
+        assert!(super::ssh_default_key("exit 2").is_err());
+    }
+}
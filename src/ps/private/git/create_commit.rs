@@ -1,7 +1,8 @@
-use super::config::{config_get_bool, config_get_string, ConfigGetError};
+use super::super::config::rewrite::SignPolicy;
+use super::config::{config_get_bool, ConfigGetError};
+use super::configured_signer::{configured_signer, ConfiguredSignerError};
 use super::create_signed_commit::{create_signed_commit, CreateSignedCommitError};
 use super::create_unsigned_commit::{create_unsigned_commit, CreateUnsignedCommitError};
-use super::signers;
 use git2;
 use std::result::Result;
 use std::str;
@@ -24,10 +25,14 @@ pub enum CreateCommitError {
     Unhandled(Box<dyn std::error::Error>),
 }
 
+/// Create the commit, signing it when `commit.gpgsign` is true or when the sign policy is `always`,
+/// rather than `config`, so that gps can sign the commits it rewrites regardless of how git is
+/// configured to sign the commits you make
 #[allow(clippy::too_many_arguments)]
 pub fn create_commit(
     repo: &'_ git2::Repository,
     config: &'_ git2::Config,
+    sign_policy: SignPolicy,
     dest_ref_name: &str,
     author: &git2::Signature<'_>,
     committer: &git2::Signature<'_>,
@@ -35,31 +40,18 @@ pub fn create_commit(
     tree: &git2::Tree<'_>,
     parents: &[&git2::Commit<'_>],
 ) -> Result<git2::Oid, CreateCommitError> {
-    let sign_commit_flag = config_get_bool(config, "commit.gpgsign")
-        .map_err(CreateCommitError::GetCommitGpgsignFailed)?
-        .unwrap_or(false);
+    let sign_commit_flag = match sign_policy {
+        SignPolicy::Config => config_get_bool(config, "commit.gpgsign")
+            .map_err(CreateCommitError::GetCommitGpgsignFailed)?
+            .unwrap_or(false),
+        SignPolicy::Always => true,
+    };
 
     if sign_commit_flag {
-        let gpg_format = config_get_string(config, "gpg.format")
-            .map_err(CreateCommitError::GetGpgFormatFailed)?
-            .unwrap_or(("openpgp").to_string());
-
-        // If program is specified for the desired format, use that program to sign the commit.
-        // Otherwise, fallback to the general program (legacy for opengpg).
-        let gpg_program_option = config_get_string(config, &format!("gpg.{}.program", gpg_format))
-            .and_then(|opt| {
-                opt.map_or_else(|| config_get_string(config, "gpg.program"), |v| Ok(Some(v)))
-            })
-            .map_err(|e| CreateCommitError::Unhandled(e.into()))?;
-
-        let signing_key_config = config_get_string(config, "user.signingkey")
-            .map_err(CreateCommitError::GetUserSigningKeyFailed)?
-            .ok_or(CreateCommitError::UserSigningKeyNotFoundInGitConfig)?;
-
-        match gpg_format.as_str() {
-            "openpgp" => create_signed_commit(
+        match configured_signer(config) {
+            Ok(signer) => create_signed_commit(
                 repo,
-                signers::gpg_signer(signing_key_config, gpg_program_option),
+                signer,
                 dest_ref_name,
                 author,
                 committer,
@@ -68,19 +60,8 @@ pub fn create_commit(
                 parents,
             )
             .map_err(CreateCommitError::CreateSignedCommitFailed),
-            "ssh" => create_signed_commit(
-                repo,
-                signers::ssh_signer(signing_key_config, gpg_program_option),
-                dest_ref_name,
-                author,
-                committer,
-                message,
-                tree,
-                parents,
-            )
-            .map_err(CreateCommitError::CreateSignedCommitFailed),
-            "x509" => {
-                eprintln!("Warning: gps currently does NOT support x509 signatures. See issue #44 - https://github.com/uptech/git-ps-rs/issues");
+            Err(ConfiguredSignerError::UnsupportedGpgFormat(gpg_format)) => {
+                eprintln!("Warning: gps currently only supports GPG, SSH & x509 signatures, not {}. See issue #44 - https://github.com/uptech/git-ps-rs/issues", gpg_format);
                 eprintln!("The commit has been created unsigned!");
                 create_unsigned_commit(
                     repo,
@@ -93,20 +74,16 @@ pub fn create_commit(
                 )
                 .map_err(CreateCommitError::CreateUnsignedCommitFailed)
             }
-            _ => {
-                eprintln!("Warning: gps currently only supports GPG & SSH signatures. See issue #44 - https://github.com/uptech/git-ps-rs/issues");
-                eprintln!("The commit has been created unsigned!");
-                create_unsigned_commit(
-                    repo,
-                    dest_ref_name,
-                    author,
-                    committer,
-                    message,
-                    tree,
-                    parents,
-                )
-                .map_err(CreateCommitError::CreateUnsignedCommitFailed)
+            Err(ConfiguredSignerError::GetGpgFormatFailed(e)) => {
+                Err(CreateCommitError::GetGpgFormatFailed(e))
+            }
+            Err(ConfiguredSignerError::GetUserSigningKeyFailed(e)) => {
+                Err(CreateCommitError::GetUserSigningKeyFailed(e))
             }
+            Err(ConfiguredSignerError::UserSigningKeyNotFoundInGitConfig) => {
+                Err(CreateCommitError::UserSigningKeyNotFoundInGitConfig)
+            }
+            Err(e) => Err(CreateCommitError::Unhandled(e.into())),
         }
     } else {
         create_unsigned_commit(
@@ -121,3 +98,96 @@ pub fn create_commit(
         .map_err(CreateCommitError::CreateUnsignedCommitFailed)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_utils::{repo_init, throwaway_x509_certificate};
+    use super::SignPolicy;
+
+    #[test]
+    fn create_commit_signs_when_sign_policy_is_always() {
+        let (td, repo) = repo_init();
+        let program_path = td.path().join("fake-gpg");
+        std::fs::write(
+            &program_path,
+            "#!/bin/sh\ncat > /dev/null\necho '-----BEGIN PGP SIGNATURE-----'\necho fake\necho '-----END PGP SIGNATURE-----'\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(
+            &program_path,
+            std::os::unix::fs::PermissionsExt::from_mode(0o755),
+        )
+        .unwrap();
+
+        let config_path = td.path().join("signing.gitconfig");
+        std::fs::write(&config_path, "").unwrap();
+        let mut config = git2::Config::open(&config_path).unwrap();
+        config.set_bool("commit.gpgsign", false).unwrap();
+        config
+            .set_str("gpg.program", program_path.to_str().unwrap())
+            .unwrap();
+        config.set_str("user.signingkey", "ABCDEF").unwrap();
+
+        let head_commit = repo.head().unwrap().peel_to_commit().unwrap();
+        let sig = repo.signature().unwrap();
+        let oid = super::create_commit(
+            &repo,
+            &config,
+            SignPolicy::Always,
+            "refs/heads/main",
+            &sig,
+            &sig,
+            "signed regardless of commit.gpgsign",
+            &head_commit.tree().unwrap(),
+            &[&head_commit],
+        )
+        .unwrap();
+
+        let (signature, _) = repo.extract_signature(&oid, None).unwrap();
+        assert!(signature
+            .as_str()
+            .unwrap()
+            .starts_with("-----BEGIN PGP SIGNATURE-----\nfake\n"));
+    }
+
+    #[test]
+    fn create_commit_signs_with_x509_when_configured() {
+        let certificate = match throwaway_x509_certificate() {
+            Some(certificate) => certificate,
+            None => return,
+        };
+        let (td, repo) = repo_init();
+
+        let config_path = td.path().join("signing.gitconfig");
+        std::fs::write(&config_path, "").unwrap();
+        let mut config = git2::Config::open(&config_path).unwrap();
+        config.set_bool("commit.gpgsign", true).unwrap();
+        config.set_str("gpg.format", "x509").unwrap();
+        config
+            .set_str("gpg.x509.program", &certificate.program)
+            .unwrap();
+        config
+            .set_str("user.signingkey", &certificate.signing_key)
+            .unwrap();
+
+        let head_commit = repo.head().unwrap().peel_to_commit().unwrap();
+        let sig = repo.signature().unwrap();
+        let oid = super::create_commit(
+            &repo,
+            &config,
+            SignPolicy::Config,
+            "refs/heads/main",
+            &sig,
+            &sig,
+            "signed with x509",
+            &head_commit.tree().unwrap(),
+            &[&head_commit],
+        )
+        .unwrap();
+
+        let (signature, signed_data) = repo.extract_signature(&oid, None).unwrap();
+        let signature = signature.as_str().unwrap();
+        assert!(signature.starts_with("-----BEGIN SIGNED MESSAGE-----"));
+        assert!(certificate.verify(signed_data.as_str().unwrap(), signature));
+    }
+}
@@ -0,0 +1,151 @@
+use super::signers;
+use git2;
+use std::result::Result;
+
+#[derive(Debug)]
+pub enum CreateSignedTagError {
+    FindTarget(git2::Error),
+    SigningFailed(signers::SignerError),
+    WriteTag(git2::Error),
+    CreateReference(git2::Error),
+}
+
+impl std::fmt::Display for CreateSignedTagError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FindTarget(e) => write!(f, "find tag target failed, {}", e),
+            Self::SigningFailed(e) => write!(f, "signing tag failed, {:?}", e),
+            Self::WriteTag(e) => write!(f, "write tag object failed, {}", e),
+            Self::CreateReference(e) => write!(f, "create tag reference failed, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CreateSignedTagError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::FindTarget(e) => Some(e),
+            Self::SigningFailed(_) => None,
+            Self::WriteTag(e) => Some(e),
+            Self::CreateReference(e) => Some(e),
+        }
+    }
+}
+
+/// Create the annotated tag `name` of the target with the signature of the tag appended to its
+/// message, as `git tag -s` does, replacing an existing tag of the same name when `force` is given
+pub fn create_signed_tag<F>(
+    repo: &'_ git2::Repository,
+    signer: F,
+    name: &str,
+    target_oid: git2::Oid,
+    tagger: &git2::Signature<'_>,
+    message: &str,
+    force: bool,
+) -> Result<git2::Oid, CreateSignedTagError>
+where
+    F: Fn(String) -> Result<String, signers::SignerError>,
+{
+    let target = repo
+        .find_object(target_oid, None)
+        .map_err(CreateSignedTagError::FindTarget)?;
+    let target_type = target
+        .kind()
+        .map(|k| k.str())
+        .unwrap_or(git2::ObjectType::Commit.str());
+
+    let mut tag_as_str = format!(
+        "object {}\ntype {}\ntag {}\ntagger {}\n\n{}",
+        target_oid,
+        target_type,
+        name,
+        signature_line(tagger),
+        message
+    );
+    if !tag_as_str.ends_with('\n') {
+        tag_as_str.push('\n');
+    }
+
+    let signature = signer(tag_as_str.clone()).map_err(CreateSignedTagError::SigningFailed)?;
+    tag_as_str.push_str(&signature);
+
+    let tag_oid = repo
+        .odb()
+        .and_then(|odb| odb.write(git2::ObjectType::Tag, tag_as_str.as_bytes()))
+        .map_err(CreateSignedTagError::WriteTag)?;
+    repo.reference(
+        &format!("refs/tags/{}", name),
+        tag_oid,
+        force,
+        "create signed tag",
+    )
+    .map_err(CreateSignedTagError::CreateReference)?;
+
+    Ok(tag_oid)
+}
+
+/// Format the signature as it appears in the header of an object, e.g. `Jane <j@x.com> 1 +0100`
+fn signature_line(signature: &git2::Signature<'_>) -> String {
+    let when = signature.when();
+    let offset = when.offset_minutes();
+    format!(
+        "{} <{}> {} {}{:02}{:02}",
+        String::from_utf8_lossy(signature.name_bytes()),
+        String::from_utf8_lossy(signature.email_bytes()),
+        when.seconds(),
+        if offset < 0 { '-' } else { '+' },
+        offset.abs() / 60,
+        offset.abs() % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_utils::{create_commit, repo_init};
+
+    #[test]
+    fn create_signed_tag_appends_signature_to_message() {
+        let (_td, repo) = repo_init();
+        let commit_oid = create_commit(&repo, "fileA.txt", &[0, 1, 2, 3], "starting numbers");
+        let tagger = git2::Signature::new(
+            "Jane Doe",
+            "jane@example.com",
+            &git2::Time::new(1700000000, -90),
+        )
+        .unwrap();
+
+        let tag_oid =
+            super::create_signed_tag(
+                &repo,
+                |content: String| {
+                    assert!(content.starts_with(&format!("object {}\ntype commit\n", commit_oid)));
+                    assert!(
+                        content.contains("\ntagger Jane Doe <jane@example.com> 1700000000 -0130\n")
+                    );
+                    Ok(
+                        "-----BEGIN PGP SIGNATURE-----\nfake\n-----END PGP SIGNATURE-----\n"
+                            .to_string(),
+                    )
+                },
+                "ps/integrated/some-id",
+                commit_oid,
+                &tagger,
+                "Integrate some patches",
+                false,
+            )
+            .unwrap();
+
+        let tag = repo.find_tag(tag_oid).unwrap();
+        assert_eq!(tag.name(), Some("ps/integrated/some-id"));
+        assert_eq!(tag.target_id(), commit_oid);
+        assert_eq!(
+            tag.message(),
+            Some("Integrate some patches\n-----BEGIN PGP SIGNATURE-----\nfake\n-----END PGP SIGNATURE-----\n")
+        );
+        assert_eq!(
+            repo.refname_to_id("refs/tags/ps/integrated/some-id")
+                .unwrap(),
+            tag_oid
+        );
+    }
+}
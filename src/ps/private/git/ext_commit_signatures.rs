@@ -0,0 +1,215 @@
+use super::super::utils;
+use git2;
+use std::result::Result;
+
+/// The outcome of verifying the signature of a commit, as reported by git's `%G?` placeholder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureStatus {
+    /// Good signature by a trusted key, certificate or allowed signer
+    Good,
+    /// Good signature by a key that isn't trusted
+    GoodUntrusted,
+    Bad,
+    Expired,
+    ExpiredKey,
+    RevokedKey,
+    /// The signature can't be checked, e.g. because the key is missing
+    CannotCheck,
+    Unsigned,
+}
+
+impl SignatureStatus {
+    /// Whether the signature is a good signature by someone trusted
+    pub fn is_trusted(&self) -> bool {
+        *self == Self::Good
+    }
+
+    fn from_code(code: &str) -> Option<Self> {
+        match code {
+            "G" => Some(Self::Good),
+            "U" => Some(Self::GoodUntrusted),
+            "B" => Some(Self::Bad),
+            "X" => Some(Self::Expired),
+            "Y" => Some(Self::ExpiredKey),
+            "R" => Some(Self::RevokedKey),
+            "E" => Some(Self::CannotCheck),
+            "N" => Some(Self::Unsigned),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for SignatureStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Good => write!(f, "good"),
+            Self::GoodUntrusted => write!(f, "good but untrusted"),
+            Self::Bad => write!(f, "bad"),
+            Self::Expired => write!(f, "expired"),
+            Self::ExpiredKey => write!(f, "made by an expired key"),
+            Self::RevokedKey => write!(f, "made by a revoked key"),
+            Self::CannotCheck => write!(f, "can't be checked"),
+            Self::Unsigned => write!(f, "unsigned"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CommitSignature {
+    pub oid: git2::Oid,
+    pub status: SignatureStatus,
+    /// The signer, e.g. the user id of the key or the subject of the certificate, if known
+    pub signer: Option<String>,
+    /// The key, e.g. its id or fingerprint, if known
+    pub key: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum ExtCommitSignaturesError {
+    PathNotUtf8,
+    ExecuteFailed(utils::ExecuteWithOutputError),
+    Failed(String),
+    UnexpectedOutput(String),
+}
+
+impl std::fmt::Display for ExtCommitSignaturesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PathNotUtf8 => write!(f, "repository path not utf-8"),
+            Self::ExecuteFailed(e) => write!(f, "{}", e),
+            Self::Failed(stderr) => write!(f, "git show failed, {}", stderr),
+            Self::UnexpectedOutput(line) => write!(f, "unexpected output, {}", line),
+        }
+    }
+}
+
+impl std::error::Error for ExtCommitSignaturesError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::PathNotUtf8 => None,
+            Self::ExecuteFailed(e) => Some(e),
+            Self::Failed(_) => None,
+            Self::UnexpectedOutput(_) => None,
+        }
+    }
+}
+
+/// Verify the signatures of the given commits with git, which supports GPG, SSH (checked against
+/// `gpg.ssh.allowedSignersFile`) & x509 signatures according to its configuration
+pub fn ext_commit_signatures(
+    repo: &git2::Repository,
+    oids: &[git2::Oid],
+) -> Result<Vec<CommitSignature>, ExtCommitSignaturesError> {
+    if oids.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let git_dir = repo
+        .path()
+        .to_str()
+        .ok_or(ExtCommitSignaturesError::PathNotUtf8)?;
+    let git_dir_arg = format!("--git-dir={}", git_dir);
+    let oid_strs: Vec<String> = oids.iter().map(|oid| oid.to_string()).collect();
+    let mut args: Vec<&str> = vec![
+        &git_dir_arg,
+        "show",
+        "--no-patch",
+        "--no-walk=unsorted",
+        "--format=%H%x1f%G?%x1f%GS%x1f%GK",
+    ];
+    args.extend(oid_strs.iter().map(|oid| oid.as_str()));
+
+    let output = utils::execute_with_output("git", &args)
+        .map_err(ExtCommitSignaturesError::ExecuteFailed)?;
+    if !output.status.success() {
+        return Err(ExtCommitSignaturesError::Failed(
+            String::from_utf8_lossy(&output.stderr).to_string(),
+        ));
+    }
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            let unexpected = || ExtCommitSignaturesError::UnexpectedOutput(line.to_string());
+            let fields: Vec<&str> = line.split('\u{1f}').collect();
+            if fields.len() != 4 {
+                return Err(unexpected());
+            }
+            let non_empty = |s: &str| Some(s.to_string()).filter(|s| !s.is_empty());
+            Ok(CommitSignature {
+                oid: git2::Oid::from_str(fields[0]).map_err(|_| unexpected())?,
+                status: SignatureStatus::from_code(fields[1]).ok_or_else(unexpected)?,
+                signer: non_empty(fields[2]),
+                key: non_empty(fields[3]),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_utils::{create_commit, repo_init, throwaway_x509_certificate};
+    use super::SignatureStatus;
+
+    #[test]
+    fn ext_commit_signatures_reports_unsigned_commits() {
+        let (_td, repo) = repo_init();
+        let first_oid = create_commit(&repo, "fileA.txt", &[0, 1], "first");
+        let second_oid = create_commit(&repo, "fileB.txt", &[2, 3], "second");
+
+        let signatures = super::ext_commit_signatures(&repo, &[second_oid, first_oid]).unwrap();
+
+        assert_eq!(signatures.len(), 2);
+        assert_eq!(signatures[0].oid, second_oid);
+        assert_eq!(signatures[1].oid, first_oid);
+        assert!(signatures
+            .iter()
+            .all(|s| s.status == SignatureStatus::Unsigned && s.signer.is_none()));
+    }
+
+    #[test]
+    fn ext_commit_signatures_trusts_x509_signatures_issued_by_trusted_ca() {
+        let certificate = match throwaway_x509_certificate() {
+            Some(certificate) => certificate,
+            None => return,
+        };
+        let (_td, repo) = repo_init();
+        let mut config = repo.config().unwrap();
+        config.set_bool("commit.gpgsign", true).unwrap();
+        config.set_str("gpg.format", "x509").unwrap();
+        config
+            .set_str("gpg.x509.program", &certificate.program)
+            .unwrap();
+        config
+            .set_str("user.signingkey", &certificate.signing_key)
+            .unwrap();
+        let config = config.snapshot().unwrap();
+
+        let head_commit = repo.head().unwrap().peel_to_commit().unwrap();
+        let sig = repo.signature().unwrap();
+        let signed_oid = super::super::create_commit(
+            &repo,
+            &config,
+            super::super::super::config::rewrite::SignPolicy::Config,
+            "refs/heads/main",
+            &sig,
+            &sig,
+            "signed",
+            &head_commit.tree().unwrap(),
+            &[&head_commit],
+        )
+        .unwrap();
+        let unsigned_oid = create_commit(&repo, "fileA.txt", &[0, 1], "unsigned");
+
+        let signatures = super::ext_commit_signatures(&repo, &[signed_oid, unsigned_oid]).unwrap();
+
+        assert_eq!(signatures[0].status, SignatureStatus::Good);
+        assert!(signatures[0]
+            .signer
+            .as_ref()
+            .unwrap()
+            .contains("gps signer"));
+        assert_eq!(signatures[1].status, SignatureStatus::Unsigned);
+    }
+}
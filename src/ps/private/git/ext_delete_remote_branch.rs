@@ -1,3 +1,4 @@
+use super::super::events::{self, Event};
 use super::super::utils;
 use std::result::Result;
 
@@ -29,5 +30,9 @@ pub fn ext_delete_remote_branch(
     let refspecs = format!(":{}", branch_name);
     utils::execute("git", &["push", remote_name, &refspecs])
         .map_err(ExtDeleteRemoteBranchError::ExecuteFailed)?;
+    events::emit(Event::BranchDeleted {
+        name: branch_name.to_string(),
+        remote: Some(remote_name.to_string()),
+    });
     Ok(())
 }
@@ -1,3 +1,4 @@
+use super::super::events::{self, Event};
 use super::super::utils;
 use std::result::Result;
 
@@ -23,6 +24,10 @@ impl std::error::Error for ExtFetchError {
 }
 
 pub fn ext_fetch() -> Result<(), ExtFetchError> {
-    utils::execute("git", &["fetch"]).map_err(ExtFetchError::ExecuteFailed)?;
-    Ok(())
+    events::emit(Event::FetchStarted);
+    let result = utils::execute("git", &["fetch"]).map_err(ExtFetchError::ExecuteFailed);
+    events::emit(Event::FetchFinished {
+        success: result.is_ok(),
+    });
+    result
 }
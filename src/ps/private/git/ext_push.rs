@@ -1,3 +1,4 @@
+use super::super::events::{self, Event};
 use super::super::utils;
 use std::result::Result;
 use std::str;
@@ -23,18 +24,30 @@ impl std::error::Error for ExtForcePushError {
     }
 }
 
+/// Push the source ref to the destination ref on the named remote of the repository with git
 pub fn ext_push(
+    repo: &git2::Repository,
     force: bool,
     remote_name: &str,
     src_ref_spec: &str,
     dest_ref_spec: &str,
 ) -> Result<(), ExtForcePushError> {
     let refspecs = format!("{}:{}", src_ref_spec, dest_ref_spec);
-    if force {
-        utils::execute("git", &["push", "-f", remote_name, &refspecs])
+    events::emit(Event::PushStarted {
+        remote: remote_name.to_string(),
+        refspecs: vec![refspecs.clone()],
+    });
+    let git_dir_arg = format!("--git-dir={}", repo.path().display());
+    let result = if force {
+        utils::execute("git", &[&git_dir_arg, "push", "-f", remote_name, &refspecs])
             .map_err(ExtForcePushError::ExecuteFailed)
     } else {
-        utils::execute("git", &["push", remote_name, &refspecs])
+        utils::execute("git", &[&git_dir_arg, "push", remote_name, &refspecs])
             .map_err(ExtForcePushError::ExecuteFailed)
-    }
+    };
+    events::emit(Event::PushFinished {
+        remote: remote_name.to_string(),
+        success: result.is_ok(),
+    });
+    result
 }
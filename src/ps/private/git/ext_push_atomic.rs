@@ -0,0 +1,149 @@
+use super::super::events::{self, Event};
+use super::super::utils;
+use std::io::{self, Read, Write};
+use std::process::{Command, Stdio};
+use std::result::Result;
+
+/// The reasons `git push --porcelain` gives for rejecting a ref because the remote ref moved
+/// since it was fetched, as opposed to e.g. a tag already existing. Unlike the human readable
+/// output, the porcelain output isn't translated.
+const REMOTE_MOVED_REASONS: [&str; 3] = ["non-fast-forward", "fetch first", "stale info"];
+
+#[derive(Debug)]
+pub enum ExtPushAtomicError {
+    ExecuteFailed(utils::ExecuteError),
+    /// git push exited unsuccessfully, with the given `--porcelain` output
+    PushFailed(utils::ExecuteError, String),
+}
+
+impl ExtPushAtomicError {
+    /// Whether the push was rejected because a ref on the remote moved, e.g. the upstream got
+    /// pushed to in the meantime, rather than failing for another reason such as authentication,
+    /// the network, a protected branch or a remote hook declining it
+    pub fn remote_moved(&self) -> bool {
+        match self {
+            Self::ExecuteFailed(_) => false,
+            // the rejected refs are reported as `!\t<src>:<dst>\t[rejected] (<reason>)`
+            Self::PushFailed(_, porcelain) => porcelain.lines().any(|line| {
+                let fields: Vec<&str> = line.split('\t').collect();
+                match fields.as_slice() {
+                    ["!", _, summary] => REMOTE_MOVED_REASONS
+                        .iter()
+                        .any(|reason| *summary == format!("[rejected] ({})", reason)),
+                    _ => false,
+                }
+            }),
+        }
+    }
+}
+
+impl std::fmt::Display for ExtPushAtomicError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ExecuteFailed(e) => write!(f, "external atomic push failed, {}", e),
+            Self::PushFailed(e, _) => write!(f, "external atomic push failed, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ExtPushAtomicError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ExecuteFailed(e) => Some(e),
+            Self::PushFailed(e, _) => Some(e),
+        }
+    }
+}
+
+/// Push all the given refspecs to the remote in a single `git push --atomic`, so either every
+/// ref is updated or none is, signing the push certificate with `git push --signed` when `signed`
+pub fn ext_push_atomic(
+    remote_name: &str,
+    refspecs: &[String],
+    signed: bool,
+) -> Result<(), ExtPushAtomicError> {
+    let mut args: Vec<&str> = vec!["push", "--atomic", "--porcelain"];
+    if signed {
+        args.push("--signed");
+    }
+    args.push(remote_name);
+    args.extend(refspecs.iter().map(|r| r.as_str()));
+    events::emit(Event::PushStarted {
+        remote: remote_name.to_string(),
+        refspecs: refspecs.to_vec(),
+    });
+    let result = execute_relaying_stdout(&args);
+    events::emit(Event::PushFinished {
+        remote: remote_name.to_string(),
+        success: result.is_ok(),
+    });
+    result
+}
+
+/// Execute git with the given args, relaying its stdout to ours while also capturing it, so the
+/// reason of a failed push can be told apart
+fn execute_relaying_stdout(args: &[&str]) -> Result<(), ExtPushAtomicError> {
+    let mut child = Command::new("git")
+        .args(args)
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| ExtPushAtomicError::ExecuteFailed(utils::ExecuteError::SpawnFailure(e)))?;
+
+    let mut stdout: Vec<u8> = vec![];
+    if let Some(mut child_stdout) = child.stdout.take() {
+        let mut buf = [0u8; 4096];
+        loop {
+            match child_stdout.read(&mut buf) {
+                Ok(0) => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+                Ok(n) => {
+                    stdout.extend_from_slice(&buf[..n]);
+                    let _ = io::stdout().write_all(&buf[..n]);
+                }
+            }
+        }
+    }
+
+    let status = child
+        .wait()
+        .map_err(|e| ExtPushAtomicError::ExecuteFailed(utils::ExecuteError::Failure(e)))?;
+    utils::check_exit_status(status).map_err(|e| {
+        ExtPushAtomicError::PushFailed(e, String::from_utf8_lossy(&stdout).to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::utils::ExecuteError;
+    use super::ExtPushAtomicError;
+
+    fn push_failed(porcelain: &str) -> ExtPushAtomicError {
+        ExtPushAtomicError::PushFailed(ExecuteError::ExitStatus(1), porcelain.to_string())
+    }
+
+    #[test]
+    fn test_remote_moved_only_for_refs_rejected_as_stale() {
+        assert!(push_failed(
+            "To origin\n!\tps/tmp/integrate:refs/heads/main\t[rejected] (fetch first)\n!\t:refs/heads/ps/rr/foo\t[rejected] (atomic push failed)\nDone\n"
+        )
+        .remote_moved());
+        assert!(
+            push_failed("!\trefs/heads/main:refs/heads/main\t[rejected] (non-fast-forward)\n")
+                .remote_moved()
+        );
+        assert!(
+            push_failed("!\trefs/heads/main:refs/heads/main\t[rejected] (stale info)\n")
+                .remote_moved()
+        );
+        assert!(!push_failed(
+            "!\tps/tmp/integrate:refs/heads/main\t[remote rejected] (protected branch hook declined)\n"
+        )
+        .remote_moved());
+        assert!(!push_failed(
+            "!\trefs/tags/ps/integrated/x:refs/tags/ps/integrated/x\t[rejected] (already exists)\n!\tps/tmp/integrate:refs/heads/main\t[rejected] (atomic push failed)\n"
+        )
+        .remote_moved());
+        assert!(!push_failed("").remote_moved());
+    }
+}
@@ -8,7 +8,7 @@ pub fn get_revs(
     start: git2::Oid,
     end: git2::Oid,
     sort: git2::Sort,
-) -> Result<git2::Revwalk, GitError> {
+) -> Result<git2::Revwalk<'_>, GitError> {
     let mut rev_walk = repo.revwalk()?;
     rev_walk.push(end)?;
     rev_walk.hide(start)?;
@@ -0,0 +1,100 @@
+use super::super::config::rewrite::CommitterPolicy;
+use git2;
+use std::result::Result;
+
+#[derive(Debug)]
+pub enum RewrittenCommitterError {
+    GetSignatureFailed(git2::Error),
+}
+
+impl std::fmt::Display for RewrittenCommitterError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::GetSignatureFailed(e) => write!(f, "get committer signature failed, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RewrittenCommitterError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::GetSignatureFailed(e) => Some(e),
+        }
+    }
+}
+
+/// Get the committer for the rewrite of the given commit according to the committer policy. When
+/// reset, the committer time is offset by `time_offset` seconds so that commits
+/// rewritten more than once within a second still differ.
+pub fn rewritten_committer(
+    repo: &git2::Repository,
+    policy: CommitterPolicy,
+    commit: &git2::Commit<'_>,
+    time_offset: i64,
+) -> Result<git2::Signature<'static>, RewrittenCommitterError> {
+    match policy {
+        CommitterPolicy::Preserve => Ok(commit.committer().to_owned()),
+        CommitterPolicy::Reset => {
+            let committer = repo
+                .signature()
+                .map_err(RewrittenCommitterError::GetSignatureFailed)?;
+            let new_time = git2::Time::new(
+                committer.when().seconds() + time_offset,
+                committer.when().offset_minutes(),
+            );
+            git2::Signature::new(
+                &String::from_utf8_lossy(committer.name_bytes()),
+                &String::from_utf8_lossy(committer.email_bytes()),
+                &new_time,
+            )
+            .map_err(RewrittenCommitterError::GetSignatureFailed)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::test_utils::{create_commit, repo_init};
+    use super::CommitterPolicy;
+
+    #[test]
+    fn rewritten_committer_preserves_original_committer() {
+        let (_td, repo) = repo_init();
+        let oid = create_commit(&repo, "fileA.txt", &[0, 1], "first");
+        let committer = git2::Signature::new(
+            "Original Committer",
+            "original@example.com",
+            &git2::Time::new(1600000000, 120),
+        )
+        .unwrap();
+        let commit = repo.find_commit(oid).unwrap();
+        let amended_oid = commit
+            .amend(None, None, Some(&committer), None, None, None)
+            .unwrap();
+        let amended_commit = repo.find_commit(amended_oid).unwrap();
+
+        let rewritten =
+            super::rewritten_committer(&repo, CommitterPolicy::Preserve, &amended_commit, 1)
+                .unwrap();
+
+        assert_eq!(rewritten.name(), Some("Original Committer"));
+        assert_eq!(rewritten.email(), Some("original@example.com"));
+        assert_eq!(rewritten.when().seconds(), 1600000000);
+        assert_eq!(rewritten.when().offset_minutes(), 120);
+    }
+
+    #[test]
+    fn rewritten_committer_resets_to_current_user() {
+        let (_td, repo) = repo_init();
+        let oid = create_commit(&repo, "fileA.txt", &[0, 1], "first");
+        let commit = repo.find_commit(oid).unwrap();
+        let current = repo.signature().unwrap();
+
+        let rewritten =
+            super::rewritten_committer(&repo, CommitterPolicy::Reset, &commit, 1).unwrap();
+
+        assert_eq!(rewritten.name(), current.name());
+        assert_eq!(rewritten.email(), current.email());
+        assert!(rewritten.when().seconds() > current.when().seconds());
+    }
+}
@@ -1,7 +1,9 @@
 mod gpg_signer;
 mod signer_error;
 mod ssh_signer;
+mod x509_signer;
 
 pub use gpg_signer::*;
 pub use signer_error::*;
 pub use ssh_signer::*;
+pub use x509_signer::*;
@@ -0,0 +1,103 @@
+use super::super::super::utils;
+use super::signer_error::SignerError;
+
+pub fn x509_signer(
+    signing_key: String,
+    program: Option<String>,
+) -> impl Fn(String) -> Result<String, SignerError> {
+    move |commit_string: String| {
+        x509_sign_string(commit_string, signing_key.clone(), program.clone())
+            .map_err(|e| SignerError::Signing(e.into()))
+    }
+}
+
+#[derive(Debug)]
+enum X509SignStringError {
+    SignCommandFailed(std::process::ExitStatus),
+    SignatureMissing,
+    Unhandled(Box<dyn std::error::Error>),
+}
+
+impl std::fmt::Display for X509SignStringError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::SignCommandFailed(status) => write!(f, "sign command failed, {}", status),
+            Self::SignatureMissing => write!(f, "sign command produced no signature"),
+            Self::Unhandled(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for X509SignStringError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::SignCommandFailed(_) => None,
+            Self::SignatureMissing => None,
+            Self::Unhandled(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+/// Sign the string with the certificate of the given key, e.g. its id, fingerprint or email
+/// address, producing a detached S/MIME signature the same way git does with `gpg.format = x509`
+fn x509_sign_string(
+    string: String,
+    signing_key: String,
+    program: Option<String>,
+) -> Result<String, X509SignStringError> {
+    let prog = program.unwrap_or("gpgsm".to_string());
+    let output = utils::execute_with_input_and_output(
+        &string,
+        &prog,
+        &["--detach-sign", "--armor", "-u", &signing_key],
+    )
+    .map_err(|e| X509SignStringError::Unhandled(e.into()))?;
+
+    if !output.status.success() {
+        return Err(X509SignStringError::SignCommandFailed(output.status));
+    }
+
+    let signature =
+        String::from_utf8(output.stdout).map_err(|e| X509SignStringError::Unhandled(e.into()))?;
+    if signature.trim().is_empty() {
+        return Err(X509SignStringError::SignatureMissing);
+    }
+    Ok(signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::test_utils::throwaway_x509_certificate;
+
+    #[test]
+    fn x509_signer_signs_with_certificate_issued_by_throwaway_ca() {
+        let certificate = match throwaway_x509_certificate() {
+            Some(certificate) => certificate,
+            None => return,
+        };
+
+        let signer = super::x509_signer(
+            certificate.signing_key.clone(),
+            Some(certificate.program.clone()),
+        );
+        let signature = signer("some commit content\n".to_string()).unwrap();
+        assert!(signature.starts_with("-----BEGIN SIGNED MESSAGE-----"));
+
+        assert!(certificate.verify("some commit content\n", &signature));
+        assert!(!certificate.verify("some other content\n", &signature));
+    }
+
+    #[test]
+    fn x509_signer_fails_with_unknown_key() {
+        let certificate = match throwaway_x509_certificate() {
+            Some(certificate) => certificate,
+            None => return,
+        };
+
+        let signer = super::x509_signer(
+            "unknown@example.com".to_string(),
+            Some(certificate.program.clone()),
+        );
+        assert!(signer("some commit content\n".to_string()).is_err());
+    }
+}
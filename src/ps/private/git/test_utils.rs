@@ -1,3 +1,5 @@
+use super::super::config::rewrite::{CommitterPolicy, SignPolicy};
+use super::super::config::PsRewriteConfig;
 use git2::{Repository, RepositoryInitOptions};
 use tempfile::TempDir;
 
@@ -21,6 +23,14 @@ pub fn repo_init() -> (TempDir, Repository) {
     (td, repo)
 }
 
+/// The rewrite config gps defaults to, resetting the committer & signing per commit.gpgsign
+pub fn rewrite_config() -> PsRewriteConfig {
+    PsRewriteConfig {
+        committer: CommitterPolicy::Reset,
+        sign: SignPolicy::Config,
+    }
+}
+
 pub fn create_commit(repo: &git2::Repository, path: &str, data: &[u8], message: &str) -> git2::Oid {
     // To implement this I was losely following
     // https://stackoverflow.com/questions/15711444/how-to-commit-to-a-git-repository-using-libgit2
@@ -58,3 +68,173 @@ pub fn create_commit(repo: &git2::Repository, path: &str, data: &[u8], message:
     )
     .unwrap()
 }
+
+/// Commit the files, given as path & content pairs, on top of HEAD, keeping the other files of
+/// its tree
+pub fn commit_files(repo: &git2::Repository, files: &[(&str, &str)], message: &str) -> git2::Oid {
+    let head_commit = repo.head().unwrap().peel_to_commit().unwrap();
+    let mut treebuilder = repo
+        .treebuilder(Some(&head_commit.tree().unwrap()))
+        .unwrap();
+    for (path, content) in files {
+        let blob_oid = repo.blob(content.as_bytes()).unwrap();
+        treebuilder
+            .insert(path, blob_oid, i32::from(git2::FileMode::Blob))
+            .unwrap();
+    }
+    let tree = repo.find_tree(treebuilder.write().unwrap()).unwrap();
+    let sig = repo.signature().unwrap();
+    repo.commit(Some("HEAD"), &sig, &sig, message, &tree, &[&head_commit])
+        .unwrap()
+}
+
+/// A throwaway CA & a certificate it issued for signer@example.com, held in their own GnuPG home,
+/// along with a gpgsm wrapper using that home to sign with it, e.g. as `gpg.x509.program`
+pub struct ThrowawayX509Certificate {
+    home: TempDir,
+    pub program: String,
+    pub signing_key: String,
+}
+
+impl ThrowawayX509Certificate {
+    /// Whether the detached signature is a valid signature of the content
+    pub fn verify(&self, content: &str, signature: &str) -> bool {
+        let content_path = self.home.path().join("content");
+        let signature_path = self.home.path().join("content.sig");
+        std::fs::write(&content_path, content).unwrap();
+        std::fs::write(&signature_path, signature).unwrap();
+        std::process::Command::new(&self.program)
+            .arg("--verify")
+            .arg(&signature_path)
+            .arg(&content_path)
+            .output()
+            .unwrap()
+            .status
+            .success()
+    }
+}
+
+impl Drop for ThrowawayX509Certificate {
+    fn drop(&mut self) {
+        let _ = std::process::Command::new("gpgconf")
+            .args(["--kill", "gpg-agent"])
+            .env("GNUPGHOME", self.home.path())
+            .output();
+    }
+}
+
+/// Generate a throwaway CA & a signing certificate issued by it with gpg & gpgsm, None when
+/// they aren't installed
+pub fn throwaway_x509_certificate() -> Option<ThrowawayX509Certificate> {
+    for program in ["gpg", "gpgsm", "gpgconf"] {
+        if std::process::Command::new(program)
+            .arg("--version")
+            .output()
+            .is_err()
+        {
+            eprintln!("{} not found, skipping x509 signing test", program);
+            return None;
+        }
+    }
+
+    let home = tempfile::Builder::new().prefix("gnupg").tempdir().unwrap();
+    let run = |program: &str, args: &[&str]| -> String {
+        let output = std::process::Command::new(program)
+            .args(args)
+            .env("GNUPGHOME", home.path())
+            .current_dir(home.path())
+            .output()
+            .unwrap();
+        assert!(
+            output.status.success(),
+            "{} {:?} failed, {}",
+            program,
+            args,
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let colon_field = |listing: &str, record: &str| -> String {
+        listing
+            .lines()
+            .find(|l| l.starts_with(record))
+            .and_then(|l| l.split(':').nth(9))
+            .unwrap()
+            .to_string()
+    };
+
+    // gpgsm can't generate unprotected keys, so gpg generates them & gpgsm certifies them
+    let mut keygrips: Vec<String> = vec![];
+    for name in ["gps throwaway ca", "gps signer"] {
+        std::fs::write(
+            home.path().join("key.params"),
+            format!(
+                "Key-Type: RSA\nKey-Length: 2048\nName-Real: {}\n%no-protection\n%commit\n",
+                name
+            ),
+        )
+        .unwrap();
+        run("gpg", &["--batch", "--gen-key", "key.params"]);
+        let listing = run("gpg", &["--with-colons", "--with-keygrip", "-k", name]);
+        keygrips.push(colon_field(&listing, "grp:"));
+    }
+
+    std::fs::write(
+        home.path().join("ca.params"),
+        format!(
+            "Key-Type: RSA\nKey-Grip: {}\nKey-Usage: cert,sign\nSerial: random\nName-DN: CN=gps throwaway CA\nExtension: 2.5.29.19 c 30060101ff020101\n",
+            keygrips[0]
+        ),
+    )
+    .unwrap();
+    run(
+        "gpgsm",
+        &["--batch", "--gen-key", "-o", "ca.pem", "ca.params"],
+    );
+    run("gpgsm", &["--batch", "--import", "ca.pem"]);
+
+    std::fs::write(
+        home.path().join("signer.params"),
+        format!(
+            "Key-Type: RSA\nKey-Grip: {}\nKey-Usage: sign\nSerial: random\nName-DN: CN=gps signer\nName-Email: signer@example.com\nSigning-Key: {}\nIssuer-DN: CN=gps throwaway CA\n",
+            keygrips[1], keygrips[0]
+        ),
+    )
+    .unwrap();
+    run(
+        "gpgsm",
+        &["--batch", "--gen-key", "-o", "signer.pem", "signer.params"],
+    );
+    run("gpgsm", &["--batch", "--import", "signer.pem"]);
+
+    // trust the CA & don't look for CRLs of the throwaway certificates
+    let ca_listing = run("gpgsm", &["--with-colons", "-k", "CN=gps throwaway CA"]);
+    std::fs::write(
+        home.path().join("trustlist.txt"),
+        format!("{} S relax\n", colon_field(&ca_listing, "fpr:")),
+    )
+    .unwrap();
+    std::fs::write(home.path().join("gpgsm.conf"), "disable-crl-checks\n").unwrap();
+    run("gpgconf", &["--reload", "gpg-agent"]);
+
+    let program = home.path().join("gpgsm");
+    std::fs::write(
+        &program,
+        format!(
+            "#!/bin/sh\nGNUPGHOME='{}' exec gpgsm \"$@\"\n",
+            home.path().display()
+        ),
+    )
+    .unwrap();
+    std::fs::set_permissions(
+        &program,
+        std::os::unix::fs::PermissionsExt::from_mode(0o755),
+    )
+    .unwrap();
+
+    Some(ThrowawayX509Certificate {
+        program: program.to_str().unwrap().to_string(),
+        signing_key: "signer@example.com".to_string(),
+        home,
+    })
+}
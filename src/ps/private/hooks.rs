@@ -1,8 +1,21 @@
 use super::{
-    paths::{path_exists_and_is_executable, PathExistsAndIsExecutable},
+    config::PsHooksConfig,
+    events::{self, Event},
+    paths::{self, path_exists_and_is_executable, PathExistsAndIsExecutable},
     utils,
 };
-use std::{path::PathBuf, process::Output};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Output,
+};
+
+mod registry;
+
+pub use super::config::hooks::{HookChaining, HookLocation};
+pub use registry::*;
+
+const HOOK_LOGS_TO_KEEP: usize = 100;
 
 #[derive(Debug)]
 pub enum FindHookError {
@@ -42,11 +55,12 @@ impl std::error::Error for FindHookError {
     }
 }
 
-pub fn find_hook(
+/// Get the path the named hook would have in each of the hook locations, in order of precedence
+pub fn hook_search_paths(
     repo_root: &str,
     repo_gitdir: &str,
     filename: &str,
-) -> Result<PathBuf, FindHookError> {
+) -> Result<Vec<(HookLocation, PathBuf)>, FindHookError> {
     let communal_repository_level_hook_pathbuf: PathBuf =
         [repo_root, ".git-ps", "hooks", filename].iter().collect();
     let repository_level_hook_pathbuf: PathBuf =
@@ -58,48 +72,267 @@ pub fn find_hook(
     user_level_hook_pathbuf.push("git-ps");
     user_level_hook_pathbuf.push("hooks");
     user_level_hook_pathbuf.push(filename);
-    match path_exists_and_is_executable(communal_repository_level_hook_pathbuf.as_path()) {
-        PathExistsAndIsExecutable::ExistsAndIsExecutable => {
-            Ok(communal_repository_level_hook_pathbuf)
-        }
-        PathExistsAndIsExecutable::ExistsButNotExecutable => Err(FindHookError::NotExecutable(
+    Ok(vec![
+        (
+            HookLocation::Communal,
             communal_repository_level_hook_pathbuf,
-        )),
-        PathExistsAndIsExecutable::DoesNotExist => {
-            match path_exists_and_is_executable(repository_level_hook_pathbuf.as_path()) {
-                PathExistsAndIsExecutable::ExistsAndIsExecutable => {
-                    Ok(repository_level_hook_pathbuf)
-                }
-                PathExistsAndIsExecutable::ExistsButNotExecutable => {
-                    Err(FindHookError::NotExecutable(repository_level_hook_pathbuf))
-                }
-                PathExistsAndIsExecutable::DoesNotExist => {
-                    match path_exists_and_is_executable(user_level_hook_pathbuf.as_path()) {
-                        PathExistsAndIsExecutable::ExistsAndIsExecutable => {
-                            Ok(user_level_hook_pathbuf)
-                        }
-                        PathExistsAndIsExecutable::ExistsButNotExecutable => {
-                            Err(FindHookError::NotExecutable(user_level_hook_pathbuf))
-                        }
-                        PathExistsAndIsExecutable::DoesNotExist => Err(FindHookError::NotFound),
-                    }
-                }
+        ),
+        (HookLocation::Repository, repository_level_hook_pathbuf),
+        (HookLocation::User, user_level_hook_pathbuf),
+    ])
+}
+
+/// Get the files that make up a hook within a single location. This is the hook file itself
+/// followed by the files in the hook's `.d` directory in lexical order, e.g. `pre_push` and then
+/// `pre_push.d/*`. Hidden files are ignored.
+pub fn hook_files(hook_pathbuf: &Path) -> Vec<(PathBuf, PathExistsAndIsExecutable)> {
+    let mut files: Vec<(PathBuf, PathExistsAndIsExecutable)> = vec![];
+
+    if hook_pathbuf.is_file() {
+        files.push((
+            hook_pathbuf.to_path_buf(),
+            path_exists_and_is_executable(hook_pathbuf),
+        ));
+    }
+
+    let mut hook_dir_name = hook_pathbuf.as_os_str().to_os_string();
+    hook_dir_name.push(".d");
+    if let Ok(entries) = fs::read_dir(PathBuf::from(hook_dir_name)) {
+        let mut dir_paths: Vec<PathBuf> = entries
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .filter(|path| {
+                !path
+                    .file_name()
+                    .and_then(|name| name.to_str())
+                    .map(|name| name.starts_with('.'))
+                    .unwrap_or(true)
+            })
+            .collect();
+        dir_paths.sort();
+        for path in dir_paths {
+            let state = path_exists_and_is_executable(&path);
+            files.push((path, state));
+        }
+    }
+
+    files
+}
+
+/// Find the hooks that make up the named hook in a single location. A hook file that isn't
+/// executable is an error, while non-executable files in the `.d` directory are skipped so that
+/// they can be disabled with `chmod -x`.
+fn find_location_hooks(hook_pathbuf: &Path) -> Result<Vec<PathBuf>, FindHookError> {
+    let mut hook_paths: Vec<PathBuf> = vec![];
+    for (path, state) in hook_files(hook_pathbuf) {
+        match state {
+            PathExistsAndIsExecutable::ExistsAndIsExecutable => hook_paths.push(path),
+            PathExistsAndIsExecutable::ExistsButNotExecutable if path == hook_pathbuf => {
+                return Err(FindHookError::NotExecutable(path))
             }
+            _ => {}
         }
     }
+    Ok(hook_paths)
+}
+
+/// Find all of the hooks to run for the given hook, in the order they should run.
+///
+/// By default only the hooks of the highest precedence location that has any are used. A
+/// location can change that by declaring `chaining` in the `[hooks]` section of its own config
+/// file. If any location with hooks declares `replace` then only its hooks are used. Otherwise,
+/// if any location with hooks declares `extend` then the hooks of all the locations are used,
+/// chained together in the configured `order`.
+pub fn find_hooks(
+    repo_root: &str,
+    repo_gitdir: &str,
+    hooks_config: &PsHooksConfig,
+    hook: &HookDefinition,
+) -> Result<Vec<(HookLocation, PathBuf)>, FindHookError> {
+    let mut locations: Vec<(HookLocation, Vec<PathBuf>)> = vec![];
+    for (location, hook_pathbuf) in hook_search_paths(repo_root, repo_gitdir, hook.name)? {
+        let hook_paths = find_location_hooks(&hook_pathbuf)?;
+        if !hook_paths.is_empty() {
+            locations.push((location, hook_paths));
+        }
+    }
+
+    let hooks = chain_location_hooks(locations, hooks_config);
+    if hooks.is_empty() {
+        Err(FindHookError::NotFound)
+    } else {
+        Ok(hooks)
+    }
+}
+
+/// Chain together the hooks found in each location, given in order of precedence, based on the
+/// chaining each location declared & the configured order.
+fn chain_location_hooks(
+    mut locations: Vec<(HookLocation, Vec<PathBuf>)>,
+    hooks_config: &PsHooksConfig,
+) -> Vec<(HookLocation, PathBuf)> {
+    let declares = |location: &HookLocation, chaining: HookChaining| {
+        hooks_config.chaining.get(location) == Some(&chaining)
+    };
+
+    if let Some(index) = locations
+        .iter()
+        .position(|(location, _)| declares(location, HookChaining::Replace))
+    {
+        locations = vec![locations.swap_remove(index)];
+    } else if locations
+        .iter()
+        .any(|(location, _)| declares(location, HookChaining::Extend))
+    {
+        // locations missing from the configured order run after the ordered ones
+        locations.sort_by_key(|(location, _)| {
+            hooks_config
+                .order
+                .iter()
+                .position(|l| l == location)
+                .unwrap_or(hooks_config.order.len())
+        });
+    } else {
+        locations.truncate(1);
+    }
+
+    locations
+        .into_iter()
+        .flat_map(|(location, hook_paths)| {
+            hook_paths
+                .into_iter()
+                .map(move |hook_path| (location, hook_path))
+        })
+        .collect()
 }
 
 #[derive(Debug)]
-pub enum HookOutputError {
+pub enum ExecuteHookError {
     PathNotUtf8,
-    HookExecutionFailed(utils::ExecuteWithOutputError),
+    ExecutionFailed(utils::ExecuteSupervisedError, PathBuf),
+    Failed(utils::ExecuteError, PathBuf),
+}
+
+impl std::fmt::Display for ExecuteHookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PathNotUtf8 => write!(f, "path not utf-8"),
+            Self::ExecutionFailed(e, log_path) => write!(
+                f,
+                "{}, see the log at {}",
+                e,
+                log_path.to_str().unwrap_or("some non utf-8 path")
+            ),
+            Self::Failed(e, log_path) => write!(
+                f,
+                "{}, see the log at {}",
+                e,
+                log_path.to_str().unwrap_or("some non utf-8 path")
+            ),
+        }
+    }
+}
+
+impl std::error::Error for ExecuteHookError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::PathNotUtf8 => None,
+            Self::ExecutionFailed(e, _) => Some(e),
+            Self::Failed(e, _) => Some(e),
+        }
+    }
+}
+
+/// Run the hook at the given path under supervision, capturing its output to a per-run log in
+/// the gitdir & applying the configured timeout. The output is returned regardless of the hook's
+/// exit status.
+fn run_hook(
+    repo_gitdir: &str,
+    hooks_config: &PsHooksConfig,
+    hook: &HookDefinition,
+    hook_path: &Path,
+    hook_args: &[&str],
+    echo_output: bool,
+) -> Result<(Output, PathBuf), ExecuteHookError> {
+    debug_assert!(
+        hook.accepts(hook_args),
+        "{} hook given {:?}, expected {:?}",
+        hook.name,
+        hook_args,
+        hook.args
+    );
+    let log_path = paths::hook_log_path(repo_gitdir, hook.name);
+    events::emit(Event::HookStarted {
+        name: hook.name.to_string(),
+    });
+    let output = utils::execute_supervised(
+        hook_path.to_str().ok_or(ExecuteHookError::PathNotUtf8)?,
+        hook_args,
+        hooks_config.timeout_for(hook.name),
+        &log_path,
+        echo_output,
+    )
+    .map_err(|e| ExecuteHookError::ExecutionFailed(e, log_path.clone()));
+    events::emit(Event::HookFinished {
+        name: hook.name.to_string(),
+        success: output.as_ref().is_ok_and(|o| o.status.success()),
+    });
+    prune_hook_logs(repo_gitdir);
+    Ok((output?, log_path))
+}
+
+/// Execute the hook at the given path, relaying its output to the terminal as well as capturing
+/// it in a per-run log in the gitdir. Any failure, including a non-zero exit status, results in
+/// an error that identifies the log.
+pub fn execute_hook(
+    repo_gitdir: &str,
+    hooks_config: &PsHooksConfig,
+    hook: &HookDefinition,
+    hook_path: &Path,
+    hook_args: &[&str],
+) -> Result<Output, ExecuteHookError> {
+    let (output, log_path) = run_hook(repo_gitdir, hooks_config, hook, hook_path, hook_args, true)?;
+    utils::check_exit_status(output.status).map_err(|e| ExecuteHookError::Failed(e, log_path))?;
+    Ok(output)
+}
+
+/// Execute each of the given hooks in sequence, stopping at the first one that fails
+pub fn execute_hooks(
+    repo_gitdir: &str,
+    hooks_config: &PsHooksConfig,
+    hook: &HookDefinition,
+    hooks: &[(HookLocation, PathBuf)],
+    hook_args: &[&str],
+) -> Result<Vec<Output>, ExecuteHookError> {
+    hooks
+        .iter()
+        .map(|(_, hook_path)| execute_hook(repo_gitdir, hooks_config, hook, hook_path, hook_args))
+        .collect()
+}
+
+/// Remove all but the most recent hook logs so that they don't accumulate forever
+fn prune_hook_logs(repo_gitdir: &str) {
+    if let Ok(entries) = fs::read_dir(paths::hook_logs_path(repo_gitdir)) {
+        let mut log_paths: Vec<PathBuf> = entries.flatten().map(|entry| entry.path()).collect();
+        if log_paths.len() > HOOK_LOGS_TO_KEEP {
+            log_paths.sort();
+            for log_path in log_paths.iter().take(log_paths.len() - HOOK_LOGS_TO_KEEP) {
+                let _ = fs::remove_file(log_path);
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum HookOutputError {
+    HookExecutionFailed(ExecuteHookError),
     HookNotFound(FindHookError),
 }
 
 impl std::fmt::Display for HookOutputError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::PathNotUtf8 => write!(f, "path not utf-8"),
             Self::HookExecutionFailed(e) => write!(f, "hook execution failed, {}", e),
             Self::HookNotFound(e) => write!(f, "hook not found, {}", e),
         }
@@ -109,27 +342,164 @@ impl std::fmt::Display for HookOutputError {
 impl std::error::Error for HookOutputError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            Self::PathNotUtf8 => None,
             Self::HookExecutionFailed(e) => Some(e),
             Self::HookNotFound(e) => Some(e),
         }
     }
 }
 
+/// Find & execute the given hook(s) capturing their output, without relaying it to the
+/// terminal. The outputs are returned regardless of the hooks' exit statuses.
 pub fn find_and_execute_hook_with_output(
     repo_root_str: &str,
     repo_gitdir_str: &str,
-    hook_name: &str,
+    hooks_config: &PsHooksConfig,
+    hook: &HookDefinition,
     hook_args: &[&str],
-) -> Result<Output, HookOutputError> {
-    let hook_output = match find_hook(repo_root_str, repo_gitdir_str, hook_name) {
-        Ok(hook_path) => utils::execute_with_output(
-            hook_path.to_str().ok_or(HookOutputError::PathNotUtf8)?,
-            hook_args,
-        )
-        .map_err(HookOutputError::HookExecutionFailed)?,
-        Err(e) => return Err(HookOutputError::HookNotFound(e)),
-    };
+) -> Result<Vec<Output>, HookOutputError> {
+    let hooks = find_hooks(repo_root_str, repo_gitdir_str, hooks_config, hook)
+        .map_err(HookOutputError::HookNotFound)?;
+    hooks
+        .iter()
+        .map(|(_, hook_path)| {
+            run_hook(
+                repo_gitdir_str,
+                hooks_config,
+                hook,
+                hook_path,
+                hook_args,
+                false,
+            )
+            .map(|(hook_output, _)| hook_output)
+            .map_err(HookOutputError::HookExecutionFailed)
+        })
+        .collect()
+}
 
-    Ok(hook_output)
+#[derive(Debug)]
+pub enum FindAndExecuteHookError {
+    FindHookFailed(FindHookError),
+    HookExecutionFailed(ExecuteHookError),
+    Vetoed(&'static str, ExecuteHookError),
+}
+
+impl std::fmt::Display for FindAndExecuteHookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::FindHookFailed(e) => write!(f, "failed to find hook, {}", e),
+            Self::HookExecutionFailed(e) => write!(f, "hook execution failed, {}", e),
+            Self::Vetoed(_, e) => write!(f, "vetoed the operation, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FindAndExecuteHookError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::FindHookFailed(e) => Some(e),
+            Self::HookExecutionFailed(e) => Some(e),
+            Self::Vetoed(_, e) => Some(e),
+        }
+    }
+}
+
+/// Find the given hook(s) and execute each of them with the given arguments, following the
+/// hook's phase. Not finding any hooks is not an error.
+///
+/// A pre hook that fails vetoes the operation, & one that exists but isn't executable is an error
+/// as skipping it would bypass its veto. Any other hook that isn't executable is skipped, emitting
+/// a [`Event::HookNotExecutable`] event.
+pub fn find_and_execute_hook(
+    repo_root: &str,
+    repo_gitdir: &str,
+    hooks_config: &PsHooksConfig,
+    hook: &HookDefinition,
+    hook_args: &[&str],
+) -> Result<Vec<Output>, FindAndExecuteHookError> {
+    match find_hooks(repo_root, repo_gitdir, hooks_config, hook) {
+        Ok(hooks) => execute_hooks(repo_gitdir, hooks_config, hook, &hooks, hook_args).map_err(
+            |e| match hook.phase {
+                HookPhase::Pre => FindAndExecuteHookError::Vetoed(hook.name, e),
+                _ => FindAndExecuteHookError::HookExecutionFailed(e),
+            },
+        ),
+        Err(FindHookError::NotFound) => Ok(vec![]),
+        Err(FindHookError::NotExecutable(path)) if hook.phase != HookPhase::Pre => {
+            events::emit(Event::HookNotExecutable {
+                name: hook.name.to_string(),
+                path,
+            });
+            Ok(vec![])
+        }
+        Err(e) => Err(FindAndExecuteHookError::FindHookFailed(e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::config::PsHooksConfig;
+    use super::{HookChaining, HookLocation};
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    fn hooks_config(chaining: &[(HookLocation, HookChaining)]) -> PsHooksConfig {
+        PsHooksConfig {
+            timeout: None,
+            timeouts: HashMap::new(),
+            order: vec![
+                HookLocation::User,
+                HookLocation::Repository,
+                HookLocation::Communal,
+            ],
+            chaining: chaining.iter().copied().collect(),
+        }
+    }
+
+    fn locations() -> Vec<(HookLocation, Vec<PathBuf>)> {
+        vec![
+            (
+                HookLocation::Communal,
+                vec![PathBuf::from("c1"), PathBuf::from("c2")],
+            ),
+            (HookLocation::User, vec![PathBuf::from("u1")]),
+        ]
+    }
+
+    fn paths(hooks: Vec<(HookLocation, PathBuf)>) -> Vec<PathBuf> {
+        hooks.into_iter().map(|(_, path)| path).collect()
+    }
+
+    #[test]
+    fn test_chain_location_hooks_defaults_to_highest_precedence_location() {
+        let hooks = super::chain_location_hooks(locations(), &hooks_config(&[]));
+        assert_eq!(paths(hooks), vec![PathBuf::from("c1"), PathBuf::from("c2")]);
+    }
+
+    #[test]
+    fn test_chain_location_hooks_extends_in_configured_order() {
+        let hooks = super::chain_location_hooks(
+            locations(),
+            &hooks_config(&[(HookLocation::User, HookChaining::Extend)]),
+        );
+        assert_eq!(
+            paths(hooks),
+            vec![
+                PathBuf::from("u1"),
+                PathBuf::from("c1"),
+                PathBuf::from("c2")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_chain_location_hooks_replace_wins_over_extend() {
+        let hooks = super::chain_location_hooks(
+            locations(),
+            &hooks_config(&[
+                (HookLocation::Communal, HookChaining::Extend),
+                (HookLocation::User, HookChaining::Replace),
+            ]),
+        );
+        assert_eq!(paths(hooks), vec![PathBuf::from("u1")]);
+    }
 }
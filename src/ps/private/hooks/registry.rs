@@ -0,0 +1,269 @@
+/// The phase of a command a hook is executed in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HookPhase {
+    /// Executed before the operation, a non-zero exit status vetoes the operation
+    Pre,
+    /// Executed after the operation has completed
+    Post,
+    /// Executed to gather information for display, the exit status is ignored
+    Info,
+}
+
+impl std::fmt::Display for HookPhase {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Pre => write!(f, "pre"),
+            Self::Post => write!(f, "post"),
+            Self::Info => write!(f, "info"),
+        }
+    }
+}
+
+/// Description of a hook that Git Patch Stack knows how to execute
+#[derive(Debug)]
+pub struct HookDefinition {
+    pub name: &'static str,
+    pub command: &'static str,
+    pub phase: HookPhase,
+    pub args: &'static [&'static str],
+}
+
+impl HookDefinition {
+    /// Whether the given arguments match the hook's declared arguments. A trailing argument
+    /// ending in `...` takes one or more values.
+    pub fn accepts(&self, args: &[&str]) -> bool {
+        match self.args.last() {
+            Some(last) if last.ends_with("...") => args.len() >= self.args.len(),
+            _ => args.len() == self.args.len(),
+        }
+    }
+}
+
+pub const PRE_REQUEST_REVIEW_HOOK: HookDefinition = HookDefinition {
+    name: "pre_request_review",
+    command: "request-review",
+    phase: HookPhase::Pre,
+    args: &["patch_sha..."],
+};
+
+pub const REQUEST_REVIEW_POST_SYNC_HOOK: HookDefinition = HookDefinition {
+    name: "request_review_post_sync",
+    command: "request-review",
+    phase: HookPhase::Post,
+    args: &[
+        "patch_upstream_branch_name",
+        "patch_stack_upstream_branch_name",
+        "patch_stack_upstream_remote_name",
+        "patch_stack_upstream_remote_url",
+    ],
+};
+
+pub const INTEGRATE_VERIFY_HOOK: HookDefinition = HookDefinition {
+    name: "integrate_verify",
+    command: "integrate",
+    phase: HookPhase::Pre,
+    args: &[
+        "patch_upstream_branch_name",
+        "patch_stack_upstream_branch_name",
+        "patch_stack_upstream_remote_name",
+        "patch_stack_upstream_remote_url",
+    ],
+};
+
+pub const PRE_PUSH_HOOK: HookDefinition = HookDefinition {
+    name: "pre_push",
+    command: "request-review, integrate, push",
+    phase: HookPhase::Pre,
+    args: &["remote_name", "local_ref", "remote_ref"],
+};
+
+pub const INTEGRATE_POST_PUSH_HOOK: HookDefinition = HookDefinition {
+    name: "integrate_post_push",
+    command: "integrate",
+    phase: HookPhase::Post,
+    args: &["upstream_head_sha"],
+};
+
+pub const POST_INTEGRATE_HOOK: HookDefinition = HookDefinition {
+    name: "post_integrate",
+    command: "integrate",
+    phase: HookPhase::Post,
+    args: &["patch_branch_name"],
+};
+
+pub const PRE_PULL_HOOK: HookDefinition = HookDefinition {
+    name: "pre_pull",
+    command: "pull",
+    phase: HookPhase::Pre,
+    args: &["patch_stack_upstream_branch_name"],
+};
+
+pub const POST_PULL_HOOK: HookDefinition = HookDefinition {
+    name: "post_pull",
+    command: "pull",
+    phase: HookPhase::Post,
+    args: &["patch_stack_upstream_branch_name"],
+};
+
+pub const PRE_REBASE_HOOK: HookDefinition = HookDefinition {
+    name: "pre_rebase",
+    command: "rebase",
+    phase: HookPhase::Pre,
+    args: &[],
+};
+
+pub const POST_REBASE_HOOK: HookDefinition = HookDefinition {
+    name: "post_rebase",
+    command: "rebase",
+    phase: HookPhase::Post,
+    args: &[],
+};
+
+pub const PRE_BRANCH_HOOK: HookDefinition = HookDefinition {
+    name: "pre_branch",
+    command: "branch",
+    phase: HookPhase::Pre,
+    args: &["patch_sha..."],
+};
+
+pub const POST_BRANCH_HOOK: HookDefinition = HookDefinition {
+    name: "post_branch",
+    command: "branch",
+    phase: HookPhase::Post,
+    args: &["branch_name", "branch_head_sha"],
+};
+
+pub const PRE_APPEND_HOOK: HookDefinition = HookDefinition {
+    name: "pre_append",
+    command: "append",
+    phase: HookPhase::Pre,
+    args: &["branch_name", "patch_sha..."],
+};
+
+pub const POST_APPEND_HOOK: HookDefinition = HookDefinition {
+    name: "post_append",
+    command: "append",
+    phase: HookPhase::Post,
+    args: &["branch_name", "branch_head_sha"],
+};
+
+pub const PRE_ISOLATE_HOOK: HookDefinition = HookDefinition {
+    name: "pre_isolate",
+    command: "isolate",
+    phase: HookPhase::Pre,
+    args: &["patch_sha..."],
+};
+
+pub const ISOLATE_POST_CHECKOUT_HOOK: HookDefinition = HookDefinition {
+    name: "isolate_post_checkout",
+    command: "isolate",
+    phase: HookPhase::Post,
+    args: &[
+        "patch_stack_upstream_remote_name",
+        "patch_stack_upstream_remote_url",
+    ],
+};
+
+pub const ISOLATE_POST_CLEANUP_HOOK: HookDefinition = HookDefinition {
+    name: "isolate_post_cleanup",
+    command: "isolate",
+    phase: HookPhase::Post,
+    args: &[],
+};
+
+pub const PRE_LIST_HOOK: HookDefinition = HookDefinition {
+    name: "pre_list",
+    command: "list",
+    phase: HookPhase::Pre,
+    args: &[],
+};
+
+pub const LIST_ADDITIONAL_INFORMATION_HOOK: HookDefinition = HookDefinition {
+    name: "list_additional_information",
+    command: "list",
+    phase: HookPhase::Info,
+    args: &["patch_index", "patch_state", "patch_sha", "patch_summary"],
+};
+
+pub const POST_LIST_HOOK: HookDefinition = HookDefinition {
+    name: "post_list",
+    command: "list",
+    phase: HookPhase::Post,
+    args: &[],
+};
+
+/// Registry of every hook Git Patch Stack executes, in the order they are generally encountered
+pub const HOOKS: &[HookDefinition] = &[
+    PRE_REQUEST_REVIEW_HOOK,
+    REQUEST_REVIEW_POST_SYNC_HOOK,
+    INTEGRATE_VERIFY_HOOK,
+    PRE_PUSH_HOOK,
+    INTEGRATE_POST_PUSH_HOOK,
+    POST_INTEGRATE_HOOK,
+    PRE_PULL_HOOK,
+    POST_PULL_HOOK,
+    PRE_REBASE_HOOK,
+    POST_REBASE_HOOK,
+    PRE_BRANCH_HOOK,
+    POST_BRANCH_HOOK,
+    PRE_APPEND_HOOK,
+    POST_APPEND_HOOK,
+    PRE_ISOLATE_HOOK,
+    ISOLATE_POST_CHECKOUT_HOOK,
+    ISOLATE_POST_CLEANUP_HOOK,
+    PRE_LIST_HOOK,
+    LIST_ADDITIONAL_INFORMATION_HOOK,
+    POST_LIST_HOOK,
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_names_are_unique() {
+        for hook in HOOKS {
+            assert_eq!(HOOKS.iter().filter(|h| h.name == hook.name).count(), 1);
+        }
+    }
+
+    #[test]
+    fn test_every_command_has_pre_and_post_hooks() {
+        for command in [
+            "request-review",
+            "integrate",
+            "pull",
+            "rebase",
+            "branch",
+            "append",
+            "isolate",
+            "list",
+        ] {
+            for phase in [HookPhase::Pre, HookPhase::Post] {
+                assert!(
+                    HOOKS
+                        .iter()
+                        .any(|h| h.phase == phase && h.command.split(", ").any(|c| c == command)),
+                    "{} has no {} hook",
+                    command,
+                    phase
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_accepts_checks_the_argument_count() {
+        assert!(POST_BRANCH_HOOK.accepts(&["ps/rr/foo", "abc123"]));
+        assert!(!POST_BRANCH_HOOK.accepts(&["ps/rr/foo"]));
+        assert!(POST_REBASE_HOOK.accepts(&[]));
+        assert!(!POST_REBASE_HOOK.accepts(&["extra"]));
+    }
+
+    #[test]
+    fn test_accepts_takes_one_or_more_variadic_values() {
+        assert!(!PRE_APPEND_HOOK.accepts(&["feature"]));
+        assert!(PRE_APPEND_HOOK.accepts(&["feature", "abc123"]));
+        assert!(PRE_APPEND_HOOK.accepts(&["feature", "abc123", "def456"]));
+    }
+}
@@ -0,0 +1,331 @@
+// This module rebases the patches of a patch stack onto a new base without touching the working
+// copy, replaying them in memory with the cherry picking machinery on a temporary branch. Patches
+// that already made it into the new base, identified by their ps-id, or by their diff's patch id
+// when they were applied without it, are dropped along the way.
+
+use super::super::super::ps;
+use super::cherry_picking;
+use super::config::PsRewriteConfig;
+use super::git;
+use std::collections::HashSet;
+use std::result::Result;
+
+const REBASE_BRANCH_NAME: &str = "ps/tmp/in_memory_rebase";
+
+#[derive(Debug)]
+pub enum InMemoryRebaseError {
+    GetUpstreamedPatchesFailed(Box<dyn std::error::Error>),
+    CreateTemporaryBranchFailed(git2::Error),
+    DeleteTemporaryBranchFailed(git2::Error),
+    CherryPickFailed(cherry_picking::CherryPickError),
+    Git(git2::Error),
+}
+
+impl From<git2::Error> for InMemoryRebaseError {
+    fn from(e: git2::Error) -> Self {
+        Self::Git(e)
+    }
+}
+
+impl std::fmt::Display for InMemoryRebaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::GetUpstreamedPatchesFailed(e) => {
+                write!(f, "failed to get the patches already upstream, {}", e)
+            }
+            Self::CreateTemporaryBranchFailed(e) => {
+                write!(f, "failed to create temporary branch, {}", e)
+            }
+            Self::DeleteTemporaryBranchFailed(e) => {
+                write!(f, "failed to delete temporary branch, {}", e)
+            }
+            Self::CherryPickFailed(e) => write!(f, "failed to cherry pick, {}", e),
+            Self::Git(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for InMemoryRebaseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::GetUpstreamedPatchesFailed(e) => Some(e.as_ref()),
+            Self::CreateTemporaryBranchFailed(e) => Some(e),
+            Self::DeleteTemporaryBranchFailed(e) => Some(e),
+            Self::CherryPickFailed(e) => Some(e),
+            Self::Git(e) => Some(e),
+        }
+    }
+}
+
+/// The ps-ids & diff patch ids of the commits in a range, used to recognize patches that were
+/// already integrated into it
+pub struct UpstreamedPatches {
+    ps_ids: HashSet<uuid::Uuid>,
+    patch_ids: HashSet<git2::Oid>,
+}
+
+impl UpstreamedPatches {
+    /// Collect the commits reachable from `upstream_oid` but not from `base_oid`
+    pub fn new(
+        repo: &git2::Repository,
+        base_oid: git2::Oid,
+        upstream_oid: git2::Oid,
+    ) -> Result<Self, InMemoryRebaseError> {
+        Self::collect(repo, Some(base_oid), upstream_oid)
+    }
+
+    /// Collect the commits reachable from `upstream_oid` but not from the history all of the
+    /// branches share with it. Only the shared history is left out, as a branch based on a newer
+    /// upstream would otherwise hide the commits the patches of older branches landed in.
+    pub fn since_shared_base(
+        repo: &git2::Repository,
+        branch_oids: &[git2::Oid],
+        upstream_oid: git2::Oid,
+    ) -> Result<Self, InMemoryRebaseError> {
+        let mut merge_bases = vec![];
+        for branch_oid in branch_oids {
+            match repo.merge_base(*branch_oid, upstream_oid) {
+                Ok(merge_base) => merge_bases.push(merge_base),
+                Err(e) if e.code() == git2::ErrorCode::NotFound => {
+                    return Self::collect(repo, None, upstream_oid)
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        let shared_base = match merge_bases.len() {
+            0 => None,
+            1 => Some(merge_bases[0]),
+            _ => match repo.merge_base_many(&merge_bases) {
+                Ok(shared_base) => Some(shared_base),
+                Err(e) if e.code() == git2::ErrorCode::NotFound => None,
+                Err(e) => return Err(e.into()),
+            },
+        };
+        Self::collect(repo, shared_base, upstream_oid)
+    }
+
+    /// Collect the commits reachable from `upstream_oid` but not from `base_oid`, all of them
+    /// without one
+    fn collect(
+        repo: &git2::Repository,
+        base_oid: Option<git2::Oid>,
+        upstream_oid: git2::Oid,
+    ) -> Result<Self, InMemoryRebaseError> {
+        let mut ps_ids = HashSet::new();
+        let mut patch_ids = HashSet::new();
+        let mut revs = repo.revwalk()?;
+        revs.push(upstream_oid)?;
+        if let Some(base_oid) = base_oid {
+            revs.hide(base_oid)?;
+        }
+        for oid in revs {
+            let commit = repo.find_commit(oid?)?;
+            // a squashed or merge commit carries the ps-ids of every patch it integrates
+            ps_ids.extend(commit.message().map(ps::extract_ps_ids).unwrap_or_default());
+            // merge commits don't have a meaningful diff of their own
+            if commit.parent_count() == 1 {
+                let patch_id = git::commit_diff_patch_id(repo, &commit)
+                    .map_err(|e| InMemoryRebaseError::GetUpstreamedPatchesFailed(e.into()))?;
+                patch_ids.insert(patch_id);
+            }
+        }
+        Ok(Self { ps_ids, patch_ids })
+    }
+
+    /// Whether the commit was integrated upstream, either with its ps-id or as the same diff
+    pub fn contains(
+        &self,
+        repo: &git2::Repository,
+        commit: &git2::Commit,
+    ) -> Result<bool, InMemoryRebaseError> {
+        if let Some(ps_id) = ps::commit_ps_id(commit) {
+            if self.ps_ids.contains(&ps_id) {
+                return Ok(true);
+            }
+        }
+        let patch_id = git::commit_diff_patch_id(repo, commit)
+            .map_err(|e| InMemoryRebaseError::GetUpstreamedPatchesFailed(e.into()))?;
+        Ok(self.patch_ids.contains(&patch_id))
+    }
+}
+
+/// The result of rebasing the patches in memory
+#[derive(Debug)]
+pub struct InMemoryRebase {
+    /// The patches dropped as they were already integrated upstream
+    pub landed: Vec<git2::Oid>,
+    /// The patches replayed onto the new base, paired with the commits replaying them
+    pub rebased: Vec<(git2::Oid, git2::Oid)>,
+    /// The patch that couldn't be replayed in memory, either due to conflicts or because it is a
+    /// merge commit, at which point the rebase stopped
+    pub stopped_at: Option<git2::Oid>,
+    /// The new head of the rebased patches, as far as the rebase got
+    pub head_oid: git2::Oid,
+}
+
+/// Rebase the patches between `base_oid` (exclusive) & `head_oid` (inclusive) onto `onto_oid`
+/// without touching the working copy or any existing refs, dropping the ones already integrated
+/// in `onto_oid`.
+///
+/// Patches that end up empty once replayed are considered integrated as well, as their changes
+/// made it upstream in a different form.
+pub fn rebase_in_memory(
+    repo: &git2::Repository,
+    config: &git2::Config,
+    rewrite_config: &PsRewriteConfig,
+    base_oid: git2::Oid,
+    head_oid: git2::Oid,
+    onto_oid: git2::Oid,
+) -> Result<InMemoryRebase, InMemoryRebaseError> {
+    let upstreamed_patches = UpstreamedPatches::new(repo, base_oid, onto_oid)?;
+    let patch_oids = git::get_revs(repo, base_oid, head_oid, git2::Sort::REVERSE)
+        .map_err(|e| InMemoryRebaseError::GetUpstreamedPatchesFailed(e.into()))?
+        .collect::<Result<Vec<git2::Oid>, git2::Error>>()?;
+
+    let onto_commit = repo.find_commit(onto_oid)?;
+    let branch = repo
+        .branch(REBASE_BRANCH_NAME, &onto_commit, true)
+        .map_err(InMemoryRebaseError::CreateTemporaryBranchFailed)?;
+    let branch_ref_name = branch
+        .get()
+        .name()
+        .expect("temporary branch name is utf-8")
+        .to_string();
+
+    let result = replay_patches(
+        repo,
+        config,
+        rewrite_config,
+        &upstreamed_patches,
+        &patch_oids,
+        &branch_ref_name,
+        onto_oid,
+    );
+
+    repo.find_branch(REBASE_BRANCH_NAME, git2::BranchType::Local)
+        .and_then(|mut b| b.delete())
+        .map_err(InMemoryRebaseError::DeleteTemporaryBranchFailed)?;
+
+    result
+}
+
+fn replay_patches(
+    repo: &git2::Repository,
+    config: &git2::Config,
+    rewrite_config: &PsRewriteConfig,
+    upstreamed_patches: &UpstreamedPatches,
+    patch_oids: &[git2::Oid],
+    branch_ref_name: &str,
+    onto_oid: git2::Oid,
+) -> Result<InMemoryRebase, InMemoryRebaseError> {
+    let mut rebase = InMemoryRebase {
+        landed: vec![],
+        rebased: vec![],
+        stopped_at: None,
+        head_oid: onto_oid,
+    };
+
+    for patch_oid in patch_oids {
+        let commit = repo.find_commit(*patch_oid)?;
+        if commit.parent_count() == 1 && upstreamed_patches.contains(repo, &commit)? {
+            rebase.landed.push(*patch_oid);
+            continue;
+        }
+
+        match cherry_picking::cherry_pick(
+            repo,
+            config,
+            rewrite_config,
+            *patch_oid,
+            None,
+            branch_ref_name,
+            0,
+            false,
+            true,
+        ) {
+            Ok(new_oid) => {
+                let new_oid = new_oid.expect("cherry picking a single commit picks one");
+                let new_commit = repo.find_commit(new_oid)?;
+                let previous_head = repo.find_commit(rebase.head_oid)?;
+                if new_commit.tree_id() == previous_head.tree_id() {
+                    // nothing left of the patch once replayed, drop the empty commit
+                    repo.find_reference(branch_ref_name)?
+                        .set_target(rebase.head_oid, "drop empty patch")?;
+                    rebase.landed.push(*patch_oid);
+                } else {
+                    rebase.rebased.push((*patch_oid, new_oid));
+                    rebase.head_oid = new_oid;
+                }
+            }
+            Err(cherry_picking::CherryPickError::ConflictsExist(_, _))
+            | Err(cherry_picking::CherryPickError::MergeCommitDetected(_)) => {
+                rebase.stopped_at = Some(*patch_oid);
+                return Ok(rebase);
+            }
+            Err(e) => return Err(InMemoryRebaseError::CherryPickFailed(e)),
+        }
+    }
+
+    Ok(rebase)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::git::test_utils;
+    use super::*;
+
+    #[test]
+    fn test_rebase_in_memory_drops_landed_patches_and_stops_at_conflicts() {
+        let (_dir, repo) = test_utils::repo_init();
+        let config = repo.config().unwrap();
+        let base_oid =
+            test_utils::commit_files(&repo, &[("a.txt", "a\n"), ("c.txt", "c\n")], "base");
+
+        // the upstream integrated the first patch reworded, the second one with its ps-id but
+        // modified, & changed what the fourth patch changes
+        let by_diff_oid =
+            test_utils::commit_files(&repo, &[("a.txt", "a changed\n")], "a upstream");
+        test_utils::commit_files(
+            &repo,
+            &[("b.txt", "b reworked\n")],
+            "b\n\n<!-- ps-id: 2d6c2e4c-4d56-4e9e-9c4c-8a7a2b1b1a11 -->",
+        );
+        let onto_oid = test_utils::commit_files(&repo, &[("c.txt", "c upstream\n")], "c upstream");
+        assert_ne!(by_diff_oid, onto_oid);
+
+        repo.reference("refs/heads/stack", base_oid, true, "test")
+            .unwrap();
+        repo.set_head("refs/heads/stack").unwrap();
+        let by_diff_patch = test_utils::commit_files(&repo, &[("a.txt", "a changed\n")], "a");
+        let by_ps_id_patch = test_utils::commit_files(
+            &repo,
+            &[("b.txt", "b\n")],
+            "b\n\n<!-- ps-id: 2d6c2e4c-4d56-4e9e-9c4c-8a7a2b1b1a11 -->",
+        );
+        let clean_patch = test_utils::commit_files(&repo, &[("d.txt", "d\n")], "d");
+        let conflicting_patch = test_utils::commit_files(&repo, &[("c.txt", "c local\n")], "c");
+        let head_oid = test_utils::commit_files(&repo, &[("e.txt", "e\n")], "e");
+
+        let rewrite_config = test_utils::rewrite_config();
+        let rebase = rebase_in_memory(
+            &repo,
+            &config,
+            &rewrite_config,
+            base_oid,
+            head_oid,
+            onto_oid,
+        )
+        .unwrap();
+        assert_eq!(rebase.landed, vec![by_diff_patch, by_ps_id_patch]);
+        assert_eq!(rebase.rebased.len(), 1);
+        assert_eq!(rebase.rebased[0].0, clean_patch);
+        assert_eq!(rebase.stopped_at, Some(conflicting_patch));
+
+        let new_head = repo.find_commit(rebase.head_oid).unwrap();
+        assert_eq!(new_head.parent_id(0).unwrap(), onto_oid);
+        assert!(repo
+            .find_branch(REBASE_BRANCH_NAME, git2::BranchType::Local)
+            .is_err());
+        assert_eq!(repo.refname_to_id("refs/heads/stack").unwrap(), head_oid);
+    }
+}
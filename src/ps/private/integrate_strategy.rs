@@ -0,0 +1,674 @@
+// This module builds what integrate publishes to the upstream according to the configured
+// strategy. The request review branches being integrated are combined on a temporary branch
+// created at the upstream, which is then pushed in their place. The fast-forward strategy
+// integrates their patches as is, while the merge & squash strategies integrate them with a merge
+// commit, or a single squashed commit, per request review branch. A dry run combines them in
+// memory instead, to check they integrate without moving any refs or creating any commits.
+
+use super::super::super::ps;
+use super::cherry_picking;
+use super::config::integrate::IntegrateStrategy;
+use super::config::PsRewriteConfig;
+use super::git;
+use std::result::Result;
+
+const INTEGRATION_BRANCH_NAME: &str = "ps/tmp/integrate";
+
+const DEFAULT_MERGE_MESSAGE_TEMPLATE: &str =
+    "Merge {branch} into {upstream}\n\n{summaries}\n\n{ps_ids}";
+
+const DEFAULT_SQUASH_MESSAGE_TEMPLATE: &str = "{summary}\n\n{summaries}\n\n{ps_ids}";
+
+const TAG_MESSAGE_TEMPLATE: &str = "Integrate {branch} into {upstream}\n\n{summaries}\n\n{ps_ids}";
+
+#[derive(Debug)]
+pub enum IntegrateStrategyError {
+    PatchesMissing(String),
+    NotFastForward(String, String),
+    MergeConflictsExist(String, String),
+    CherryPickFailed(cherry_picking::CherryPickError),
+    CreateTemporaryBranchFailed(git2::Error),
+    DeleteTemporaryBranchFailed(git2::Error),
+    CreateCommitFailed(git::CreateCommitError),
+    InvalidTagName(String),
+    GetSignerFailed(git::ConfiguredSignerError),
+    CreateTagFailed(git::CreateSignedTagError),
+    Git(git2::Error),
+}
+
+impl From<git2::Error> for IntegrateStrategyError {
+    fn from(e: git2::Error) -> Self {
+        Self::Git(e)
+    }
+}
+
+impl std::fmt::Display for IntegrateStrategyError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::PatchesMissing(branch) => write!(f, "{} has no patches to integrate", branch),
+            Self::NotFastForward(branch, upstream) => write!(
+                f,
+                "{} isn't based on the latest {}, pull & request review again",
+                branch, upstream
+            ),
+            Self::MergeConflictsExist(branch, upstream) => write!(
+                f,
+                "integrating {} into {} has conflicts, pull & request review again",
+                branch, upstream
+            ),
+            Self::CherryPickFailed(e) => write!(f, "failed to cherry pick, {}", e),
+            Self::CreateTemporaryBranchFailed(e) => {
+                write!(f, "failed to create temporary branch, {}", e)
+            }
+            Self::DeleteTemporaryBranchFailed(e) => {
+                write!(f, "failed to delete temporary branch, {}", e)
+            }
+            Self::CreateCommitFailed(e) => write!(f, "failed to create commit, {:?}", e),
+            Self::InvalidTagName(name) => write!(f, "{} is not a valid tag name", name),
+            Self::GetSignerFailed(e) => write!(f, "failed to get signer, {}", e),
+            Self::CreateTagFailed(e) => write!(f, "failed to create signed tag, {}", e),
+            Self::Git(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for IntegrateStrategyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::PatchesMissing(_) => None,
+            Self::NotFastForward(_, _) => None,
+            Self::MergeConflictsExist(_, _) => None,
+            Self::CherryPickFailed(e) => Some(e),
+            Self::CreateTemporaryBranchFailed(e) => Some(e),
+            Self::DeleteTemporaryBranchFailed(e) => Some(e),
+            Self::CreateCommitFailed(_) => None,
+            Self::InvalidTagName(_) => None,
+            Self::GetSignerFailed(e) => Some(e),
+            Self::CreateTagFailed(e) => Some(e),
+            Self::Git(e) => Some(e),
+        }
+    }
+}
+
+/// The temporary branch combining the request review branches being integrated on top of the
+/// upstream, to push to the upstream in their place
+#[derive(Debug)]
+pub struct IntegrationBranch {
+    ref_name: String,
+    upstream_oid: git2::Oid,
+    rewrite_config: PsRewriteConfig,
+    /// The commit the upstream is updated to when the branch is pushed
+    pub head_oid: git2::Oid,
+}
+
+impl IntegrationBranch {
+    /// Create the temporary branch at `upstream_oid`, creating & rewriting the commits on it
+    /// according to `rewrite_config`
+    pub fn create(
+        repo: &git2::Repository,
+        upstream_oid: git2::Oid,
+        rewrite_config: PsRewriteConfig,
+    ) -> Result<Self, IntegrateStrategyError> {
+        let upstream_commit = repo.find_commit(upstream_oid)?;
+        let branch = repo
+            .branch(INTEGRATION_BRANCH_NAME, &upstream_commit, true)
+            .map_err(IntegrateStrategyError::CreateTemporaryBranchFailed)?;
+        let ref_name = branch
+            .get()
+            .name()
+            .expect("temporary branch name is utf-8")
+            .to_string();
+        Ok(Self {
+            ref_name,
+            upstream_oid,
+            rewrite_config,
+            head_oid: upstream_oid,
+        })
+    }
+
+    /// The name of the temporary branch
+    pub fn branch_name(&self) -> &str {
+        INTEGRATION_BRANCH_NAME
+    }
+
+    /// Integrate the patches of the request review branch `branch_name`, reachable from
+    /// `branch_oid` but not from the upstream, according to `strategy`, returning the new head.
+    ///
+    /// With the fast-forward strategy the first request review branch has to be based on the
+    /// upstream, as it is integrated as is, while the patches of the following ones are replayed
+    /// on top of it. The message template of the merge & squash strategies supports the
+    /// `{branch}`, `{upstream}`, `{summary}` (of the first patch), `{summaries}` (a bulleted list)
+    /// & `{ps_ids}` placeholders. Squashing a single patch keeps its message as is unless a
+    /// template is given.
+    #[allow(clippy::too_many_arguments)]
+    pub fn integrate(
+        &mut self,
+        repo: &git2::Repository,
+        config: &git2::Config,
+        strategy: IntegrateStrategy,
+        message_template: Option<&str>,
+        branch_name: &str,
+        branch_oid: git2::Oid,
+        upstream_name: &str,
+    ) -> Result<git2::Oid, IntegrateStrategyError> {
+        let patches = branch_patches(repo, self.upstream_oid, branch_name, branch_oid)?;
+        let first_patch = patches
+            .first()
+            .ok_or_else(|| IntegrateStrategyError::PatchesMissing(branch_name.to_string()))?;
+
+        let head_oid = match strategy {
+            IntegrateStrategy::FastForward => {
+                self.fast_forward(repo, config, branch_name, branch_oid, upstream_name)?
+            }
+            _ => {
+                let head_commit = repo.find_commit(self.head_oid)?;
+                let branch_commit = repo.find_commit(branch_oid)?;
+                let mut index = repo.merge_commits(&head_commit, &branch_commit, None)?;
+                if index.has_conflicts() {
+                    return Err(IntegrateStrategyError::MergeConflictsExist(
+                        branch_name.to_string(),
+                        upstream_name.to_string(),
+                    ));
+                }
+                let tree = repo.find_tree(index.write_tree_to(repo)?)?;
+
+                let message = match (strategy, message_template) {
+                    (IntegrateStrategy::Squash, None) if patches.len() == 1 => {
+                        first_patch.message().unwrap_or_default().to_string()
+                    }
+                    (_, Some(template)) => {
+                        render_message(template, branch_name, upstream_name, &patches)
+                    }
+                    (IntegrateStrategy::Merge, None) => render_message(
+                        DEFAULT_MERGE_MESSAGE_TEMPLATE,
+                        branch_name,
+                        upstream_name,
+                        &patches,
+                    ),
+                    (_, None) => render_message(
+                        DEFAULT_SQUASH_MESSAGE_TEMPLATE,
+                        branch_name,
+                        upstream_name,
+                        &patches,
+                    ),
+                };
+
+                let committer = repo.signature()?;
+                match strategy {
+                    IntegrateStrategy::Merge => git::create_commit(
+                        repo,
+                        config,
+                        self.rewrite_config.sign,
+                        &self.ref_name,
+                        &committer,
+                        &committer,
+                        &message,
+                        &tree,
+                        &[&head_commit, &branch_commit],
+                    ),
+                    _ => git::create_commit(
+                        repo,
+                        config,
+                        self.rewrite_config.sign,
+                        &self.ref_name,
+                        &first_patch.author(),
+                        &committer,
+                        &message,
+                        &tree,
+                        &[&head_commit],
+                    ),
+                }
+                .map_err(IntegrateStrategyError::CreateCommitFailed)?
+            }
+        };
+
+        self.head_oid = head_oid;
+        Ok(head_oid)
+    }
+
+    /// Create the signed annotated tag, named after `name_template`, of the commit the request
+    /// review branch `branch_name` got integrated as, listing the ps-ids of its patches. The name
+    /// template supports the `{ps_id}` (of the first patch) & `{branch}` placeholders. Returns the
+    /// name of the tag.
+    #[allow(clippy::too_many_arguments)]
+    pub fn tag(
+        &self,
+        repo: &git2::Repository,
+        config: &git2::Config,
+        name_template: &str,
+        branch_name: &str,
+        branch_oid: git2::Oid,
+        integrated_oid: git2::Oid,
+        upstream_name: &str,
+    ) -> Result<String, IntegrateStrategyError> {
+        let patches = branch_patches(repo, self.upstream_oid, branch_name, branch_oid)?;
+        let ps_id = patches
+            .iter()
+            .find_map(ps::commit_ps_id)
+            .ok_or_else(|| IntegrateStrategyError::PatchesMissing(branch_name.to_string()))?;
+        let tag_name = name_template
+            .replace("{ps_id}", &ps_id.to_string())
+            .replace("{branch}", branch_name);
+        if !git2::Reference::is_valid_name(&format!("refs/tags/{}", tag_name)) {
+            return Err(IntegrateStrategyError::InvalidTagName(tag_name));
+        }
+
+        let signer =
+            git::configured_signer(config).map_err(IntegrateStrategyError::GetSignerFailed)?;
+        git::create_signed_tag(
+            repo,
+            signer,
+            &tag_name,
+            integrated_oid,
+            &repo.signature()?,
+            &render_message(TAG_MESSAGE_TEMPLATE, branch_name, upstream_name, &patches),
+            true,
+        )
+        .map_err(IntegrateStrategyError::CreateTagFailed)?;
+        Ok(tag_name)
+    }
+
+    fn fast_forward(
+        &self,
+        repo: &git2::Repository,
+        config: &git2::Config,
+        branch_name: &str,
+        branch_oid: git2::Oid,
+        upstream_name: &str,
+    ) -> Result<git2::Oid, IntegrateStrategyError> {
+        if self.head_oid == branch_oid || repo.graph_descendant_of(branch_oid, self.head_oid)? {
+            repo.find_reference(&self.ref_name)?
+                .set_target(branch_oid, "fast-forward integration")?;
+            return Ok(branch_oid);
+        }
+        if self.head_oid == self.upstream_oid {
+            return Err(IntegrateStrategyError::NotFastForward(
+                branch_name.to_string(),
+                upstream_name.to_string(),
+            ));
+        }
+
+        match cherry_picking::cherry_pick(
+            repo,
+            config,
+            &self.rewrite_config,
+            self.upstream_oid,
+            Some(branch_oid),
+            &self.ref_name,
+            0,
+            false,
+            false,
+        ) {
+            Ok(new_oid) => Ok(new_oid.unwrap_or(self.head_oid)),
+            Err(cherry_picking::CherryPickError::ConflictsExist(_, _)) => {
+                Err(IntegrateStrategyError::MergeConflictsExist(
+                    branch_name.to_string(),
+                    upstream_name.to_string(),
+                ))
+            }
+            Err(e) => Err(IntegrateStrategyError::CherryPickFailed(e)),
+        }
+    }
+
+    /// Delete the temporary branch
+    pub fn delete(self, repo: &git2::Repository) -> Result<(), IntegrateStrategyError> {
+        repo.find_branch(INTEGRATION_BRANCH_NAME, git2::BranchType::Local)
+            .and_then(|mut b| b.delete())
+            .map_err(IntegrateStrategyError::DeleteTemporaryBranchFailed)
+    }
+}
+
+/// The request review branches being integrated combined on top of the upstream in memory, to
+/// check they integrate the same way `IntegrationBranch` would combine them, without creating the
+/// temporary branch or any commits, & so without signing anything
+#[derive(Debug)]
+pub struct DryRunIntegration {
+    upstream_oid: git2::Oid,
+    /// The commit the integration is at while it is an existing one, i.e. until patches get
+    /// replayed or merged onto it
+    head_oid: Option<git2::Oid>,
+    head_tree_oid: git2::Oid,
+}
+
+impl DryRunIntegration {
+    /// Start the dry run at `upstream_oid`
+    pub fn new(
+        repo: &git2::Repository,
+        upstream_oid: git2::Oid,
+    ) -> Result<Self, IntegrateStrategyError> {
+        let head_tree_oid = repo.find_commit(upstream_oid)?.tree_id();
+        Ok(Self {
+            upstream_oid,
+            head_oid: Some(upstream_oid),
+            head_tree_oid,
+        })
+    }
+
+    /// Check the patches of the request review branch `branch_name`, reachable from `branch_oid`
+    /// but not from the upstream, integrate according to `strategy`, see
+    /// `IntegrationBranch::integrate()`
+    pub fn integrate(
+        &mut self,
+        repo: &git2::Repository,
+        strategy: IntegrateStrategy,
+        branch_name: &str,
+        branch_oid: git2::Oid,
+        upstream_name: &str,
+    ) -> Result<(), IntegrateStrategyError> {
+        let patches = branch_patches(repo, self.upstream_oid, branch_name, branch_oid)?;
+        if patches.is_empty() {
+            return Err(IntegrateStrategyError::PatchesMissing(
+                branch_name.to_string(),
+            ));
+        }
+        let conflicts = || {
+            IntegrateStrategyError::MergeConflictsExist(
+                branch_name.to_string(),
+                upstream_name.to_string(),
+            )
+        };
+
+        match strategy {
+            IntegrateStrategy::FastForward => {
+                if let Some(head_oid) = self.head_oid {
+                    if head_oid == branch_oid || repo.graph_descendant_of(branch_oid, head_oid)? {
+                        self.head_oid = Some(branch_oid);
+                        self.head_tree_oid = repo.find_commit(branch_oid)?.tree_id();
+                        return Ok(());
+                    }
+                    if head_oid == self.upstream_oid {
+                        return Err(IntegrateStrategyError::NotFastForward(
+                            branch_name.to_string(),
+                            upstream_name.to_string(),
+                        ));
+                    }
+                }
+
+                let mut tree_oid = self.head_tree_oid;
+                for patch in patches.iter() {
+                    if patch.parent_count() > 1 {
+                        return Err(IntegrateStrategyError::CherryPickFailed(
+                            cherry_picking::CherryPickError::MergeCommitDetected(
+                                patch.id().to_string(),
+                            ),
+                        ));
+                    }
+                    let mut index = repo.merge_trees(
+                        &patch.parent(0)?.tree()?,
+                        &repo.find_tree(tree_oid)?,
+                        &patch.tree()?,
+                        None,
+                    )?;
+                    if index.has_conflicts() {
+                        return Err(conflicts());
+                    }
+                    tree_oid = index.write_tree_to(repo)?;
+                }
+                self.head_tree_oid = tree_oid;
+            }
+            _ => {
+                let base_oid = repo.merge_base(self.upstream_oid, branch_oid)?;
+                let mut index = repo.merge_trees(
+                    &repo.find_commit(base_oid)?.tree()?,
+                    &repo.find_tree(self.head_tree_oid)?,
+                    &repo.find_commit(branch_oid)?.tree()?,
+                    None,
+                )?;
+                if index.has_conflicts() {
+                    return Err(conflicts());
+                }
+                self.head_tree_oid = index.write_tree_to(repo)?;
+            }
+        }
+
+        self.head_oid = None;
+        Ok(())
+    }
+}
+
+/// The patches of the request review branch, reachable from `branch_oid` but not from the
+/// upstream, oldest first
+fn branch_patches<'a>(
+    repo: &'a git2::Repository,
+    upstream_oid: git2::Oid,
+    branch_name: &str,
+    branch_oid: git2::Oid,
+) -> Result<Vec<git2::Commit<'a>>, IntegrateStrategyError> {
+    let patch_oids = git::get_revs(repo, upstream_oid, branch_oid, git2::Sort::REVERSE)
+        .map_err(|_| IntegrateStrategyError::PatchesMissing(branch_name.to_string()))?
+        .collect::<Result<Vec<git2::Oid>, git2::Error>>()?;
+    Ok(patch_oids
+        .iter()
+        .map(|oid| repo.find_commit(*oid))
+        .collect::<Result<Vec<git2::Commit>, git2::Error>>()?)
+}
+
+fn render_message(
+    template: &str,
+    branch_name: &str,
+    upstream_name: &str,
+    patches: &[git2::Commit],
+) -> String {
+    let summaries: Vec<String> = patches
+        .iter()
+        .map(|c| format!("* {}", c.summary().unwrap_or_default()))
+        .collect();
+    let ps_ids: Vec<String> = patches
+        .iter()
+        .filter_map(ps::commit_ps_id)
+        .map(|id| format!("<!-- ps-id: {} -->", id))
+        .collect();
+    let summary = patches
+        .first()
+        .and_then(|c| c.summary())
+        .unwrap_or_default();
+
+    let message = template
+        .replace("{branch}", branch_name)
+        .replace("{upstream}", upstream_name)
+        .replace("{summary}", summary)
+        .replace("{summaries}", &summaries.join("\n"))
+        .replace("{ps_ids}", &ps_ids.join("\n"));
+    format!("{}\n", message.trim_end())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::git::test_utils;
+    use super::*;
+
+    /// Create a request review branch at `base_oid` with a patch per file
+    fn rr_branch(
+        repo: &git2::Repository,
+        name: &str,
+        base_oid: git2::Oid,
+        files: &[&str],
+    ) -> git2::Oid {
+        let ref_name = format!("refs/heads/{}", name);
+        repo.reference(&ref_name, base_oid, true, "test").unwrap();
+        repo.set_head(&ref_name).unwrap();
+        let mut oid = base_oid;
+        for path in files {
+            oid = test_utils::commit_files(
+                repo,
+                &[(path, "content\n")],
+                &format!("{}\n\n<!-- ps-id: {} -->", path, uuid::Uuid::new_v4()),
+            );
+        }
+        oid
+    }
+
+    #[test]
+    fn test_integration_branch_fast_forwards_then_replays() {
+        let (_dir, repo) = test_utils::repo_init();
+        let config = repo.config().unwrap();
+        let upstream_oid = test_utils::commit_files(&repo, &[("base.txt", "base\n")], "base");
+        let foo_oid = rr_branch(&repo, "ps/rr/foo", upstream_oid, &["a.txt", "b.txt"]);
+        let bar_oid = rr_branch(&repo, "ps/rr/bar", upstream_oid, &["c.txt"]);
+
+        let mut integration =
+            IntegrationBranch::create(&repo, upstream_oid, test_utils::rewrite_config()).unwrap();
+        let ff = IntegrateStrategy::FastForward;
+        let foo_head = integration
+            .integrate(&repo, &config, ff, None, "ps/rr/foo", foo_oid, "main")
+            .unwrap();
+        assert_eq!(foo_head, foo_oid);
+        let bar_head = integration
+            .integrate(&repo, &config, ff, None, "ps/rr/bar", bar_oid, "main")
+            .unwrap();
+        let bar_commit = repo.find_commit(bar_head).unwrap();
+        assert_eq!(bar_commit.parent_id(0).unwrap(), foo_oid);
+        assert_eq!(bar_commit.summary(), Some("c.txt"));
+        assert_eq!(
+            repo.refname_to_id("refs/heads/ps/tmp/integrate").unwrap(),
+            bar_head
+        );
+        integration.delete(&repo).unwrap();
+        assert!(repo
+            .find_branch(INTEGRATION_BRANCH_NAME, git2::BranchType::Local)
+            .is_err());
+
+        // a request review branch based on an older upstream can't be fast-forwarded to
+        let moved_upstream_oid = rr_branch(&repo, "main", upstream_oid, &["d.txt"]);
+        let mut integration =
+            IntegrationBranch::create(&repo, moved_upstream_oid, test_utils::rewrite_config())
+                .unwrap();
+        assert!(matches!(
+            integration.integrate(&repo, &config, ff, None, "ps/rr/foo", foo_oid, "main"),
+            Err(IntegrateStrategyError::NotFastForward(_, _))
+        ));
+        integration.delete(&repo).unwrap();
+    }
+
+    #[test]
+    fn test_integration_branch_merges_and_squashes_the_patches() {
+        let (_dir, repo) = test_utils::repo_init();
+        let config = repo.config().unwrap();
+        let base_oid = test_utils::commit_files(&repo, &[("base.txt", "base\n")], "base");
+        let upstream_oid = test_utils::commit_files(&repo, &[("up.txt", "up\n")], "upstream moved");
+
+        repo.reference("refs/heads/ps/rr/foo", base_oid, true, "test")
+            .unwrap();
+        repo.set_head("refs/heads/ps/rr/foo").unwrap();
+        test_utils::commit_files(
+            &repo,
+            &[("a.txt", "a\n")],
+            "a\n\n<!-- ps-id: 2dce2a21-72b9-487a-b641-4a0b157b76e8 -->",
+        );
+        let branch_oid = test_utils::commit_files(
+            &repo,
+            &[("b.txt", "b\n")],
+            "b\n\n<!-- ps-id: 9c1d7f4e-08a3-4d7b-9e2a-5b6c3f1e0a27 -->",
+        );
+
+        let mut merge =
+            IntegrationBranch::create(&repo, upstream_oid, test_utils::rewrite_config()).unwrap();
+        let merge_oid = merge
+            .integrate(
+                &repo,
+                &config,
+                IntegrateStrategy::Merge,
+                None,
+                "ps/rr/foo",
+                branch_oid,
+                "main",
+            )
+            .unwrap();
+        let merge_commit = repo.find_commit(merge_oid).unwrap();
+        assert_eq!(
+            merge_commit.parent_ids().collect::<Vec<_>>(),
+            vec![upstream_oid, branch_oid]
+        );
+        assert_eq!(
+            merge_commit.message().unwrap(),
+            "Merge ps/rr/foo into main\n\n* a\n* b\n\n<!-- ps-id: 2dce2a21-72b9-487a-b641-4a0b157b76e8 -->\n<!-- ps-id: 9c1d7f4e-08a3-4d7b-9e2a-5b6c3f1e0a27 -->\n"
+        );
+        merge.delete(&repo).unwrap();
+
+        let mut squash =
+            IntegrationBranch::create(&repo, upstream_oid, test_utils::rewrite_config()).unwrap();
+        let squash_oid = squash
+            .integrate(
+                &repo,
+                &config,
+                IntegrateStrategy::Squash,
+                Some("{summary} & co ({branch})\n\n{summaries}"),
+                "ps/rr/foo",
+                branch_oid,
+                "main",
+            )
+            .unwrap();
+        let squash_commit = repo.find_commit(squash_oid).unwrap();
+        assert_eq!(
+            squash_commit.parent_ids().collect::<Vec<_>>(),
+            vec![upstream_oid]
+        );
+        assert_eq!(
+            squash_commit.message().unwrap(),
+            "a & co (ps/rr/foo)\n\n* a\n* b\n"
+        );
+        let squash_tree = squash_commit.tree().unwrap();
+        for path in ["up.txt", "a.txt", "b.txt"].iter() {
+            assert!(squash_tree.get_name(path).is_some());
+        }
+        squash.delete(&repo).unwrap();
+    }
+
+    #[test]
+    fn test_dry_run_integration_checks_without_touching_refs() {
+        let (_dir, repo) = test_utils::repo_init();
+        let upstream_oid = test_utils::commit_files(&repo, &[("base.txt", "base\n")], "base");
+        let foo_oid = rr_branch(&repo, "ps/rr/foo", upstream_oid, &["a.txt"]);
+        let bar_oid = rr_branch(&repo, "ps/rr/bar", upstream_oid, &["b.txt"]);
+        repo.reference("refs/heads/ps/rr/clash", upstream_oid, true, "test")
+            .unwrap();
+        repo.set_head("refs/heads/ps/rr/clash").unwrap();
+        let clash_oid = test_utils::commit_files(&repo, &[("a.txt", "other\n")], "clashing a.txt");
+        let ref_names = |repo: &git2::Repository| -> Vec<(String, Option<git2::Oid>)> {
+            repo.references()
+                .unwrap()
+                .map(|r| {
+                    let r = r.unwrap();
+                    (r.name().unwrap().to_string(), r.target())
+                })
+                .collect()
+        };
+        let refs_before = ref_names(&repo);
+
+        let ff = IntegrateStrategy::FastForward;
+        let mut dry_run = DryRunIntegration::new(&repo, upstream_oid).unwrap();
+        dry_run
+            .integrate(&repo, ff, "ps/rr/foo", foo_oid, "main")
+            .unwrap();
+        dry_run
+            .integrate(&repo, ff, "ps/rr/bar", bar_oid, "main")
+            .unwrap();
+        assert!(matches!(
+            dry_run.integrate(&repo, ff, "ps/rr/clash", clash_oid, "main"),
+            Err(IntegrateStrategyError::MergeConflictsExist(_, _))
+        ));
+
+        let mut dry_run = DryRunIntegration::new(&repo, upstream_oid).unwrap();
+        dry_run
+            .integrate(
+                &repo,
+                IntegrateStrategy::Merge,
+                "ps/rr/foo",
+                foo_oid,
+                "main",
+            )
+            .unwrap();
+        assert!(matches!(
+            dry_run.integrate(
+                &repo,
+                IntegrateStrategy::Merge,
+                "ps/rr/clash",
+                clash_oid,
+                "main"
+            ),
+            Err(IntegrateStrategyError::MergeConflictsExist(_, _))
+        ));
+
+        assert_eq!(refs_before, ref_names(&repo));
+    }
+}
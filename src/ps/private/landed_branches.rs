@@ -0,0 +1,165 @@
+// This module finds the request review branches whose patches all landed upstream, whether they
+// were integrated with gps by someone else or merged through the forge's UI, so they can be
+// cleaned up rather than lingering forever.
+
+use super::in_memory_rebase::{InMemoryRebaseError, UpstreamedPatches};
+use super::rr_branch_name::RrBranchNaming;
+use std::result::Result;
+
+#[derive(Debug)]
+pub enum LandedBranchesError {
+    GetUpstreamedPatchesFailed(InMemoryRebaseError),
+    Git(git2::Error),
+}
+
+impl From<git2::Error> for LandedBranchesError {
+    fn from(e: git2::Error) -> Self {
+        Self::Git(e)
+    }
+}
+
+impl std::fmt::Display for LandedBranchesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::GetUpstreamedPatchesFailed(e) => {
+                write!(f, "failed to get the patches already upstream, {}", e)
+            }
+            Self::Git(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for LandedBranchesError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::GetUpstreamedPatchesFailed(e) => Some(e),
+            Self::Git(e) => Some(e),
+        }
+    }
+}
+
+/// Get the names of the local request review branches, other than the checked out one, whose
+/// every commit is in `upstream_oid`, either as is, with the same ps-id, or as the same diff,
+/// sorted.
+pub fn landed_branches(
+    repo: &git2::Repository,
+    rr_branch_naming: &RrBranchNaming,
+    upstream_oid: git2::Oid,
+) -> Result<Vec<String>, LandedBranchesError> {
+    let mut review_branches: Vec<(String, git2::Oid)> = vec![];
+    for branch in repo.branches(Some(git2::BranchType::Local))? {
+        let (branch, _) = branch?;
+        if branch.is_head() {
+            continue;
+        }
+        let name = match branch.name()? {
+            Some(name) if rr_branch_naming.is_rr_branch_name(name) => name.to_string(),
+            _ => continue,
+        };
+        if let Some(oid) = branch.get().target() {
+            review_branches.push((name, oid));
+        }
+    }
+    if review_branches.is_empty() {
+        return Ok(vec![]);
+    }
+
+    // the patches landed upstream are in the commits the branches don't all have, so the upstream
+    // history the branches share doesn't need to be looked at
+    let branch_oids: Vec<git2::Oid> = review_branches.iter().map(|(_, oid)| *oid).collect();
+    let upstreamed_patches = UpstreamedPatches::since_shared_base(repo, &branch_oids, upstream_oid)
+        .map_err(LandedBranchesError::GetUpstreamedPatchesFailed)?;
+
+    let mut landed: Vec<String> = vec![];
+    for (name, oid) in review_branches {
+        let mut revs = repo.revwalk()?;
+        revs.push(oid)?;
+        revs.hide(upstream_oid)?;
+        let mut all_landed = true;
+        for rev in revs {
+            let commit = repo.find_commit(rev?)?;
+            if commit.parent_count() != 1
+                || !upstreamed_patches
+                    .contains(repo, &commit)
+                    .map_err(LandedBranchesError::GetUpstreamedPatchesFailed)?
+            {
+                all_landed = false;
+                break;
+            }
+        }
+        if all_landed {
+            landed.push(name);
+        }
+    }
+    landed.sort();
+    Ok(landed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::config::PsRequestReviewConfig;
+    use super::super::git::test_utils;
+    use super::*;
+
+    fn branch_at(repo: &git2::Repository, name: &str, base_oid: git2::Oid, files: &[(&str, &str)]) {
+        let ref_name = format!("refs/heads/{}", name);
+        repo.reference(&ref_name, base_oid, true, "test").unwrap();
+        repo.set_head(&ref_name).unwrap();
+        for (path, content) in files {
+            test_utils::commit_files(
+                repo,
+                &[(path, content)],
+                &format!("{}\n\n<!-- ps-id: {} -->", path, uuid::Uuid::new_v4()),
+            );
+        }
+    }
+
+    #[test]
+    fn test_landed_branches_only_lists_review_branches_fully_upstream() {
+        let (_dir, repo) = test_utils::repo_init();
+        let base_oid = test_utils::commit_files(&repo, &[("base.txt", "base\n")], "base");
+
+        branch_at(&repo, "ps/rr/by_diff", base_oid, &[("a.txt", "a\n")]);
+        branch_at(
+            &repo,
+            "ps/rr/partly",
+            base_oid,
+            &[("a.txt", "a\n"), ("c.txt", "c\n")],
+        );
+        branch_at(&repo, "ps/rr/pending", base_oid, &[("d.txt", "d\n")]);
+        branch_at(&repo, "custom", base_oid, &[("a.txt", "a\n")]);
+
+        // the upstream got a.txt applied as the same diff without a ps-id & the patch of
+        // ps/rr/by_ps_id squashed into a different diff keeping its ps-id
+        branch_at(&repo, "ps/rr/by_ps_id", base_oid, &[("b.txt", "b\n")]);
+        let by_ps_id_commit = repo.head().unwrap().peel_to_commit().unwrap();
+        let ps_id_message = by_ps_id_commit.message().unwrap().to_string();
+        branch_at(&repo, "main", base_oid, &[]);
+        test_utils::commit_files(&repo, &[("a.txt", "a\n")], "a upstream");
+        let upstream_oid =
+            test_utils::commit_files(&repo, &[("b.txt", "b squashed\n")], &ps_id_message);
+
+        // a newer branch based on the upstream, which contains the landed patches, mustn't keep
+        // them from being recognized
+        branch_at(
+            &repo,
+            "ps/rr/on_upstream",
+            upstream_oid,
+            &[("e.txt", "e\n")],
+        );
+        repo.set_head("refs/heads/main").unwrap();
+
+        let rr_branch_naming = RrBranchNaming::new(&PsRequestReviewConfig {
+            verify_isolation: false,
+            branch_name_template: "ps/rr/{slug}".to_string(),
+            branch_name_max_length: None,
+            branch_name_slug_separator: None,
+            ticket_pattern: None,
+        })
+        .unwrap();
+        assert_eq!(
+            landed_branches(&repo, &rr_branch_naming, upstream_oid).unwrap(),
+            vec!["ps/rr/by_diff", "ps/rr/by_ps_id"]
+        );
+    }
+}
@@ -1,7 +1,17 @@
 use ansi_term::{ANSIGenericString, Style};
-use std::{fmt, str::Utf8Error};
+use std::{
+    fmt,
+    str::Utf8Error,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    },
+    thread,
+};
 
-use super::{hooks, utils};
+use super::{config::PsHooksConfig, hooks, utils};
+
+const MAX_PARALLEL_LIST_HOOKS: usize = 8;
 
 #[derive(Debug, PartialEq, Clone)]
 struct ListCell {
@@ -18,40 +28,40 @@ impl ListCell {
             .unwrap_or(str)
     }
 
-    fn get_colored_text<'a>(&'a self, str: &'a str) -> ANSIGenericString<str> {
-        if self.color.is_some() && self.bg_color.is_some() {
-            self.color.unwrap().on(self.bg_color.unwrap()).paint(str)
-        } else if self.color.is_some() && self.bg_color.is_none() {
-            self.color.unwrap().paint(str)
-        } else if self.color.is_none() & self.bg_color.is_some() {
-            Style::new().on(self.bg_color.unwrap()).paint(str)
-        } else {
-            ANSIGenericString::from(str)
+    fn get_colored_text<'a>(
+        &'a self,
+        str: &'a str,
+        with_color: bool,
+    ) -> ANSIGenericString<'a, str> {
+        if !with_color {
+            return ANSIGenericString::from(str);
+        }
+        match (self.color, self.bg_color) {
+            (Some(color), Some(bg_color)) => color.on(bg_color).paint(str),
+            (Some(color), None) => color.paint(str),
+            (None, Some(bg_color)) => Style::new().on(bg_color).paint(str),
+            (None, None) => ANSIGenericString::from(str),
         }
     }
 }
 
-impl fmt::Display for ListCell {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl ListCell {
+    fn render(&self, with_color: bool) -> String {
         let without_newlines = utils::strip_newlines(&self.value);
         let str_fixed_width = self.get_str_fixed_width(without_newlines);
-        let colored_text = self.get_colored_text(&str_fixed_width);
-        write!(f, "{}", colored_text)
+        self.get_colored_text(&str_fixed_width, with_color)
+            .to_string()
     }
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Default)]
 pub struct ListRow {
     cells: Vec<ListCell>,
-    with_color: bool,
 }
 
 impl ListRow {
-    pub fn new(with_color: bool) -> Self {
-        Self {
-            with_color,
-            cells: vec![],
-        }
+    pub fn new() -> Self {
+        Self { cells: vec![] }
     }
 
     pub fn add_cell(
@@ -61,26 +71,42 @@ impl ListRow {
         bg_color: Option<ansi_term::Colour>,
         value: impl fmt::Display,
     ) {
-        let color = if self.with_color { text_color } else { None };
-        let bg_color = if self.with_color { bg_color } else { None };
         let cell = ListCell {
             width,
-            color,
+            color: text_color,
             bg_color,
             value: value.to_string(),
         };
         self.cells.push(cell)
     }
+
+    /// Add a cell whose value isn't known yet, returning the index to later set it with
+    pub fn add_pending_cell(
+        &mut self,
+        width: Option<usize>,
+        text_color: Option<ansi_term::Colour>,
+        bg_color: Option<ansi_term::Colour>,
+    ) -> usize {
+        self.add_cell(width, text_color, bg_color, "");
+        self.cells.len() - 1
+    }
+
+    pub fn set_cell_value(&mut self, index: usize, value: impl fmt::Display) {
+        if let Some(cell) = self.cells.get_mut(index) {
+            cell.value = value.to_string();
+        }
+    }
 }
 
-impl fmt::Display for ListRow {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+impl ListRow {
+    /// Render the cells of the row, coloured unless `with_color` is false
+    pub fn render(&self, with_color: bool) -> String {
         let mut row_str = String::new();
 
         for column in &self.cells {
-            row_str.push_str(&column.to_string());
+            row_str.push_str(&column.render(with_color));
         }
-        write!(f, "{}", row_str)
+        row_str
     }
 }
 
@@ -108,20 +134,84 @@ impl std::error::Error for ListHookError {
     }
 }
 
+/// The output of the list_additional_information hooks for a patch
+#[derive(Debug, Default)]
+pub struct ListHookOutput {
+    pub text: String,
+    /// Whether all of the hooks exited successfully
+    pub succeeded: bool,
+}
+
 pub fn execute_list_additional_info_hook(
     repo_root_str: &str,
     repo_gitdir_str: &str,
+    hooks_config: &PsHooksConfig,
     args: &[&str],
-) -> Result<String, ListHookError> {
-    let hook_output = hooks::find_and_execute_hook_with_output(
+) -> Result<ListHookOutput, ListHookError> {
+    let hook_outputs = hooks::find_and_execute_hook_with_output(
         repo_root_str,
         repo_gitdir_str,
-        "list_additional_information",
+        hooks_config,
+        &hooks::LIST_ADDITIONAL_INFORMATION_HOOK,
         args,
     )
     .map_err(ListHookError::GetHookOutputError)?;
-    String::from_utf8(hook_output.stdout)
-        .map_err(|e| ListHookError::HookOutputInvalid(e.utf8_error()))
+    let succeeded = hook_outputs.iter().all(|output| output.status.success());
+    let hook_stdouts = hook_outputs
+        .into_iter()
+        .map(|output| {
+            String::from_utf8(output.stdout)
+                .map(|stdout| stdout.trim_end().to_string())
+                .map_err(|e| ListHookError::HookOutputInvalid(e.utf8_error()))
+        })
+        .collect::<Result<Vec<String>, ListHookError>>()?;
+    Ok(ListHookOutput {
+        text: hook_stdouts.join(" "),
+        succeeded,
+    })
+}
+
+/// Execute the list_additional_information hook once for each of the given sets of arguments,
+/// running up to `MAX_PARALLEL_LIST_HOOKS` of them at a time. The results are returned in the
+/// same order as the given sets of arguments.
+pub fn execute_list_additional_info_hooks(
+    repo_root_str: &str,
+    repo_gitdir_str: &str,
+    hooks_config: &PsHooksConfig,
+    invocations: &[Vec<String>],
+) -> Vec<Result<ListHookOutput, ListHookError>> {
+    let next_invocation = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<ListHookOutput, ListHookError>>>> =
+        Mutex::new(invocations.iter().map(|_| None).collect());
+
+    thread::scope(|s| {
+        for _ in 0..invocations.len().min(MAX_PARALLEL_LIST_HOOKS) {
+            s.spawn(|| loop {
+                let i = next_invocation.fetch_add(1, Ordering::SeqCst);
+                let args = match invocations.get(i) {
+                    Some(args) => args,
+                    None => break,
+                };
+                let arg_strs: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+                let res = execute_list_additional_info_hook(
+                    repo_root_str,
+                    repo_gitdir_str,
+                    hooks_config,
+                    &arg_strs,
+                );
+                if let Ok(mut results) = results.lock() {
+                    results[i] = Some(res);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap_or_else(|e| e.into_inner())
+        .into_iter()
+        .map(|res| res.unwrap_or_else(|| Ok(ListHookOutput::default())))
+        .collect()
 }
 
 #[cfg(test)]
@@ -137,7 +227,7 @@ mod tests {
             bg_color: None,
             value: "hello".to_string(),
         };
-        assert_eq!(format!("{}", cell), "\u{1b}[34mhell\u{1b}[0m");
+        assert_eq!(cell.render(true), "\u{1b}[34mhell\u{1b}[0m");
     }
 
     #[test]
@@ -148,7 +238,7 @@ mod tests {
             bg_color: None,
             value: "hello".to_string(),
         };
-        assert_eq!(format!("{}", cell), "hello ");
+        assert_eq!(cell.render(true), "hello ");
     }
 
     #[test]
@@ -159,30 +249,23 @@ mod tests {
             bg_color: None,
             value: "hello".to_string(),
         };
-        assert_eq!(format!("{}", cell), "hello");
+        assert_eq!(cell.render(true), "hello");
     }
 
     #[test]
     fn test_list_row_new() {
-        let row = ListRow::new(true);
-        assert_eq!(
-            row,
-            ListRow {
-                with_color: true,
-                cells: vec![]
-            }
-        );
+        let row = ListRow::new();
+        assert_eq!(row, ListRow { cells: vec![] });
     }
 
     #[test]
     fn test_list_row_add_cell() {
-        let mut row = ListRow::new(false);
+        let mut row = ListRow::new();
         let cell_value = "hello".to_string();
         row.add_cell(None, None, None, &cell_value);
         assert_eq!(
             row,
             ListRow {
-                with_color: false,
                 cells: vec![ListCell {
                     width: None,
                     color: None,
@@ -194,8 +277,8 @@ mod tests {
     }
 
     #[test]
-    fn test_list_row_fmt_with_color() {
-        let mut row = ListRow::new(true);
+    fn test_list_row_render_with_color() {
+        let mut row = ListRow::new();
         let first_cell = ListCell {
             width: Some(10),
             color: Some(Blue),
@@ -220,12 +303,12 @@ mod tests {
             second_cell.bg_color,
             second_cell.value,
         );
-        assert_eq!(format!("{}", row), "\u{1b}[34mHello     \u{1b}[0mWorld")
+        assert_eq!(row.render(true), "\u{1b}[34mHello     \u{1b}[0mWorld")
     }
 
     #[test]
-    fn test_list_row_fmt_without_color() {
-        let mut row = ListRow::new(false);
+    fn test_list_row_render_without_color() {
+        let mut row = ListRow::new();
         let first_cell = ListCell {
             width: Some(10),
             color: Some(Blue),
@@ -250,6 +333,6 @@ mod tests {
             second_cell.bg_color,
             second_cell.value,
         );
-        assert_eq!(format!("{}", row), "Hello     World")
+        assert_eq!(row.render(false), "Hello     World")
     }
 }
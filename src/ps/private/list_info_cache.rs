@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const LIST_INFO_CACHE_NAME: &str = "list_additional_information";
+
+#[derive(Debug)]
+pub enum SaveListInfoCacheError {
+    CreateDir(io::Error),
+    Serialize(serde_json::Error),
+    Write(io::Error),
+}
+
+impl std::fmt::Display for SaveListInfoCacheError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CreateDir(e) => write!(f, "failed to create cache directory, {}", e),
+            Self::Serialize(e) => write!(f, "failed to serialize cache, {}", e),
+            Self::Write(e) => write!(f, "failed to write cache, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SaveListInfoCacheError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::CreateDir(e) => Some(e),
+            Self::Serialize(e) => Some(e),
+            Self::Write(e) => Some(e),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+struct ListInfoCacheEntry {
+    output: String,
+    created_at: u64,
+}
+
+/// Cache of list_additional_information hook output keyed by the patch & the state of the branch
+/// it is being listed for, see `list_info_cache_key()`
+#[derive(Debug, Serialize, Deserialize, Default)]
+pub struct ListInfoCache {
+    entries: HashMap<String, ListInfoCacheEntry>,
+}
+
+/// Build the key identifying a hook invocation from the patch's ps-id & commit sha along with
+/// the name & state of the review branch it is being listed for
+pub fn list_info_cache_key(
+    ps_id: &uuid::Uuid,
+    commit_oid: &git2::Oid,
+    branch_name: &str,
+    branch_state: &str,
+) -> String {
+    format!("{}:{}:{}:{}", ps_id, commit_oid, branch_name, branch_state)
+}
+
+fn secs_since_epoch(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+impl ListInfoCache {
+    /// Load the cache from the given path. A missing or unreadable cache is simply empty.
+    pub fn load(path: &Path) -> Self {
+        fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    /// Get the cached output for the given key if it is younger than the ttl
+    pub fn get(&self, key: &str, ttl: Duration, now: SystemTime) -> Option<&str> {
+        self.entries
+            .get(key)
+            .filter(|entry| secs_since_epoch(now).saturating_sub(entry.created_at) < ttl.as_secs())
+            .map(|entry| entry.output.as_str())
+    }
+
+    pub fn insert(&mut self, key: String, output: String, now: SystemTime) {
+        self.entries.insert(
+            key,
+            ListInfoCacheEntry {
+                output,
+                created_at: secs_since_epoch(now),
+            },
+        );
+    }
+
+    /// Drop all the entries that are older than the ttl so the cache doesn't grow forever
+    pub fn prune(&mut self, ttl: Duration, now: SystemTime) {
+        let now_secs = secs_since_epoch(now);
+        self.entries
+            .retain(|_, entry| now_secs.saturating_sub(entry.created_at) < ttl.as_secs());
+    }
+
+    /// Save the cache to the given path, replacing it atomically so concurrent readers never
+    /// see a partially written cache
+    pub fn save(&self, path: &Path) -> Result<(), SaveListInfoCacheError> {
+        let dir = path.parent().unwrap_or_else(|| Path::new("."));
+        fs::create_dir_all(dir).map_err(SaveListInfoCacheError::CreateDir)?;
+        let content = serde_json::to_string(self).map_err(SaveListInfoCacheError::Serialize)?;
+        let mut file =
+            tempfile::NamedTempFile::new_in(dir).map_err(SaveListInfoCacheError::Write)?;
+        file.write_all(content.as_bytes())
+            .map_err(SaveListInfoCacheError::Write)?;
+        file.persist(path)
+            .map_err(|e| SaveListInfoCacheError::Write(e.error))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_list_info_cache_expires_entries_after_ttl() {
+        let now = SystemTime::now();
+        let ttl = Duration::from_secs(60);
+        let mut cache = ListInfoCache::default();
+        cache.insert("key".to_string(), "output".to_string(), now);
+
+        assert_eq!(cache.get("key", ttl, now), Some("output"));
+        assert_eq!(cache.get("key", ttl, now + Duration::from_secs(61)), None);
+        assert_eq!(cache.get("missing", ttl, now), None);
+    }
+
+    #[test]
+    fn test_list_info_cache_round_trips_through_file() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("cache").join("list.json");
+        let now = SystemTime::now();
+        let mut cache = ListInfoCache::default();
+        cache.insert("key".to_string(), "output".to_string(), now);
+        cache.save(&path).unwrap();
+
+        let loaded = ListInfoCache::load(&path);
+        assert_eq!(
+            loaded.get("key", Duration::from_secs(60), now),
+            Some("output")
+        );
+    }
+}
@@ -1,11 +1,23 @@
 pub mod branch;
+pub mod branch_scan_cache;
 pub mod checkout;
 pub mod cherry_picking;
 pub mod config;
+pub mod conflicts;
+pub mod dependencies;
+pub mod events;
 pub mod git;
 pub mod hooks;
+pub mod in_memory_rebase;
+pub mod integrate_strategy;
+pub mod landed_branches;
 pub mod list;
+pub mod list_info_cache;
 pub mod paths;
+pub mod push;
+pub mod rr_branch_name;
+pub mod signatures;
+pub mod stacks;
 pub mod state_computation;
 pub mod string_file_io;
 pub mod utils;
@@ -1,8 +1,16 @@
 use git2;
 use is_executable::IsExecutable;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
 
 const ISOLATE_LAST_BRANCH_RELATIVE_PATH: &str = "GIT-PATCH-STACK-ISOLATE-LAST-BRANCH";
+const HOOK_LOGS_RELATIVE_PATH: &str = "git-ps/logs/hooks";
+const CACHE_RELATIVE_PATH: &str = "git-ps/cache";
+const CONTINUE_STATE_RELATIVE_PATH: &str = "git-ps/continue.json";
+const CONFLICT_WORKTREE_RELATIVE_PATH: &str = "git-ps/conflict-worktree";
+
+static HOOK_LOG_SEQUENCE: AtomicUsize = AtomicUsize::new(0);
 
 #[derive(Debug)]
 pub enum PathsError {
@@ -31,6 +39,51 @@ pub fn isolate_last_branch_path(repo: &git2::Repository) -> PathBuf {
     repo.path().join(ISOLATE_LAST_BRANCH_RELATIVE_PATH)
 }
 
+pub fn hook_logs_path(repo_gitdir: &str) -> PathBuf {
+    Path::new(repo_gitdir).join(HOOK_LOGS_RELATIVE_PATH)
+}
+
+/// Path of the log capturing a single run of the named hook, unique to this process & moment
+pub fn hook_log_path(repo_gitdir: &str, hook_name: &str) -> PathBuf {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    hook_logs_path(repo_gitdir).join(format!(
+        "{}-{:09}-{}-{}-{}.log",
+        since_epoch.as_secs(),
+        since_epoch.subsec_nanos(),
+        std::process::id(),
+        HOOK_LOG_SEQUENCE.fetch_add(1, Ordering::SeqCst),
+        hook_name
+    ))
+}
+
+/// Path of the named cache file within the gitdir
+pub fn cache_path(repo_gitdir: &str, cache_name: &str) -> PathBuf {
+    Path::new(repo_gitdir)
+        .join(CACHE_RELATIVE_PATH)
+        .join(format!("{}.json", cache_name))
+}
+
+/// Path of the gitdir shared by all the worktrees of the repository, the gitdir itself unless the
+/// repository is a linked worktree
+pub fn repo_common_dir(repo: &git2::Repository) -> PathBuf {
+    match std::fs::read_to_string(repo.path().join("commondir")) {
+        Ok(content) => repo.path().join(content.trim()),
+        Err(_) => repo.path().to_path_buf(),
+    }
+}
+
+/// Path of the state of the operation interrupted by a conflict, shared by all the worktrees
+pub fn continue_state_path(repo: &git2::Repository) -> PathBuf {
+    repo_common_dir(repo).join(CONTINUE_STATE_RELATIVE_PATH)
+}
+
+/// Path of the temporary worktree conflicts are resolved in
+pub fn conflict_worktree_path(repo: &git2::Repository) -> PathBuf {
+    repo_common_dir(repo).join(CONFLICT_WORKTREE_RELATIVE_PATH)
+}
+
 pub fn communal_repository_level_config_path(repo_root: &str) -> PathBuf {
     let path_string = format!("{}/.git-ps/config.toml", repo_root);
     Path::new(path_string.as_str()).to_path_buf()
@@ -0,0 +1,110 @@
+use super::config;
+use super::git;
+use super::hooks;
+use super::paths;
+use std::result::Result;
+
+#[derive(Debug)]
+pub enum PushError {
+    GetRepoRootPathFailed(Box<dyn std::error::Error>),
+    PathNotUtf8,
+    GetConfigFailed(Box<dyn std::error::Error>),
+    PrePushHookFailed(hooks::FindAndExecuteHookError),
+    PushFailed(git::ExtForcePushError),
+    PushAtomicFailed(git::ExtPushAtomicError),
+}
+
+impl std::fmt::Display for PushError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::GetRepoRootPathFailed(e) => {
+                write!(f, "failed to get repositories root path, {}", e)
+            }
+            Self::PathNotUtf8 => write!(f, "path not utf-8"),
+            Self::GetConfigFailed(e) => write!(f, "failed to get config, {}", e),
+            Self::PrePushHookFailed(e) => write!(f, "pre_push hook vetoed the push, {}", e),
+            Self::PushFailed(e) => write!(f, "push failed, {}", e),
+            Self::PushAtomicFailed(e) => write!(f, "atomic push failed, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PushError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::GetRepoRootPathFailed(e) => Some(e.as_ref()),
+            Self::PathNotUtf8 => None,
+            Self::GetConfigFailed(e) => Some(e.as_ref()),
+            Self::PrePushHookFailed(e) => Some(e),
+            Self::PushFailed(e) => Some(e),
+            Self::PushAtomicFailed(e) => Some(e),
+        }
+    }
+}
+
+/// Push the given source ref to the destination ref on the named remote, giving the `pre_push`
+/// hook the chance to veto the push first.
+pub fn push(
+    repo: &git2::Repository,
+    force: bool,
+    remote_name: &str,
+    src_ref_spec: &str,
+    dest_ref_spec: &str,
+) -> Result<(), PushError> {
+    let repo_root_path =
+        paths::repo_root_path(repo).map_err(|e| PushError::GetRepoRootPathFailed(e.into()))?;
+    let repo_root_str = repo_root_path.to_str().ok_or(PushError::PathNotUtf8)?;
+    let repo_gitdir_str = repo.path().to_str().ok_or(PushError::PathNotUtf8)?;
+    let config = config::get_config(repo, repo_root_str, repo_gitdir_str)
+        .map_err(|e| PushError::GetConfigFailed(e.into()))?;
+
+    hooks::find_and_execute_hook(
+        repo_root_str,
+        repo_gitdir_str,
+        &config.hooks,
+        &hooks::PRE_PUSH_HOOK,
+        &[remote_name, src_ref_spec, dest_ref_spec],
+    )
+    .map_err(PushError::PrePushHookFailed)?;
+
+    git::ext_push(repo, force, remote_name, src_ref_spec, dest_ref_spec)
+        .map_err(PushError::PushFailed)
+}
+
+/// Push the given source ref to the destination ref & the given tags to the named remote & delete
+/// the given branches from it in a single atomic push, giving the `pre_push` hook the chance to
+/// veto the push of the source ref first. With `signed` the push certificate is signed.
+pub fn push_atomic(
+    repo: &git2::Repository,
+    remote_name: &str,
+    src_ref_spec: &str,
+    dest_ref_spec: &str,
+    tag_names: &[String],
+    deleted_branch_names: &[String],
+    signed: bool,
+) -> Result<(), PushError> {
+    let repo_root_path =
+        paths::repo_root_path(repo).map_err(|e| PushError::GetRepoRootPathFailed(e.into()))?;
+    let repo_root_str = repo_root_path.to_str().ok_or(PushError::PathNotUtf8)?;
+    let repo_gitdir_str = repo.path().to_str().ok_or(PushError::PathNotUtf8)?;
+    let config = config::get_config(repo, repo_root_str, repo_gitdir_str)
+        .map_err(|e| PushError::GetConfigFailed(e.into()))?;
+
+    hooks::find_and_execute_hook(
+        repo_root_str,
+        repo_gitdir_str,
+        &config.hooks,
+        &hooks::PRE_PUSH_HOOK,
+        &[remote_name, src_ref_spec, dest_ref_spec],
+    )
+    .map_err(PushError::PrePushHookFailed)?;
+
+    let mut refspecs = vec![format!("{}:{}", src_ref_spec, dest_ref_spec)];
+    refspecs.extend(
+        tag_names
+            .iter()
+            .map(|t| format!("refs/tags/{}:refs/tags/{}", t, t)),
+    );
+    refspecs.extend(deleted_branch_names.iter().map(|b| format!(":{}", b)));
+    git::ext_push_atomic(remote_name, &refspecs, signed).map_err(PushError::PushAtomicFailed)
+}
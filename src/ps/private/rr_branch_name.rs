@@ -0,0 +1,446 @@
+use super::super::super::ps;
+use super::config;
+use regex::Regex;
+use std::time::{SystemTime, UNIX_EPOCH};
+use uuid::Uuid;
+
+/// Number of characters of the ps-id used by the `{ps_id}` placeholder
+const PS_ID_PREFIX_LENGTH: usize = 8;
+
+/// Characters trimmed from the end of a name after it has been truncated
+const TRAILING_SEPARATORS: &[char] = &['/', '-', '_', '.'];
+
+#[derive(Debug)]
+pub enum RrBranchNameTemplateError {
+    UnknownPlaceholder(String),
+    UnclosedPlaceholder,
+    InvalidTicketPattern(regex::Error),
+}
+
+impl std::fmt::Display for RrBranchNameTemplateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownPlaceholder(name) => write!(f, "unknown placeholder {{{}}}", name),
+            Self::UnclosedPlaceholder => write!(f, "placeholder missing closing }}"),
+            Self::InvalidTicketPattern(e) => write!(f, "invalid ticket pattern, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RrBranchNameTemplateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::UnknownPlaceholder(_) => None,
+            Self::UnclosedPlaceholder => None,
+            Self::InvalidTicketPattern(e) => Some(e),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum GenerateRrBranchNameError {
+    TicketPatternMissing,
+    TicketNotFound,
+    PatchIdMissing,
+}
+
+impl std::fmt::Display for GenerateRrBranchNameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TicketPatternMissing => write!(
+                f,
+                "the branch name template uses {{ticket}} but request_review.ticket_pattern isn't configured"
+            ),
+            Self::TicketNotFound => write!(f, "ticket not found in the commit message"),
+            Self::PatchIdMissing => write!(f, "patch is missing its ps-id"),
+        }
+    }
+}
+
+impl std::error::Error for GenerateRrBranchNameError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        None
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placeholder {
+    User,
+    Slug,
+    PsId,
+    Index,
+    Ticket,
+    Date,
+}
+
+impl Placeholder {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "user" => Some(Self::User),
+            "slug" => Some(Self::Slug),
+            "ps_id" => Some(Self::PsId),
+            "index" => Some(Self::Index),
+            "ticket" => Some(Self::Ticket),
+            "date" => Some(Self::Date),
+            _ => None,
+        }
+    }
+
+    /// Regex matching any value the placeholder can be rendered as
+    fn pattern(&self) -> &'static str {
+        match self {
+            Self::User | Self::Ticket => "[^/]+",
+            // slugs may be nested, e.g. with a / slug separator or from earlier naming schemes
+            Self::Slug => ".*",
+            Self::PsId => "[0-9a-f]+",
+            Self::Index => "[0-9]+",
+            Self::Date => "[0-9]{4}-[0-9]{2}-[0-9]{2}",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Placeholder(Placeholder),
+}
+
+fn parse_template(template: &str) -> Result<Vec<Segment>, RrBranchNameTemplateError> {
+    let mut segments: Vec<Segment> = vec![];
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        if start > 0 {
+            segments.push(Segment::Literal(rest[..start].to_string()));
+        }
+        let end = rest[start..]
+            .find('}')
+            .ok_or(RrBranchNameTemplateError::UnclosedPlaceholder)?
+            + start;
+        let name = &rest[start + 1..end];
+        let placeholder = Placeholder::from_name(name)
+            .ok_or_else(|| RrBranchNameTemplateError::UnknownPlaceholder(name.to_string()))?;
+        segments.push(Segment::Placeholder(placeholder));
+        rest = &rest[end + 1..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Literal(rest.to_string()));
+    }
+    Ok(segments)
+}
+
+/// The patch specific values a request review branch name is rendered from
+pub struct RrBranchNameContext<'a> {
+    pub user: &'a str,
+    pub summary: &'a str,
+    pub message: &'a str,
+    pub ps_id: Option<Uuid>,
+    pub patch_index: usize,
+    pub now: SystemTime,
+}
+
+/// Generates request review branch names from the configured template & recognizes the branch
+/// names it generates.
+///
+/// The template supports the `{user}`, `{slug}`, `{ps_id}`, `{index}`, `{ticket}` and `{date}`
+/// placeholders, e.g. `{user}/{ticket}/{slug}`.
+#[derive(Debug)]
+pub struct RrBranchNaming {
+    segments: Vec<Segment>,
+    max_length: Option<usize>,
+    slug_separator: Option<String>,
+    ticket_pattern: Option<Regex>,
+    matcher: Regex,
+}
+
+impl RrBranchNaming {
+    pub fn new(
+        rr_config: &config::PsRequestReviewConfig,
+    ) -> Result<Self, RrBranchNameTemplateError> {
+        let segments = parse_template(&rr_config.branch_name_template)?;
+        let ticket_pattern = rr_config
+            .ticket_pattern
+            .as_ref()
+            .map(|pattern| Regex::new(pattern))
+            .transpose()
+            .map_err(RrBranchNameTemplateError::InvalidTicketPattern)?;
+
+        let pattern: String = segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(text) => regex::escape(text),
+                Segment::Placeholder(placeholder) => placeholder.pattern().to_string(),
+            })
+            .collect();
+        // generated names may carry a numeric suffix to avoid colliding with existing branches
+        let matcher = Regex::new(&format!("^{}(-[0-9]+)?$", pattern))
+            .expect("escaped template & placeholder patterns form a valid regex");
+
+        Ok(Self {
+            segments,
+            max_length: rr_config.branch_name_max_length,
+            slug_separator: rr_config.branch_name_slug_separator.clone(),
+            ticket_pattern,
+            matcher,
+        })
+    }
+
+    /// Whether the given branch name looks like one generated from the template. The temporary
+    /// branches Git Patch Stack creates under ps/tmp/, e.g. by isolate, never are.
+    pub fn is_rr_branch_name(&self, branch_name: &str) -> bool {
+        !branch_name.starts_with("ps/tmp/") && self.matcher.is_match(branch_name)
+    }
+
+    /// Generate a branch name for the patch, truncated to the max length & suffixed with -2, -3,
+    /// etc. until `is_taken` reports the name as available.
+    pub fn generate(
+        &self,
+        context: &RrBranchNameContext,
+        is_taken: impl Fn(&str) -> bool,
+    ) -> Result<String, GenerateRrBranchNameError> {
+        let slug = match &self.slug_separator {
+            Some(separator) => slugify_with_separator(context.summary, separator),
+            None => ps::slugify(context.summary),
+        };
+
+        let mut values: Vec<String> = vec![];
+        for segment in self.segments.iter() {
+            values.push(match segment {
+                Segment::Literal(text) => text.clone(),
+                Segment::Placeholder(Placeholder::User) => {
+                    slugify_with_separator(context.user, "-")
+                }
+                Segment::Placeholder(Placeholder::Slug) => slug.clone(),
+                Segment::Placeholder(Placeholder::PsId) => context
+                    .ps_id
+                    .ok_or(GenerateRrBranchNameError::PatchIdMissing)?
+                    .to_string()
+                    .chars()
+                    .take(PS_ID_PREFIX_LENGTH)
+                    .collect(),
+                Segment::Placeholder(Placeholder::Index) => context.patch_index.to_string(),
+                Segment::Placeholder(Placeholder::Ticket) => self.ticket(context.message)?,
+                Segment::Placeholder(Placeholder::Date) => utc_date(context.now),
+            });
+        }
+
+        let mut attempt: usize = 1;
+        loop {
+            let suffix = if attempt == 1 {
+                String::new()
+            } else {
+                format!("-{}", attempt)
+            };
+            let name = format!(
+                "{}{}",
+                self.truncate(&values, suffix.chars().count()),
+                suffix
+            );
+            if !is_taken(&name) {
+                return Ok(name);
+            }
+            attempt += 1;
+        }
+    }
+
+    fn ticket(&self, message: &str) -> Result<String, GenerateRrBranchNameError> {
+        let pattern = self
+            .ticket_pattern
+            .as_ref()
+            .ok_or(GenerateRrBranchNameError::TicketPatternMissing)?;
+        let captures = pattern
+            .captures(message)
+            .ok_or(GenerateRrBranchNameError::TicketNotFound)?;
+        // prefer the first capture group so patterns can match surrounding context
+        let ticket = captures.get(1).or_else(|| captures.get(0)).unwrap();
+        Ok(ticket.as_str().to_string())
+    }
+
+    /// Join the rendered segments, shortening the slug first & the whole name only when that
+    /// isn't enough, so the name plus the reserved characters fits within the max length
+    fn truncate(&self, values: &[String], reserved: usize) -> String {
+        let max_length = match self.max_length {
+            Some(max_length) => max_length.saturating_sub(reserved),
+            None => return values.concat(),
+        };
+
+        let length: usize = values.iter().map(|v| v.chars().count()).sum();
+        if length <= max_length {
+            return values.concat();
+        }
+
+        let mut excess = length - max_length;
+        let mut shortened: Vec<String> = values.to_vec();
+        for (segment, value) in self.segments.iter().zip(shortened.iter_mut()) {
+            if segment == &Segment::Placeholder(Placeholder::Slug) && excess > 0 {
+                let slug_length = value.chars().count();
+                let keep = slug_length.saturating_sub(excess);
+                excess -= slug_length - keep;
+                *value = value
+                    .chars()
+                    .take(keep)
+                    .collect::<String>()
+                    .trim_end_matches(TRAILING_SEPARATORS)
+                    .to_string();
+            }
+        }
+
+        let name = shortened.concat();
+        if excess == 0 {
+            return name;
+        }
+        name.chars()
+            .take(max_length)
+            .collect::<String>()
+            .trim_end_matches(TRAILING_SEPARATORS)
+            .to_string()
+    }
+}
+
+/// Slugify the text collapsing each run of non-alphanumeric characters into a single separator
+fn slugify_with_separator(text: &str, separator: &str) -> String {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| word.to_lowercase())
+        .collect::<Vec<String>>()
+        .join(separator)
+}
+
+/// Format the UTC date of the given time as YYYY-MM-DD
+fn utc_date(time: SystemTime) -> String {
+    let days = time
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+        / 86_400;
+
+    // convert days since the epoch to a civil date, see
+    // http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+    let z = days as i64 + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+    use std::time::Duration;
+
+    fn rr_config(template: &str) -> config::PsRequestReviewConfig {
+        config::PsRequestReviewConfig {
+            verify_isolation: true,
+            branch_name_template: template.to_string(),
+            branch_name_max_length: None,
+            branch_name_slug_separator: None,
+            ticket_pattern: None,
+        }
+    }
+
+    fn context<'a>(summary: &'a str, message: &'a str) -> RrBranchNameContext<'a> {
+        RrBranchNameContext {
+            user: "Jane Doe",
+            summary,
+            message,
+            ps_id: Some(Uuid::from_str("2dce2a21-72b9-487a-b641-4a0b157b76e8").unwrap()),
+            patch_index: 3,
+            // 2024-02-29T12:00:00Z
+            now: UNIX_EPOCH + Duration::from_secs(1_709_208_000),
+        }
+    }
+
+    #[test]
+    fn test_default_template_matches_original_naming() {
+        let naming = RrBranchNaming::new(&rr_config("ps/rr/{slug}")).unwrap();
+        let name = naming
+            .generate(&context("Hello & Goodbye", ""), |_| false)
+            .unwrap();
+        assert_eq!(name, "ps/rr/hello___goodbye");
+        assert!(naming.is_rr_branch_name(&name));
+        assert!(!naming.is_rr_branch_name("main"));
+        assert!(!naming.is_rr_branch_name("ps/tmp/isolate"));
+    }
+
+    #[test]
+    fn test_nested_slugs_are_recognized() {
+        let mut config = rr_config("ps/rr/{slug}");
+        config.branch_name_slug_separator = Some("/".to_string());
+        let naming = RrBranchNaming::new(&config).unwrap();
+        let name = naming
+            .generate(&context("Add the thing", ""), |_| false)
+            .unwrap();
+        assert_eq!(name, "ps/rr/add/the/thing");
+        assert!(naming.is_rr_branch_name(&name));
+        assert!(naming.is_rr_branch_name("ps/rr/a/b"));
+        assert!(!naming.is_rr_branch_name("feature/a/b"));
+    }
+
+    #[test]
+    fn test_template_renders_every_placeholder() {
+        let mut config = rr_config("{user}/{ticket}/{date}-{index}-{ps_id}-{slug}");
+        config.branch_name_slug_separator = Some("-".to_string());
+        config.ticket_pattern = Some(r"\[([A-Z]+-[0-9]+)\]".to_string());
+        let naming = RrBranchNaming::new(&config).unwrap();
+
+        let name = naming
+            .generate(
+                &context("Add the thing", "Add the thing\n\nFixes [ABC-123]"),
+                |_| false,
+            )
+            .unwrap();
+        assert_eq!(name, "jane-doe/ABC-123/2024-02-29-3-2dce2a21-add-the-thing");
+        assert!(naming.is_rr_branch_name(&name));
+        assert!(!naming.is_rr_branch_name("feature"));
+    }
+
+    #[test]
+    fn test_generate_fails_when_ticket_is_missing() {
+        let mut config = rr_config("{ticket}/{slug}");
+        config.ticket_pattern = Some(r"[A-Z]+-[0-9]+".to_string());
+        let naming = RrBranchNaming::new(&config).unwrap();
+        assert!(matches!(
+            naming.generate(&context("Add the thing", "Add the thing"), |_| false),
+            Err(GenerateRrBranchNameError::TicketNotFound)
+        ));
+    }
+
+    #[test]
+    fn test_generate_truncates_slug_and_avoids_collisions() {
+        let mut config = rr_config("ps/rr/{slug}");
+        config.branch_name_max_length = Some(16);
+        let naming = RrBranchNaming::new(&config).unwrap();
+
+        let name = naming
+            .generate(&context("Some rather long summary", ""), |_| false)
+            .unwrap();
+        assert_eq!(name, "ps/rr/some_rathe");
+
+        let name = naming
+            .generate(&context("Some rather long summary", ""), |n| {
+                n == "ps/rr/some_rathe" || n == "ps/rr/some_rat-2"
+            })
+            .unwrap();
+        assert_eq!(name, "ps/rr/some_rat-3");
+        assert!(naming.is_rr_branch_name(&name));
+    }
+
+    #[test]
+    fn test_new_rejects_unknown_placeholders() {
+        assert!(matches!(
+            RrBranchNaming::new(&rr_config("ps/rr/{nope}")),
+            Err(RrBranchNameTemplateError::UnknownPlaceholder(_))
+        ));
+        assert!(matches!(
+            RrBranchNaming::new(&rr_config("ps/rr/{slug")),
+            Err(RrBranchNameTemplateError::UnclosedPlaceholder)
+        ));
+    }
+}
@@ -0,0 +1,65 @@
+// This module verifies the signatures of the commits of branches, e.g. of request review branches
+// that collaborators may have pushed commits to, so that commits which aren't signed by someone
+// trusted can be kept from being integrated.
+
+use super::git;
+use std::result::Result;
+
+#[derive(Debug)]
+pub enum BranchCommitSignaturesError {
+    GetCommonAncestor(Box<dyn std::error::Error>),
+    GetCommits(Box<dyn std::error::Error>),
+    VerifySignatures(git::ExtCommitSignaturesError),
+}
+
+impl std::fmt::Display for BranchCommitSignaturesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::GetCommonAncestor(e) => write!(f, "get common ancestor failed, {}", e),
+            Self::GetCommits(e) => write!(f, "get commits failed, {}", e),
+            Self::VerifySignatures(e) => write!(f, "verify signatures failed, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BranchCommitSignaturesError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::GetCommonAncestor(e) => Some(e.as_ref()),
+            Self::GetCommits(e) => Some(e.as_ref()),
+            Self::VerifySignatures(e) => Some(e),
+        }
+    }
+}
+
+/// Verify the signatures of the commits of the branch at `head_oid` that aren't on the branch at
+/// `base_oid`, e.g. the upstream, oldest first
+pub fn branch_commit_signatures(
+    repo: &git2::Repository,
+    base_oid: git2::Oid,
+    head_oid: git2::Oid,
+) -> Result<Vec<git::CommitSignature>, BranchCommitSignaturesError> {
+    let common_ancestor_oid = git::common_ancestor(repo, head_oid, base_oid)
+        .map_err(|e| BranchCommitSignaturesError::GetCommonAncestor(e.into()))?;
+    let oids = git::get_revs(repo, common_ancestor_oid, head_oid, git2::Sort::REVERSE)
+        .map_err(|e| BranchCommitSignaturesError::GetCommits(e.into()))?
+        .collect::<Result<Vec<git2::Oid>, git2::Error>>()
+        .map_err(|e| BranchCommitSignaturesError::GetCommits(e.into()))?;
+    git::ext_commit_signatures(repo, &oids).map_err(BranchCommitSignaturesError::VerifySignatures)
+}
+
+/// Describe the commit signatures that aren't good signatures by someone trusted, e.g. `abc1234
+/// unsigned`, one per commit
+pub fn describe_untrusted(signatures: &[git::CommitSignature]) -> Vec<String> {
+    signatures
+        .iter()
+        .filter(|s| !s.status.is_trusted())
+        .map(|s| {
+            let short_oid: String = s.oid.to_string().chars().take(7).collect();
+            match s.signer {
+                Some(ref signer) => format!("{} {} ({})", short_oid, s.status, signer),
+                None => format!("{} {}", short_oid, s.status),
+            }
+        })
+        .collect()
+}
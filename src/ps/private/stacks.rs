@@ -0,0 +1,117 @@
+// This module keeps track of the named patch stacks of a repository. A patch stack is a local
+// branch along with the remote tracking branch it is based on, its upstream. The stacks created
+// through gps are marked in the repository's git config with `branch.<name>.gpsStack = true`,
+// which git drops along with the rest of the branch's config when the branch is deleted.
+
+use std::result::Result;
+
+/// The git config variable, within the branch's section, marking a branch as a patch stack
+pub const STACK_CONFIG_VARIABLE: &str = "gpsStack";
+
+#[derive(Debug)]
+pub enum StacksError {
+    ReadConfigFailed(git2::Error),
+    WriteConfigFailed(git2::Error),
+}
+
+impl std::fmt::Display for StacksError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ReadConfigFailed(e) => write!(f, "failed to read repository config, {}", e),
+            Self::WriteConfigFailed(e) => write!(f, "failed to write repository config, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StacksError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::ReadConfigFailed(e) => Some(e),
+            Self::WriteConfigFailed(e) => Some(e),
+        }
+    }
+}
+
+fn stack_config_key(name: &str) -> String {
+    format!("branch.{}.{}", name, STACK_CONFIG_VARIABLE)
+}
+
+/// Mark the local branch as a patch stack
+pub fn register_stack(repo: &git2::Repository, name: &str) -> Result<(), StacksError> {
+    let mut config = repo.config().map_err(StacksError::WriteConfigFailed)?;
+    config
+        .set_bool(&stack_config_key(name), true)
+        .map_err(StacksError::WriteConfigFailed)
+}
+
+/// Remove the patch stack mark from the local branch, if it has one
+pub fn unregister_stack(repo: &git2::Repository, name: &str) -> Result<(), StacksError> {
+    let mut config = repo.config().map_err(StacksError::ReadConfigFailed)?;
+    match config.remove(&stack_config_key(name)) {
+        Err(e) if e.code() != git2::ErrorCode::NotFound => Err(StacksError::WriteConfigFailed(e)),
+        _ => Ok(()),
+    }
+}
+
+/// Get the names of the local branches marked as patch stacks that still exist, sorted
+pub fn registered_stacks(repo: &git2::Repository) -> Result<Vec<String>, StacksError> {
+    let mut config = repo.config().map_err(StacksError::ReadConfigFailed)?;
+    let snapshot = config.snapshot().map_err(StacksError::ReadConfigFailed)?;
+    // variable names are case insensitive, they come back lowercased
+    let suffix = format!(".{}", STACK_CONFIG_VARIABLE.to_lowercase());
+    let mut names: Vec<String> = vec![];
+    let mut entries = snapshot
+        .entries(Some(r"^branch\..*\.gpsstack$"))
+        .map_err(StacksError::ReadConfigFailed)?;
+    while let Some(entry) = entries.next() {
+        let entry = entry.map_err(StacksError::ReadConfigFailed)?;
+        let name = entry
+            .name()
+            .and_then(|n| n.strip_prefix("branch."))
+            .and_then(|n| n.strip_suffix(&suffix));
+        if let Some(name) = name {
+            let marked = snapshot.get_bool(&stack_config_key(name)).unwrap_or(false);
+            if marked
+                && repo.find_branch(name, git2::BranchType::Local).is_ok()
+                && !names.iter().any(|n| n == name)
+            {
+                names.push(name.to_string());
+            }
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
+/// Get the upstream of the local branch, e.g. `refs/remotes/origin/main`, if it has one
+pub fn stack_upstream_ref_name(repo: &git2::Repository, name: &str) -> Option<String> {
+    repo.branch_upstream_name(&format!("refs/heads/{}", name))
+        .ok()
+        .and_then(|buf| buf.as_str().map(|s| s.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::git::test_utils;
+    use super::*;
+
+    #[test]
+    fn test_registered_stacks_only_lists_existing_marked_branches() {
+        let (_dir, repo) = test_utils::repo_init();
+        let head_commit = repo.head().unwrap().peel_to_commit().unwrap();
+        repo.branch("topic-a", &head_commit, false).unwrap();
+        repo.branch("topic-b", &head_commit, false).unwrap();
+
+        register_stack(&repo, "topic-b").unwrap();
+        register_stack(&repo, "topic-a").unwrap();
+        register_stack(&repo, "gone").unwrap();
+        assert_eq!(
+            registered_stacks(&repo).unwrap(),
+            vec!["topic-a", "topic-b"]
+        );
+
+        unregister_stack(&repo, "topic-a").unwrap();
+        unregister_stack(&repo, "never-registered").unwrap();
+        assert_eq!(registered_stacks(&repo).unwrap(), vec!["topic-b"]);
+    }
+}
@@ -1,6 +1,15 @@
 use super::super::super::ps;
+use super::branch_scan_cache;
+use super::config;
 use super::git;
-use std::collections::HashMap;
+use super::paths;
+use super::rr_branch_name::RrBranchNaming;
+use std::collections::{HashMap, HashSet};
+use std::convert::TryFrom;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
 use uuid::Uuid;
 
 #[derive(Debug)]
@@ -38,15 +47,25 @@ impl std::error::Error for GetListPatchInfoError {
 /// * `repo` - reference to the repository
 /// * `base_oid` - the sha in git2::oid form of the commit at the base of the patch stack
 /// * `head_ref_name` - name of HEAD of patch stack branch
+/// * `scan_config` - configuration of which branches are scanned & how
+/// * `rr_branch_naming` - naming of request review branches, used to recognize them
 pub fn get_list_patch_info(
     repo: &git2::Repository,
     base_oid: git2::Oid,
     head_ref_name: &str,
+    scan_config: &config::PsBranchScanConfig,
+    rr_branch_naming: &RrBranchNaming,
 ) -> Result<std::collections::HashMap<Uuid, PatchGitInfo>, GetListPatchInfoError> {
     let mut patch_info_collection: HashMap<Uuid, PatchGitInfo> = HashMap::new();
 
-    let list_branch_info = get_list_local_branches_with_info(repo, base_oid, head_ref_name)
-        .map_err(GetListPatchInfoError::GetListLocalBranchesWithInfoFailed)?;
+    let list_branch_info = get_list_local_branches_with_info(
+        repo,
+        base_oid,
+        head_ref_name,
+        scan_config,
+        rr_branch_naming,
+    )
+    .map_err(GetListPatchInfoError::GetListLocalBranchesWithInfoFailed)?;
 
     for bi in list_branch_info {
         for patch_info in bi.patches.iter() {
@@ -67,10 +86,15 @@ pub fn get_list_patch_info(
 }
 
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum GetListLocalBranchesWithInfoError {
     GetBranchesFailed(git2::Error),
     GetBranchPairFailed(git2::Error),
     GetListBranchInfoFailed(GetListBranchInfoError),
+    PathNotUtf8,
+    ScanBranchFailed(GetPatchInfoCollectionError),
+    ScanMissing(git2::Oid),
+    ReadCachedScanFailed(git2::Error),
 }
 
 impl std::fmt::Display for GetListLocalBranchesWithInfoError {
@@ -79,6 +103,10 @@ impl std::fmt::Display for GetListLocalBranchesWithInfoError {
             Self::GetBranchesFailed(e) => write!(f, "get braches failed, {}", e),
             Self::GetBranchPairFailed(e) => write!(f, "get branch pair failed, {}", e),
             Self::GetListBranchInfoFailed(e) => write!(f, "get list branch info failed, {}", e),
+            Self::PathNotUtf8 => write!(f, "path not utf-8"),
+            Self::ScanBranchFailed(e) => write!(f, "scan branch failed, {}", e),
+            Self::ScanMissing(oid) => write!(f, "scan of branch head {} missing", oid),
+            Self::ReadCachedScanFailed(e) => write!(f, "read cached scan failed, {}", e),
         }
     }
 }
@@ -89,38 +117,179 @@ impl std::error::Error for GetListLocalBranchesWithInfoError {
             Self::GetBranchesFailed(e) => Some(e),
             Self::GetBranchPairFailed(e) => Some(e),
             Self::GetListBranchInfoFailed(e) => Some(e),
+            Self::PathNotUtf8 => None,
+            Self::ScanBranchFailed(e) => Some(e),
+            Self::ScanMissing(_) => None,
+            Self::ReadCachedScanFailed(e) => Some(e),
         }
     }
 }
 
+/// Scan the local branches, other than the patch stack branch, for the patches they contain.
+///
+/// Scanning a branch means walking the commits between it and the patch stack base and computing
+/// the diff patch id of each patch. The scans are cached, keyed by the branch head & base oids,
+/// so only branches whose refs moved since the last scan are walked again, and those are walked
+/// in parallel.
 pub fn get_list_local_branches_with_info(
     repo: &git2::Repository,
     base_oid: git2::Oid,
     head_ref_name: &str,
+    scan_config: &config::PsBranchScanConfig,
+    rr_branch_naming: &RrBranchNaming,
 ) -> Result<std::vec::Vec<ListBranchInfo>, GetListLocalBranchesWithInfoError> {
     let local_branches: git2::Branches = repo
         .branches(Some(git2::BranchType::Local))
         .map_err(GetListLocalBranchesWithInfoError::GetBranchesFailed)?;
 
-    let mut branch_info_collection: Vec<ListBranchInfo> = Vec::new();
+    let mut scan_targets: Vec<BranchScanTarget> = Vec::new();
 
     for branch_pair_result in local_branches {
         let branch_pair =
             branch_pair_result.map_err(GetListLocalBranchesWithInfoError::GetBranchPairFailed)?;
         let branch = branch_pair.0;
 
-        if branch.name().unwrap().unwrap() == head_ref_name {
+        let scan_target = get_branch_scan_target(&branch, repo)
+            .map_err(GetListLocalBranchesWithInfoError::GetListBranchInfoFailed)?;
+
+        if scan_target.name == head_ref_name
+            || (scan_config.review_branches_only
+                && !rr_branch_naming.is_rr_branch_name(&scan_target.name))
+        {
             continue;
         }
 
-        let branch_info = get_list_branch_info(&branch, base_oid, repo)
-            .map_err(GetListLocalBranchesWithInfoError::GetListBranchInfoFailed)?;
-        branch_info_collection.push(branch_info);
+        scan_targets.push(scan_target);
+    }
+
+    let repo_gitdir_str = repo
+        .path()
+        .to_str()
+        .ok_or(GetListLocalBranchesWithInfoError::PathNotUtf8)?;
+    let cache_path = paths::cache_path(repo_gitdir_str, branch_scan_cache::BRANCH_SCAN_CACHE_NAME);
+    let mut cache = if scan_config.cache {
+        branch_scan_cache::BranchScanCache::load(&cache_path)
+    } else {
+        branch_scan_cache::BranchScanCache::default()
+    };
+
+    let mut used_keys: HashSet<String> = HashSet::new();
+    let mut heads_to_scan: Vec<git2::Oid> = Vec::new();
+    for head_oid in scan_targets.iter().flat_map(|t| t.head_oids()) {
+        let key = branch_scan_cache::branch_scan_key(&head_oid, &base_oid);
+        // a cached scan that can't be read, e.g. because the cache is corrupt, is a miss
+        let cached_scan = cache
+            .scan(&key)
+            .filter(|scan| PatchInfoCollection::try_from(*scan).is_ok());
+        if cached_scan.is_none() && !used_keys.contains(&key) {
+            heads_to_scan.push(head_oid);
+        }
+        used_keys.insert(key);
+    }
+
+    let scans = scan_branches(
+        repo.path(),
+        &heads_to_scan,
+        base_oid,
+        cache.patch_ids(),
+        scan_config.jobs,
+    );
+    for (head_oid, scan) in heads_to_scan.iter().zip(scans) {
+        let scan = scan.map_err(GetListLocalBranchesWithInfoError::ScanBranchFailed)?;
+        cache.insert_scan(
+            branch_scan_cache::branch_scan_key(head_oid, &base_oid),
+            scan,
+        );
+    }
+
+    let mut branch_info_collection: Vec<ListBranchInfo> = Vec::new();
+    for scan_target in scan_targets {
+        let scan = cached_scan(&cache, &scan_target.head_oid, &base_oid)?;
+        let upstream = match scan_target.upstream {
+            Some(upstream_target) => {
+                let upstream_scan = cached_scan(&cache, &upstream_target.head_oid, &base_oid)?;
+                Some(ListUpstreamBranchInfo {
+                    name: upstream_target.name,
+                    patches: upstream_scan.patch_info_entries,
+                    commit_count: upstream_scan.commit_count,
+                })
+            }
+            None => None,
+        };
+        branch_info_collection.push(ListBranchInfo {
+            name: scan_target.name,
+            patches: scan.patch_info_entries,
+            upstream,
+        });
+    }
+
+    if scan_config.cache {
+        cache.retain_scans(&used_keys);
+        // the cache only saves work, so failing to write it, e.g. because the gitdir is
+        // read-only or the disk is full, doesn't fail the scan
+        let _ = cache.save(&cache_path);
     }
 
     Ok(branch_info_collection)
 }
 
+fn cached_scan(
+    cache: &branch_scan_cache::BranchScanCache,
+    head_oid: &git2::Oid,
+    base_oid: &git2::Oid,
+) -> Result<PatchInfoCollection, GetListLocalBranchesWithInfoError> {
+    cache
+        .scan(&branch_scan_cache::branch_scan_key(head_oid, base_oid))
+        .ok_or(GetListLocalBranchesWithInfoError::ScanMissing(*head_oid))
+        .and_then(|scan| {
+            PatchInfoCollection::try_from(scan)
+                .map_err(GetListLocalBranchesWithInfoError::ReadCachedScanFailed)
+        })
+}
+
+/// Scan the commits between each of the given branch heads and the base, spreading the scans
+/// across the given number of threads. The results are in the same order as the branch heads.
+fn scan_branches(
+    repo_gitdir: &Path,
+    branch_head_oids: &[git2::Oid],
+    base_oid: git2::Oid,
+    known_patch_ids: &HashMap<String, String>,
+    jobs: usize,
+) -> Vec<Result<branch_scan_cache::CachedBranchScan, GetPatchInfoCollectionError>> {
+    let next_index = AtomicUsize::new(0);
+    let results: Mutex<Vec<Option<Result<_, _>>>> =
+        Mutex::new((0..branch_head_oids.len()).map(|_| None).collect());
+
+    thread::scope(|s| {
+        for _ in 0..jobs.min(branch_head_oids.len()) {
+            s.spawn(|| {
+                let repo_result = git2::Repository::open(repo_gitdir);
+                loop {
+                    let index = next_index.fetch_add(1, Ordering::SeqCst);
+                    let branch_head_oid = match branch_head_oids.get(index) {
+                        Some(oid) => oid,
+                        None => break,
+                    };
+                    let result = match &repo_result {
+                        Ok(repo) => scan_branch(repo, *branch_head_oid, base_oid, known_patch_ids),
+                        Err(e) => Err(GetPatchInfoCollectionError::OpenRepository(
+                            git2::Error::new(e.code(), e.class(), e.message()),
+                        )),
+                    };
+                    results.lock().unwrap()[index] = Some(result);
+                }
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|r| r.expect("every branch head is scanned"))
+        .collect()
+}
+
 #[derive(Debug, Clone)]
 pub struct ListBranchInfo {
     pub name: String,
@@ -137,17 +306,38 @@ pub struct PatchInfo {
 #[derive(Debug, Clone)]
 pub struct ListUpstreamBranchInfo {
     pub name: String,
-    pub reference: String,
     pub patches: Vec<PatchInfo>,
     pub commit_count: usize,
 }
 
+/// A local branch to scan along with its upstream branch, if it has one
+struct BranchScanTarget {
+    name: String,
+    head_oid: git2::Oid,
+    upstream: Option<UpstreamBranchScanTarget>,
+}
+
+struct UpstreamBranchScanTarget {
+    name: String,
+    head_oid: git2::Oid,
+}
+
+impl BranchScanTarget {
+    fn head_oids(&self) -> Vec<git2::Oid> {
+        let mut oids = vec![self.head_oid];
+        if let Some(upstream) = &self.upstream {
+            oids.push(upstream.head_oid);
+        }
+        oids
+    }
+}
+
 #[derive(Debug)]
 pub enum GetListBranchInfoError {
     GetNameFailed(git2::Error),
     NameInvalidUtf8,
     ReferenceInvalidUtf8,
-    GetPatchInfoCollectionFailed(GetPatchInfoCollectionError),
+    GetBranchHeadOidFailed,
 }
 
 impl std::fmt::Display for GetListBranchInfoError {
@@ -156,9 +346,7 @@ impl std::fmt::Display for GetListBranchInfoError {
             Self::GetNameFailed(e) => write!(f, "get name failed, {}", e),
             Self::NameInvalidUtf8 => write!(f, "name invalid utf-8"),
             Self::ReferenceInvalidUtf8 => write!(f, "reference invalid utf-8"),
-            Self::GetPatchInfoCollectionFailed(e) => {
-                write!(f, "get patch info collection failed, {}", e)
-            }
+            Self::GetBranchHeadOidFailed => write!(f, "get branch head oid failed"),
         }
     }
 }
@@ -169,16 +357,15 @@ impl std::error::Error for GetListBranchInfoError {
             Self::GetNameFailed(e) => Some(e),
             Self::NameInvalidUtf8 => None,
             Self::ReferenceInvalidUtf8 => None,
-            Self::GetPatchInfoCollectionFailed(e) => Some(e),
+            Self::GetBranchHeadOidFailed => None,
         }
     }
 }
 
-pub fn get_list_branch_info(
+fn get_branch_scan_target(
     branch: &git2::Branch,
-    base_oid: git2::Oid,
     repo: &git2::Repository,
-) -> Result<ListBranchInfo, GetListBranchInfoError> {
+) -> Result<BranchScanTarget, GetListBranchInfoError> {
     let name = branch
         .name()
         .map_err(GetListBranchInfoError::GetNameFailed)?
@@ -189,13 +376,15 @@ pub fn get_list_branch_info(
         .name()
         .ok_or(GetListBranchInfoError::ReferenceInvalidUtf8)?;
 
-    let patch_info_collection = get_patch_info_collection(branch, repo, base_oid)
-        .map_err(GetListBranchInfoError::GetPatchInfoCollectionFailed)?;
+    let head_oid = branch
+        .get()
+        .target()
+        .ok_or(GetListBranchInfoError::GetBranchHeadOidFailed)?;
 
     let upstream_remote_opt = repo.branch_upstream_remote(refname).ok();
     let upstream_branch_opt = branch.upstream().ok();
 
-    let mut upstream_info: Option<ListUpstreamBranchInfo> = None;
+    let mut upstream: Option<UpstreamBranchScanTarget> = None;
 
     if let (Some(upstream_branch), Some(_)) = (upstream_branch_opt, upstream_remote_opt) {
         let upstream_branch_name = upstream_branch
@@ -203,33 +392,27 @@ pub fn get_list_branch_info(
             .map_err(GetListBranchInfoError::GetNameFailed)?
             .ok_or(GetListBranchInfoError::NameInvalidUtf8)?;
 
-        let upstream_branch_refname = upstream_branch
+        let upstream_head_oid = upstream_branch
             .get()
-            .name()
-            .ok_or(GetListBranchInfoError::ReferenceInvalidUtf8)?;
+            .target()
+            .ok_or(GetListBranchInfoError::GetBranchHeadOidFailed)?;
 
-        let upstream_patch_info_collection =
-            get_patch_info_collection(&upstream_branch, repo, base_oid)
-                .map_err(GetListBranchInfoError::GetPatchInfoCollectionFailed)?;
-
-        upstream_info = Some(ListUpstreamBranchInfo {
+        upstream = Some(UpstreamBranchScanTarget {
             name: upstream_branch_name.to_string(),
-            reference: upstream_branch_refname.to_string(),
-            patches: upstream_patch_info_collection.patch_info_entries,
-            commit_count: upstream_patch_info_collection.commit_count,
+            head_oid: upstream_head_oid,
         })
     }
 
-    Ok(ListBranchInfo {
+    Ok(BranchScanTarget {
         name: name.to_string(),
-        patches: patch_info_collection.patch_info_entries,
-        upstream: upstream_info,
+        head_oid,
+        upstream,
     })
 }
 
 #[derive(Debug)]
 pub enum GetPatchInfoCollectionError {
-    GetBranchHeadOid,
+    OpenRepository(git2::Error),
     GetCommonAncestor(git::CommonAncestorError),
     GetCommits(git::GitError),
     GetRevisionOid(git2::Error),
@@ -240,7 +423,7 @@ pub enum GetPatchInfoCollectionError {
 impl std::fmt::Display for GetPatchInfoCollectionError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::GetBranchHeadOid => write!(f, "get branch head oid failed"),
+            Self::OpenRepository(e) => write!(f, "open repository failed, {}", e),
             Self::GetCommonAncestor(e) => write!(f, "get common ancestor failed, {}", e),
             Self::GetCommits(e) => write!(f, "get commits failed, {}", e),
             Self::GetRevisionOid(e) => write!(f, "get revision oid failed, {}", e),
@@ -253,7 +436,7 @@ impl std::fmt::Display for GetPatchInfoCollectionError {
 impl std::error::Error for GetPatchInfoCollectionError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
-            Self::GetBranchHeadOid => None,
+            Self::OpenRepository(e) => Some(e),
             Self::GetCommonAncestor(e) => Some(e),
             Self::GetCommits(e) => Some(e),
             Self::GetRevisionOid(e) => Some(e),
@@ -268,19 +451,40 @@ pub struct PatchInfoCollection {
     pub patch_info_entries: Vec<PatchInfo>,
 }
 
-pub fn get_patch_info_collection(
-    branch: &git2::Branch,
+impl TryFrom<&branch_scan_cache::CachedBranchScan> for PatchInfoCollection {
+    type Error = git2::Error;
+
+    fn try_from(scan: &branch_scan_cache::CachedBranchScan) -> Result<Self, Self::Error> {
+        let patch_info_entries = scan
+            .patches
+            .iter()
+            .map(|patch| {
+                Ok(PatchInfo {
+                    patch_id: patch.patch_id,
+                    commit_diff_id: git2::Oid::from_str(&patch.commit_diff_id)?,
+                })
+            })
+            .collect::<Result<Vec<PatchInfo>, git2::Error>>()?;
+
+        Ok(PatchInfoCollection {
+            commit_count: scan.commit_count,
+            patch_info_entries,
+        })
+    }
+}
+
+/// Scan the commits between the branch head and the base for patches, computing the diff patch
+/// id of each patch unless it is already known.
+fn scan_branch(
     repo: &git2::Repository,
+    branch_head_oid: git2::Oid,
     base_oid: git2::Oid,
-) -> Result<PatchInfoCollection, GetPatchInfoCollectionError> {
+    known_patch_ids: &HashMap<String, String>,
+) -> Result<branch_scan_cache::CachedBranchScan, GetPatchInfoCollectionError> {
     // go through all the commits between this branch head and common ancestor of the currently
     // checked out branch's upstream branch. e.g. between the common ancestor of origin/main
     // and this branch. I think doing the common ancestor between the currently checked out
     // branch and the current branch would work as well.
-    let branch_head_oid = branch
-        .get()
-        .target()
-        .ok_or(GetPatchInfoCollectionError::GetBranchHeadOid)?;
     let common_ancestor_oid = git::common_ancestor(repo, branch_head_oid, base_oid)
         .map_err(GetPatchInfoCollectionError::GetCommonAncestor)?;
 
@@ -292,7 +496,7 @@ pub fn get_patch_info_collection(
     )
     .map_err(GetPatchInfoCollectionError::GetCommits)?;
 
-    let mut patch_info_entries: Vec<PatchInfo> = Vec::new();
+    let mut patches: Vec<branch_scan_cache::CachedPatch> = Vec::new();
     let mut commit_count: usize = 0;
 
     for oid_result in revwalk {
@@ -303,18 +507,26 @@ pub fn get_patch_info_collection(
             .map_err(GetPatchInfoCollectionError::FindCommit)?;
 
         if let Some(ps_id) = ps::commit_ps_id(&commit) {
-            let commit_diff_id = git::commit_diff_patch_id(repo, &commit)
-                .map_err(GetPatchInfoCollectionError::GetCommitDiffPatchId)?;
-
-            patch_info_entries.push(PatchInfo {
+            let commit_oid = oid.to_string();
+            let commit_diff_id = match known_patch_ids.get(&commit_oid) {
+                Some(commit_diff_id) if git2::Oid::from_str(commit_diff_id).is_ok() => {
+                    commit_diff_id.clone()
+                }
+                _ => git::commit_diff_patch_id(repo, &commit)
+                    .map_err(GetPatchInfoCollectionError::GetCommitDiffPatchId)?
+                    .to_string(),
+            };
+
+            patches.push(branch_scan_cache::CachedPatch {
+                commit_oid,
                 patch_id: ps_id,
                 commit_diff_id,
             });
         }
     }
 
-    Ok(PatchInfoCollection {
+    Ok(branch_scan_cache::CachedBranchScan {
         commit_count,
-        patch_info_entries,
+        patches,
     })
 }
@@ -50,6 +50,19 @@ fn handle_error_no_code(_: ExitStatus) -> ExecuteError {
     return ExecuteError::ExitMissingSignal;
 }
 
+/// Map the exit status of an external command into a result, treating anything
+/// other than success as an error.
+pub fn check_exit_status(status: ExitStatus) -> Result<(), ExecuteError> {
+    if status.success() {
+        Ok(())
+    } else {
+        Err(match status.code() {
+            Some(code) => ExecuteError::ExitStatus(code),
+            None => handle_error_no_code(status),
+        })
+    }
+}
+
 /// Execute an external command in the foreground allowing it to take over the
 /// terminal while waiting for the external application to complete with an
 /// exit status.
@@ -58,16 +71,7 @@ pub fn execute(exe: &str, args: &[&str]) -> Result<(), ExecuteError> {
         Err(e) => Err(ExecuteError::SpawnFailure(e)),
         Ok(mut child) => match child.wait() {
             Err(e) => Err(ExecuteError::Failure(e)),
-            Ok(status) => {
-                if status.success() {
-                    Ok(())
-                } else {
-                    Err(match status.code() {
-                        Some(code) => ExecuteError::ExitStatus(code),
-                        None => handle_error_no_code(status),
-                    })
-                }
-            }
+            Ok(status) => check_exit_status(status),
         },
     }
 }
@@ -0,0 +1,366 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+#[cfg(target_family = "unix")]
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Child, Command, Output, Stdio};
+use std::result::Result;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+#[cfg(target_family = "unix")]
+const TERMINATION_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+#[derive(Debug)]
+pub enum ExecuteSupervisedError {
+    CreateLogFailed(io::Error),
+    SpawnFailure(io::Error),
+    Failure(io::Error),
+    TimedOut(Duration),
+    Interrupted,
+}
+
+impl std::fmt::Display for ExecuteSupervisedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::CreateLogFailed(e) => write!(f, "failed to create log file, {}", e),
+            Self::SpawnFailure(e) => write!(f, "Spawn failed with {}", e),
+            Self::Failure(e) => write!(f, "Execute failure {}", e),
+            Self::TimedOut(duration) => {
+                write!(f, "timed out after {:?}", duration)
+            }
+            Self::Interrupted => write!(f, "interrupted"),
+        }
+    }
+}
+
+impl std::error::Error for ExecuteSupervisedError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::CreateLogFailed(e) => Some(e),
+            Self::SpawnFailure(e) => Some(e),
+            Self::Failure(e) => Some(e),
+            Self::TimedOut(_) => None,
+            Self::Interrupted => None,
+        }
+    }
+}
+
+/// Execute an external command under supervision.
+///
+/// The command's stdout & stderr are captured and appended to the file at `log_path`, and when
+/// `echo_output` is true they are also relayed to our own stdout & stderr as they are produced.
+///
+/// Without a `timeout` the command inherits our stdin & stays in the foreground process group, so
+/// it can still prompt, e.g. for a passphrase, or open an editor. With a `timeout` it is started
+/// in its own process group with stdin detached, so that the whole group can be terminated once
+/// the timeout elapses.
+///
+/// If we receive an interrupt (Ctrl-C) while waiting on the command, it is terminated as well.
+///
+/// Note: A non-zero exit status is NOT considered an error here. The exit status is returned as
+/// part of the `Output` so that the caller can decide how to treat it.
+pub fn execute_supervised(
+    exe: &str,
+    args: &[&str],
+    timeout: Option<Duration>,
+    log_path: &Path,
+    echo_output: bool,
+) -> Result<Output, ExecuteSupervisedError> {
+    if let Some(parent) = log_path.parent() {
+        fs::create_dir_all(parent).map_err(ExecuteSupervisedError::CreateLogFailed)?;
+    }
+    let mut log_file = File::create(log_path).map_err(ExecuteSupervisedError::CreateLogFailed)?;
+    writeln!(log_file, "$ {} {}", exe, args.join(" "))
+        .map_err(ExecuteSupervisedError::CreateLogFailed)?;
+    let log = Arc::new(Mutex::new(log_file));
+
+    let own_process_group = timeout.is_some();
+    let mut command = Command::new(exe);
+    command
+        .args(args)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if own_process_group {
+        command.stdin(Stdio::null());
+        #[cfg(target_family = "unix")]
+        command.process_group(0);
+    } else {
+        command.stdin(Stdio::inherit());
+    }
+
+    let _interrupt_guard = InterruptGuard::install();
+
+    let mut child = command
+        .spawn()
+        .map_err(ExecuteSupervisedError::SpawnFailure)?;
+
+    let stdout_relay = child.stdout.take().map(|stdout| {
+        relay(
+            stdout,
+            Arc::clone(&log),
+            echo_output.then(|| Box::new(io::stdout()) as Box<_>),
+        )
+    });
+    let stderr_relay = child.stderr.take().map(|stderr| {
+        relay(
+            stderr,
+            Arc::clone(&log),
+            echo_output.then(|| Box::new(io::stderr()) as Box<_>),
+        )
+    });
+
+    let started_at = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait().map_err(ExecuteSupervisedError::Failure)? {
+            break status;
+        }
+
+        if interrupted() {
+            terminate(&mut child, own_process_group);
+            append_to_log(&log, "\n[gps] interrupted, terminated process\n");
+            return Err(ExecuteSupervisedError::Interrupted);
+        }
+
+        if let Some(duration) = timeout {
+            if started_at.elapsed() >= duration {
+                terminate(&mut child, own_process_group);
+                append_to_log(
+                    &log,
+                    &format!(
+                        "\n[gps] timed out after {:?}, terminated process group\n",
+                        duration
+                    ),
+                );
+                return Err(ExecuteSupervisedError::TimedOut(duration));
+            }
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let stdout = stdout_relay
+        .map(|handle| handle.join().unwrap_or_default())
+        .unwrap_or_default();
+    let stderr = stderr_relay
+        .map(|handle| handle.join().unwrap_or_default())
+        .unwrap_or_default();
+    append_to_log(&log, &format!("\n[gps] exited with {}\n", status));
+
+    Ok(Output {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+/// Spawn a thread that reads everything from the given `source`, appending it to the log and
+/// optionally echoing it to the given `echo` writer. The thread results in all the bytes read.
+fn relay<R: Read + Send + 'static>(
+    mut source: R,
+    log: Arc<Mutex<File>>,
+    mut echo: Option<Box<dyn Write + Send>>,
+) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut captured: Vec<u8> = vec![];
+        let mut buf = [0u8; 4096];
+        loop {
+            match source.read(&mut buf) {
+                Ok(0) => break,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(_) => break,
+                Ok(n) => {
+                    captured.extend_from_slice(&buf[..n]);
+                    if let Ok(mut log_file) = log.lock() {
+                        let _ = log_file.write_all(&buf[..n]);
+                    }
+                    if let Some(writer) = echo.as_mut() {
+                        let _ = writer.write_all(&buf[..n]);
+                        let _ = writer.flush();
+                    }
+                }
+            }
+        }
+        captured
+    })
+}
+
+fn append_to_log(log: &Arc<Mutex<File>>, message: &str) {
+    if let Ok(mut log_file) = log.lock() {
+        let _ = log_file.write_all(message.as_bytes());
+    }
+}
+
+/// Terminate the child, along with its process group when it leads its own, politely at first and
+/// then forcefully if it hasn't exited within the grace period.
+#[cfg(target_family = "unix")]
+fn terminate(child: &mut Child, own_process_group: bool) {
+    if !own_process_group {
+        // the child shares our process group, which the interrupt was delivered to as well
+        let _ = child.kill();
+        let _ = child.wait();
+        return;
+    }
+
+    let pgid = child.id() as libc::pid_t;
+    unsafe {
+        libc::kill(-pgid, libc::SIGTERM);
+    }
+
+    let terminated_at = Instant::now();
+    while terminated_at.elapsed() < TERMINATION_GRACE_PERIOD {
+        if let Ok(Some(_)) = child.try_wait() {
+            // the group leader has exited, make sure nothing it spawned is left behind
+            unsafe {
+                libc::kill(-pgid, libc::SIGKILL);
+            }
+            return;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    unsafe {
+        libc::kill(-pgid, libc::SIGKILL);
+    }
+    let _ = child.wait();
+}
+
+#[cfg(target_family = "windows")]
+fn terminate(child: &mut Child, _own_process_group: bool) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+static INTERRUPT_GUARDS: Mutex<usize> = Mutex::new(0);
+
+/// The SIGINT disposition in place before the first guard was installed, restored by the last
+static PREVIOUS_INTERRUPT_HANDLER: AtomicUsize = AtomicUsize::new(0);
+
+fn interrupted() -> bool {
+    INTERRUPTED.load(Ordering::SeqCst)
+}
+
+/// While at least one of these guards is alive an interrupt (Ctrl-C) is recorded rather than
+/// terminating gps, so that the supervised command's process group can be cleaned up first.
+struct InterruptGuard;
+
+impl InterruptGuard {
+    fn install() -> Self {
+        let mut count = INTERRUPT_GUARDS.lock().unwrap_or_else(|e| e.into_inner());
+        if *count == 0 {
+            INTERRUPTED.store(false, Ordering::SeqCst);
+            set_interrupt_handler(true);
+        }
+        *count += 1;
+        InterruptGuard
+    }
+}
+
+impl Drop for InterruptGuard {
+    fn drop(&mut self) {
+        let mut count = INTERRUPT_GUARDS.lock().unwrap_or_else(|e| e.into_inner());
+        *count -= 1;
+        if *count == 0 {
+            set_interrupt_handler(false);
+        }
+    }
+}
+
+#[cfg(target_family = "unix")]
+extern "C" fn record_interrupt(_signal: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+#[cfg(target_family = "unix")]
+fn set_interrupt_handler(record: bool) {
+    if record {
+        let previous = unsafe {
+            libc::signal(
+                libc::SIGINT,
+                record_interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t,
+            )
+        };
+        let previous = if previous == libc::SIG_ERR {
+            libc::SIG_DFL
+        } else {
+            previous
+        };
+        PREVIOUS_INTERRUPT_HANDLER.store(previous as usize, Ordering::SeqCst);
+    } else {
+        let previous = PREVIOUS_INTERRUPT_HANDLER.load(Ordering::SeqCst) as libc::sighandler_t;
+        unsafe {
+            libc::signal(libc::SIGINT, previous);
+        }
+    }
+}
+
+#[cfg(target_family = "windows")]
+fn set_interrupt_handler(_record: bool) {}
+
+#[cfg(all(test, target_family = "unix"))]
+mod tests {
+    use std::time::{Duration, Instant};
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_execute_supervised_captures_output_to_log() {
+        let dir = TempDir::new().unwrap();
+        let log_path = dir.path().join("logs").join("hook.log");
+        let output = super::execute_supervised(
+            "sh",
+            &["-c", "echo out; echo err 1>&2; exit 3"],
+            None,
+            &log_path,
+            false,
+        )
+        .unwrap();
+
+        assert_eq!(output.status.code(), Some(3));
+        assert_eq!(String::from_utf8(output.stdout).unwrap(), "out\n");
+        assert_eq!(String::from_utf8(output.stderr).unwrap(), "err\n");
+        let log = std::fs::read_to_string(&log_path).unwrap();
+        assert!(log.contains("out\n"));
+        assert!(log.contains("err\n"));
+    }
+
+    #[test]
+    fn test_execute_supervised_stays_in_foreground_process_group_without_timeout() {
+        let dir = TempDir::new().unwrap();
+        let log_path = dir.path().join("hook.log");
+        let output =
+            super::execute_supervised("sh", &["-c", "ps -o pgid= -p $$"], None, &log_path, false)
+                .unwrap();
+
+        let pgid: libc::pid_t = String::from_utf8(output.stdout)
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        assert_eq!(pgid, unsafe { libc::getpgrp() });
+    }
+
+    #[test]
+    fn test_execute_supervised_terminates_on_timeout() {
+        let dir = TempDir::new().unwrap();
+        let log_path = dir.path().join("hook.log");
+        let started_at = Instant::now();
+        let res = super::execute_supervised(
+            "sh",
+            &["-c", "sleep 30 & sleep 30"],
+            Some(Duration::from_millis(200)),
+            &log_path,
+            false,
+        );
+
+        assert!(matches!(
+            res,
+            Err(super::ExecuteSupervisedError::TimedOut(_))
+        ));
+        assert!(started_at.elapsed() < Duration::from_secs(10));
+    }
+}
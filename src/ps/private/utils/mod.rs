@@ -1,12 +1,14 @@
 mod execute;
+mod execute_supervised;
 mod mergable;
 mod print_warn;
 mod string_manipulation;
 
 pub use execute::{
-    execute, execute_with_input_and_output, execute_with_output, ExecuteError,
+    check_exit_status, execute, execute_with_input_and_output, execute_with_output, ExecuteError,
     ExecuteWithOutputError,
 };
+pub use execute_supervised::{execute_supervised, ExecuteSupervisedError};
 pub use mergable::merge_option;
 pub use mergable::Mergable;
 pub use print_warn::print_warn;
@@ -1,6 +1,11 @@
 use super::super::super::ps;
 use super::super::private;
+use super::super::private::config;
+use super::super::private::conflicts;
 use super::super::private::git;
+use super::super::private::hooks;
+use super::super::private::paths;
+use std::path::PathBuf;
 use std::result::Result;
 
 #[derive(Debug)]
@@ -14,6 +19,11 @@ pub enum AppendError {
     FailedToMapIndexesForCherryPick(Box<dyn std::error::Error>),
     AddPatchIdsFailed(Box<dyn std::error::Error>),
     CherryPickFailed(Box<dyn std::error::Error>),
+    GetConfigFailed(Box<dyn std::error::Error>),
+    StoppedForConflictResolution(String, PathBuf),
+    StopForConflictResolutionFailed(Box<dyn std::error::Error>),
+    PreAppendHookFailed(Box<dyn std::error::Error>),
+    PostAppendHookFailed(Box<dyn std::error::Error>),
     Unhandled(Box<dyn std::error::Error>),
 }
 
@@ -33,6 +43,18 @@ impl std::fmt::Display for AppendError {
             }
             Self::AddPatchIdsFailed(e) => write!(f, "failed to add patch ids, {}", e),
             Self::CherryPickFailed(e) => write!(f, "failed to cherry pick, {}", e),
+            Self::GetConfigFailed(e) => write!(f, "failed to get config, {}", e),
+            Self::StoppedForConflictResolution(oid, path) => write!(
+                f,
+                "stopped at conflicts playing {}, resolve them in the worktree at {}, stage them with git add & run gps continue, or gps continue --abort",
+                oid,
+                path.display()
+            ),
+            Self::StopForConflictResolutionFailed(e) => {
+                write!(f, "failed to stop for conflict resolution, {}", e)
+            }
+            Self::PreAppendHookFailed(e) => write!(f, "pre_append hook failed, {}", e),
+            Self::PostAppendHookFailed(e) => write!(f, "post_append hook failed, {}", e),
             Self::Unhandled(e) => write!(f, "{}", e),
         }
     }
@@ -50,6 +72,11 @@ impl std::error::Error for AppendError {
             Self::FailedToMapIndexesForCherryPick(e) => Some(e.as_ref()),
             Self::AddPatchIdsFailed(e) => Some(e.as_ref()),
             Self::CherryPickFailed(e) => Some(e.as_ref()),
+            Self::GetConfigFailed(e) => Some(e.as_ref()),
+            Self::StoppedForConflictResolution(_, _) => None,
+            Self::StopForConflictResolutionFailed(e) => Some(e.as_ref()),
+            Self::PreAppendHookFailed(e) => Some(e.as_ref()),
+            Self::PostAppendHookFailed(e) => Some(e.as_ref()),
             Self::Unhandled(e) => Some(e.as_ref()),
         }
     }
@@ -70,7 +97,19 @@ pub fn append(
         .map_err(|e| AppendError::FindBranchFailed(e.into()))?;
     let branch_ref_name = branch.get().name().ok_or(AppendError::BranchNameNotUtf8)?;
 
-    ps::add_patch_ids(&repo, &config).map_err(|e| AppendError::AddPatchIdsFailed(e.into()))?;
+    let repo_root_str = paths::repo_root_path(&repo)
+        .map_err(|e| AppendError::GetConfigFailed(e.into()))?
+        .to_str()
+        .ok_or_else(|| AppendError::GetConfigFailed("repository path not utf-8".into()))?;
+    let repo_gitdir_str = repo
+        .path()
+        .to_str()
+        .ok_or_else(|| AppendError::GetConfigFailed("repository gitdir not utf-8".into()))?;
+    let ps_config = config::get_config(&repo, repo_root_str, repo_gitdir_str)
+        .map_err(|e| AppendError::GetConfigFailed(e.into()))?;
+
+    ps::add_patch_ids(&repo, &config, &ps_config.rewrite)
+        .map_err(|e| AppendError::AddPatchIdsFailed(e.into()))?;
 
     let patch_stack =
         ps::get_patch_stack(&repo).map_err(|e| AppendError::GetPatchStackFailed(e.into()))?;
@@ -84,17 +123,78 @@ pub fn append(
     )
     .map_err(|e| AppendError::FailedToMapIndexesForCherryPick(e.into()))?;
 
-    private::cherry_picking::cherry_pick(
+    let end_patch_index = end_patch_index_optional.unwrap_or(start_patch_index);
+    let mut hook_args: Vec<String> = vec![branch_name.clone()];
+    hook_args.extend(
+        patches_vec
+            .iter()
+            .filter(|p| p.index >= start_patch_index && p.index <= end_patch_index)
+            .map(|p| p.oid.to_string()),
+    );
+    let hook_arg_strs: Vec<&str> = hook_args.iter().map(|arg| arg.as_str()).collect();
+    hooks::find_and_execute_hook(
+        repo_root_str,
+        repo_gitdir_str,
+        &ps_config.hooks,
+        &hooks::PRE_APPEND_HOOK,
+        &hook_arg_strs,
+    )
+    .map_err(|e| AppendError::PreAppendHookFailed(e.into()))?;
+
+    let original_branch_oid = branch.get().target();
+    let branch_head_oid = match private::cherry_picking::cherry_pick(
         &repo,
         &config,
+        &ps_config.rewrite,
         cherry_pick_range.root_oid,
         cherry_pick_range.leaf_oid,
         branch_ref_name,
         1,
         false,
         true,
+    ) {
+        Err(private::cherry_picking::CherryPickError::ConflictsExist(src_oid, dst_oid))
+            if ps_config.conflicts.resolve_in_worktree =>
+        {
+            let target = conflicts::CherryPickTarget {
+                dest_ref_name: branch_ref_name,
+                leaf_oid: cherry_pick_range
+                    .leaf_oid
+                    .unwrap_or(cherry_pick_range.root_oid),
+                committer_time_offset: 1,
+                add_missing_patch_ids: false,
+                original_oid: original_branch_oid,
+            };
+            let worktree_path = conflicts::stop_for_resolution(
+                &repo,
+                conflicts::InterruptedOperation::Append,
+                &target,
+                &src_oid,
+                &dst_oid,
+            )
+            .map_err(|e| AppendError::StopForConflictResolutionFailed(e.into()))?;
+            return Err(AppendError::StoppedForConflictResolution(
+                src_oid,
+                worktree_path,
+            ));
+        }
+        result => result.map_err(|e| AppendError::CherryPickFailed(e.into()))?,
+    };
+
+    hooks::find_and_execute_hook(
+        repo_root_str,
+        repo_gitdir_str,
+        &ps_config.hooks,
+        &hooks::POST_APPEND_HOOK,
+        &[
+            &branch_name,
+            &branch_head_oid
+                .or(original_branch_oid)
+                .map(|oid| oid.to_string())
+                .unwrap_or_default(),
+        ],
     )
-    .map_err(|e| AppendError::CherryPickFailed(e.into()))?;
+    .map_err(|e| AppendError::PostAppendHookFailed(e.into()))?;
 
     Ok(())
 }
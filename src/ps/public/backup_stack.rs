@@ -11,6 +11,32 @@ pub enum BackupStackError {
     PushFailed(git::ExtForcePushError),
 }
 
+impl std::fmt::Display for BackupStackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OpenRepositoryFailed(e) => write!(f, "open repository failed, {}", e),
+            Self::CurrentBranchNameMissing => write!(f, "current branch name missing"),
+            Self::GetUpstreamBranchNameFailed => write!(f, "get upstream branch name failed"),
+            Self::GetRemoteNameFailed => write!(f, "get remote name failed"),
+            Self::ConvertStringToStrFailed => write!(f, "remote name not utf-8"),
+            Self::PushFailed(e) => write!(f, "push failed, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for BackupStackError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::OpenRepositoryFailed(e) => Some(e),
+            Self::CurrentBranchNameMissing => None,
+            Self::GetUpstreamBranchNameFailed => None,
+            Self::GetRemoteNameFailed => None,
+            Self::ConvertStringToStrFailed => None,
+            Self::PushFailed(e) => Some(e),
+        }
+    }
+}
+
 pub fn backup_stack(branch_name: String) -> Result<(), BackupStackError> {
     let repo = git::create_cwd_repo().map_err(BackupStackError::OpenRepositoryFailed)?;
 
@@ -26,7 +52,7 @@ pub fn backup_stack(branch_name: String) -> Result<(), BackupStackError> {
         .ok_or(BackupStackError::ConvertStringToStrFailed)?;
 
     // e.g. git push <remote> <stack-branch>:<branch-name>
-    git::ext_push(true, remote_name_str, &cur_branch_name, &branch_name)
+    git::ext_push(&repo, true, remote_name_str, &cur_branch_name, &branch_name)
         .map_err(BackupStackError::PushFailed)?;
 
     Ok(())
@@ -1,12 +1,23 @@
+use super::super::super::ps;
 use super::super::private;
+use super::super::private::config;
 use super::super::private::git;
+use super::super::private::hooks;
+use super::super::private::paths;
+use std::path::PathBuf;
 use std::result::Result;
 
 #[derive(Debug)]
 pub enum BranchError {
     OpenRepositoryFailed(Box<dyn std::error::Error>),
+    GetConfigFailed(Box<dyn std::error::Error>),
+    GetPatchListFailed(Box<dyn std::error::Error>),
+    PreBranchHookFailed(Box<dyn std::error::Error>),
+    PostBranchHookFailed(Box<dyn std::error::Error>),
+    BranchNameNotUtf8,
     ConflictsExist(String, String),
     MergeCommitDetected(String),
+    StoppedForConflictResolution(String, PathBuf),
     Unhandled(Box<dyn std::error::Error>),
 }
 
@@ -19,6 +30,9 @@ impl From<private::branch::BranchError> for BranchError {
             private::branch::BranchError::MergeCommitDetected(oid) => {
                 Self::MergeCommitDetected(oid)
             }
+            private::branch::BranchError::StoppedForConflictResolution(oid, path) => {
+                Self::StoppedForConflictResolution(oid, path)
+            }
             _ => Self::Unhandled(value.into()),
         }
     }
@@ -28,12 +42,23 @@ impl std::fmt::Display for BranchError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             Self::OpenRepositoryFailed(e) => write!(f, "failed to open repository {}", e),
+            Self::GetConfigFailed(e) => write!(f, "failed to get config, {}", e),
+            Self::GetPatchListFailed(e) => write!(f, "failed to get patch list, {}", e),
+            Self::PreBranchHookFailed(e) => write!(f, "pre_branch hook failed, {}", e),
+            Self::PostBranchHookFailed(e) => write!(f, "post_branch hook failed, {}", e),
+            Self::BranchNameNotUtf8 => write!(f, "branch name isn't utf8"),
             Self::ConflictsExist(src_oid, dst_oid) => write!(
                 f,
                 "conflict(s) found when playing {} on top of {}",
                 src_oid, dst_oid
             ),
             Self::MergeCommitDetected(oid) => write!(f, "merge commit detected with sha {}", oid),
+            Self::StoppedForConflictResolution(oid, path) => write!(
+                f,
+                "stopped at conflicts playing {}, resolve them in the worktree at {}, stage them with git add & run gps continue, or gps continue --abort",
+                oid,
+                path.display()
+            ),
             Self::Unhandled(e) => write!(f, "{}", e),
         }
     }
@@ -43,8 +68,14 @@ impl std::error::Error for BranchError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             Self::OpenRepositoryFailed(e) => Some(e.as_ref()),
+            Self::GetConfigFailed(e) => Some(e.as_ref()),
+            Self::GetPatchListFailed(e) => Some(e.as_ref()),
+            Self::PreBranchHookFailed(e) => Some(e.as_ref()),
+            Self::PostBranchHookFailed(e) => Some(e.as_ref()),
+            Self::BranchNameNotUtf8 => None,
             Self::ConflictsExist(_, _) => None,
             Self::MergeCommitDetected(_) => None,
+            Self::StoppedForConflictResolution(_, _) => None,
             Self::Unhandled(e) => Some(e.as_ref()),
         }
     }
@@ -56,6 +87,61 @@ pub fn branch(
     branch_name: Option<String>,
 ) -> Result<(), BranchError> {
     let repo = git::create_cwd_repo().map_err(|e| BranchError::OpenRepositoryFailed(e.into()))?;
-    private::branch::branch(&repo, start_patch_index, end_patch_index, branch_name)?;
+
+    let repo_root_str = paths::repo_root_path(&repo)
+        .map_err(|e| BranchError::GetConfigFailed(e.into()))?
+        .to_str()
+        .ok_or_else(|| BranchError::GetConfigFailed("repository path not utf-8".into()))?;
+    let repo_gitdir_str = repo
+        .path()
+        .to_str()
+        .ok_or_else(|| BranchError::GetConfigFailed("repository gitdir not utf-8".into()))?;
+    let ps_config = config::get_config(&repo, repo_root_str, repo_gitdir_str)
+        .map_err(|e| BranchError::GetConfigFailed(e.into()))?;
+
+    let patch_stack =
+        ps::get_patch_stack(&repo).map_err(|e| BranchError::GetPatchListFailed(e.into()))?;
+    let patches_vec = ps::get_patch_list(&repo, &patch_stack)
+        .map_err(|e| BranchError::GetPatchListFailed(e.into()))?;
+    ps::patch_range_within_stack_bounds(start_patch_index, end_patch_index, &patches_vec)
+        .map_err(|e| private::branch::BranchError::PatchIndexRangeOutOfBounds(e.into()))?;
+
+    let last_patch_index = end_patch_index.unwrap_or(start_patch_index);
+    let patch_shas: Vec<String> = patches_vec
+        .iter()
+        .filter(|p| p.index >= start_patch_index && p.index <= last_patch_index)
+        .map(|p| p.oid.to_string())
+        .collect();
+    let patch_sha_strs: Vec<&str> = patch_shas.iter().map(|sha| sha.as_str()).collect();
+    hooks::find_and_execute_hook(
+        repo_root_str,
+        repo_gitdir_str,
+        &ps_config.hooks,
+        &hooks::PRE_BRANCH_HOOK,
+        &patch_sha_strs,
+    )
+    .map_err(|e| BranchError::PreBranchHookFailed(e.into()))?;
+
+    let (patch_branch, new_commit_oid) = private::branch::branch(
+        &repo,
+        start_patch_index,
+        end_patch_index,
+        branch_name,
+        Some(private::conflicts::InterruptedOperation::Branch),
+    )?;
+
+    let patch_branch_name = patch_branch
+        .get()
+        .shorthand()
+        .ok_or(BranchError::BranchNameNotUtf8)?;
+    hooks::find_and_execute_hook(
+        repo_root_str,
+        repo_gitdir_str,
+        &ps_config.hooks,
+        &hooks::POST_BRANCH_HOOK,
+        &[patch_branch_name, &new_commit_oid.to_string()],
+    )
+    .map_err(|e| BranchError::PostBranchHookFailed(e.into()))?;
+
     Ok(())
 }
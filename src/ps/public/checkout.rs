@@ -7,6 +7,24 @@ pub enum CheckoutError {
     CheckoutOperationFailed(private::checkout::CheckoutError),
 }
 
+impl std::fmt::Display for CheckoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OpenRepositoryFailed(e) => write!(f, "open repository failed, {}", e),
+            Self::CheckoutOperationFailed(e) => write!(f, "checkout patch failed, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for CheckoutError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::OpenRepositoryFailed(e) => Some(e),
+            Self::CheckoutOperationFailed(e) => Some(e),
+        }
+    }
+}
+
 pub fn checkout(patch_index: usize) -> Result<(), CheckoutError> {
     let repo = git::create_cwd_repo().map_err(CheckoutError::OpenRepositoryFailed)?;
     private::checkout::checkout(&repo, patch_index)
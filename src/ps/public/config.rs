@@ -0,0 +1,318 @@
+use super::super::private::config;
+use super::super::private::git;
+use super::super::private::paths;
+use super::super::private::rr_branch_name;
+use std::path::PathBuf;
+use std::result::Result;
+
+pub use super::super::private::config::{
+    ConfigLevel, ConfigOverride, ConfigOverrideSource, PsConfig,
+};
+
+#[derive(Debug)]
+pub enum ConfigError {
+    OpenRepositoryFailed(Box<dyn std::error::Error>),
+    GetRepoRootPathFailed(Box<dyn std::error::Error>),
+    PathNotUtf8,
+    GetConfigLevelPathFailed(paths::UserLevelConfigPathError),
+    ReadConfigFailed(PathBuf, Box<dyn std::error::Error>),
+    ReadConfigContentFailed(PathBuf, std::io::Error),
+    SetConfigValueFailed(PathBuf, Box<dyn std::error::Error>),
+    ConfigOverrideInvalid(Box<dyn std::error::Error>),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OpenRepositoryFailed(e) => write!(f, "failed to open repository, {}", e),
+            Self::GetRepoRootPathFailed(e) => {
+                write!(f, "failed to get repositories root path, {}", e)
+            }
+            Self::PathNotUtf8 => write!(f, "path not utf-8"),
+            Self::GetConfigLevelPathFailed(e) => {
+                write!(f, "failed to get config file path, {}", e)
+            }
+            Self::ReadConfigFailed(path, e) => write!(f, "{}: {}", path.display(), e),
+            Self::ReadConfigContentFailed(path, e) => {
+                write!(f, "failed to read {}, {}", path.display(), e)
+            }
+            Self::SetConfigValueFailed(path, e) => {
+                write!(f, "failed to set value in {}, {}", path.display(), e)
+            }
+            Self::ConfigOverrideInvalid(e) => write!(f, "invalid config override, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::OpenRepositoryFailed(e) => Some(e.as_ref()),
+            Self::GetRepoRootPathFailed(e) => Some(e.as_ref()),
+            Self::PathNotUtf8 => None,
+            Self::GetConfigLevelPathFailed(e) => Some(e),
+            Self::ReadConfigFailed(_, e) => Some(e.as_ref()),
+            Self::ReadConfigContentFailed(_, e) => Some(e),
+            Self::SetConfigValueFailed(_, e) => Some(e.as_ref()),
+            Self::ConfigOverrideInvalid(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+/// Where a configured value comes from
+#[derive(Debug, Clone)]
+pub enum ConfigOrigin {
+    File(ConfigLevel, PathBuf),
+    Override(ConfigOverrideSource),
+}
+
+impl std::fmt::Display for ConfigOrigin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::File(level, path) => write!(f, "{}:{}", level, path.display()),
+            Self::Override(source) => write!(f, "{}", source),
+        }
+    }
+}
+
+/// A configured value along with where it comes from
+#[derive(Debug)]
+pub struct ConfigEntry {
+    pub key: String,
+    pub value: toml::Value,
+    pub origin: ConfigOrigin,
+}
+
+/// The outcome of validating the config file of a level
+#[derive(Debug)]
+pub struct LevelValidation {
+    pub level: ConfigLevel,
+    pub path: PathBuf,
+    pub exists: bool,
+    /// Unknown keys & invalid values found in the file
+    pub problems: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct ConfigValidation {
+    pub levels: Vec<LevelValidation>,
+    /// Problems with the merged config, e.g. an invalid override or branch name template
+    pub problems: Vec<String>,
+    /// The effective config, available when the config is valid
+    pub config: Option<PsConfig>,
+}
+
+impl ConfigValidation {
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty() && self.levels.iter().all(|l| l.problems.is_empty())
+    }
+}
+
+struct RepoPaths {
+    repo: git2::Repository,
+    root: String,
+    gitdir: String,
+}
+
+fn repo_paths() -> Result<RepoPaths, ConfigError> {
+    let repo = git::create_cwd_repo().map_err(|e| ConfigError::OpenRepositoryFailed(e.into()))?;
+    let repo_root_path =
+        paths::repo_root_path(&repo).map_err(|e| ConfigError::GetRepoRootPathFailed(e.into()))?;
+    let root = repo_root_path
+        .to_str()
+        .ok_or(ConfigError::PathNotUtf8)?
+        .to_string();
+    let gitdir = repo
+        .path()
+        .to_str()
+        .ok_or(ConfigError::PathNotUtf8)?
+        .to_string();
+    Ok(RepoPaths { repo, root, gitdir })
+}
+
+fn level_path(level: ConfigLevel, repo_paths: &RepoPaths) -> Result<PathBuf, ConfigError> {
+    config::config_level_path(level, &repo_paths.root, &repo_paths.gitdir)
+        .map_err(ConfigError::GetConfigLevelPathFailed)
+}
+
+/// Read the config table of each level that has a config file, in order of precedence
+fn level_tables(
+    repo_paths: &RepoPaths,
+) -> Result<Vec<(ConfigLevel, PathBuf, toml::Table)>, ConfigError> {
+    let mut tables = vec![];
+    for level in config::CONFIG_LEVELS {
+        let path = level_path(level, repo_paths)?;
+        if let Some(table) = config::read_config_table(&path)
+            .map_err(|e| ConfigError::ReadConfigFailed(path.clone(), e.into()))?
+        {
+            tables.push((level, path, table));
+        }
+    }
+    Ok(tables)
+}
+
+/// Set the `section.key=value` overrides given on the command line, which take precedence over
+/// the environment variable overrides & all the config files
+pub fn set_overrides(raw: &[String]) -> Result<(), ConfigError> {
+    config::set_command_line_overrides(raw)
+        .map_err(|e| ConfigError::ConfigOverrideInvalid(e.into()))
+}
+
+/// The `GPS_*` environment variable overrides that are ignored because their keys aren't known
+/// config keys, e.g. because of a typo
+pub fn ignored_environment_overrides() -> Vec<ConfigOverride> {
+    config::ignored_environment_overrides()
+}
+
+/// List the configured values that are in effect, each along with where it comes from, sorted
+/// by key. Values that aren't configured at any level or overridden, and therefore use the
+/// defaults, aren't included.
+pub fn list() -> Result<Vec<ConfigEntry>, ConfigError> {
+    let repo_paths = repo_paths()?;
+    let mut entries: Vec<ConfigEntry> = vec![];
+    for (level, path, table) in level_tables(&repo_paths)? {
+        for (key, value) in config::config_entries(&table) {
+            entries.retain(|e| e.key != key);
+            entries.push(ConfigEntry {
+                key,
+                value,
+                origin: ConfigOrigin::File(level, path.clone()),
+            });
+        }
+    }
+
+    let overrides = config::config_overrides();
+    let overrides_table = config::overrides_config_table(&overrides)
+        .map_err(|e| ConfigError::ConfigOverrideInvalid(e.into()))?;
+    for (key, value) in config::config_entries(&overrides_table) {
+        // the override setting the key, or a table it is within, last is what is in effect
+        let source = overrides
+            .iter()
+            .rev()
+            .find(|o| {
+                o.key == key
+                    || o.key.starts_with(&format!("{}.", key))
+                    || key.starts_with(&format!("{}.", o.key))
+            })
+            .map(|o| o.source.clone())
+            .unwrap_or(ConfigOverrideSource::CommandLine);
+        entries.retain(|e| e.key != key);
+        entries.push(ConfigEntry {
+            key,
+            value,
+            origin: ConfigOrigin::Override(source),
+        });
+    }
+
+    entries.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(entries)
+}
+
+/// Get the value of the dotted key, e.g. `list.reverse_order`, either as configured at the
+/// given level or as in effect across all the levels & overrides
+pub fn get(key: &str, level: Option<ConfigLevel>) -> Result<Option<ConfigEntry>, ConfigError> {
+    match level {
+        Some(level) => {
+            let repo_paths = repo_paths()?;
+            let path = level_path(level, &repo_paths)?;
+            let table = config::read_config_table(&path)
+                .map_err(|e| ConfigError::ReadConfigFailed(path.clone(), e.into()))?
+                .unwrap_or_default();
+            Ok(config::config_value(&table, key).map(|value| ConfigEntry {
+                key: key.to_string(),
+                value: value.clone(),
+                origin: ConfigOrigin::File(level, path),
+            }))
+        }
+        None => {
+            // the effective entry is the one the key is, or is within, e.g. the value of
+            // list.patch_sha.color comes from whichever level set list.patch_sha
+            let entry = list()?
+                .into_iter()
+                .find(|e| key == e.key || key.starts_with(&format!("{}.", e.key)));
+            Ok(entry.and_then(|e| {
+                let value = match key.strip_prefix(&format!("{}.", e.key)) {
+                    Some(rest) => e
+                        .value
+                        .as_table()
+                        .and_then(|t| config::config_value(t, rest))
+                        .cloned()?,
+                    None => e.value,
+                };
+                Some(ConfigEntry {
+                    key: key.to_string(),
+                    value,
+                    origin: e.origin,
+                })
+            }))
+        }
+    }
+}
+
+/// Set the dotted key to the value in the config file of the given level, returning the path
+/// of the file. The value is rejected if it would make the config file invalid.
+pub fn set(key: &str, value: &str, level: ConfigLevel) -> Result<PathBuf, ConfigError> {
+    let repo_paths = repo_paths()?;
+    let path = level_path(level, &repo_paths)?;
+    config::set_config_value(&path, key, value)
+        .map_err(|e| ConfigError::SetConfigValueFailed(path.clone(), e.into()))?;
+    Ok(path)
+}
+
+/// Validate the config file of each level, rejecting unknown keys & invalid values such as bad
+/// colors, and then the merged config, providing the effective config when it is all valid
+pub fn validate() -> Result<ConfigValidation, ConfigError> {
+    let repo_paths = repo_paths()?;
+
+    let mut levels: Vec<LevelValidation> = vec![];
+    for level in config::CONFIG_LEVELS {
+        let path = level_path(level, &repo_paths)?;
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => Some(content),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+            Err(e) => return Err(ConfigError::ReadConfigContentFailed(path, e)),
+        };
+        let problems = match &content {
+            Some(content) => match config::validate_config_content(content) {
+                Ok(unknown_keys) => unknown_keys
+                    .into_iter()
+                    .map(|key| format!("unknown key {}", key))
+                    .collect(),
+                Err(e) => vec![e.to_string().trim_end().to_string()],
+            },
+            None => vec![],
+        };
+        levels.push(LevelValidation {
+            level,
+            path,
+            exists: content.is_some(),
+            problems,
+        });
+    }
+
+    let mut validation = ConfigValidation {
+        levels,
+        problems: vec![],
+        config: None,
+    };
+    if !validation.is_valid() {
+        return Ok(validation);
+    }
+
+    match config::get_config(&repo_paths.repo, &repo_paths.root, &repo_paths.gitdir) {
+        Ok(ps_config) => {
+            if let Err(e) = rr_branch_name::RrBranchNaming::new(&ps_config.request_review) {
+                validation.problems.push(format!(
+                    "request_review.branch_name_template or ticket_pattern invalid, {}",
+                    e
+                ));
+            }
+            if validation.problems.is_empty() {
+                validation.config = Some(ps_config);
+            }
+        }
+        Err(e) => validation.problems.push(e.to_string()),
+    }
+
+    Ok(validation)
+}
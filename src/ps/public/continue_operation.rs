@@ -0,0 +1,214 @@
+use super::super::private::config;
+use super::super::private::conflicts;
+use super::super::private::git;
+use super::super::private::paths;
+use super::super::private::utils;
+use super::pull;
+use super::request_review;
+use super::sync;
+use std::path::PathBuf;
+use std::result::Result;
+
+#[derive(Debug)]
+pub enum ContinueError {
+    OpenRepositoryFailed(Box<dyn std::error::Error>),
+    OpenGitConfigFailed(Box<dyn std::error::Error>),
+    GetRepoRootPathFailed(Box<dyn std::error::Error>),
+    PathNotUtf8,
+    GetConfigFailed(Box<dyn std::error::Error>),
+    NothingToContinue,
+    ReadStateFailed(Box<dyn std::error::Error>),
+    ResumeCherryPickFailed(Box<dyn std::error::Error>),
+    StoppedForConflictResolution(String, PathBuf),
+    RebaseContinueFailed(Box<dyn std::error::Error>),
+    RebaseAbortFailed(Box<dyn std::error::Error>),
+    RestoreDestinationFailed(Box<dyn std::error::Error>),
+    FindPatchBranchFailed(Box<dyn std::error::Error>),
+    SyncFailed(Box<dyn std::error::Error>),
+    PostSyncHookFailed(Box<dyn std::error::Error>),
+    FinishPullFailed(Box<dyn std::error::Error>),
+    ClearStateFailed(Box<dyn std::error::Error>),
+}
+
+impl std::fmt::Display for ContinueError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OpenRepositoryFailed(e) => write!(f, "failed to open repository, {}", e),
+            Self::OpenGitConfigFailed(e) => write!(f, "failed to open git config, {}", e),
+            Self::GetRepoRootPathFailed(e) => {
+                write!(f, "failed to get repository root path, {}", e)
+            }
+            Self::PathNotUtf8 => write!(f, "path not utf-8"),
+            Self::GetConfigFailed(e) => write!(f, "failed to get config, {}", e),
+            Self::NothingToContinue => write!(f, "no operation is waiting to be continued"),
+            Self::ReadStateFailed(e) => write!(f, "failed to read the continue state, {}", e),
+            Self::ResumeCherryPickFailed(e) => write!(f, "failed to resume cherry pick, {}", e),
+            Self::StoppedForConflictResolution(oid, path) => write!(
+                f,
+                "stopped at conflicts playing {}, resolve them in the worktree at {}, stage them with git add & run gps continue, or gps continue --abort",
+                oid,
+                path.display()
+            ),
+            Self::RebaseContinueFailed(e) => write!(f, "failed to continue the rebase, {}", e),
+            Self::RebaseAbortFailed(e) => write!(f, "failed to abort the rebase, {}", e),
+            Self::RestoreDestinationFailed(e) => {
+                write!(f, "failed to restore the branch being cherry picked to, {}", e)
+            }
+            Self::FindPatchBranchFailed(e) => write!(f, "failed to find patch branch, {}", e),
+            Self::SyncFailed(e) => write!(f, "failed to sync patch branch, {}", e),
+            Self::PostSyncHookFailed(e) => {
+                write!(f, "request_review_post_sync hook failed, {}", e)
+            }
+            Self::FinishPullFailed(e) => write!(f, "failed to finish pull, {}", e),
+            Self::ClearStateFailed(e) => write!(f, "failed to clear the continue state, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ContinueError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::OpenRepositoryFailed(e) => Some(e.as_ref()),
+            Self::OpenGitConfigFailed(e) => Some(e.as_ref()),
+            Self::GetRepoRootPathFailed(e) => Some(e.as_ref()),
+            Self::PathNotUtf8 => None,
+            Self::GetConfigFailed(e) => Some(e.as_ref()),
+            Self::NothingToContinue => None,
+            Self::ReadStateFailed(e) => Some(e.as_ref()),
+            Self::ResumeCherryPickFailed(e) => Some(e.as_ref()),
+            Self::StoppedForConflictResolution(_, _) => None,
+            Self::RebaseContinueFailed(e) => Some(e.as_ref()),
+            Self::RebaseAbortFailed(e) => Some(e.as_ref()),
+            Self::RestoreDestinationFailed(e) => Some(e.as_ref()),
+            Self::FindPatchBranchFailed(e) => Some(e.as_ref()),
+            Self::SyncFailed(e) => Some(e.as_ref()),
+            Self::PostSyncHookFailed(e) => Some(e.as_ref()),
+            Self::FinishPullFailed(e) => Some(e.as_ref()),
+            Self::ClearStateFailed(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+/// Resume the `branch`, `request-review`, `append` or `pull` that stopped at a conflict, once the
+/// conflict has been resolved & staged, or with `abort` put things back the way they were before
+/// it. When it finished a pull, it returns what followed the pull's rebase, e.g. the request review
+/// branches of landed patches to confirm the deletion of.
+pub fn continue_operation(abort: bool) -> Result<Option<pull::PullFollowUp>, ContinueError> {
+    let repo = git::create_cwd_repo().map_err(|e| ContinueError::OpenRepositoryFailed(e.into()))?;
+    let state = conflicts::read_state(&repo)
+        .map_err(|e| ContinueError::ReadStateFailed(e.into()))?
+        .ok_or(ContinueError::NothingToContinue)?;
+
+    if abort {
+        if let conflicts::InterruptedOperation::Pull { .. } = state.operation {
+            if git::in_rebase(repo.path()) {
+                utils::execute("git", &["rebase", "--abort"])
+                    .map_err(|e| ContinueError::RebaseAbortFailed(e.into()))?;
+            }
+        }
+        if let Some(pending) = &state.cherry_pick {
+            conflicts::restore_destination(&repo, pending)
+                .map_err(|e| ContinueError::RestoreDestinationFailed(e.into()))?;
+        }
+        conflicts::clear_state(&repo).map_err(|e| ContinueError::ClearStateFailed(e.into()))?;
+        return Ok(None);
+    }
+
+    let repo_root_path =
+        paths::repo_root_path(&repo).map_err(|e| ContinueError::GetRepoRootPathFailed(e.into()))?;
+    let repo_root_str = repo_root_path.to_str().ok_or(ContinueError::PathNotUtf8)?;
+    let repo_gitdir_str = repo.path().to_str().ok_or(ContinueError::PathNotUtf8)?;
+    let ps_config = config::get_config(&repo, repo_root_str, repo_gitdir_str)
+        .map_err(|e| ContinueError::GetConfigFailed(e.into()))?;
+
+    // finish the cherry pick the operation stopped in, the state is kept until the rest of the
+    // operation succeeded as well, so it can be continued again when that fails
+    let mut cherry_picked_ref_name = state.cherry_picked_ref_name.clone();
+    if let Some(pending) = &state.cherry_pick {
+        let config = git2::Config::open_default()
+            .map_err(|e| ContinueError::OpenGitConfigFailed(e.into()))?;
+        match conflicts::resume_cherry_pick(
+            &repo,
+            &config,
+            &ps_config.rewrite,
+            &state.operation,
+            pending,
+        )
+        .map_err(|e| ContinueError::ResumeCherryPickFailed(e.into()))?
+        {
+            conflicts::ResumeOutcome::Completed => {}
+            conflicts::ResumeOutcome::Stopped(oid, worktree_path) => {
+                return Err(ContinueError::StoppedForConflictResolution(
+                    oid,
+                    worktree_path,
+                ))
+            }
+        }
+        conflicts::write_state(
+            &repo,
+            &conflicts::ContinueState {
+                operation: state.operation.clone(),
+                cherry_pick: None,
+                cherry_picked_ref_name: Some(pending.dest_ref_name.clone()),
+            },
+        )
+        .map_err(|e| ContinueError::ResumeCherryPickFailed(e.into()))?;
+        cherry_picked_ref_name = Some(pending.dest_ref_name.clone());
+    }
+
+    let pull_follow_up = match &state.operation {
+        conflicts::InterruptedOperation::Branch | conflicts::InterruptedOperation::Append => None,
+        conflicts::InterruptedOperation::RequestReview { post_sync_hook } => {
+            if let Some(cherry_picked_ref_name) = cherry_picked_ref_name {
+                let mut patch_branch = repo
+                    .find_reference(&cherry_picked_ref_name)
+                    .map(git2::Branch::wrap)
+                    .map_err(|e| ContinueError::FindPatchBranchFailed(e.into()))?;
+                let cur_patch_stack_remote_name = sync::patch_stack_remote_name(&repo)
+                    .map_err(|e| ContinueError::SyncFailed(e.into()))?;
+                let (patch_upstream_branch_name, _) =
+                    sync::push_patch_branch(&repo, &mut patch_branch, &cur_patch_stack_remote_name)
+                        .map_err(|e| ContinueError::SyncFailed(e.into()))?;
+
+                if *post_sync_hook {
+                    let found_hooks = request_review::find_post_sync_hooks(
+                        repo_root_str,
+                        repo_gitdir_str,
+                        &ps_config,
+                    )
+                    .map_err(|e| ContinueError::PostSyncHookFailed(e.into()))?;
+                    if let Some(found_hooks) = found_hooks {
+                        request_review::execute_post_sync_hooks(
+                            &repo,
+                            repo_gitdir_str,
+                            &ps_config,
+                            &found_hooks,
+                            &patch_upstream_branch_name,
+                        )
+                        .map_err(|e| ContinueError::PostSyncHookFailed(e.into()))?;
+                    }
+                }
+            }
+            None
+        }
+        conflicts::InterruptedOperation::Pull {
+            upstream_branch_name,
+        } => {
+            if git::in_rebase(repo.path()) {
+                utils::execute("git", &["rebase", "--continue"])
+                    .map_err(|e| ContinueError::RebaseContinueFailed(e.into()))?;
+            }
+            let follow_up = pull::finish_pull(
+                repo_root_str,
+                repo_gitdir_str,
+                &ps_config,
+                upstream_branch_name,
+            )
+            .map_err(|e| ContinueError::FinishPullFailed(e.into()))?;
+            Some(follow_up)
+        }
+    };
+
+    conflicts::clear_state(&repo).map_err(|e| ContinueError::ClearStateFailed(e.into()))?;
+    Ok(pull_follow_up)
+}
@@ -0,0 +1,78 @@
+use super::super::super::ps;
+use super::super::private::dependencies;
+use super::super::private::git;
+
+#[derive(Debug)]
+pub enum DepsError {
+    OpenRepositoryFailed(Box<dyn std::error::Error>),
+    GetPatchStackFailed(Box<dyn std::error::Error>),
+    GetPatchListFailed(Box<dyn std::error::Error>),
+    PatchStackBaseNotFound,
+    ComputeDependenciesFailed(Box<dyn std::error::Error>),
+}
+
+impl std::fmt::Display for DepsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OpenRepositoryFailed(e) => write!(f, "failed to open repository, {}", e),
+            Self::GetPatchStackFailed(e) => write!(f, "failed to get patch stack, {}", e),
+            Self::GetPatchListFailed(e) => write!(f, "failed to get patch list, {}", e),
+            Self::PatchStackBaseNotFound => write!(f, "patch stack base not found"),
+            Self::ComputeDependenciesFailed(e) => {
+                write!(f, "failed to compute patch dependencies, {}", e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for DepsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::OpenRepositoryFailed(e) => Some(e.as_ref()),
+            Self::GetPatchStackFailed(e) => Some(e.as_ref()),
+            Self::GetPatchListFailed(e) => Some(e.as_ref()),
+            Self::PatchStackBaseNotFound => None,
+            Self::ComputeDependenciesFailed(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+/// A patch of the stack along with the earlier patches it directly depends on
+#[derive(Debug)]
+pub struct PatchDependencies {
+    pub index: usize,
+    pub oid: git2::Oid,
+    pub summary: String,
+    /// Indexes of the earlier patches it can't be applied to the patch stack base without
+    pub depends_on: Vec<usize>,
+}
+
+/// Compute the dependency graph between the patches of the current patch stack
+pub fn deps() -> Result<Vec<PatchDependencies>, DepsError> {
+    let repo = git::create_cwd_repo().map_err(|e| DepsError::OpenRepositoryFailed(e.into()))?;
+
+    let patch_stack =
+        ps::get_patch_stack(&repo).map_err(|e| DepsError::GetPatchStackFailed(e.into()))?;
+    let patches_vec = ps::get_patch_list(&repo, &patch_stack)
+        .map_err(|e| DepsError::GetPatchListFailed(e.into()))?;
+    let base_oid = patch_stack
+        .base
+        .peel_to_commit()
+        .map_err(|_| DepsError::PatchStackBaseNotFound)?
+        .id();
+
+    let patch_oids: Vec<git2::Oid> = patches_vec.iter().map(|p| p.oid).collect();
+    let dependencies = dependencies::patch_dependencies(&repo, base_oid, &patch_oids)
+        .map_err(|e| DepsError::ComputeDependenciesFailed(e.into()))?;
+
+    Ok(patches_vec
+        .into_iter()
+        .zip(dependencies)
+        .map(|(patch, depends_on)| PatchDependencies {
+            index: patch.index,
+            oid: patch.oid,
+            summary: patch.summary,
+            depends_on,
+        })
+        .collect())
+}
@@ -0,0 +1,9 @@
+// This module exposes the events long running operations emit as they progress, so that library
+// users can subscribe to them, e.g. to render progress in their own UIs.
+//
+//     let _subscription = gps::events::subscribe(|event: &gps::events::Event| {
+//         println!("{:?}", event);
+//     });
+//     gps::pull();
+
+pub use super::super::private::events::{subscribe, Event, Observer, Subscription};
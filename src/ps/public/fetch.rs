@@ -39,7 +39,9 @@ impl std::error::Error for FetchError {
     }
 }
 
-pub fn fetch(color: bool) -> Result<(), FetchError> {
+/// Fetch from the remote, returning the commits on the upstream branch that aren't in the patch
+/// stack yet when `fetch.show_upstream_patches_after_fetch` is enabled
+pub fn fetch() -> Result<Option<Vec<upstream_patches::UpstreamPatch>>, FetchError> {
     git::ext_fetch().map_err(FetchError::FetchFailed)?;
 
     let repo = git::create_cwd_repo().map_err(|_| FetchError::RepositoryMissing)?;
@@ -48,12 +50,14 @@ pub fn fetch(color: bool) -> Result<(), FetchError> {
     let repo_root_str = repo_root_path.to_str().ok_or(FetchError::PathNotUtf8)?;
     let repo_gitdir_path = repo.path();
     let repo_gitdir_str = repo_gitdir_path.to_str().ok_or(FetchError::PathNotUtf8)?;
-    let config =
-        config::get_config(repo_root_str, repo_gitdir_str).map_err(FetchError::GetConfigFailed)?;
+    let config = config::get_config(&repo, repo_root_str, repo_gitdir_str)
+        .map_err(FetchError::GetConfigFailed)?;
 
     if config.fetch.show_upstream_patches_after_fetch {
-        upstream_patches::upstream_patches(color).map_err(FetchError::UpstreamPatchesFailure)?;
+        let patches = upstream_patches::find_upstream_patches(&repo)
+            .map_err(FetchError::UpstreamPatchesFailure)?;
+        Ok(Some(patches))
+    } else {
+        Ok(None)
     }
-
-    Ok(())
 }
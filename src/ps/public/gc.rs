@@ -0,0 +1,164 @@
+use super::super::private::config;
+use super::super::private::events::{self, Event};
+use super::super::private::git;
+use super::super::private::landed_branches;
+use super::super::private::paths;
+use super::super::private::rr_branch_name;
+use std::result::Result;
+
+#[derive(Debug)]
+pub enum GcError {
+    OpenRepositoryFailed(Box<dyn std::error::Error>),
+    GetRepoRootPathFailed(Box<dyn std::error::Error>),
+    PathNotUtf8,
+    GetConfigFailed(Box<dyn std::error::Error>),
+    RrBranchNamingInvalid(Box<dyn std::error::Error>),
+    CurrentBranchNameMissing,
+    GetUpstreamBranchFailed(Box<dyn std::error::Error>),
+    FindLandedBranchesFailed(Box<dyn std::error::Error>),
+    DeleteLocalBranchFailed(String, Box<dyn std::error::Error>),
+}
+
+impl std::fmt::Display for GcError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OpenRepositoryFailed(e) => write!(f, "failed to open repository, {}", e),
+            Self::GetRepoRootPathFailed(e) => {
+                write!(f, "failed to get repository root path, {}", e)
+            }
+            Self::PathNotUtf8 => write!(f, "path not utf-8"),
+            Self::GetConfigFailed(e) => write!(f, "failed to get config, {}", e),
+            Self::RrBranchNamingInvalid(e) => {
+                write!(f, "request review branch name template is invalid, {}", e)
+            }
+            Self::CurrentBranchNameMissing => write!(f, "current branch name missing"),
+            Self::GetUpstreamBranchFailed(e) => {
+                write!(f, "failed to get the upstream branch, {}", e)
+            }
+            Self::FindLandedBranchesFailed(e) => {
+                write!(f, "failed to find the branches of landed patches, {}", e)
+            }
+            Self::DeleteLocalBranchFailed(name, e) => {
+                write!(f, "failed to delete local branch {}, {}", name, e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for GcError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::OpenRepositoryFailed(e) => Some(e.as_ref()),
+            Self::GetRepoRootPathFailed(e) => Some(e.as_ref()),
+            Self::PathNotUtf8 => None,
+            Self::GetConfigFailed(e) => Some(e.as_ref()),
+            Self::RrBranchNamingInvalid(e) => Some(e.as_ref()),
+            Self::CurrentBranchNameMissing => None,
+            Self::GetUpstreamBranchFailed(e) => Some(e.as_ref()),
+            Self::FindLandedBranchesFailed(e) => Some(e.as_ref()),
+            Self::DeleteLocalBranchFailed(_, e) => Some(e.as_ref()),
+        }
+    }
+}
+
+/// A remote branch that couldn't be deleted along with its local branch, e.g. because the forge
+/// deleted it already when the patch was merged through its UI
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteBranchNotDeleted {
+    pub name: String,
+    pub reason: String,
+}
+
+/// Find the request review branches whose patches all landed in the upstream of the current patch
+/// stack, the ones `gps gc` offers to delete
+pub fn landed_branches() -> Result<Vec<String>, GcError> {
+    let repo = git::create_cwd_repo().map_err(|e| GcError::OpenRepositoryFailed(e.into()))?;
+    let repo_root_path =
+        paths::repo_root_path(&repo).map_err(|e| GcError::GetRepoRootPathFailed(e.into()))?;
+    let repo_root_str = repo_root_path.to_str().ok_or(GcError::PathNotUtf8)?;
+    let repo_gitdir_str = repo.path().to_str().ok_or(GcError::PathNotUtf8)?;
+    let config = config::get_config(&repo, repo_root_str, repo_gitdir_str)
+        .map_err(|e| GcError::GetConfigFailed(e.into()))?;
+
+    find_landed_branches(&repo, &config)
+}
+
+/// Delete the given branches locally & their remote tracking branches remotely, returning the
+/// remote branches that couldn't be deleted, which doesn't keep the local branches from going
+pub fn delete_branches(names: &[String]) -> Result<Vec<RemoteBranchNotDeleted>, GcError> {
+    let repo = git::create_cwd_repo().map_err(|e| GcError::OpenRepositoryFailed(e.into()))?;
+    delete_branches_in(&repo, names)
+}
+
+/// Find the request review branches of the landed patches, as `landed_branches` does, in an
+/// already opened repository
+pub fn find_landed_branches(
+    repo: &git2::Repository,
+    config: &config::PsConfig,
+) -> Result<Vec<String>, GcError> {
+    let rr_branch_naming = rr_branch_name::RrBranchNaming::new(&config.request_review)
+        .map_err(|e| GcError::RrBranchNamingInvalid(e.into()))?;
+
+    let cur_branch_name = git::get_current_branch(repo).ok_or(GcError::CurrentBranchNameMissing)?;
+    let upstream_branch_name = git::branch_upstream_name(repo, &cur_branch_name)
+        .map_err(|e| GcError::GetUpstreamBranchFailed(e.into()))?;
+    let upstream_oid = repo
+        .refname_to_id(&upstream_branch_name)
+        .map_err(|e| GcError::GetUpstreamBranchFailed(e.into()))?;
+
+    landed_branches::landed_branches(repo, &rr_branch_naming, upstream_oid)
+        .map_err(|e| GcError::FindLandedBranchesFailed(e.into()))
+}
+
+/// Delete the branches, as `delete_branches` does, in an already opened repository
+pub fn delete_branches_in(
+    repo: &git2::Repository,
+    names: &[String],
+) -> Result<Vec<RemoteBranchNotDeleted>, GcError> {
+    let mut not_deleted = vec![];
+    for name in names.iter() {
+        if let Some(remote_branch) = delete_branch_and_its_remote(repo, name)? {
+            not_deleted.push(remote_branch);
+        }
+    }
+    Ok(not_deleted)
+}
+
+fn delete_branch_and_its_remote(
+    repo: &git2::Repository,
+    name: &str,
+) -> Result<Option<RemoteBranchNotDeleted>, GcError> {
+    let mut local_branch = repo
+        .find_branch(name, git2::BranchType::Local)
+        .map_err(|e| GcError::DeleteLocalBranchFailed(name.to_string(), e.into()))?;
+
+    let mut not_deleted = None;
+    if let Ok(remote_branch) = local_branch.upstream() {
+        let remote = repo.branch_upstream_remote(&format!("refs/heads/{}", name));
+        let remote_branch_name = remote_branch.name();
+        if let (Ok(remote), Ok(Some(remote_branch_name))) = (remote, remote_branch_name) {
+            let remote_str = remote.as_str().ok_or(GcError::PathNotUtf8)?;
+            let remote_branch_name_relative_to_remote =
+                str::replace(remote_branch_name, &format!("{}/", remote_str), "");
+            // the forge may have deleted it already when the patch was merged through its UI, in
+            // which case the local branch still goes
+            if let Err(e) =
+                git::ext_delete_remote_branch(remote_str, &remote_branch_name_relative_to_remote)
+            {
+                not_deleted = Some(RemoteBranchNotDeleted {
+                    name: remote_branch_name.to_string(),
+                    reason: e.to_string(),
+                });
+            }
+        }
+    }
+
+    local_branch
+        .delete()
+        .map_err(|e| GcError::DeleteLocalBranchFailed(name.to_string(), e.into()))?;
+    events::emit(Event::BranchDeleted {
+        name: name.to_string(),
+        remote: None,
+    });
+    Ok(not_deleted)
+}
@@ -0,0 +1,120 @@
+use super::super::private::config;
+use super::super::private::git;
+use super::super::private::hooks;
+use super::super::private::paths;
+use std::path::PathBuf;
+use std::result::Result;
+
+pub use super::super::private::hooks::{HookLocation, HookPhase};
+
+#[derive(Debug)]
+pub enum ListHooksError {
+    OpenRepositoryFailed(Box<dyn std::error::Error>),
+    GetRepoRootPathFailed(Box<dyn std::error::Error>),
+    PathNotUtf8,
+    GetConfigFailed(Box<dyn std::error::Error>),
+    GetHookSearchPathsFailed(hooks::FindHookError),
+}
+
+impl std::fmt::Display for ListHooksError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OpenRepositoryFailed(e) => write!(f, "failed to open repository, {}", e),
+            Self::GetRepoRootPathFailed(e) => {
+                write!(f, "failed to get repositories root path, {}", e)
+            }
+            Self::PathNotUtf8 => write!(f, "path not utf-8"),
+            Self::GetConfigFailed(e) => write!(f, "failed to get config, {}", e),
+            Self::GetHookSearchPathsFailed(e) => {
+                write!(f, "failed to get hook search paths, {}", e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ListHooksError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::OpenRepositoryFailed(e) => Some(e.as_ref()),
+            Self::GetRepoRootPathFailed(e) => Some(e.as_ref()),
+            Self::PathNotUtf8 => None,
+            Self::GetConfigFailed(e) => Some(e.as_ref()),
+            Self::GetHookSearchPathsFailed(e) => Some(e),
+        }
+    }
+}
+
+/// A hook file found in one of the hook locations
+#[derive(Debug)]
+pub struct HookFile {
+    pub location: HookLocation,
+    pub path: PathBuf,
+    pub executable: bool,
+    /// Whether the file is run when the hook is executed, given the chaining of the locations
+    pub runs: bool,
+}
+
+/// How a registered hook resolves in the current repository
+#[derive(Debug)]
+pub struct HookResolution {
+    pub name: &'static str,
+    pub command: &'static str,
+    pub phase: HookPhase,
+    pub args: &'static [&'static str],
+    /// The hook files found in each of the locations, in order of precedence
+    pub files: Vec<HookFile>,
+    /// The reason the hook can't be resolved, e.g. a hook file that isn't executable
+    pub error: Option<hooks::FindHookError>,
+}
+
+/// Resolve every registered hook against the hook locations of the current repository
+pub fn list_hooks() -> Result<Vec<HookResolution>, ListHooksError> {
+    let repo =
+        git::create_cwd_repo().map_err(|e| ListHooksError::OpenRepositoryFailed(e.into()))?;
+    let repo_root_path = paths::repo_root_path(&repo)
+        .map_err(|e| ListHooksError::GetRepoRootPathFailed(e.into()))?;
+    let repo_root_str = repo_root_path.to_str().ok_or(ListHooksError::PathNotUtf8)?;
+    let repo_gitdir_str = repo.path().to_str().ok_or(ListHooksError::PathNotUtf8)?;
+    let config = config::get_config(&repo, repo_root_str, repo_gitdir_str)
+        .map_err(|e| ListHooksError::GetConfigFailed(e.into()))?;
+
+    let mut resolutions: Vec<HookResolution> = vec![];
+    for hook in hooks::HOOKS {
+        let (running_hooks, error) =
+            match hooks::find_hooks(repo_root_str, repo_gitdir_str, &config.hooks, hook) {
+                Ok(found_hooks) => (found_hooks, None),
+                Err(hooks::FindHookError::NotFound) => (vec![], None),
+                Err(e) => (vec![], Some(e)),
+            };
+
+        let mut files: Vec<HookFile> = vec![];
+        for (location, hook_path) in
+            hooks::hook_search_paths(repo_root_str, repo_gitdir_str, hook.name)
+                .map_err(ListHooksError::GetHookSearchPathsFailed)?
+        {
+            for (path, state) in hooks::hook_files(&hook_path) {
+                let runs = running_hooks.iter().any(|(_, p)| p == &path);
+                files.push(HookFile {
+                    location,
+                    executable: matches!(
+                        state,
+                        paths::PathExistsAndIsExecutable::ExistsAndIsExecutable
+                    ),
+                    path,
+                    runs,
+                });
+            }
+        }
+
+        resolutions.push(HookResolution {
+            name: hook.name,
+            command: hook.command,
+            phase: hook.phase,
+            args: hook.args,
+            files,
+            error,
+        });
+    }
+
+    Ok(resolutions)
+}
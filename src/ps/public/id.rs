@@ -1,9 +1,12 @@
 use super::super::super::ps;
+use super::super::private::config;
 use super::super::private::git;
+use super::super::private::paths;
 
 #[derive(Debug)]
 pub enum IdError {
     OpenGitConfigFailed(Box<dyn std::error::Error>),
+    GetConfigFailed(Box<dyn std::error::Error>),
     AddPatchIdsFailed(Box<dyn std::error::Error>),
     Unhandled(Box<dyn std::error::Error>),
 }
@@ -14,6 +17,7 @@ impl std::fmt::Display for IdError {
             Self::OpenGitConfigFailed(e) => {
                 write!(f, "Failed to open git config, {}", e)
             }
+            Self::GetConfigFailed(e) => write!(f, "failed to get config, {}", e),
             Self::AddPatchIdsFailed(e) => write!(f, "add patch ids failed, {}", e),
             Self::Unhandled(e) => write!(f, "{}", e),
         }
@@ -24,6 +28,7 @@ impl std::error::Error for IdError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             Self::OpenGitConfigFailed(e) => Some(e.as_ref()),
+            Self::GetConfigFailed(e) => Some(e.as_ref()),
             Self::AddPatchIdsFailed(e) => Some(e.as_ref()),
             Self::Unhandled(e) => Some(e.as_ref()),
         }
@@ -36,5 +41,17 @@ pub fn id() -> Result<(), IdError> {
     let config =
         git2::Config::open_default().map_err(|e| IdError::OpenGitConfigFailed(e.into()))?;
 
-    ps::add_patch_ids(&repo, &config).map_err(|e| IdError::AddPatchIdsFailed(e.into()))
+    let repo_root_str = paths::repo_root_path(&repo)
+        .map_err(|e| IdError::GetConfigFailed(e.into()))?
+        .to_str()
+        .ok_or_else(|| IdError::GetConfigFailed("repository path not utf-8".into()))?;
+    let repo_gitdir_str = repo
+        .path()
+        .to_str()
+        .ok_or_else(|| IdError::GetConfigFailed("repository gitdir not utf-8".into()))?;
+    let ps_config = config::get_config(&repo, repo_root_str, repo_gitdir_str)
+        .map_err(|e| IdError::GetConfigFailed(e.into()))?;
+
+    ps::add_patch_ids(&repo, &config, &ps_config.rewrite)
+        .map_err(|e| IdError::AddPatchIdsFailed(e.into()))
 }
@@ -1,14 +1,19 @@
 use super::super::super::ps;
 use super::super::private::config;
+use super::super::private::events::{self, Event};
 use super::super::private::git;
 use super::super::private::hooks;
+use super::super::private::in_memory_rebase;
+use super::super::private::integrate_strategy;
 use super::super::private::paths;
+use super::super::private::rr_branch_name;
 use super::super::private::state_computation;
-use super::super::private::utils;
 use super::super::public::pull;
-use super::super::public::show;
+use super::integrate_check;
+use super::sync;
 use super::verify_isolation;
 use std::collections::HashMap;
+use std::time::Duration;
 use uuid::Uuid;
 
 #[derive(Debug)]
@@ -22,22 +27,16 @@ pub enum IntegrateError {
     GetRepoRootPathFailed(Box<dyn std::error::Error>),
     PathNotUtf8,
     GetConfigFailed(Box<dyn std::error::Error>),
-    ShowFailed(Box<dyn std::error::Error>),
+    RrBranchNamingInvalid(Box<dyn std::error::Error>),
     UserVerificationFailed(Box<dyn std::error::Error>),
+    UserDeclined,
     FetchFailed(Box<dyn std::error::Error>),
     PatchStackBaseNotFound,
     PatchStackHeadNoName,
     GetListPatchInfoFailed(Box<dyn std::error::Error>),
-    HasNoAssociatedBranch,
-    AssociatedBranchAmbiguous,
-    FindPatchCommitFailed(Box<dyn std::error::Error>),
-    MissingPatchId,
-    MissingPatchInfo,
     UpstreamBranchInfoMissing,
-    CommitCountMissmatch(usize, usize),
-    PatchAndRemotePatchIdMissmatch(usize),
-    PatchDiffHashMissmatch(usize),
-    PatchMissingDiffHash,
+    RunChecksFailed(Box<dyn std::error::Error>),
+    CheckFailed(integrate_check::IntegrateCheck),
     CreateOrReplaceBranchFailed(Box<dyn std::error::Error>),
     IsolationVerificationFailed(Box<dyn std::error::Error>),
     GetPatchBranchNameFailed(Box<dyn std::error::Error>),
@@ -46,9 +45,14 @@ pub enum IntegrateError {
     GetUpstreamBranchNameFailed,
     GetRemoteNameFailed,
     ConvertStringToStrFailed,
+    PrepareIntegrationFailed(Box<dyn std::error::Error>),
     PushFailed(Box<dyn std::error::Error>),
+    DeleteIntegrationBranchFailed(Box<dyn std::error::Error>),
+    TagFailed(Box<dyn std::error::Error>),
+    RebuildBranchFailed(String, Box<dyn std::error::Error>),
+    RebuildBranchConflicts(String),
     HookExecutionFailed(Box<dyn std::error::Error>),
-    VerifyHookExecutionFailed(Box<dyn std::error::Error>),
+    ForceNotAllowed,
     HookNotFound(Box<dyn std::error::Error>),
     FindPatchBranchFailed(Box<dyn std::error::Error>),
     GetBranchUpstreamRemoteFailed(Box<dyn std::error::Error>),
@@ -124,30 +128,23 @@ impl std::fmt::Display for IntegrateError {
             Self::GetRepoRootPathFailed(e) => write!(f, "get repository root path failed, {}", e),
             Self::PathNotUtf8 => write!(f, "path not utf-8"),
             Self::GetConfigFailed(e) => write!(f, "get patch stack config failed, {}", e),
-            Self::ShowFailed(e) => write!(f, "show failed, {}", e),
+            Self::RrBranchNamingInvalid(e) => {
+                write!(f, "invalid request review branch name template, {}", e)
+            }
             Self::UserVerificationFailed(e) => write!(f, "user verification failed, {}", e),
+            Self::UserDeclined => write!(f, "user declined to integrate the patch(es)"),
             Self::FetchFailed(e) => write!(f, "fetch failed, {}", e),
             Self::PatchStackBaseNotFound => write!(f, "patch stack base not found"),
             Self::PatchStackHeadNoName => write!(f, "patch stack head no name"),
             Self::GetListPatchInfoFailed(e) => write!(f, "get list patch info failed, {}", e),
-            Self::HasNoAssociatedBranch => write!(f, "has no associated branch"),
-            Self::AssociatedBranchAmbiguous => write!(f, "associated branch is ambiguous"),
-            Self::FindPatchCommitFailed(e) => write!(f, "find patch commit failed, {}", e),
-            Self::MissingPatchId => write!(f, "missing patch id"),
-            Self::MissingPatchInfo => write!(f, "missing patch info"),
             Self::UpstreamBranchInfoMissing => write!(f, "upstream branch info missing"),
-            Self::CommitCountMissmatch(count_one, count_two) => write!(
-                f,
-                "commit count missmatch with {} and {}",
-                count_one, count_two
-            ),
-            Self::PatchAndRemotePatchIdMissmatch(idx) => write!(
+            Self::RunChecksFailed(e) => write!(f, "failed to run the safety checks, {}", e),
+            Self::CheckFailed(check) => write!(
                 f,
-                "patch and remote patch id missmatch for patch index {}",
-                idx
+                "safety check \"{}\" failed, {}",
+                check.kind.description(),
+                check.detail
             ),
-            Self::PatchDiffHashMissmatch(idx) => write!(f, "patch diff hash missmatch {}", idx),
-            Self::PatchMissingDiffHash => write!(f, "patch missing diff hash"),
             Self::CreateOrReplaceBranchFailed(e) => {
                 write!(f, "create or replace branch failed, {}", e)
             }
@@ -160,9 +157,31 @@ impl std::fmt::Display for IntegrateError {
             Self::GetUpstreamBranchNameFailed => write!(f, "get upstream branch name failed"),
             Self::GetRemoteNameFailed => write!(f, "get remote name failed"),
             Self::ConvertStringToStrFailed => write!(f, "convert string to str failed"),
+            Self::PrepareIntegrationFailed(e) => {
+                write!(f, "failed to prepare the integration, {}", e)
+            }
             Self::PushFailed(e) => write!(f, "push failed, {}", e),
+            Self::DeleteIntegrationBranchFailed(e) => {
+                write!(f, "failed to delete the integration branch, {}", e)
+            }
+            Self::TagFailed(e) => write!(f, "failed to tag the integrated patches, {}", e),
+            Self::RebuildBranchFailed(branch, e) => {
+                write!(
+                    f,
+                    "failed to rebuild {} on the latest upstream, {}",
+                    branch, e
+                )
+            }
+            Self::RebuildBranchConflicts(branch) => write!(
+                f,
+                "{} no longer applies cleanly on the latest upstream, pull & request review again",
+                branch
+            ),
             Self::HookExecutionFailed(e) => write!(f, "hook execution failed, {}", e),
-            Self::VerifyHookExecutionFailed(e) => write!(f, "verify hook execution failed, {}", e),
+            Self::ForceNotAllowed => write!(
+                f,
+                "force not allowed, integrate.require_verify is enabled for this patch stack"
+            ),
             Self::HookNotFound(e) => write!(f, "hook not found, {}", e),
             Self::FindPatchBranchFailed(e) => write!(f, "find patch branch failed, {}", e),
             Self::GetBranchUpstreamRemoteFailed(e) => {
@@ -203,22 +222,16 @@ impl std::error::Error for IntegrateError {
             Self::GetRepoRootPathFailed(e) => Some(e.as_ref()),
             Self::PathNotUtf8 => None,
             Self::GetConfigFailed(e) => Some(e.as_ref()),
-            Self::ShowFailed(e) => Some(e.as_ref()),
+            Self::RrBranchNamingInvalid(e) => Some(e.as_ref()),
             Self::UserVerificationFailed(e) => Some(e.as_ref()),
+            Self::UserDeclined => None,
             Self::FetchFailed(e) => Some(e.as_ref()),
             Self::PatchStackBaseNotFound => None,
             Self::PatchStackHeadNoName => None,
             Self::GetListPatchInfoFailed(e) => Some(e.as_ref()),
-            Self::HasNoAssociatedBranch => None,
-            Self::AssociatedBranchAmbiguous => None,
-            Self::FindPatchCommitFailed(e) => Some(e.as_ref()),
-            Self::MissingPatchId => None,
-            Self::MissingPatchInfo => None,
             Self::UpstreamBranchInfoMissing => None,
-            Self::CommitCountMissmatch(_, _) => None,
-            Self::PatchAndRemotePatchIdMissmatch(_) => None,
-            Self::PatchDiffHashMissmatch(_) => None,
-            Self::PatchMissingDiffHash => None,
+            Self::RunChecksFailed(e) => Some(e.as_ref()),
+            Self::CheckFailed(_) => None,
             Self::CreateOrReplaceBranchFailed(e) => Some(e.as_ref()),
             Self::IsolationVerificationFailed(e) => Some(e.as_ref()),
             Self::GetPatchBranchNameFailed(e) => Some(e.as_ref()),
@@ -227,9 +240,14 @@ impl std::error::Error for IntegrateError {
             Self::GetUpstreamBranchNameFailed => None,
             Self::GetRemoteNameFailed => None,
             Self::ConvertStringToStrFailed => None,
+            Self::PrepareIntegrationFailed(e) => Some(e.as_ref()),
             Self::PushFailed(e) => Some(e.as_ref()),
+            Self::DeleteIntegrationBranchFailed(e) => Some(e.as_ref()),
+            Self::TagFailed(e) => Some(e.as_ref()),
+            Self::RebuildBranchFailed(_, e) => Some(e.as_ref()),
+            Self::RebuildBranchConflicts(_) => None,
             Self::HookExecutionFailed(e) => Some(e.as_ref()),
-            Self::VerifyHookExecutionFailed(e) => Some(e.as_ref()),
+            Self::ForceNotAllowed => None,
             Self::HookNotFound(e) => Some(e.as_ref()),
             Self::FindPatchBranchFailed(e) => Some(e.as_ref()),
             Self::GetBranchUpstreamRemoteFailed(e) => Some(e.as_ref()),
@@ -248,51 +266,63 @@ impl std::error::Error for IntegrateError {
     }
 }
 
-pub fn integrate(
-    start_patch_index: usize,
-    end_patch_index: Option<usize>,
+/// A request review branch verified, or with `force` created, for integration
+struct IntegrateEntry {
+    patch_branch_name: String,
+    patch_branch_ref_name: String,
+    patch_branch_oid: git2::Oid,
+}
+
+/// Integrate each of the given patch index ranges, `(start_patch_index, end_patch_index)`, into
+/// the patch stacks upstream. Every range is verified up front, then they are combined according
+/// to the integrate strategy & published in a single atomic push, which also deletes their remote
+/// request review branches unless they are kept.
+///
+/// With `retry` a push rejected because the upstream moved in the mean time is retried, backing
+/// off between attempts, after fetching again, rebuilding the request review branches that no
+/// longer fast-forward the upstream & re-running the integrate_verify hook.
+///
+/// With `sign_push` the push certificate is signed, & with `tag` a signed tag listing the ps-ids of
+/// each integrated patch series is created & pushed along with it, also when enabled in the config.
+///
+/// When `integrate.prompt_for_reassurance` is enabled, `reassure` is given the patch index ranges
+/// to present them to the user & ask whether to go ahead, the integration being aborted unless it
+/// returns true. When `integrate.pull_after_integrate` is enabled, it returns the result of the
+/// pull.
+#[allow(clippy::too_many_arguments)]
+pub fn integrate<F>(
+    patch_index_ranges: &[(usize, Option<usize>)],
     force: bool,
     keep_branch: bool,
+    retry: bool,
+    sign_push: bool,
+    tag: bool,
     given_branch_name_option: Option<String>,
-    color: bool,
-) -> Result<(), IntegrateError> {
+    reassure: F,
+) -> Result<Option<pull::PullResult>, IntegrateError>
+where
+    F: FnOnce(&[(usize, Option<usize>)]) -> Result<bool, Box<dyn std::error::Error>>,
+{
     // x validate patch indexes are within bounds
     // x add patch ids (will change sha of commit in stack)
     // x prompt_for_reassurance (based on config)
     // x git fetch - update the knowledge of the remotes
-    // - if NOT force
+    // x for each patch index range
     //     x figure out associated branch
-    //     x verify has associated branch, exit with error
-    //     x check to make sure patches match between stack & remote
-    //     - execute hook to verify PR approval & CI status
-    // x create/replace the request review branch
-    //     - in fresh case, it creates the branch, in existing case it updates it to the latest state from ps
-    // x verify isolation (verifies cherry-pick cleanly but also verify isolation hook passes)
-    // x publish the patch(es) from local patch branch up to patch stack upstream
+    //     - if NOT force
+    //         x verify has associated branch, exit with error
+    //         x check to make sure patches match between stack & remote
+    //         x execute hook to verify PR approval & CI status
+    //         x verify isolation (verifies cherry-pick cleanly but also verify isolation hook passes)
+    //     - if FORCE
+    //         x verify isolation (verifies cherry-pick cleanly but also verify isolation hook passes)
+    //         x create/replace the request review branch
+    //              - in fresh case, it creates the branch, in existing case it updates it to the latest state from ps
+    // x combine the patch branches according to the integrate strategy
+    // x publish the combination up to patch stack upstream, optionally (based on config) deleting
+    //   the remote request review branches in the same atomic push
     // x execute integrate post push hook
-    // x optionally (based on config) delete remote if exists request review branch
-    // x optionally (based on config) delete local request review branch
-    // x optionnaly pull (based on config)
-
-    // x validate patch indexes are within bounds
-    // x add patch ids (will change sha of commit in stack)
-    // x prompt_for_reassurance (based on config)
-    // x git fetch - update the knowledge of the remotes
-    // x figure out associated branch
-    // - if NOT force
-    //     x verify has associated branch, exit with error
-    //     x check to make sure patches match between stack & remote
-    //     x execute hook to verify PR approval & CI status
-    //     x verify isolation (verifies cherry-pick cleanly but also verify isolation hook passes)
-    //     x publish the patch(es) from the remote patch branch up to patch stack upstream
-    // - if FORCE
-    //     x create/replace the request review branch
-    //          - in fresh case, it creates the branch, in existing case it updates it to the latest state from ps
-    //     x verify isolation (verifies cherry-pick cleanly but also verify isolation hook passes)
-    //     x publish the patch(es) from local patch branch up to patch stack upstream
-    // x execute integrate post push hook
-    // x optionally (based on config) delete remote if exists request review branch
-    // x optionally (based on config) delete local request review branch
+    // x optionally (based on config) delete local request review branches
     // x optionnaly pull (based on config)
 
     let repo = git::create_cwd_repo().map_err(|_| IntegrateError::RepositoryNotFound)?;
@@ -303,14 +333,13 @@ pub fn integrate(
         .map_err(|e| IntegrateError::GetPatchListFailed(e.into()))?;
 
     // validate patch indexes are within bounds
-    ps::patch_range_within_stack_bounds(start_patch_index, end_patch_index, &patches_vec)
-        .map_err(|e| IntegrateError::PatchIndexRangeOutOfBounds(e.into()))?;
+    for (start_patch_index, end_patch_index) in patch_index_ranges.iter() {
+        ps::patch_range_within_stack_bounds(*start_patch_index, *end_patch_index, &patches_vec)
+            .map_err(|e| IntegrateError::PatchIndexRangeOutOfBounds(e.into()))?;
+    }
 
-    // add patch ids to commits in patch stack missing them
     let git_config =
         git2::Config::open_default().map_err(|e| IntegrateError::OpenGitConfigFailed(e.into()))?;
-    ps::add_patch_ids(&repo, &git_config)?;
-
     let repo_root_path = paths::repo_root_path(&repo)
         .map_err(|e| IntegrateError::GetRepoRootPathFailed(e.into()))?;
     let repo_root_str = repo_root_path.to_str().ok_or(IntegrateError::PathNotUtf8)?;
@@ -319,26 +348,24 @@ pub fn integrate(
         .to_str()
         .ok_or(IntegrateError::PathNotUtf8)?;
 
-    let config = config::get_config(repo_root_str, repo_gitdir_str)
+    let config = config::get_config(&repo, repo_root_str, repo_gitdir_str)
         .map_err(|e| IntegrateError::GetConfigFailed(e.into()))?;
 
+    // add patch ids to commits in patch stack missing them
+    ps::add_patch_ids(&repo, &git_config, &config.rewrite)?;
+
+    let rr_branch_naming = rr_branch_name::RrBranchNaming::new(&config.request_review)
+        .map_err(|e| IntegrateError::RrBranchNamingInvalid(e.into()))?;
+
+    if force && config.integrate.require_verify {
+        return Err(IntegrateError::ForceNotAllowed);
+    }
+
     // prompt for reassurance
-    if config.integrate.prompt_for_reassurance {
-        match show::show(start_patch_index, end_patch_index) {
-            Err(show::ShowError::ExitSignal(13)) => utils::print_warn(
-                color,
-                r#"
-Warning: showing the patch exited with a SIGPIPE. This is likely because you
-exited the pager (e.g. less) without going to the last page.
-
-See https://github.com/uptech/git-ps-rs/issues/120 for details on why this
-happens.
-"#,
-            ),
-            Err(e) => return Err(IntegrateError::ShowFailed(e.into())),
-            Ok(_) => (),
-        }
-        get_verification().map_err(|e| IntegrateError::UserVerificationFailed(e.into()))?;
+    if config.integrate.prompt_for_reassurance
+        && !reassure(patch_index_ranges).map_err(IntegrateError::UserVerificationFailed)?
+    {
+        return Err(IntegrateError::UserDeclined);
     }
 
     // fetch so we get new remote state
@@ -357,418 +384,487 @@ happens.
         .ok_or(IntegrateError::PatchStackHeadNoName)?;
 
     let patch_info_collection: HashMap<Uuid, state_computation::PatchGitInfo> =
-        state_computation::get_list_patch_info(&repo, patch_stack_base_commit.id(), head_ref_name)
-            .map_err(|e| IntegrateError::GetListPatchInfoFailed(e.into()))?;
-
-    // figure out the associated branch
-    let range_patch_branches = ps::patch_series_unique_branch_names(
-        &repo,
-        &patches_vec,
-        &patch_info_collection,
-        start_patch_index,
-        end_patch_index,
-    );
-
-    let patch_branch_name: String;
-    let patch_branch_ref_name: String;
-
-    if !force {
-        // verify has associated branch, exit with error
-        if range_patch_branches.is_empty() {
-            return Err(IntegrateError::HasNoAssociatedBranch);
-        } else if range_patch_branches.len() > 1 && given_branch_name_option.is_none() {
-            return Err(IntegrateError::AssociatedBranchAmbiguous);
-        }
-
-        let patch_associated_branch_name = match given_branch_name_option {
-            Some(ref bn) => bn.clone(),
-            None => range_patch_branches.first().unwrap().to_string(),
-        };
-
-        // get a patch id of any patch in series
-        let some_patches_basic_info = patches_vec.get(start_patch_index).unwrap();
-        let some_patch_commit = repo
-            .find_commit(some_patches_basic_info.oid)
-            .map_err(|e| IntegrateError::FindPatchCommitFailed(e.into()))?;
-        let some_patch_id =
-            ps::commit_ps_id(&some_patch_commit).ok_or(IntegrateError::MissingPatchId)?;
-
-        let some_patch_info_option = patch_info_collection.get(&some_patch_id);
-        if some_patch_info_option.is_none() {
-            return Err(IntegrateError::MissingPatchInfo);
-        }
-
-        let mut branches_iter = some_patch_info_option.unwrap().branches.iter();
-        let upstream_branch_info = branches_iter
-            .find(|b| b.name == patch_associated_branch_name)
-            .and_then(|lbi| lbi.upstream.as_ref());
-        let upstream_branch_info_ref = upstream_branch_info.as_ref();
-
-        let patch_series_indexes = match end_patch_index {
-            Some(end_index) => (start_patch_index..=end_index).collect(),
-            None => vec![start_patch_index],
-        };
-
-        if upstream_branch_info_ref.is_none() {
-            return Err(IntegrateError::UpstreamBranchInfoMissing);
-        }
+        state_computation::get_list_patch_info(
+            &repo,
+            patch_stack_base_commit.id(),
+            head_ref_name,
+            &config.branch_scan,
+            &rr_branch_naming,
+        )
+        .map_err(|e| IntegrateError::GetListPatchInfoFailed(e.into()))?;
+
+    let cur_patch_stack_branch_name =
+        git::get_current_branch(&repo).ok_or(IntegrateError::CurrentBranchNameMissing)?;
+    let cur_patch_stack_upstream_branch_name =
+        git::branch_upstream_name(&repo, cur_patch_stack_branch_name.as_str())
+            .map_err(|_| IntegrateError::GetUpstreamBranchNameFailed)?;
+    let cur_patch_stack_remote_name = repo
+        .branch_remote_name(&cur_patch_stack_upstream_branch_name)
+        .map_err(|_| IntegrateError::GetRemoteNameFailed)?;
+    let cur_patch_stack_remote_name_str = cur_patch_stack_remote_name
+        .as_str()
+        .ok_or(IntegrateError::ConvertStringToStrFailed)?;
+    let cur_patch_stack_remote = repo
+        .find_remote(cur_patch_stack_remote_name_str)
+        .map_err(|e| IntegrateError::FindRemoteFailed(e.into()))?;
+    let cur_patch_stack_remote_url_str = cur_patch_stack_remote
+        .url()
+        .ok_or(IntegrateError::RemoteUrlNotUtf8)?;
+
+    let pattern = format!("refs/remotes/{}/", cur_patch_stack_remote_name_str);
+    let cur_patch_stack_upstream_branch_shorthand =
+        str::replace(&cur_patch_stack_upstream_branch_name, pattern.as_str(), "");
+
+    let upstream_oid = repo
+        .refname_to_id(&cur_patch_stack_upstream_branch_name)
+        .map_err(|_| IntegrateError::GetUpstreamBranchNameFailed)?;
+    let check_context = integrate_check::CheckContext {
+        repo: &repo,
+        config: &config,
+        patches: &patches_vec,
+        patch_info_collection: &patch_info_collection,
+        rr_branch_naming: &rr_branch_naming,
+        patch_stack_base_oid: patch_stack_base_commit.id(),
+        upstream_oid,
+        upstream_branch_shorthand: &cur_patch_stack_upstream_branch_shorthand,
+        remote_name: cur_patch_stack_remote_name_str,
+        remote_url: cur_patch_stack_remote_url_str,
+        repo_root_str,
+        repo_gitdir_str,
+    };
+    // the ranges are checked to combine in memory before anything is published
+    let mut dry_run_integration =
+        integrate_strategy::DryRunIntegration::new(&repo, upstream_oid)
+            .map_err(|e| IntegrateError::PrepareIntegrationFailed(e.into()))?;
+
+    let mut entries: Vec<IntegrateEntry> = vec![];
+    for (start_patch_index, end_patch_index) in patch_index_ranges.iter() {
+        let start_patch_index = *start_patch_index;
+        let end_patch_index = *end_patch_index;
+
+        if !force {
+            let report = integrate_check::check_range(
+                &check_context,
+                start_patch_index,
+                end_patch_index,
+                given_branch_name_option.as_deref(),
+                &mut dry_run_integration,
+                true,
+            )
+            .map_err(|e| IntegrateError::RunChecksFailed(e.into()))?;
+            if let Some(failed_check) = report.first_failure() {
+                return Err(IntegrateError::CheckFailed(failed_check.clone()));
+            }
+            // the patch(es) are published from the remote patch branch, as that is what was
+            // reviewed
+            let (patch_associated_branch_name, remote_branch_oid) =
+                match (report.branch_name, report.remote_branch_oid) {
+                    (Some(branch_name), Some(remote_branch_oid)) => {
+                        (branch_name, remote_branch_oid)
+                    }
+                    _ => return Err(IntegrateError::UpstreamBranchInfoMissing),
+                };
+
+            // since we are NOT recreating/updating the branch from the patch stack anymore here
+            // we need to get the associated branch information from the computed state
+            let associated_branch = repo
+                .find_branch(&patch_associated_branch_name, git2::BranchType::Local)
+                .map_err(|e| IntegrateError::UnhandledError(e.into()))?;
+
+            entries.push(IntegrateEntry {
+                patch_branch_name: associated_branch
+                    .name()
+                    .map_err(|e| IntegrateError::GetPatchBranchNameFailed(e.into()))?
+                    .ok_or(IntegrateError::CreatedBranchMissingName)?
+                    .to_string(),
+                patch_branch_ref_name: associated_branch
+                    .get()
+                    .name()
+                    .ok_or(IntegrateError::CreatedBranchMissingName)
+                    .map_err(|e| IntegrateError::GetPatchBranchNameFailed(e.into()))?
+                    .to_string(),
+                patch_branch_oid: remote_branch_oid,
+            });
+        } else {
+            // verify isolation
+            if config.integrate.verify_isolation {
+                verify_isolation::verify_isolation(start_patch_index, end_patch_index)?;
+            }
 
-        if upstream_branch_info_ref.unwrap().commit_count != patch_series_indexes.len() {
-            return Err(IntegrateError::CommitCountMissmatch(
-                patch_series_indexes.len(),
-                upstream_branch_info_ref.unwrap().commit_count,
-            ));
+            // create/replace the request review branch
+            let (patch_branch, new_commit_oid) = ps::private::branch::branch(
+                &repo,
+                start_patch_index,
+                end_patch_index,
+                given_branch_name_option.clone(),
+                None,
+            )?;
+
+            entries.push(IntegrateEntry {
+                patch_branch_name: patch_branch
+                    .name()
+                    .map_err(|e| IntegrateError::GetPatchBranchNameFailed(e.into()))?
+                    .ok_or(IntegrateError::CreatedBranchMissingName)?
+                    .to_string(),
+                patch_branch_ref_name: patch_branch.get().name().unwrap().to_string(),
+                patch_branch_oid: new_commit_oid,
+            });
         }
+    }
 
-        // get commits from patch stack for the patch series
-        let patch_series_commits: Vec<git2::Commit> = patch_series_indexes
-            .iter()
-            .map(|i| patches_vec.get(*i).unwrap())
-            .map(|pi| repo.find_commit(pi.oid).unwrap())
-            .collect();
-
-        // check to make sure patches match between stack & remote
-        for (idx, patch_series_commit) in patch_series_commits.iter().enumerate() {
-            let remote_patch_info = upstream_branch_info_ref.unwrap().patches.get(idx).unwrap();
-
-            match ps::commit_ps_id(patch_series_commit) {
-                None => return Err(IntegrateError::MissingPatchId),
-                Some(patch_id) => {
-                    if patch_id != remote_patch_info.patch_id {
-                        return Err(IntegrateError::PatchAndRemotePatchIdMissmatch(
-                            start_patch_index + idx,
-                        ));
-                    }
+    // the remote request review branches on the patch stacks remote are deleted in the same
+    // atomic push, the ones on other remotes afterwards
+    let delete_branches = !keep_branch && !config.integrate.keep_branch;
+    let mut atomically_deleted_branch_names: Vec<String> = vec![];
+    let mut remote_branches_to_delete: Vec<(String, String)> = vec![];
+    if delete_branches {
+        for entry in entries.iter() {
+            if let Some((remote_name, branch_name)) = remote_rr_branch(
+                &repo,
+                &entry.patch_branch_name,
+                &entry.patch_branch_ref_name,
+            )? {
+                if remote_name == cur_patch_stack_remote_name_str {
+                    atomically_deleted_branch_names.push(branch_name);
+                } else {
+                    remote_branches_to_delete.push((remote_name, branch_name));
                 }
             }
+        }
+    }
+
+    let mut attempt: u32 = 1;
+    let integrated_oids = loop {
+        let upstream_oid = repo
+            .refname_to_id(&cur_patch_stack_upstream_branch_name)
+            .map_err(|_| IntegrateError::GetUpstreamBranchNameFailed)?;
 
-            match git::commit_diff_patch_id(&repo, patch_series_commit) {
-                Ok(patch_diff_hash) => {
-                    if patch_diff_hash != remote_patch_info.commit_diff_id {
-                        return Err(IntegrateError::PatchDiffHashMissmatch(
-                            start_patch_index + idx,
-                        ));
+        match combine_and_push(
+            &repo,
+            &git_config,
+            &config,
+            &entries,
+            upstream_oid,
+            &cur_patch_stack_upstream_branch_shorthand,
+            cur_patch_stack_remote_name_str,
+            &atomically_deleted_branch_names,
+            sign_push || config.integrate.sign_push,
+            tag || config.integrate.tag,
+        ) {
+            Ok(integrated_oids) => break integrated_oids,
+            Err(IntegrateError::PushFailed(ref e))
+                if retry
+                    && attempt < config.integrate.retry_max_attempts
+                    && push_rejected(e.as_ref()) =>
+            {
+                let delay = config
+                    .integrate
+                    .retry_backoff
+                    .saturating_mul(2u64.saturating_pow(attempt - 1));
+                attempt += 1;
+                events::emit(Event::PushRetryScheduled {
+                    upstream: cur_patch_stack_upstream_branch_shorthand.clone(),
+                    delay_secs: delay,
+                    attempt,
+                    max_attempts: config.integrate.retry_max_attempts,
+                });
+                std::thread::sleep(Duration::from_secs(delay));
+
+                git::ext_fetch().map_err(|e| IntegrateError::FetchFailed(e.into()))?;
+                let upstream_oid = repo
+                    .refname_to_id(&cur_patch_stack_upstream_branch_name)
+                    .map_err(|_| IntegrateError::GetUpstreamBranchNameFailed)?;
+                for entry in entries.iter_mut() {
+                    rebuild_if_stale(
+                        &repo,
+                        &git_config,
+                        &config,
+                        entry,
+                        upstream_oid,
+                        cur_patch_stack_remote_name_str,
+                    )?;
+                    if !force {
+                        let verify_hook_check = integrate_check::check_verify_hook(
+                            &check_context,
+                            &entry.patch_branch_name,
+                        );
+                        if verify_hook_check.status == integrate_check::IntegrateCheckStatus::Failed
+                        {
+                            return Err(IntegrateError::CheckFailed(verify_hook_check));
+                        }
                     }
                 }
-                Err(_) => {
-                    return Err(IntegrateError::PatchMissingDiffHash);
-                }
             }
+            Err(e) => return Err(e),
         }
+    };
+    for branch_name in atomically_deleted_branch_names.iter() {
+        events::emit(Event::BranchDeleted {
+            name: branch_name.clone(),
+            remote: Some(cur_patch_stack_remote_name_str.to_string()),
+        });
+    }
 
-        // execute hook to verify PR approval & CI status
-        let cur_patch_stack_branch_name =
-            git::get_current_branch(&repo).ok_or(IntegrateError::CurrentBranchNameMissing)?;
-        let cur_patch_stack_upstream_branch_name =
-            git::branch_upstream_name(&repo, cur_patch_stack_branch_name.as_str())
-                .map_err(|_| IntegrateError::GetUpstreamBranchNameFailed)?;
-        let cur_patch_stack_remote_name = repo
-            .branch_remote_name(&cur_patch_stack_upstream_branch_name)
-            .map_err(|_| IntegrateError::GetRemoteNameFailed)?;
-        let cur_patch_stack_upstream_branch_remote_name_str = cur_patch_stack_remote_name
-            .as_str()
-            .ok_or(IntegrateError::ConvertStringToStrFailed)?;
-        let cur_patch_stack_upstream_branch_remote = repo
-            .find_remote(cur_patch_stack_upstream_branch_remote_name_str)
-            .map_err(|e| IntegrateError::FindRemoteFailed(e.into()))?;
-        let cur_patch_stack_upstream_branch_remote_url_str = cur_patch_stack_upstream_branch_remote
-            .url()
-            .ok_or(IntegrateError::RemoteUrlNotUtf8)?;
-
-        let pattern = format!(
-            "refs/remotes/{}/",
-            cur_patch_stack_upstream_branch_remote_name_str
-        );
-        let cur_patch_stack_upstream_branch_name_relative_to_remote =
-            str::replace(&cur_patch_stack_upstream_branch_name, pattern.as_str(), "");
-
-        match hooks::find_hook(repo_root_str, repo_gitdir_str, "integrate_verify") {
-            Ok(hook_path) => utils::execute(
-                hook_path.to_str().ok_or(IntegrateError::PathNotUtf8)?,
-                &[
-                    &patch_associated_branch_name,
-                    &cur_patch_stack_upstream_branch_name_relative_to_remote,
-                    cur_patch_stack_upstream_branch_remote_name_str,
-                    cur_patch_stack_upstream_branch_remote_url_str,
-                ],
-            )
-            .map_err(|e| IntegrateError::VerifyHookExecutionFailed(e.into()))?,
-            Err(hooks::FindHookError::NotFound) => {}
-            Err(hooks::FindHookError::NotExecutable(hook_path)) => {
-                integrate_verify_hook_not_executable(
-                    color,
-                    hook_path.to_str().unwrap_or("unknow path"),
+    // execute the integrate_post_push hook
+    match hooks::find_hooks(
+        repo_root_str,
+        repo_gitdir_str,
+        &config.hooks,
+        &hooks::INTEGRATE_POST_PUSH_HOOK,
+    ) {
+        Ok(found_hooks) => {
+            for integrated_oid in integrated_oids.iter() {
+                hooks::execute_hooks(
+                    repo_gitdir_str,
+                    &config.hooks,
+                    &hooks::INTEGRATE_POST_PUSH_HOOK,
+                    &found_hooks,
+                    &[&integrated_oid.to_string()],
                 )
+                .map_err(|e| IntegrateError::HookExecutionFailed(e.into()))?;
             }
-            Err(e) => return Err(IntegrateError::HookNotFound(e.into())),
         }
-
-        // verify isolation
-        if config.integrate.verify_isolation {
-            verify_isolation::verify_isolation(start_patch_index, end_patch_index, color)?;
+        Err(hooks::FindHookError::NotFound) => {}
+        Err(hooks::FindHookError::NotExecutable(hook_path)) => {
+            events::emit(Event::HookNotExecutable {
+                name: hooks::INTEGRATE_POST_PUSH_HOOK.name.to_string(),
+                path: hook_path,
+            })
         }
+        Err(e) => return Err(IntegrateError::HookNotFound(e.into())),
+    }
 
-        // since we are NOT recreating/updating the branch from the patch stack anymore here
-        // we need to get the associated branch information from the computed state
-        let associated_branch = repo
-            .find_branch(&patch_associated_branch_name, git2::BranchType::Local)
-            .map_err(|e| IntegrateError::UnhandledError(e.into()))?;
-
-        // publish the patch(es) from remote patch branch up to patch stack upstream
-        patch_branch_name = associated_branch
-            .name()
-            .map_err(|e| IntegrateError::GetPatchBranchNameFailed(e.into()))?
-            .ok_or(IntegrateError::CreatedBranchMissingName)?
-            .to_string();
-        patch_branch_ref_name = associated_branch
-            .get()
-            .name()
-            .ok_or(IntegrateError::CreatedBranchMissingName)
-            .map_err(|e| IntegrateError::GetPatchBranchNameFailed(e.into()))?
-            .to_string();
-
-        let cur_patch_stack_branch_name =
-            git::get_current_branch(&repo).ok_or(IntegrateError::CurrentBranchNameMissing)?;
-        let cur_patch_stack_branch_upstream_name =
-            git::branch_upstream_name(&repo, cur_patch_stack_branch_name.as_str())
-                .map_err(|_| IntegrateError::GetUpstreamBranchNameFailed)?;
-        let cur_patch_stack_remote_name = repo
-            .branch_remote_name(&cur_patch_stack_branch_upstream_name)
-            .map_err(|_| IntegrateError::GetRemoteNameFailed)?;
-        let cur_patch_stack_remote_name_str = cur_patch_stack_remote_name
-            .as_str()
-            .ok_or(IntegrateError::ConvertStringToStrFailed)?;
-
-        let pattern = format!("refs/remotes/{}/", cur_patch_stack_remote_name_str);
-        let cur_patch_stack_upstream_branch_shorthand =
-            str::replace(&cur_patch_stack_branch_upstream_name, pattern.as_str(), "");
-
-        let patch_series_remote_branch = str::replace(
-            &upstream_branch_info_ref.unwrap().reference,
-            "refs/remotes/",
-            "",
-        );
-
-        // publish the patch from the remote patch branch up to the patch stack uptstream
-        // e.g. git push origin ps/rr/whatever-branch:main
-        // e.g. git push origin origin/ps/rr/whatever-branch:main
-        git::ext_push(
-            false,
-            cur_patch_stack_remote_name_str,            // origin
-            &patch_series_remote_branch,                // origin/ps/rr/whatever-branch
-            &cur_patch_stack_upstream_branch_shorthand, // main
-        )
-        .map_err(|e| IntegrateError::PushFailed(e.into()))?;
-
-        // get top most commit of the remote branch
-        let upstream_branch = repo
-            .find_branch(
-                &upstream_branch_info_ref.unwrap().name,
-                git2::BranchType::Remote,
-            )
-            .map_err(|e| IntegrateError::UnhandledError(e.into()))?;
-        let upstream_branch_head_commit: git2::Commit = upstream_branch
-            .get()
-            .peel_to_commit()
-            .map_err(|e| IntegrateError::UnhandledError(e.into()))?;
-        let upstream_branch_head_commit_oid_string: String =
-            upstream_branch_head_commit.id().to_string();
-
-        // execute the integrate_post_push hook
-        match hooks::find_hook(repo_root_str, repo_gitdir_str, "integrate_post_push") {
-            Ok(hook_path) => utils::execute(
-                hook_path.to_str().ok_or(IntegrateError::PathNotUtf8)?,
-                &[&upstream_branch_head_commit_oid_string],
-            )
-            .map_err(|e| IntegrateError::HookExecutionFailed(e.into()))?,
-            Err(hooks::FindHookError::NotFound) => {}
-            Err(hooks::FindHookError::NotExecutable(hook_path)) => {
-                integrate_post_push_hook_not_executable(
-                    color,
-                    hook_path.to_str().unwrap_or("unknow path"),
-                )
-            }
-            Err(e) => return Err(IntegrateError::HookNotFound(e.into())),
-        }
-    } else {
-        // verify isolation
-        if config.integrate.verify_isolation {
-            verify_isolation::verify_isolation(start_patch_index, end_patch_index, color)?;
+    //  delete local & remaining remote rr branches (based on command line option)
+    if delete_branches {
+        for (remote_name, branch_name) in remote_branches_to_delete.iter() {
+            git::ext_delete_remote_branch(remote_name, branch_name)
+                .map_err(|e| IntegrateError::DeleteRemoteBranchFailed(e.into()))?;
         }
 
-        // create/replace the request review branch
-        let (patch_branch, new_commit_oid) = ps::private::branch::branch(
-            &repo,
-            start_patch_index,
-            end_patch_index,
-            given_branch_name_option,
-        )?;
-
-        // publish the patch(es) from local patch branch up to patch stack upstream
-        patch_branch_name = patch_branch
-            .name()
-            .map_err(|e| IntegrateError::GetPatchBranchNameFailed(e.into()))?
-            .ok_or(IntegrateError::CreatedBranchMissingName)?
-            .to_string();
-        patch_branch_ref_name = patch_branch.get().name().unwrap().to_string();
-
-        let cur_patch_stack_branch_name =
-            git::get_current_branch(&repo).ok_or(IntegrateError::CurrentBranchNameMissing)?;
-        let cur_patch_stack_branch_upstream_name =
-            git::branch_upstream_name(&repo, cur_patch_stack_branch_name.as_str())
-                .map_err(|_| IntegrateError::GetUpstreamBranchNameFailed)?;
-        let cur_patch_stack_remote_name = repo
-            .branch_remote_name(&cur_patch_stack_branch_upstream_name)
-            .map_err(|_| IntegrateError::GetRemoteNameFailed)?;
-        let cur_patch_stack_remote_name_str = cur_patch_stack_remote_name
-            .as_str()
-            .ok_or(IntegrateError::ConvertStringToStrFailed)?;
-
-        let pattern = format!("refs/remotes/{}/", cur_patch_stack_remote_name_str);
-        let cur_patch_stack_upstream_branch_shorthand =
-            str::replace(&cur_patch_stack_branch_upstream_name, pattern.as_str(), "");
-
-        // publish the patch from the local patch branch up to the patch stack uptstream
-        // e.g. git push origin ps/rr/whatever-branch:main
-        git::ext_push(
-            false,
-            cur_patch_stack_remote_name_str,
-            &patch_branch_name,
-            &cur_patch_stack_upstream_branch_shorthand,
-        )
-        .map_err(|e| IntegrateError::PushFailed(e.into()))?;
-
-        // execute the integrate_post_push hook
-        match hooks::find_hook(repo_root_str, repo_gitdir_str, "integrate_post_push") {
-            Ok(hook_path) => utils::execute(
-                hook_path.to_str().ok_or(IntegrateError::PathNotUtf8)?,
-                &[&format!("{}", new_commit_oid)],
-            )
-            .map_err(|e| IntegrateError::HookExecutionFailed(e.into()))?,
-            Err(hooks::FindHookError::NotFound) => {}
-            Err(hooks::FindHookError::NotExecutable(hook_path)) => {
-                integrate_post_push_hook_not_executable(
-                    color,
-                    hook_path.to_str().unwrap_or("unknow path"),
-                )
-            }
-            Err(e) => return Err(IntegrateError::HookNotFound(e.into())),
+        for entry in entries.iter() {
+            // TODO: this branch name should be the same as the one that was given
+            repo.find_branch(&entry.patch_branch_name, git2::BranchType::Local)
+                .map_err(|e| IntegrateError::FindPatchBranchFailed(e.into()))?
+                .delete()
+                .map_err(|e| IntegrateError::DeleteLocalBranchFailed(e.into()))?;
+            events::emit(Event::BranchDeleted {
+                name: entry.patch_branch_name.clone(),
+                remote: None,
+            });
         }
     }
 
-    //  delete local & remote rr branch (based on command line option)
-    if !keep_branch {
-        // TODO: this branch name should be the same as the one that was given
-        let mut local_branch = repo
-            .find_branch(&patch_branch_name, git2::BranchType::Local)
-            .map_err(|e| IntegrateError::FindPatchBranchFailed(e.into()))?;
-
-        // if we have a remote tracking branch, delete it
-        if let Ok(remote_branch) = local_branch.upstream() {
-            let remote_branch_remote = repo
-                .branch_upstream_remote(&patch_branch_ref_name)
-                .map_err(|e| IntegrateError::GetBranchUpstreamRemoteFailed(e.into()))?;
-            let remote_branch_remote_str = remote_branch_remote
-                .as_str()
-                .ok_or(IntegrateError::BranchUpstreamRemoteNotValidUtf8)?;
-
-            let remote_branch_name = remote_branch
-                .name()
-                .map_err(|e| IntegrateError::GetPatchBranchNameFailed(e.into()))?
-                .ok_or(IntegrateError::RemoteRrBranchNameMissing)?;
-
-            let pattern = format!("{}/", remote_branch_remote_str);
-            let patch_associated_upstream_branch_name_relative_to_remote =
-                str::replace(remote_branch_name, &pattern, "");
-
-            git::ext_delete_remote_branch(
-                remote_branch_remote_str,
-                &patch_associated_upstream_branch_name_relative_to_remote,
-            )
-            .map_err(|e| IntegrateError::DeleteRemoteBranchFailed(e.into()))?;
-        }
-
-        // now that we have deleted the remote, lets delete the local
-        local_branch
-            .delete()
-            .map_err(|e| IntegrateError::DeleteLocalBranchFailed(e.into()))?;
+    for entry in entries.iter() {
+        hooks::find_and_execute_hook(
+            repo_root_str,
+            repo_gitdir_str,
+            &config.hooks,
+            &hooks::POST_INTEGRATE_HOOK,
+            &[&entry.patch_branch_name],
+        )
+        .map_err(|e| IntegrateError::HookExecutionFailed(e.into()))?;
     }
 
     if config.integrate.pull_after_integrate {
-        pull::pull(color).map_err(|e| IntegrateError::PullFailed(e.into()))?;
+        let pull_result = pull::pull().map_err(|e| IntegrateError::PullFailed(e.into()))?;
+        return Ok(Some(pull_result));
     }
 
-    Ok(())
+    Ok(None)
 }
 
-#[derive(Debug)]
-pub enum GetVerificationError {
-    ReadLineFailed(std::io::Error),
-    UserRejected(String),
-}
-
-impl std::fmt::Display for GetVerificationError {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::UserRejected(s) => write!(f, "user rejected, {}", s),
-            Self::ReadLineFailed(e) => write!(f, "read line failed, {}", e),
+/// Combine the patch branches on top of the upstream according to the integrate strategy &
+/// publish the combination up to the patch stack upstream, along with a signed tag per patch
+/// branch when `tag`, deleting the given remote branches in the same atomic push, returning the
+/// commit each patch branch got integrated as
+#[allow(clippy::too_many_arguments)]
+fn combine_and_push(
+    repo: &git2::Repository,
+    git_config: &git2::Config,
+    config: &config::PsConfig,
+    entries: &[IntegrateEntry],
+    upstream_oid: git2::Oid,
+    upstream_branch_shorthand: &str,
+    remote_name: &str,
+    deleted_branch_names: &[String],
+    sign_push: bool,
+    tag: bool,
+) -> Result<Vec<git2::Oid>, IntegrateError> {
+    let mut integration_branch =
+        integrate_strategy::IntegrationBranch::create(repo, upstream_oid, config.rewrite)
+            .map_err(|e| IntegrateError::PrepareIntegrationFailed(e.into()))?;
+    let mut integrated_oids: Vec<git2::Oid> = vec![];
+    for entry in entries.iter() {
+        match integration_branch.integrate(
+            repo,
+            git_config,
+            config.integrate.strategy,
+            config.integrate.message_template.as_deref(),
+            &entry.patch_branch_name,
+            entry.patch_branch_oid,
+            upstream_branch_shorthand,
+        ) {
+            Ok(oid) => integrated_oids.push(oid),
+            Err(e) => {
+                integration_branch
+                    .delete(repo)
+                    .map_err(|e| IntegrateError::DeleteIntegrationBranchFailed(e.into()))?;
+                return Err(IntegrateError::PrepareIntegrationFailed(e.into()));
+            }
         }
     }
-}
 
-impl std::error::Error for GetVerificationError {
-    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
-        match self {
-            Self::ReadLineFailed(e) => Some(e),
-            Self::UserRejected(_) => None,
+    let mut tag_names: Vec<String> = vec![];
+    if tag {
+        for (entry, integrated_oid) in entries.iter().zip(integrated_oids.iter()) {
+            match integration_branch.tag(
+                repo,
+                git_config,
+                &config.integrate.tag_name_template,
+                &entry.patch_branch_name,
+                entry.patch_branch_oid,
+                *integrated_oid,
+                upstream_branch_shorthand,
+            ) {
+                Ok(tag_name) => tag_names.push(tag_name),
+                Err(e) => {
+                    delete_tags(repo, &tag_names);
+                    integration_branch
+                        .delete(repo)
+                        .map_err(|e| IntegrateError::DeleteIntegrationBranchFailed(e.into()))?;
+                    return Err(IntegrateError::TagFailed(e.into()));
+                }
+            }
         }
     }
-}
 
-fn get_verification() -> Result<(), GetVerificationError> {
-    let mut answer = String::new();
-    println!("\n\nAre you sure you want to integrate this patch? (y/N)");
-    std::io::stdin()
-        .read_line(&mut answer)
-        .map_err(GetVerificationError::ReadLineFailed)?;
-    let normalized_answer = answer.to_lowercase().trim().to_string();
-    if normalized_answer == "yes" || normalized_answer == "y" {
-        Ok(())
-    } else {
-        Err(GetVerificationError::UserRejected(normalized_answer))
+    // e.g. git push --atomic origin ps/tmp/integrate:main refs/tags/ps/integrated/<ps-id>:refs/tags/ps/integrated/<ps-id> :ps/rr/whatever-branch
+    let push_result = ps::private::push::push_atomic(
+        repo,
+        remote_name,
+        integration_branch.branch_name(),
+        upstream_branch_shorthand,
+        &tag_names,
+        deleted_branch_names,
+        sign_push,
+    );
+    integration_branch
+        .delete(repo)
+        .map_err(|e| IntegrateError::DeleteIntegrationBranchFailed(e.into()))?;
+    if push_result.is_err() {
+        delete_tags(repo, &tag_names);
     }
-}
-
-fn integrate_post_push_hook_not_executable(color: bool, hook_path: &str) {
-    let msg = format!(
-        r#"
-The integrate_post_push hook was found at
+    push_result.map_err(|e| IntegrateError::PushFailed(e.into()))?;
 
-  {}
+    Ok(integrated_oids)
+}
 
-but it is NOT executable. Due to this the hook is being skipped. Generally
-this can be corrected with the following.
+/// Delete the given local tags, e.g. of an integration that didn't get pushed, as they'd point to
+/// commits that never landed
+fn delete_tags(repo: &git2::Repository, tag_names: &[String]) {
+    for tag_name in tag_names.iter() {
+        let _ = repo.tag_delete(tag_name);
+    }
+}
 
-  chmod u+x {}
-"#,
-        hook_path, hook_path
-    );
-    utils::print_warn(color, &msg);
+/// Whether the push was rejected because the remote moved, e.g. someone pushed to the upstream
+/// in the meantime, which retrying can resolve, rather than failing for any other reason
+fn push_rejected(e: &(dyn std::error::Error + 'static)) -> bool {
+    match e.downcast_ref::<ps::private::push::PushError>() {
+        Some(ps::private::push::PushError::PushAtomicFailed(e)) => e.remote_moved(),
+        _ => false,
+    }
 }
 
-fn integrate_verify_hook_not_executable(color: bool, hook_path: &str) {
-    let msg = format!(
-        r#"
-The integrate_verify hook was found at
+/// Rebuild the patch branch on top of the upstream when the fast-forward strategy can no longer
+/// integrate it as is, updating its remote branch so what lands is what is verified
+fn rebuild_if_stale(
+    repo: &git2::Repository,
+    git_config: &git2::Config,
+    config: &config::PsConfig,
+    entry: &mut IntegrateEntry,
+    upstream_oid: git2::Oid,
+    remote_name: &str,
+) -> Result<(), IntegrateError> {
+    let rebuild_failed = |e: Box<dyn std::error::Error>| {
+        IntegrateError::RebuildBranchFailed(entry.patch_branch_name.clone(), e)
+    };
+
+    if config.integrate.strategy != config::integrate::IntegrateStrategy::FastForward
+        || entry.patch_branch_oid == upstream_oid
+        || repo
+            .graph_descendant_of(entry.patch_branch_oid, upstream_oid)
+            .map_err(|e| rebuild_failed(e.into()))?
+    {
+        return Ok(());
+    }
 
-  {}
+    let base_oid = repo
+        .merge_base(entry.patch_branch_oid, upstream_oid)
+        .map_err(|e| rebuild_failed(e.into()))?;
+    let rebase = in_memory_rebase::rebase_in_memory(
+        repo,
+        git_config,
+        &config.rewrite,
+        base_oid,
+        entry.patch_branch_oid,
+        upstream_oid,
+    )
+    .map_err(|e| rebuild_failed(e.into()))?;
+    if rebase.stopped_at.is_some() {
+        return Err(IntegrateError::RebuildBranchConflicts(
+            entry.patch_branch_name.clone(),
+        ));
+    }
 
-but it is NOT executable. Due to this the hook is being skipped. Generally
-this can be corrected with the following.
+    let reference = repo
+        .find_reference(&entry.patch_branch_ref_name)
+        .and_then(|mut r| r.set_target(rebase.head_oid, "rebuild on upstream"))
+        .map_err(|e| rebuild_failed(e.into()))?;
+    sync::push_patch_branch(repo, &mut git2::Branch::wrap(reference), remote_name)
+        .map_err(|e| rebuild_failed(e.into()))?;
+    events::emit(Event::BranchRebuilt {
+        name: entry.patch_branch_name.clone(),
+        new_oid: rebase.head_oid,
+    });
+
+    entry.patch_branch_oid = rebase.head_oid;
+    Ok(())
+}
 
-  chmod u+x {}
-"#,
-        hook_path, hook_path
-    );
-    utils::print_warn(color, &msg);
+/// Get the remote & the name relative to it of the remote tracking branch of the local request
+/// review branch, if it has one
+fn remote_rr_branch(
+    repo: &git2::Repository,
+    patch_branch_name: &str,
+    patch_branch_ref_name: &str,
+) -> Result<Option<(String, String)>, IntegrateError> {
+    let local_branch = repo
+        .find_branch(patch_branch_name, git2::BranchType::Local)
+        .map_err(|e| IntegrateError::FindPatchBranchFailed(e.into()))?;
+    let remote_branch = match local_branch.upstream() {
+        Ok(remote_branch) => remote_branch,
+        Err(_) => return Ok(None),
+    };
+
+    let remote_branch_remote = repo
+        .branch_upstream_remote(patch_branch_ref_name)
+        .map_err(|e| IntegrateError::GetBranchUpstreamRemoteFailed(e.into()))?;
+    let remote_branch_remote_str = remote_branch_remote
+        .as_str()
+        .ok_or(IntegrateError::BranchUpstreamRemoteNotValidUtf8)?;
+
+    let remote_branch_name = remote_branch
+        .name()
+        .map_err(|e| IntegrateError::GetPatchBranchNameFailed(e.into()))?
+        .ok_or(IntegrateError::RemoteRrBranchNameMissing)?;
+
+    let pattern = format!("{}/", remote_branch_remote_str);
+    let patch_associated_upstream_branch_name_relative_to_remote =
+        str::replace(remote_branch_name, &pattern, "");
+
+    Ok(Some((
+        remote_branch_remote_str.to_string(),
+        patch_associated_upstream_branch_name_relative_to_remote,
+    )))
 }
@@ -0,0 +1,1050 @@
+// This module runs every safety check `integrate` runs before it publishes, without publishing,
+// so that people & CI bots can find out whether, & if not why, patches can be integrated. Rather
+// than stopping at the first failed check it records the outcome of each one in a report, skipping
+// the ones that depend on a check that failed. `integrate` runs the same checks, stopping at the
+// first one that fails.
+
+use super::super::super::ps;
+use super::super::private::config;
+use super::super::private::events::{self, Event};
+use super::super::private::git;
+use super::super::private::hooks;
+use super::super::private::integrate_strategy;
+use super::super::private::paths;
+use super::super::private::rr_branch_name;
+use super::super::private::signatures;
+use super::super::private::state_computation;
+use super::verify_isolation;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub enum IntegrateCheckError {
+    RepositoryNotFound,
+    GetPatchStackFailed(Box<dyn std::error::Error>),
+    GetPatchListFailed(Box<dyn std::error::Error>),
+    PatchIndexRangeOutOfBounds(Box<dyn std::error::Error>),
+    GetRepoRootPathFailed(Box<dyn std::error::Error>),
+    PathNotUtf8,
+    GetConfigFailed(Box<dyn std::error::Error>),
+    RrBranchNamingInvalid(Box<dyn std::error::Error>),
+    FetchFailed(Box<dyn std::error::Error>),
+    PatchStackBaseNotFound,
+    PatchStackHeadNoName,
+    GetListPatchInfoFailed(Box<dyn std::error::Error>),
+    CurrentBranchNameMissing,
+    GetUpstreamBranchNameFailed,
+    GetRemoteNameFailed,
+    FindRemoteFailed(Box<dyn std::error::Error>),
+    RemoteUrlNotUtf8,
+    FindPatchCommitFailed(Box<dyn std::error::Error>),
+    GetRemotePatchesFailed(Box<dyn std::error::Error>),
+    DiffPatchesFailed(Box<dyn std::error::Error>),
+    VerifySignaturesFailed(Box<dyn std::error::Error>),
+    PrepareIntegrationFailed(Box<dyn std::error::Error>),
+}
+
+impl std::fmt::Display for IntegrateCheckError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RepositoryNotFound => write!(f, "repository not found"),
+            Self::GetPatchStackFailed(e) => write!(f, "get patch stack failed, {}", e),
+            Self::GetPatchListFailed(e) => write!(f, "get patch list failed, {}", e),
+            Self::PatchIndexRangeOutOfBounds(e) => {
+                write!(f, "patch index range out of bounds, {}", e)
+            }
+            Self::GetRepoRootPathFailed(e) => write!(f, "get repository root path failed, {}", e),
+            Self::PathNotUtf8 => write!(f, "path not utf-8"),
+            Self::GetConfigFailed(e) => write!(f, "get config failed, {}", e),
+            Self::RrBranchNamingInvalid(e) => {
+                write!(f, "request review branch naming invalid, {}", e)
+            }
+            Self::FetchFailed(e) => write!(f, "fetch failed, {}", e),
+            Self::PatchStackBaseNotFound => write!(f, "patch stack base not found"),
+            Self::PatchStackHeadNoName => write!(f, "patch stack head has no name"),
+            Self::GetListPatchInfoFailed(e) => write!(f, "get list patch info failed, {}", e),
+            Self::CurrentBranchNameMissing => write!(f, "current branch name missing"),
+            Self::GetUpstreamBranchNameFailed => write!(f, "get upstream branch name failed"),
+            Self::GetRemoteNameFailed => write!(f, "get remote name failed"),
+            Self::FindRemoteFailed(e) => write!(f, "find remote failed, {}", e),
+            Self::RemoteUrlNotUtf8 => write!(f, "remote url not utf-8"),
+            Self::FindPatchCommitFailed(e) => write!(f, "find patch commit failed, {}", e),
+            Self::GetRemotePatchesFailed(e) => write!(f, "get remote patches failed, {}", e),
+            Self::DiffPatchesFailed(e) => write!(f, "diff patches failed, {}", e),
+            Self::VerifySignaturesFailed(e) => write!(f, "verify signatures failed, {}", e),
+            Self::PrepareIntegrationFailed(e) => write!(f, "prepare integration failed, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for IntegrateCheckError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::RepositoryNotFound => None,
+            Self::GetPatchStackFailed(e) => Some(e.as_ref()),
+            Self::GetPatchListFailed(e) => Some(e.as_ref()),
+            Self::PatchIndexRangeOutOfBounds(e) => Some(e.as_ref()),
+            Self::GetRepoRootPathFailed(e) => Some(e.as_ref()),
+            Self::PathNotUtf8 => None,
+            Self::GetConfigFailed(e) => Some(e.as_ref()),
+            Self::RrBranchNamingInvalid(e) => Some(e.as_ref()),
+            Self::FetchFailed(e) => Some(e.as_ref()),
+            Self::PatchStackBaseNotFound => None,
+            Self::PatchStackHeadNoName => None,
+            Self::GetListPatchInfoFailed(e) => Some(e.as_ref()),
+            Self::CurrentBranchNameMissing => None,
+            Self::GetUpstreamBranchNameFailed => None,
+            Self::GetRemoteNameFailed => None,
+            Self::FindRemoteFailed(e) => Some(e.as_ref()),
+            Self::RemoteUrlNotUtf8 => None,
+            Self::FindPatchCommitFailed(e) => Some(e.as_ref()),
+            Self::GetRemotePatchesFailed(e) => Some(e.as_ref()),
+            Self::DiffPatchesFailed(e) => Some(e.as_ref()),
+            Self::VerifySignaturesFailed(e) => Some(e.as_ref()),
+            Self::PrepareIntegrationFailed(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegrateCheckStatus {
+    Passed,
+    Failed,
+    /// Not run, because a check it depends on failed or it isn't enabled
+    Skipped,
+}
+
+/// The safety checks integrate runs, in the order it runs them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegrateCheckKind {
+    AssociatedBranch,
+    Pushed,
+    CommitCount,
+    PatchIds,
+    PatchDiffs,
+    Signatures,
+    VerifyHook,
+    Isolation,
+    Integrates,
+}
+
+impl IntegrateCheckKind {
+    /// All of the checks, in the order they run
+    pub const ALL: [IntegrateCheckKind; 9] = [
+        Self::AssociatedBranch,
+        Self::Pushed,
+        Self::CommitCount,
+        Self::PatchIds,
+        Self::PatchDiffs,
+        Self::Signatures,
+        Self::VerifyHook,
+        Self::Isolation,
+        Self::Integrates,
+    ];
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::AssociatedBranch => "has a single associated branch",
+            Self::Pushed => "associated branch is pushed",
+            Self::CommitCount => "remote branch has the same number of commits",
+            Self::PatchIds => "patch ids match the remote branch",
+            Self::PatchDiffs => "patches match the remote branch",
+            Self::Signatures => "remote branch commits are signed by someone trusted",
+            Self::VerifyHook => "integrate_verify hook passes",
+            Self::Isolation => "patches pass in isolation",
+            Self::Integrates => "integrates into the upstream",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct IntegrateCheck {
+    pub kind: IntegrateCheckKind,
+    pub status: IntegrateCheckStatus,
+    /// Why the check passed, failed or was skipped
+    pub detail: String,
+    /// The diff between the patch on the remote branch & the local patch, for each patch that
+    /// doesn't match
+    pub diff: Option<String>,
+}
+
+impl IntegrateCheck {
+    fn new(kind: IntegrateCheckKind, status: IntegrateCheckStatus, detail: String) -> Self {
+        Self {
+            kind,
+            status,
+            detail,
+            diff: None,
+        }
+    }
+}
+
+/// The outcome of the safety checks for one patch index range
+#[derive(Debug, Clone)]
+pub struct IntegrateCheckReport {
+    pub start_patch_index: usize,
+    pub end_patch_index: Option<usize>,
+    pub branch_name: Option<String>,
+    /// The head of the remote branch the checks ran against, once it was found to be pushed
+    pub remote_branch_oid: Option<git2::Oid>,
+    pub checks: Vec<IntegrateCheck>,
+}
+
+impl IntegrateCheckReport {
+    /// The first check that failed, None when integrating the range is safe
+    pub fn first_failure(&self) -> Option<&IntegrateCheck> {
+        self.checks
+            .iter()
+            .find(|c| c.status == IntegrateCheckStatus::Failed)
+    }
+
+    fn record(&mut self, kind: IntegrateCheckKind, status: IntegrateCheckStatus, detail: String) {
+        self.checks.push(IntegrateCheck::new(kind, status, detail));
+    }
+
+    /// Record the given checks as skipped because of the check that failed
+    fn skip(&mut self, kinds: &[IntegrateCheckKind], failed_kind: IntegrateCheckKind) {
+        for kind in kinds.iter() {
+            self.record(
+                *kind,
+                IntegrateCheckStatus::Skipped,
+                format!("skipped, depends on \"{}\"", failed_kind.description()),
+            );
+        }
+    }
+
+    /// Record the checks that haven't run yet as skipped because of the check that failed
+    fn skip_rest(&mut self, failed_kind: IntegrateCheckKind) {
+        let rest: Vec<IntegrateCheckKind> = IntegrateCheckKind::ALL
+            .iter()
+            .filter(|kind| !self.checks.iter().any(|c| c.kind == **kind))
+            .copied()
+            .collect();
+        self.skip(&rest, failed_kind);
+    }
+
+    /// With `fail_fast`, when a check failed, record the checks that haven't run yet as skipped &
+    /// return true
+    fn stop_at_failure(&mut self, fail_fast: bool) -> bool {
+        match self.first_failure().map(|c| c.kind) {
+            Some(failed_kind) if fail_fast => {
+                self.skip_rest(failed_kind);
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Run the safety checks `integrate` runs for each of the given patch index ranges,
+/// `(start_patch_index, end_patch_index)`, without publishing anything, returning a report of what
+/// passed & failed for each of them.
+pub fn check(
+    patch_index_ranges: &[(usize, Option<usize>)],
+    given_branch_name_option: Option<String>,
+) -> Result<Vec<IntegrateCheckReport>, IntegrateCheckError> {
+    let repo = git::create_cwd_repo().map_err(|_| IntegrateCheckError::RepositoryNotFound)?;
+
+    let patch_stack = ps::get_patch_stack(&repo)
+        .map_err(|e| IntegrateCheckError::GetPatchStackFailed(e.into()))?;
+    let patches_vec = ps::get_patch_list(&repo, &patch_stack)
+        .map_err(|e| IntegrateCheckError::GetPatchListFailed(e.into()))?;
+
+    for (start_patch_index, end_patch_index) in patch_index_ranges.iter() {
+        ps::patch_range_within_stack_bounds(*start_patch_index, *end_patch_index, &patches_vec)
+            .map_err(|e| IntegrateCheckError::PatchIndexRangeOutOfBounds(e.into()))?;
+    }
+
+    let repo_root_path = paths::repo_root_path(&repo)
+        .map_err(|e| IntegrateCheckError::GetRepoRootPathFailed(e.into()))?;
+    let repo_root_str = repo_root_path
+        .to_str()
+        .ok_or(IntegrateCheckError::PathNotUtf8)?;
+    let repo_gitdir_str = repo
+        .path()
+        .to_str()
+        .ok_or(IntegrateCheckError::PathNotUtf8)?;
+
+    let config = config::get_config(&repo, repo_root_str, repo_gitdir_str)
+        .map_err(|e| IntegrateCheckError::GetConfigFailed(e.into()))?;
+    let rr_branch_naming = rr_branch_name::RrBranchNaming::new(&config.request_review)
+        .map_err(|e| IntegrateCheckError::RrBranchNamingInvalid(e.into()))?;
+
+    git::ext_fetch().map_err(|e| IntegrateCheckError::FetchFailed(e.into()))?;
+
+    let patch_stack_base_oid = patch_stack
+        .base
+        .peel_to_commit()
+        .map_err(|_| IntegrateCheckError::PatchStackBaseNotFound)?
+        .id();
+    let head_ref_name = patch_stack
+        .head
+        .shorthand()
+        .ok_or(IntegrateCheckError::PatchStackHeadNoName)?;
+
+    let patch_info_collection: HashMap<Uuid, state_computation::PatchGitInfo> =
+        state_computation::get_list_patch_info(
+            &repo,
+            patch_stack_base_oid,
+            head_ref_name,
+            &config.branch_scan,
+            &rr_branch_naming,
+        )
+        .map_err(|e| IntegrateCheckError::GetListPatchInfoFailed(e.into()))?;
+
+    let cur_patch_stack_branch_name =
+        git::get_current_branch(&repo).ok_or(IntegrateCheckError::CurrentBranchNameMissing)?;
+    let cur_patch_stack_upstream_branch_name =
+        git::branch_upstream_name(&repo, cur_patch_stack_branch_name.as_str())
+            .map_err(|_| IntegrateCheckError::GetUpstreamBranchNameFailed)?;
+    let cur_patch_stack_remote_name = repo
+        .branch_remote_name(&cur_patch_stack_upstream_branch_name)
+        .map_err(|_| IntegrateCheckError::GetRemoteNameFailed)?;
+    let cur_patch_stack_remote_name_str = cur_patch_stack_remote_name
+        .as_str()
+        .ok_or(IntegrateCheckError::GetRemoteNameFailed)?;
+    let cur_patch_stack_remote = repo
+        .find_remote(cur_patch_stack_remote_name_str)
+        .map_err(|e| IntegrateCheckError::FindRemoteFailed(e.into()))?;
+    let cur_patch_stack_remote_url_str = cur_patch_stack_remote
+        .url()
+        .ok_or(IntegrateCheckError::RemoteUrlNotUtf8)?;
+
+    let pattern = format!("refs/remotes/{}/", cur_patch_stack_remote_name_str);
+    let cur_patch_stack_upstream_branch_shorthand =
+        str::replace(&cur_patch_stack_upstream_branch_name, pattern.as_str(), "");
+    let upstream_oid = repo
+        .refname_to_id(&cur_patch_stack_upstream_branch_name)
+        .map_err(|_| IntegrateCheckError::GetUpstreamBranchNameFailed)?;
+
+    let context = CheckContext {
+        repo: &repo,
+        config: &config,
+        patches: &patches_vec,
+        patch_info_collection: &patch_info_collection,
+        rr_branch_naming: &rr_branch_naming,
+        patch_stack_base_oid,
+        upstream_oid,
+        upstream_branch_shorthand: &cur_patch_stack_upstream_branch_shorthand,
+        remote_name: cur_patch_stack_remote_name_str,
+        remote_url: cur_patch_stack_remote_url_str,
+        repo_root_str,
+        repo_gitdir_str,
+    };
+
+    // the ranges are combined in memory, the way integrate combines them on its temporary branch,
+    // to check that they integrate into the upstream together without touching any refs
+    let mut dry_run_integration =
+        integrate_strategy::DryRunIntegration::new(&repo, upstream_oid)
+            .map_err(|e| IntegrateCheckError::PrepareIntegrationFailed(e.into()))?;
+
+    patch_index_ranges
+        .iter()
+        .map(|(start_patch_index, end_patch_index)| {
+            check_range(
+                &context,
+                *start_patch_index,
+                *end_patch_index,
+                given_branch_name_option.as_deref(),
+                &mut dry_run_integration,
+                false,
+            )
+        })
+        .collect()
+}
+
+/// The state of the patch stack & its upstream the safety checks run against, shared by the
+/// checks of all the patch index ranges
+pub(crate) struct CheckContext<'a> {
+    pub repo: &'a git2::Repository,
+    pub config: &'a config::PsConfig,
+    pub patches: &'a [ps::ListPatch],
+    pub patch_info_collection: &'a HashMap<Uuid, state_computation::PatchGitInfo>,
+    pub rr_branch_naming: &'a rr_branch_name::RrBranchNaming,
+    pub patch_stack_base_oid: git2::Oid,
+    pub upstream_oid: git2::Oid,
+    /// The name of the upstream branch relative to its remote, e.g. main
+    pub upstream_branch_shorthand: &'a str,
+    pub remote_name: &'a str,
+    pub remote_url: &'a str,
+    pub repo_root_str: &'a str,
+    pub repo_gitdir_str: &'a str,
+}
+
+/// Run the safety checks for the patch index range in order, recording the outcome of each one in
+/// the returned report. With `fail_fast` the checks following the first failed one are skipped,
+/// the way `integrate` stops at it.
+pub(crate) fn check_range(
+    context: &CheckContext,
+    start_patch_index: usize,
+    end_patch_index: Option<usize>,
+    given_branch_name: Option<&str>,
+    dry_run_integration: &mut integrate_strategy::DryRunIntegration,
+    fail_fast: bool,
+) -> Result<IntegrateCheckReport, IntegrateCheckError> {
+    use IntegrateCheckKind::*;
+    use IntegrateCheckStatus::*;
+
+    let repo = context.repo;
+    let config = context.config;
+    let mut report = IntegrateCheckReport {
+        start_patch_index,
+        end_patch_index,
+        branch_name: None,
+        remote_branch_oid: None,
+        checks: vec![],
+    };
+
+    // figure out the associated branch, when the patches are also on other branches, e.g. a left
+    // over isolate branch, the request review branch is the one to integrate
+    let range_patch_branches = ps::patch_series_unique_branch_names(
+        repo,
+        context.patches,
+        context.patch_info_collection,
+        start_patch_index,
+        end_patch_index,
+    );
+    let range_patch_rr_branches: Vec<&String> = range_patch_branches
+        .iter()
+        .filter(|b| context.rr_branch_naming.is_rr_branch_name(b))
+        .collect();
+    let patch_associated_branch_name = match given_branch_name {
+        Some(bn) => bn.to_string(),
+        None if range_patch_branches.is_empty() => {
+            report.record(
+                AssociatedBranch,
+                Failed,
+                "the patch(es) have no associated branch, associate them with gps branch or gps request-review".to_string(),
+            );
+            report.skip_rest(AssociatedBranch);
+            return Ok(report);
+        }
+        None if range_patch_branches.len() > 1 && range_patch_rr_branches.len() != 1 => {
+            report.record(
+                AssociatedBranch,
+                Failed,
+                format!(
+                    "the patch(es) are associated with {}, specify which one with -n",
+                    range_patch_branches.join(", ")
+                ),
+            );
+            report.skip_rest(AssociatedBranch);
+            return Ok(report);
+        }
+        None if range_patch_branches.len() > 1 => range_patch_rr_branches[0].to_string(),
+        None => range_patch_branches.first().unwrap().to_string(),
+    };
+    report.branch_name = Some(patch_associated_branch_name.clone());
+    report.record(
+        AssociatedBranch,
+        Passed,
+        format!("associated with {}", patch_associated_branch_name),
+    );
+
+    let patch_series_indexes: Vec<usize> = match end_patch_index {
+        Some(end_index) => (start_patch_index..=end_index).collect(),
+        None => vec![start_patch_index],
+    };
+    let patch_series_commits: Vec<git2::Commit> = patch_series_indexes
+        .iter()
+        .map(|i| repo.find_commit(context.patches.get(*i).unwrap().oid))
+        .collect::<Result<Vec<git2::Commit>, git2::Error>>()
+        .map_err(|e| IntegrateCheckError::FindPatchCommitFailed(e.into()))?;
+
+    // verify the associated branch is pushed
+    let upstream_branch_info = patch_series_commits
+        .iter()
+        .find_map(ps::commit_ps_id)
+        .and_then(|patch_id| context.patch_info_collection.get(&patch_id))
+        .and_then(|patch_info| {
+            patch_info
+                .branches
+                .iter()
+                .find(|b| b.name == patch_associated_branch_name)
+        })
+        .and_then(|lbi| lbi.upstream.as_ref());
+    let upstream_branch_info = match upstream_branch_info {
+        Some(upstream_branch_info) => upstream_branch_info,
+        None => {
+            report.record(
+                Pushed,
+                Failed,
+                format!(
+                    "{} has not been pushed, push it with gps sync or gps request-review",
+                    patch_associated_branch_name
+                ),
+            );
+            report.skip_rest(Pushed);
+            return Ok(report);
+        }
+    };
+    report.record(
+        Pushed,
+        Passed,
+        format!("pushed to {}", upstream_branch_info.name),
+    );
+    let remote_branch_head_oid = repo
+        .find_branch(&upstream_branch_info.name, git2::BranchType::Remote)
+        .and_then(|b| b.get().peel_to_commit())
+        .map_err(|e| IntegrateCheckError::GetRemotePatchesFailed(e.into()))?
+        .id();
+    report.remote_branch_oid = Some(remote_branch_head_oid);
+
+    // check to make sure patches match between stack & remote
+    let mut local_matches_remote = true;
+    if upstream_branch_info.commit_count != patch_series_commits.len() {
+        local_matches_remote = false;
+        report.record(
+            CommitCount,
+            Failed,
+            format!(
+                "the patch series consists of {} patches but {} has {} commits",
+                patch_series_commits.len(),
+                upstream_branch_info.name,
+                upstream_branch_info.commit_count
+            ),
+        );
+        report.skip(&[PatchIds, PatchDiffs], CommitCount);
+    } else {
+        report.record(
+            CommitCount,
+            Passed,
+            format!(
+                "{} has the {} commit(s) of the patch series",
+                upstream_branch_info.name, upstream_branch_info.commit_count
+            ),
+        );
+
+        let remote_patch_commits =
+            remote_patch_commits(repo, context.patch_stack_base_oid, remote_branch_head_oid)?;
+
+        let mut id_missmatches: Vec<String> = vec![];
+        let mut diff_missmatches: Vec<String> = vec![];
+        let mut diffs: Vec<String> = vec![];
+        for (idx, patch_series_commit) in patch_series_commits.iter().enumerate() {
+            let patch_index = start_patch_index + idx;
+            let remote_patch_info = upstream_branch_info.patches.get(idx);
+            let patch_id = ps::commit_ps_id(patch_series_commit);
+            match (patch_id, remote_patch_info) {
+                (Some(patch_id), Some(remote_patch_info))
+                    if patch_id == remote_patch_info.patch_id => {}
+                (patch_id, remote_patch_info) => id_missmatches.push(format!(
+                    "patch {} has id {} but the remote patch has {}",
+                    patch_index,
+                    patch_id.map_or("none".to_string(), |id| id.to_string()),
+                    remote_patch_info.map_or("none".to_string(), |p| p.patch_id.to_string())
+                )),
+            }
+
+            let local_diff_id = git::commit_diff_patch_id(repo, patch_series_commit).ok();
+            if remote_patch_info.map(|p| p.commit_diff_id) != local_diff_id {
+                diff_missmatches.push(patch_index.to_string());
+                if let Some(remote_patch_commit) = remote_patch_commits.get(idx) {
+                    let diff =
+                        git::commit_diffs_diff(repo, remote_patch_commit, patch_series_commit)
+                            .map_err(|e| IntegrateCheckError::DiffPatchesFailed(e.into()))?;
+                    if let Some(diff) = diff {
+                        diffs.push(format!(
+                            "patch {} ({} on {} -> {} locally)\n{}",
+                            patch_index,
+                            short_sha(remote_patch_commit.id()),
+                            upstream_branch_info.name,
+                            short_sha(patch_series_commit.id()),
+                            diff
+                        ));
+                    }
+                }
+            }
+        }
+
+        if id_missmatches.is_empty() {
+            report.record(
+                PatchIds,
+                Passed,
+                "each patch has the same id as its remote patch".to_string(),
+            );
+        } else {
+            local_matches_remote = false;
+            report.record(PatchIds, Failed, id_missmatches.join("\n"));
+        }
+
+        if diff_missmatches.is_empty() {
+            report.record(
+                PatchDiffs,
+                Passed,
+                "each patch has the same diff as its remote patch".to_string(),
+            );
+        } else {
+            local_matches_remote = false;
+            report.record(
+                PatchDiffs,
+                Failed,
+                format!(
+                    "patch(es) {} differ from the remote patches, update the remote with gps sync or gps request-review",
+                    diff_missmatches.join(", ")
+                ),
+            );
+            if !diffs.is_empty() {
+                report.checks.last_mut().unwrap().diff = Some(diffs.join("\n"));
+            }
+        }
+    }
+    if report.stop_at_failure(fail_fast) {
+        return Ok(report);
+    }
+
+    // verify the commits on the remote branch are signed by someone trusted
+    if config.integrate.verify_signatures {
+        let commit_signatures = signatures::branch_commit_signatures(
+            repo,
+            context.upstream_oid,
+            remote_branch_head_oid,
+        )
+        .map_err(|e| IntegrateCheckError::VerifySignaturesFailed(e.into()))?;
+        let untrusted = signatures::describe_untrusted(&commit_signatures);
+        if untrusted.is_empty() {
+            report.record(
+                Signatures,
+                Passed,
+                format!(
+                    "the {} commit(s) on {} have good signatures by someone trusted",
+                    commit_signatures.len(),
+                    upstream_branch_info.name
+                ),
+            );
+        } else {
+            report.record(Signatures, Failed, untrusted.join("\n"));
+        }
+    } else {
+        report.record(
+            Signatures,
+            Skipped,
+            "integrate.verify_signatures is disabled".to_string(),
+        );
+    }
+    if report.stop_at_failure(fail_fast) {
+        return Ok(report);
+    }
+
+    // verify PR approval & CI status
+    report
+        .checks
+        .push(check_verify_hook(context, &patch_associated_branch_name));
+    if report.stop_at_failure(fail_fast) {
+        return Ok(report);
+    }
+
+    // verify isolation
+    if config.integrate.verify_isolation {
+        match verify_isolation::verify_isolation(start_patch_index, end_patch_index) {
+            Ok(_) => report.record(
+                Isolation,
+                Passed,
+                "the patch(es) apply & pass the isolate_post_checkout hook on their own"
+                    .to_string(),
+            ),
+            Err(e) => report.record(Isolation, Failed, e.to_string()),
+        }
+    } else {
+        report.record(
+            Isolation,
+            Skipped,
+            "integrate.verify_isolation is disabled".to_string(),
+        );
+    }
+    if report.stop_at_failure(fail_fast) {
+        return Ok(report);
+    }
+
+    // verify what was reviewed integrates into the upstream, along with the ranges before it
+    if local_matches_remote {
+        match dry_run_integration.integrate(
+            repo,
+            config.integrate.strategy,
+            &patch_associated_branch_name,
+            remote_branch_head_oid,
+            context.upstream_branch_shorthand,
+        ) {
+            Ok(_) => report.record(
+                Integrates,
+                Passed,
+                format!(
+                    "{} integrates into {}",
+                    upstream_branch_info.name, context.upstream_branch_shorthand
+                ),
+            ),
+            Err(e) => report.record(Integrates, Failed, e.to_string()),
+        }
+    } else {
+        report.record(
+            Integrates,
+            Skipped,
+            "skipped, the remote branch doesn't match the patch(es)".to_string(),
+        );
+    }
+
+    Ok(report)
+}
+
+/// Execute the integrate_verify hook to verify the request review branch is approved & passes CI
+pub(crate) fn check_verify_hook(context: &CheckContext, patch_branch_name: &str) -> IntegrateCheck {
+    use IntegrateCheckKind::VerifyHook;
+    use IntegrateCheckStatus::*;
+
+    let config = context.config;
+    match hooks::find_hooks(
+        context.repo_root_str,
+        context.repo_gitdir_str,
+        &config.hooks,
+        &hooks::INTEGRATE_VERIFY_HOOK,
+    ) {
+        Ok(found_hooks) => match hooks::execute_hooks(
+            context.repo_gitdir_str,
+            &config.hooks,
+            &hooks::INTEGRATE_VERIFY_HOOK,
+            &found_hooks,
+            &[
+                patch_branch_name,
+                context.upstream_branch_shorthand,
+                context.remote_name,
+                context.remote_url,
+            ],
+        ) {
+            Ok(_) => IntegrateCheck::new(VerifyHook, Passed, "exited 0".to_string()),
+            Err(e) => IntegrateCheck::new(VerifyHook, Failed, e.to_string()),
+        },
+        Err(hooks::FindHookError::NotFound) if config.integrate.require_verify => {
+            IntegrateCheck::new(
+                VerifyHook,
+                Failed,
+                "not found, integrate.require_verify is enabled for this patch stack".to_string(),
+            )
+        }
+        Err(hooks::FindHookError::NotFound) => {
+            IntegrateCheck::new(VerifyHook, Skipped, "not found".to_string())
+        }
+        Err(hooks::FindHookError::NotExecutable(hook_path)) if config.integrate.require_verify => {
+            IntegrateCheck::new(
+                VerifyHook,
+                Failed,
+                format!(
+                    "found at {} but it is NOT executable, integrate.require_verify is enabled for this patch stack",
+                    hook_path.display()
+                ),
+            )
+        }
+        Err(hooks::FindHookError::NotExecutable(hook_path)) => {
+            let check = IntegrateCheck::new(
+                VerifyHook,
+                Skipped,
+                format!("found at {} but it is NOT executable", hook_path.display()),
+            );
+            events::emit(Event::HookNotExecutable {
+                name: hooks::INTEGRATE_VERIFY_HOOK.name.to_string(),
+                path: hook_path,
+            });
+            check
+        }
+        Err(e) => IntegrateCheck::new(VerifyHook, Failed, e.to_string()),
+    }
+}
+
+/// Get the commits of the remote branch carrying patches, in the same order as they are scanned
+/// when computing the state of its patches
+fn remote_patch_commits<'a>(
+    repo: &'a git2::Repository,
+    patch_stack_base_oid: git2::Oid,
+    remote_branch_head_oid: git2::Oid,
+) -> Result<Vec<git2::Commit<'a>>, IntegrateCheckError> {
+    let common_ancestor_oid =
+        git::common_ancestor(repo, remote_branch_head_oid, patch_stack_base_oid)
+            .map_err(|e| IntegrateCheckError::GetRemotePatchesFailed(e.into()))?;
+    let revwalk = git::get_revs(
+        repo,
+        common_ancestor_oid,
+        remote_branch_head_oid,
+        git2::Sort::REVERSE,
+    )
+    .map_err(|e| IntegrateCheckError::GetRemotePatchesFailed(e.into()))?;
+
+    let mut commits: Vec<git2::Commit> = vec![];
+    for oid in revwalk {
+        let commit = oid
+            .and_then(|oid| repo.find_commit(oid))
+            .map_err(|e| IntegrateCheckError::GetRemotePatchesFailed(e.into()))?;
+        if ps::commit_ps_id(&commit).is_some() {
+            commits.push(commit);
+        }
+    }
+    Ok(commits)
+}
+
+fn short_sha(oid: git2::Oid) -> String {
+    oid.to_string().chars().take(7).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        check_range, CheckContext, IntegrateCheckKind, IntegrateCheckReport, IntegrateCheckStatus,
+    };
+    use crate::ps;
+    use crate::ps::private::config;
+    use crate::ps::private::git::test_utils::{commit_files, repo_init};
+    use crate::ps::private::integrate_strategy::DryRunIntegration;
+    use crate::ps::private::rr_branch_name::RrBranchNaming;
+    use crate::ps::private::state_computation;
+    use tempfile::TempDir;
+    use IntegrateCheckKind::*;
+    use IntegrateCheckStatus::*;
+
+    const PS_ID: &str = "2dce2a21-72b9-487a-b641-4a0b157b76e8";
+    const OTHER_PS_ID: &str = "9c1d7f4e-08a3-4d7b-9e2a-5b6c3f1e0a27";
+
+    fn message(summary: &str, ps_id: &str) -> String {
+        format!("{}\n\n<!-- ps-id: {} -->\n", summary, ps_id)
+    }
+
+    /// A stack of a single patch, first, on main tracking origin/main, returning the base of the
+    /// stack & the patch
+    fn stack_repo() -> (TempDir, git2::Repository, git2::Oid, git2::Oid) {
+        let (td, repo) = repo_init();
+        let base_oid = repo.head().unwrap().target().unwrap();
+        repo.remote("origin", "/dev/null").unwrap();
+        repo.reference("refs/remotes/origin/main", base_oid, false, "upstream")
+            .unwrap();
+        repo.find_branch("main", git2::BranchType::Local)
+            .unwrap()
+            .set_upstream(Some("origin/main"))
+            .unwrap();
+        let patch_oid = commit_files(&repo, &[("first.txt", "first\n")], &message("first", PS_ID));
+        (td, repo, base_oid, patch_oid)
+    }
+
+    /// Create a commit of the file on top of the parent without moving any ref
+    fn commit_on(
+        repo: &git2::Repository,
+        parent_oid: git2::Oid,
+        (path, content): (&str, &str),
+        message: &str,
+    ) -> git2::Oid {
+        let parent = repo.find_commit(parent_oid).unwrap();
+        let mut treebuilder = repo.treebuilder(Some(&parent.tree().unwrap())).unwrap();
+        let blob_oid = repo.blob(content.as_bytes()).unwrap();
+        treebuilder
+            .insert(path, blob_oid, i32::from(git2::FileMode::Blob))
+            .unwrap();
+        let tree = repo.find_tree(treebuilder.write().unwrap()).unwrap();
+        let sig = repo.signature().unwrap();
+        repo.commit(None, &sig, &sig, message, &tree, &[&parent])
+            .unwrap()
+    }
+
+    /// Branch ps/rr/first off the patch & make it track origin/ps/rr/first, as if it was pushed,
+    /// with the remote branch at `remote_head_oid`
+    fn push_rr_branch(repo: &git2::Repository, patch_oid: git2::Oid, remote_head_oid: git2::Oid) {
+        let patch_commit = repo.find_commit(patch_oid).unwrap();
+        let mut branch = repo.branch("ps/rr/first", &patch_commit, false).unwrap();
+        repo.reference(
+            "refs/remotes/origin/ps/rr/first",
+            remote_head_oid,
+            false,
+            "pushed",
+        )
+        .unwrap();
+        branch.set_upstream(Some("origin/ps/rr/first")).unwrap();
+    }
+
+    /// Run the checks of the patch, with isolation verification disabled
+    fn run_checks(repo: &git2::Repository, fail_fast: bool) -> IntegrateCheckReport {
+        let repo_root_str = repo.workdir().unwrap().to_str().unwrap();
+        let repo_gitdir_str = repo.path().to_str().unwrap();
+        let mut config = config::get_config(repo, repo_root_str, repo_gitdir_str).unwrap();
+        config.integrate.verify_isolation = false;
+        let rr_branch_naming = RrBranchNaming::new(&config.request_review).unwrap();
+
+        let patch_stack = ps::get_patch_stack(repo).unwrap();
+        let patches = ps::get_patch_list(repo, &patch_stack).unwrap();
+        let patch_stack_base_oid = patch_stack.base.target().unwrap();
+        let patch_info_collection = state_computation::get_list_patch_info(
+            repo,
+            patch_stack_base_oid,
+            "main",
+            &config.branch_scan,
+            &rr_branch_naming,
+        )
+        .unwrap();
+        let upstream_oid = repo.refname_to_id("refs/remotes/origin/main").unwrap();
+
+        let context = CheckContext {
+            repo,
+            config: &config,
+            patches: &patches,
+            patch_info_collection: &patch_info_collection,
+            rr_branch_naming: &rr_branch_naming,
+            patch_stack_base_oid,
+            upstream_oid,
+            upstream_branch_shorthand: "main",
+            remote_name: "origin",
+            remote_url: "/dev/null",
+            repo_root_str,
+            repo_gitdir_str,
+        };
+        let mut dry_run_integration = DryRunIntegration::new(repo, upstream_oid).unwrap();
+        check_range(&context, 0, None, None, &mut dry_run_integration, fail_fast).unwrap()
+    }
+
+    fn statuses(report: &IntegrateCheckReport) -> Vec<(IntegrateCheckKind, IntegrateCheckStatus)> {
+        report.checks.iter().map(|c| (c.kind, c.status)).collect()
+    }
+
+    fn check_of(report: &IntegrateCheckReport, kind: IntegrateCheckKind) -> &super::IntegrateCheck {
+        report.checks.iter().find(|c| c.kind == kind).unwrap()
+    }
+
+    #[test]
+    fn test_patch_matching_its_remote_branch_passes() {
+        let (_td, repo, _base_oid, patch_oid) = stack_repo();
+        push_rr_branch(&repo, patch_oid, patch_oid);
+
+        let report = run_checks(&repo, false);
+
+        assert_eq!(
+            statuses(&report),
+            vec![
+                (AssociatedBranch, Passed),
+                (Pushed, Passed),
+                (CommitCount, Passed),
+                (PatchIds, Passed),
+                (PatchDiffs, Passed),
+                (Signatures, Skipped),
+                (VerifyHook, Skipped),
+                (Isolation, Skipped),
+                (Integrates, Passed),
+            ]
+        );
+        assert!(report.first_failure().is_none());
+        assert_eq!(report.branch_name.as_deref(), Some("ps/rr/first"));
+        assert_eq!(report.remote_branch_oid, Some(patch_oid));
+    }
+
+    #[test]
+    fn test_commit_count_mismatch_skips_the_patch_comparisons() {
+        let (_td, repo, _base_oid, patch_oid) = stack_repo();
+        let extra_oid = commit_on(&repo, patch_oid, ("extra.txt", "extra\n"), "extra");
+        push_rr_branch(&repo, patch_oid, extra_oid);
+
+        let report = run_checks(&repo, false);
+
+        let commit_count = check_of(&report, CommitCount);
+        assert_eq!(commit_count.status, Failed);
+        assert_eq!(
+            commit_count.detail,
+            "the patch series consists of 1 patches but origin/ps/rr/first has 2 commits"
+        );
+        assert_eq!(check_of(&report, PatchIds).status, Skipped);
+        assert_eq!(check_of(&report, PatchDiffs).status, Skipped);
+        assert_eq!(check_of(&report, Integrates).status, Skipped);
+        assert_eq!(report.first_failure().unwrap().kind, CommitCount);
+    }
+
+    #[test]
+    fn test_patch_id_mismatch_is_reported() {
+        let (_td, repo, base_oid, patch_oid) = stack_repo();
+        let remote_oid = commit_on(
+            &repo,
+            base_oid,
+            ("first.txt", "first\n"),
+            &message("first", OTHER_PS_ID),
+        );
+        push_rr_branch(&repo, patch_oid, remote_oid);
+
+        let report = run_checks(&repo, false);
+
+        let patch_ids = check_of(&report, PatchIds);
+        assert_eq!(patch_ids.status, Failed);
+        assert_eq!(
+            patch_ids.detail,
+            format!(
+                "patch 0 has id {} but the remote patch has {}",
+                PS_ID, OTHER_PS_ID
+            )
+        );
+        assert_eq!(check_of(&report, PatchDiffs).status, Passed);
+        assert_eq!(check_of(&report, Integrates).status, Skipped);
+    }
+
+    #[test]
+    fn test_patch_diff_mismatch_is_reported_along_with_the_diff() {
+        let (_td, repo, base_oid, patch_oid) = stack_repo();
+        let remote_oid = commit_on(
+            &repo,
+            base_oid,
+            ("first.txt", "reviewed\n"),
+            &message("first", PS_ID),
+        );
+        push_rr_branch(&repo, patch_oid, remote_oid);
+
+        let report = run_checks(&repo, false);
+
+        assert_eq!(check_of(&report, PatchIds).status, Passed);
+        let patch_diffs = check_of(&report, PatchDiffs);
+        assert_eq!(patch_diffs.status, Failed);
+        assert!(patch_diffs.detail.starts_with("patch(es) 0 differ"));
+        let diff = patch_diffs.diff.as_ref().unwrap();
+        assert!(diff.contains("-+reviewed"));
+        assert!(diff.contains("++first"));
+        assert_eq!(check_of(&report, Integrates).status, Skipped);
+    }
+
+    #[test]
+    fn test_missing_associated_branch_skips_every_other_check() {
+        let (_td, repo, _base_oid, _patch_oid) = stack_repo();
+
+        let report = run_checks(&repo, false);
+
+        assert_eq!(report.checks.len(), IntegrateCheckKind::ALL.len());
+        assert_eq!(report.checks[0].kind, AssociatedBranch);
+        assert_eq!(report.checks[0].status, Failed);
+        for check in report.checks[1..].iter() {
+            assert_eq!(check.status, Skipped);
+            assert_eq!(
+                check.detail,
+                "skipped, depends on \"has a single associated branch\""
+            );
+        }
+        assert!(report.remote_branch_oid.is_none());
+    }
+
+    #[test]
+    fn test_fail_fast_skips_the_checks_after_the_first_failure() {
+        let (_td, repo, base_oid, patch_oid) = stack_repo();
+        let remote_oid = commit_on(
+            &repo,
+            base_oid,
+            ("first.txt", "reviewed\n"),
+            &message("first", PS_ID),
+        );
+        push_rr_branch(&repo, patch_oid, remote_oid);
+
+        let report = run_checks(&repo, true);
+
+        assert_eq!(
+            statuses(&report),
+            vec![
+                (AssociatedBranch, Passed),
+                (Pushed, Passed),
+                (CommitCount, Passed),
+                (PatchIds, Passed),
+                (PatchDiffs, Failed),
+                (Signatures, Skipped),
+                (VerifyHook, Skipped),
+                (Isolation, Skipped),
+                (Integrates, Skipped),
+            ]
+        );
+        assert!(report.checks[5..]
+            .iter()
+            .all(|c| c.detail == "skipped, depends on \"patches match the remote branch\""));
+    }
+}
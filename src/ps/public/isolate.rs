@@ -1,5 +1,7 @@
 use super::super::super::ps;
 use super::super::private::cherry_picking;
+use super::super::private::config;
+use super::super::private::dependencies;
 use super::super::private::git;
 use super::super::private::hooks;
 use super::super::private::paths;
@@ -21,13 +23,16 @@ pub enum IsolateError {
     BranchNameNotUtf8,
     MergeCommitDetected(String),
     ConflictsExist(String, String),
+    DependenciesMissing(Vec<usize>),
+    DependenciesFailed(Box<dyn std::error::Error>),
     FailedToCheckout(Box<dyn std::error::Error>),
     GetCurrentBranchFailed,
     StoreLastBranchFailed(Box<dyn std::error::Error>),
     ReadLastBranchFailed(Box<dyn std::error::Error>),
     GetRepoRootPathFailed(Box<dyn std::error::Error>),
     PathNotUtf8,
-    HookNotFound(Box<dyn std::error::Error>),
+    GetConfigFailed(Box<dyn std::error::Error>),
+    PreIsolateHookFailed(Box<dyn std::error::Error>),
     HookExecutionFailed(Box<dyn std::error::Error>),
     FindIsolateBranchFailed(Box<dyn std::error::Error>),
     DeleteIsolateBranchFailed(Box<dyn std::error::Error>),
@@ -80,6 +85,17 @@ impl std::fmt::Display for IsolateError {
                 "conflict(s) detected when playing {} on top of {}",
                 src_oid, dst_oid
             ),
+            Self::DependenciesMissing(indexes) => {
+                let indexes: Vec<String> = indexes.iter().map(|i| i.to_string()).collect();
+                write!(
+                    f,
+                    "the patches depend on earlier patches ({}), include them in the patch range or set branch.include_dependencies to include them automatically",
+                    indexes.join(", ")
+                )
+            }
+            Self::DependenciesFailed(e) => {
+                write!(f, "failed to include the patches depended on, {}", e)
+            }
             Self::FailedToCheckout(e) => write!(f, "failed to checkout branch, {}", e),
             Self::GetCurrentBranchFailed => write!(f, "failed to get current branch"),
             Self::StoreLastBranchFailed(e) => write!(f, "failed to store last branch, {}", e),
@@ -88,7 +104,8 @@ impl std::fmt::Display for IsolateError {
                 write!(f, "failed to get repositories root path, {}", e)
             }
             Self::PathNotUtf8 => write!(f, "path not utf-8"),
-            Self::HookNotFound(e) => write!(f, "hook not found, {}", e),
+            Self::GetConfigFailed(e) => write!(f, "failed to get config, {}", e),
+            Self::PreIsolateHookFailed(e) => write!(f, "pre_isolate hook failed, {}", e),
             Self::HookExecutionFailed(e) => write!(f, "hook execution failed, {}", e),
             Self::FindIsolateBranchFailed(e) => write!(f, "failed to find isolate branch, {}", e),
             Self::DeleteIsolateBranchFailed(e) => {
@@ -123,13 +140,16 @@ impl std::error::Error for IsolateError {
             Self::BranchNameNotUtf8 => None,
             Self::MergeCommitDetected(_) => None,
             Self::ConflictsExist(_, _) => None,
+            Self::DependenciesMissing(_) => None,
+            Self::DependenciesFailed(e) => Some(e.as_ref()),
             Self::FailedToCheckout(e) => Some(e.as_ref()),
             Self::GetCurrentBranchFailed => None,
             Self::StoreLastBranchFailed(e) => Some(e.as_ref()),
             Self::ReadLastBranchFailed(e) => Some(e.as_ref()),
             Self::GetRepoRootPathFailed(e) => Some(e.as_ref()),
             Self::PathNotUtf8 => None,
-            Self::HookNotFound(e) => Some(e.as_ref()),
+            Self::GetConfigFailed(e) => Some(e.as_ref()),
+            Self::PreIsolateHookFailed(e) => Some(e.as_ref()),
             Self::HookExecutionFailed(e) => Some(e.as_ref()),
             Self::FindIsolateBranchFailed(e) => Some(e.as_ref()),
             Self::DeleteIsolateBranchFailed(e) => Some(e.as_ref()),
@@ -148,8 +168,9 @@ impl std::error::Error for IsolateError {
 pub fn isolate(
     start_patch_index_optional: Option<usize>,
     end_patch_index_optional: Option<usize>,
-    color: bool,
 ) -> Result<(), IsolateError> {
+    // a temporary branch that is never pushed for review, so it doesn't follow the request review
+    // branch name template, which in turn never recognizes branches under ps/tmp/
     let isolate_branch_name = "ps/tmp/isolate";
     let repo = ps::private::git::create_cwd_repo()
         .map_err(|e| IsolateError::OpenGitRepositoryFailed(e.into()))?;
@@ -176,13 +197,7 @@ pub fn isolate(
                 .peel_to_commit()
                 .map_err(|_| IsolateError::PatchStackBaseNotFound)?;
 
-            let branch = repo
-                .branch(isolate_branch_name, &patch_stack_base_commit, true)
-                .map_err(|_| IsolateError::CreateBranchFailed)?;
-
-            let branch_ref_name = branch.get().name().ok_or(IsolateError::BranchNameNotUtf8)?;
-
-            // cherry pick the patch or patch range onto new isolation branch
+            // map the patch or patch range to the commits to cherry pick onto the isolation branch
             let cherry_pick_range = cherry_picking::map_range_for_cherry_pick(
                 &patches_vec,
                 patch_index,
@@ -190,16 +205,85 @@ pub fn isolate(
             )
             .map_err(|e| IsolateError::FailedToMapIndexesForCherryPick(e.into()))?;
 
-            cherry_picking::cherry_pick(
+            let repo_root_path = paths::repo_root_path(&repo)
+                .map_err(|e| IsolateError::GetRepoRootPathFailed(e.into()))?;
+            let repo_root_str = repo_root_path.to_str().ok_or(IsolateError::PathNotUtf8)?;
+            let ps_config = config::get_config(&repo, repo_root_str, repo_gitdir_str)
+                .map_err(|e| IsolateError::GetConfigFailed(e.into()))?;
+
+            let end_patch_index = end_patch_index_optional.unwrap_or(patch_index);
+            let patch_shas: Vec<String> = patches_vec
+                .iter()
+                .filter(|p| p.index >= patch_index && p.index <= end_patch_index)
+                .map(|p| p.oid.to_string())
+                .collect();
+            let patch_sha_strs: Vec<&str> = patch_shas.iter().map(|sha| sha.as_str()).collect();
+            hooks::find_and_execute_hook(
+                repo_root_str,
+                repo_gitdir_str,
+                &ps_config.hooks,
+                &hooks::PRE_ISOLATE_HOOK,
+                &patch_sha_strs,
+            )
+            .map_err(|e| IsolateError::PreIsolateHookFailed(e.into()))?;
+
+            let branch = repo
+                .branch(isolate_branch_name, &patch_stack_base_commit, true)
+                .map_err(|_| IsolateError::CreateBranchFailed)?;
+
+            let branch_ref_name = branch.get().name().ok_or(IsolateError::BranchNameNotUtf8)?;
+
+            // the earlier patches the range depends on, directly or indirectly
+            let range_prerequisites = || {
+                let patch_oids: Vec<git2::Oid> = patches_vec.iter().map(|p| p.oid).collect();
+                dependencies::range_prerequisites(
+                    &repo,
+                    patch_stack_base_commit.id(),
+                    &patch_oids,
+                    patch_index,
+                    end_patch_index_optional,
+                )
+                .map_err(|e| IsolateError::DependenciesFailed(e.into()))
+            };
+
+            let include_dependencies = ps_config.branch.include_dependencies;
+            if include_dependencies {
+                for prerequisite_index in range_prerequisites()? {
+                    dependencies::cherry_pick_prerequisite(
+                        &repo,
+                        &config,
+                        &ps_config.rewrite,
+                        patches_vec[prerequisite_index].oid,
+                        branch_ref_name,
+                    )
+                    .map_err(|e| IsolateError::DependenciesFailed(e.into()))?;
+                }
+            }
+
+            match cherry_picking::cherry_pick(
                 &repo,
                 &config,
+                &ps_config.rewrite,
                 cherry_pick_range.root_oid,
                 cherry_pick_range.leaf_oid,
                 branch_ref_name,
                 0,
                 false,
                 true,
-            )?;
+            ) {
+                // explain the conflict when it is due to patches missing from the isolation
+                Err(cherry_picking::CherryPickError::ConflictsExist(src_oid, dst_oid))
+                    if !include_dependencies =>
+                {
+                    match range_prerequisites() {
+                        Ok(prerequisites) if !prerequisites.is_empty() => {
+                            Err(IsolateError::DependenciesMissing(prerequisites))
+                        }
+                        _ => Err(IsolateError::ConflictsExist(src_oid, dst_oid)),
+                    }
+                }
+                result => result.map_err(IsolateError::from),
+            }?;
 
             // get currently checked out branch name
             let checked_out_branch = git::get_current_branch_shorthand(&repo)
@@ -228,35 +312,14 @@ pub fn isolate(
             utils::execute("git", &["checkout", isolate_branch_name])
                 .map_err(|e| IsolateError::FailedToCheckout(e.into()))?;
 
-            let repo_root_path = paths::repo_root_path(&repo)
-                .map_err(|e| IsolateError::GetRepoRootPathFailed(e.into()))?;
-            let repo_root_str = repo_root_path.to_str().ok_or(IsolateError::PathNotUtf8)?;
-            match hooks::find_hook(repo_root_str, repo_gitdir_str, "isolate_post_checkout") {
-                Ok(hook_path) => utils::execute(
-                    hook_path.to_str().ok_or(IsolateError::PathNotUtf8)?,
-                    &[remote_name_str, remote_url_str],
-                )
-                .map_err(|e| IsolateError::HookExecutionFailed(e.into()))?,
-                Err(hooks::FindHookError::NotFound) => {}
-                Err(hooks::FindHookError::NotExecutable(hook_path)) => {
-                    let path_str = hook_path.to_str().unwrap_or("unknow path");
-                    let msg = format!(
-                        r#"
-  The isolate_post_checkout hook was found at
-
-    {}
-
-  but it is NOT executable. Due to this the hook is being skipped. Generally
-  this can be corrected with the following.
-
-    chmod u+x {}
-"#,
-                        path_str, path_str
-                    );
-                    utils::print_warn(color, &msg);
-                }
-                Err(e) => return Err(IsolateError::HookNotFound(e.into())),
-            }
+            hooks::find_and_execute_hook(
+                repo_root_str,
+                repo_gitdir_str,
+                &ps_config.hooks,
+                &hooks::ISOLATE_POST_CHECKOUT_HOOK,
+                &[remote_name_str, remote_url_str],
+            )
+            .map_err(|e| IsolateError::HookExecutionFailed(e.into()))?;
 
             Ok(())
         }
@@ -280,32 +343,17 @@ pub fn isolate(
             let repo_root_path = paths::repo_root_path(&repo)
                 .map_err(|e| IsolateError::GetRepoRootPathFailed(e.into()))?;
             let repo_root_str = repo_root_path.to_str().ok_or(IsolateError::PathNotUtf8)?;
+            let ps_config = config::get_config(&repo, repo_root_str, repo_gitdir_str)
+                .map_err(|e| IsolateError::GetConfigFailed(e.into()))?;
 
-            match hooks::find_hook(repo_root_str, repo_gitdir_str, "isolate_post_cleanup") {
-                Ok(hook_path) => {
-                    utils::execute(hook_path.to_str().ok_or(IsolateError::PathNotUtf8)?, &[])
-                        .map_err(|e| IsolateError::HookExecutionFailed(e.into()))?
-                }
-                Err(hooks::FindHookError::NotFound) => {}
-                Err(hooks::FindHookError::NotExecutable(hook_path)) => {
-                    let path_str = hook_path.to_str().unwrap_or("unknow path");
-                    let msg = format!(
-                        r#"
-  The isolate_post_cleanup hook was found at
-
-    {}
-
-  but it is NOT executable. Due to this the hook is being skipped. Generally
-  this can be corrected with the following.
-
-    chmod u+x {}
-"#,
-                        path_str, path_str
-                    );
-                    utils::print_warn(color, &msg);
-                }
-                Err(e) => return Err(IsolateError::HookNotFound(e.into())),
-            }
+            hooks::find_and_execute_hook(
+                repo_root_str,
+                repo_gitdir_str,
+                &ps_config.hooks,
+                &hooks::ISOLATE_POST_CLEANUP_HOOK,
+                &[],
+            )
+            .map_err(|e| IsolateError::HookExecutionFailed(e.into()))?;
 
             Ok(())
         }
@@ -3,11 +3,16 @@ use super::super::private::config;
 use super::super::private::config::list::ColorSelector;
 use super::super::private::git;
 use super::super::private::git::RebaseTodoCommand;
+use super::super::private::hooks;
 use super::super::private::list;
+use super::super::private::list_info_cache;
 use super::super::private::paths;
+use super::super::private::rr_branch_name;
 use super::super::private::state_computation;
+use super::patch_stack;
 use ansi_term::Color;
 use std::cmp::Ordering;
+use std::time::{Duration, SystemTime};
 
 #[derive(Debug)]
 pub enum ListError {
@@ -19,8 +24,13 @@ pub enum ListError {
     GetConfigFailed(Box<dyn std::error::Error>),
     GetCommitDiffPatchIdFailed(Box<dyn std::error::Error>),
     GetHookOutputError(Box<dyn std::error::Error>),
+    GetListPatchInfoFailed(Box<dyn std::error::Error>),
+    RrBranchNamingInvalid(Box<dyn std::error::Error>),
     CurrentBranchNameMissing,
     GetUpstreamBranchNameFailed,
+    GetBehindCountFailed(Box<dyn std::error::Error>),
+    PreListHookFailed(Box<dyn std::error::Error>),
+    PostListHookFailed(Box<dyn std::error::Error>),
 }
 
 impl std::fmt::Display for ListError {
@@ -38,8 +48,19 @@ impl std::fmt::Display for ListError {
                 write!(f, "get commit diff patch id failed, {}", e)
             }
             Self::GetHookOutputError(e) => write!(f, "get hook output failed, {}", e),
+            Self::GetListPatchInfoFailed(e) => {
+                write!(f, "get list of patch git info failed, {}", e)
+            }
+            Self::RrBranchNamingInvalid(e) => {
+                write!(f, "invalid request review branch name template, {}", e)
+            }
             Self::CurrentBranchNameMissing => write!(f, "current branch name missing"),
             Self::GetUpstreamBranchNameFailed => write!(f, "get upstream branch name failed"),
+            Self::GetBehindCountFailed(e) => {
+                write!(f, "count commits behind the upstream branch failed, {}", e)
+            }
+            Self::PreListHookFailed(e) => write!(f, "pre_list hook failed, {}", e),
+            Self::PostListHookFailed(e) => write!(f, "post_list hook failed, {}", e),
         }
     }
 }
@@ -55,8 +76,13 @@ impl std::error::Error for ListError {
             Self::GetConfigFailed(e) => Some(e.as_ref()),
             Self::GetCommitDiffPatchIdFailed(e) => Some(e.as_ref()),
             Self::GetHookOutputError(e) => Some(e.as_ref()),
+            Self::GetListPatchInfoFailed(e) => Some(e.as_ref()),
+            Self::RrBranchNamingInvalid(e) => Some(e.as_ref()),
             Self::CurrentBranchNameMissing => None,
             Self::GetUpstreamBranchNameFailed => None,
+            Self::GetBehindCountFailed(e) => Some(e.as_ref()),
+            Self::PreListHookFailed(e) => Some(e.as_ref()),
+            Self::PostListHookFailed(e) => Some(e.as_ref()),
         }
     }
 }
@@ -71,11 +97,10 @@ fn is_connected_to_prev_row(prev_patch_branches: &[String], cur_patch_branches:
 
 fn rebase_todo_command_to_row(
     todo: &RebaseTodoCommand,
-    color: bool,
     patch_series_index_color: Option<Color>,
     patch_series_sha_color: Option<Color>,
 ) -> list::ListRow {
-    let mut row = list::ListRow::new(color);
+    let mut row = list::ListRow::new();
     match todo {
         RebaseTodoCommand::Pick {
             line: _,
@@ -196,35 +221,106 @@ fn rebase_todo_command_to_row(
     }
 }
 
-fn get_behind_count(
-    repo: &git2::Repository,
-    patch_stack: &ps::PatchStack,
-    patch_stack_upstream_tracking_branch_name: &str,
-) -> usize {
-    let patch_stack_branch_upstream = repo
-        .find_branch(
-            patch_stack_upstream_tracking_branch_name,
-            git2::BranchType::Remote,
-        )
-        .expect("cur patch stack branch upstream to exist");
-
-    let patch_stack_branch_upstream_oid = patch_stack_branch_upstream
-        .into_reference()
-        .target()
-        .expect("cur patch stack branch upstream to have a target");
-
-    let behind_com_anc = git::common_ancestor(
-        repo,
-        patch_stack.head.target().expect("HEAD to have an oid"),
-        patch_stack_branch_upstream_oid,
-    )
-    .expect("common ancestor between HEAD and upstream tracking branch to exist");
+/// A row cell awaiting the output of the list_additional_information hook
+struct PendingHookCell {
+    row_index: usize,
+    cell_index: usize,
+    cache_key: String,
+    args: Vec<String>,
+}
+
+/// Fill in the pending cells with the list_additional_information hook output, using the cached
+/// output where it is still fresh (unless refreshing) & executing the hook in parallel for the
+/// rest.
+fn fill_in_pending_hook_cells(
+    rows: &mut [list::ListRow],
+    pending_hook_cells: &[PendingHookCell],
+    repo_root_str: &str,
+    repo_gitdir_str: &str,
+    config: &config::PsConfig,
+    refresh: bool,
+) -> Result<(), ListError> {
+    if pending_hook_cells.is_empty() {
+        return Ok(());
+    }
+
+    let now = SystemTime::now();
+    let ttl = Duration::from_secs(config.list.extra_patch_info_cache_ttl);
+    let cache_path = paths::cache_path(repo_gitdir_str, list_info_cache::LIST_INFO_CACHE_NAME);
+    let mut cache = list_info_cache::ListInfoCache::load(&cache_path);
+
+    let mut misses: Vec<&PendingHookCell> = vec![];
+    for pending in pending_hook_cells {
+        match cache.get(&pending.cache_key, ttl, now) {
+            Some(output) if !refresh => {
+                rows[pending.row_index].set_cell_value(pending.cell_index, format!("{} ", output))
+            }
+            _ => misses.push(pending),
+        }
+    }
+
+    let invocations: Vec<Vec<String>> = misses.iter().map(|p| p.args.clone()).collect();
+    let outputs = list::execute_list_additional_info_hooks(
+        repo_root_str,
+        repo_gitdir_str,
+        &config.hooks,
+        &invocations,
+    );
+    for (pending, output) in misses.iter().zip(outputs) {
+        let output = output.map_err(|e| ListError::GetHookOutputError(e.into()))?;
+        rows[pending.row_index].set_cell_value(pending.cell_index, format!("{} ", output.text));
+        // a failed run may be transient, e.g. a network hiccup, so it is retried next time
+        if output.succeeded {
+            cache.insert(pending.cache_key.clone(), output.text, now);
+        }
+    }
+
+    if !ttl.is_zero() {
+        cache.prune(ttl, now);
+        // the cache only saves work, so the listing doesn't fail when it can't be written, e.g.
+        // because the gitdir is read-only or the disk is full
+        let _ = cache.save(&cache_path);
+    }
 
-    git::count_commits(repo, patch_stack_branch_upstream_oid, behind_com_anc)
-        .expect("to be able to count commits from remote tracking branch to common ancestor")
+    Ok(())
+}
+
+/// A row of the listing, which the caller renders with or without the colours of the list config
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListingRow(list::ListRow);
+
+impl ListingRow {
+    pub fn render(&self, color: bool) -> String {
+        self.0.render(color)
+    }
+}
+
+/// A rebase of the patch stack in progress
+#[derive(Debug, Clone, PartialEq)]
+pub struct RebaseInProgress {
+    /// The name of the branch being rebased
+    pub head_name: String,
+    /// The commit the branch is being rebased onto
+    pub onto: String,
+    /// The remaining commands of the rebase todo as rows, in the order they will be done
+    pub todos: Vec<ListingRow>,
+}
+
+/// The listing of the patch stack, as `gps list` shows it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Listing {
+    pub status: patch_stack::StackStatus,
+    /// The patches as rows, with the patch at the top of the stack first unless `reverse_order`
+    pub rows: Vec<ListingRow>,
+    /// Whether `list.reverse_order` is enabled, in which case the rebase todos follow the patches
+    pub reverse_order: bool,
+    pub rebase: Option<RebaseInProgress>,
 }
 
-pub fn list(color: bool) -> Result<(), ListError> {
+/// List the patches of the patch stack along with the state of their branches & the output of the
+/// list_additional_information hook, rendering them as rows, refreshing the cached hook output
+/// when `refresh`
+pub fn list(refresh: bool) -> Result<Listing, ListError> {
     let repo = git::create_cwd_repo().map_err(|_| ListError::RepositoryNotFound)?;
 
     let repo_root_path =
@@ -232,57 +328,48 @@ pub fn list(color: bool) -> Result<(), ListError> {
     let repo_root_str = repo_root_path.to_str().ok_or(ListError::PathNotUtf8)?;
     let repo_gitdir_path = repo.path();
     let repo_gitdir_str = repo_gitdir_path.to_str().ok_or(ListError::PathNotUtf8)?;
-    let config = config::get_config(repo_root_str, repo_gitdir_str)
+    let config = config::get_config(&repo, repo_root_str, repo_gitdir_str)
         .map_err(|e| ListError::GetConfigFailed(e.into()))?;
+    let rr_branch_naming = rr_branch_name::RrBranchNaming::new(&config.request_review)
+        .map_err(|e| ListError::RrBranchNamingInvalid(e.into()))?;
+
+    hooks::find_and_execute_hook(
+        repo_root_str,
+        repo_gitdir_str,
+        &config.hooks,
+        &hooks::PRE_LIST_HOOK,
+        &[],
+    )
+    .map_err(|e| ListError::PreListHookFailed(e.into()))?;
 
-    if git::in_rebase(repo_gitdir_path) {
-        let rebase_head_name = git::in_rebase_head_name(repo_gitdir_path)
+    let rebase = if git::in_rebase(repo_gitdir_path) {
+        let head_name = git::in_rebase_head_name(repo_gitdir_path)
             .unwrap()
             .trim()
             .replace("refs/heads/", "");
-
-        let rebase_onto = git::in_rebase_onto(repo_gitdir_path)
+        let onto = git::in_rebase_onto(repo_gitdir_path)
             .unwrap()
             .trim()
             .to_string();
-        if color {
-            print!(
-                "{}",
-                Color::Red.paint(format!(
-                    "rebase of '{}' in progress; onto",
-                    rebase_head_name
+        let todos = git::in_rebase_todos(repo_gitdir_path)
+            .unwrap()
+            .iter()
+            .map(|todo| {
+                ListingRow(rebase_todo_command_to_row(
+                    todo,
+                    config.list.patch_index.select_color(false),
+                    config.list.patch_sha.select_color(false),
                 ))
-            );
-            println!(" {}", Color::Yellow.paint(format!("{:.7} ", rebase_onto)));
-        } else {
-            println!(
-                "rebase of '{}' in progress; onto {:.7}",
-                rebase_head_name, rebase_onto
-            );
-        }
-
-        if !config.list.reverse_order {
-            let todos_vec = git::in_rebase_todos(repo_gitdir_path).unwrap();
-            println!(
-                "Next commands to do ({} remaining commands)",
-                todos_vec.len()
-            );
-            for todo in todos_vec.iter().rev() {
-                println!(
-                    "{}",
-                    rebase_todo_command_to_row(
-                        todo,
-                        color,
-                        config.list.patch_index.select_color(false),
-                        config.list.patch_sha.select_color(false)
-                    )
-                );
-            }
-            println!("(use \"git rebase --edit-todo\" to view and edit)");
-            println!("(use \"git rebase --continue\" once you are satisfied with your changes)");
-            println!();
-        }
-    }
+            })
+            .collect();
+        Some(RebaseInProgress {
+            head_name,
+            onto,
+            todos,
+        })
+    } else {
+        None
+    };
 
     let cur_patch_stack_branch_ref = match git::in_rebase(repo_gitdir_path) {
         true => git::in_rebase_head_name(repo_gitdir_path)
@@ -309,19 +396,26 @@ pub fn list(color: bool) -> Result<(), ListError> {
 
     let base_oid = patch_stack.base.target().unwrap();
 
-    let patch_info_collection =
-        state_computation::get_list_patch_info(&repo, base_oid, &cur_patch_stack_branch_name)
-            .unwrap();
-
-    let behind_count = get_behind_count(&repo, &patch_stack, &cur_patch_stack_branch_upstream_name);
-
-    println!(
-        "{} tracking {} [ahead {}, behind {}]",
+    let patch_info_collection = state_computation::get_list_patch_info(
+        &repo,
+        base_oid,
         &cur_patch_stack_branch_name,
-        &cur_patch_stack_branch_upstream_name,
-        list_of_patches.len(),
-        behind_count,
-    );
+        &config.branch_scan,
+        &rr_branch_naming,
+    )
+    .map_err(|e| ListError::GetListPatchInfoFailed(e.into()))?;
+
+    let behind_count =
+        patch_stack::behind_count(&repo, &patch_stack, &cur_patch_stack_branch_upstream_name)
+            .map_err(ListError::GetBehindCountFailed)?;
+
+    let status = patch_stack::StackStatus {
+        branch_name: cur_patch_stack_branch_name.clone(),
+        upstream_branch_name: cur_patch_stack_branch_upstream_name.clone(),
+        ahead: list_of_patches.len(),
+        behind: behind_count,
+        rebase_in_progress: rebase.is_some(),
+    };
 
     let list_of_patches_iter: Box<dyn Iterator<Item = _>> = if config.list.reverse_order {
         Box::new(list_of_patches.into_iter())
@@ -329,22 +423,19 @@ pub fn list(color: bool) -> Result<(), ListError> {
         Box::new(list_of_patches.into_iter().rev())
     };
 
+    let mut rows: Vec<list::ListRow> = vec![];
+    let mut pending_hook_cells: Vec<PendingHookCell> = vec![];
     let mut prev_patch_branches: Vec<String> = vec![];
     let mut connected_to_prev_row: bool;
     let mut prev_row_had_alternate_colors: bool = true;
 
     for patch in list_of_patches_iter {
-        let mut row = list::ListRow::new(color);
+        let mut row = list::ListRow::new();
 
         let commit = repo.find_commit(patch.oid).unwrap();
 
-        let commit_diff_id: Option<git2::Oid> = match git::commit_diff_patch_id(&repo, &commit) {
-            Ok(id) => Some(id),
-            Err(git::CommitDiffPatchIdError::GetDiffFailed(git::CommitDiffError::MergeCommit)) => {
-                None
-            }
-            Err(e) => return Err(ListError::GetCommitDiffPatchIdFailed(e.into())),
-        };
+        let commit_diff_id = patch_stack::commit_diff_id(&repo, &commit)
+            .map_err(ListError::GetCommitDiffPatchIdFailed)?;
 
         if let Some(ps_id) = ps::commit_ps_id(&commit) {
             if let Some(patch_info) = patch_info_collection.get(&ps_id) {
@@ -392,7 +483,7 @@ pub fn list(color: bool) -> Result<(), ListError> {
                         Ordering::Less => {}
                         Ordering::Equal => {
                             let branch_info = patch_info.branches.first().unwrap();
-                            if !branch_info.name.starts_with("ps/rr/") {
+                            if !rr_branch_naming.is_rr_branch_name(&branch_info.name) {
                                 row.add_cell(
                                     None,
                                     fg_color,
@@ -411,58 +502,9 @@ pub fn list(color: bool) -> Result<(), ListError> {
                     // branch somehow and then once they select a branch then we can use the
                     // tracking branch of that branch to know where to push changes.
 
-                    let mut state_string = String::new();
-
-                    let branch_patch: state_computation::PatchInfo = b
-                        .patches
-                        .iter()
-                        .filter(|p| p.patch_id == ps_id)
-                        .map(|p| p.to_owned())
-                        .collect::<Vec<state_computation::PatchInfo>>()
-                        .first()
-                        .unwrap()
-                        .clone();
-                    state_string.push('l');
-
-                    match commit_diff_id {
-                        Some(id) => {
-                            if branch_patch.commit_diff_id != id {
-                                state_string.push('*');
-                            }
-                        }
-                        None => state_string.push('*'),
-                    }
-
-                    let upstream_opt = b.upstream.clone();
-                    if let Some(upstream) = upstream_opt {
-                        let upstream_branch_patch_opt: Option<state_computation::PatchInfo> =
-                            upstream
-                                .patches
-                                .iter()
-                                .filter(|p| p.patch_id == ps_id)
-                                .map(|p| p.to_owned())
-                                .collect::<Vec<state_computation::PatchInfo>>()
-                                .first()
-                                .cloned();
-
-                        if upstream_branch_patch_opt.is_some() {
-                            state_string.push('r');
-                            match commit_diff_id {
-                                Some(id) => {
-                                    if let Some(upstream_branch_patch) = upstream_branch_patch_opt {
-                                        if upstream_branch_patch.commit_diff_id != id {
-                                            state_string.push('*');
-                                        }
-                                    }
-                                }
-                                None => state_string.push('*'),
-                            }
-
-                            if upstream.patches.len() < upstream.commit_count {
-                                state_string.push('!');
-                            }
-                        }
-                    }
+                    let state_string =
+                        patch_stack::BranchState::new(b, ps_id, commit_diff_id, &rr_branch_naming)
+                            .state_string();
                     row.add_cell(
                         None,
                         extra_patch_info_color,
@@ -471,24 +513,28 @@ pub fn list(color: bool) -> Result<(), ListError> {
                     );
 
                     if config.list.add_extra_patch_info {
-                        let hook_stdout = list::execute_list_additional_info_hook(
-                            repo_root_str,
-                            repo_gitdir_str,
-                            &[
-                                &patch.index.to_string(),
-                                &state_string,
-                                &patch.oid.to_string(),
-                                &patch.summary,
-                            ],
-                        )
-                        .map_err(|e| ListError::GetHookOutputError(e.into()))?;
                         let hook_stdout_len = config.list.extra_patch_info_length;
-                        row.add_cell(
+                        let cell_index = row.add_pending_cell(
                             Some(hook_stdout_len + 1),
                             extra_patch_info_color,
                             bg_color,
-                            format!("{} ", hook_stdout),
                         );
+                        pending_hook_cells.push(PendingHookCell {
+                            row_index: rows.len(),
+                            cell_index,
+                            cache_key: list_info_cache::list_info_cache_key(
+                                &ps_id,
+                                &patch.oid,
+                                &b.name,
+                                &state_string,
+                            ),
+                            args: vec![
+                                patch.index.to_string(),
+                                state_string.clone(),
+                                patch.oid.to_string(),
+                                patch.summary.clone(),
+                            ],
+                        });
                     }
                 }
                 row.add_cell(Some(2), fg_color, bg_color, ")");
@@ -499,31 +545,31 @@ pub fn list(color: bool) -> Result<(), ListError> {
             row.add_cell(None, fg_color, bg_color, "()")
         }
 
-        println!("{}", row);
+        rows.push(row);
     }
 
-    if git::in_rebase(repo_gitdir_path) && config.list.reverse_order {
-        let todos_vec = git::in_rebase_todos(repo_gitdir_path).unwrap();
-        println!();
-        println!(
-            "Next commands to do ({} remaining commands)",
-            todos_vec.len()
-        );
-        for todo in todos_vec.iter() {
-            println!(
-                "{}",
-                rebase_todo_command_to_row(
-                    todo,
-                    color,
-                    config.list.patch_index.select_color(false),
-                    config.list.patch_sha.select_color(false)
-                )
-            );
-        }
-        println!("(use \"git rebase --edit-todo\" to view and edit)");
-        println!("(use \"git rebase --continue\" once you are satisfied with your changes)");
-        println!();
-    }
-
-    Ok(())
+    fill_in_pending_hook_cells(
+        &mut rows,
+        &pending_hook_cells,
+        repo_root_str,
+        repo_gitdir_str,
+        &config,
+        refresh,
+    )?;
+
+    hooks::find_and_execute_hook(
+        repo_root_str,
+        repo_gitdir_str,
+        &config.hooks,
+        &hooks::POST_LIST_HOOK,
+        &[],
+    )
+    .map_err(|e| ListError::PostListHookFailed(e.into()))?;
+
+    Ok(Listing {
+        status,
+        rows: rows.into_iter().map(ListingRow).collect(),
+        reverse_order: config.list.reverse_order,
+        rebase,
+    })
 }
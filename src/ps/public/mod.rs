@@ -2,18 +2,29 @@ pub mod append;
 pub mod backup_stack;
 pub mod branch;
 pub mod checkout;
+pub mod config;
+pub mod continue_operation;
+pub mod deps;
+pub mod events;
 pub mod fetch;
+pub mod gc;
+pub mod hooks;
 pub mod id;
 pub mod integrate;
+pub mod integrate_check;
 pub mod isolate;
 pub mod latest_github_release;
 pub mod list;
+pub mod patch_stack;
 pub mod pull;
 pub mod push;
 pub mod rebase;
 pub mod request_review;
 pub mod sha;
 pub mod show;
+pub mod stack;
 pub mod sync;
+pub mod transfer;
 pub mod upstream_patches;
 pub mod verify_isolation;
+pub mod verify_signatures;
@@ -0,0 +1,529 @@
+// This is the `patch_stack` module. It is the entry point for using Git Patch Stack as a library.
+// The `PatchStack` handle opens the patch stack checked out in a repository, by default the one
+// containing the current working directory, & its methods return data about it, e.g. its patches
+// & their branch states, rather than printing it, leaving presentation to the caller, e.g. the CLI
+// commands.
+
+use super::super::super::ps;
+use super::super::private::config;
+use super::super::private::git;
+use super::super::private::paths;
+use super::super::private::rr_branch_name;
+use super::super::private::state_computation;
+use super::sync;
+use super::upstream_patches;
+use uuid::Uuid;
+
+#[derive(Debug)]
+pub enum PatchStackError {
+    RepositoryNotFound(Box<dyn std::error::Error>),
+    GetPatchStackFailed(Box<dyn std::error::Error>),
+    GetPatchListFailed(Box<dyn std::error::Error>),
+    PatchIndexNotFound(usize),
+    GetRepoRootPathFailed(Box<dyn std::error::Error>),
+    PathNotUtf8,
+    GetConfigFailed(Box<dyn std::error::Error>),
+    RrBranchNamingInvalid(Box<dyn std::error::Error>),
+    CurrentBranchNameMissing,
+    GetUpstreamBranchNameFailed,
+    GetBehindCountFailed(Box<dyn std::error::Error>),
+    GetListPatchInfoFailed(Box<dyn std::error::Error>),
+    GetCommitDiffPatchIdFailed(Box<dyn std::error::Error>),
+    GetUpstreamPatchesFailed(upstream_patches::UpstreamPatchesError),
+    SyncFailed(sync::SyncError),
+}
+
+impl std::fmt::Display for PatchStackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RepositoryNotFound(e) => write!(f, "repository not found, {}", e),
+            Self::GetPatchStackFailed(e) => write!(f, "get patch stack failed, {}", e),
+            Self::GetPatchListFailed(e) => {
+                write!(f, "get patch stack list of patches failed, {}", e)
+            }
+            Self::PatchIndexNotFound(index) => write!(f, "patch with index {} not found", index),
+            Self::GetRepoRootPathFailed(e) => write!(f, "get repository root path failed, {}", e),
+            Self::PathNotUtf8 => write!(f, "path not utf-8"),
+            Self::GetConfigFailed(e) => write!(f, "get config failed, {}", e),
+            Self::RrBranchNamingInvalid(e) => {
+                write!(f, "invalid request review branch name template, {}", e)
+            }
+            Self::CurrentBranchNameMissing => write!(f, "current branch name missing"),
+            Self::GetUpstreamBranchNameFailed => write!(f, "get upstream branch name failed"),
+            Self::GetBehindCountFailed(e) => {
+                write!(f, "count commits behind the upstream branch failed, {}", e)
+            }
+            Self::GetListPatchInfoFailed(e) => {
+                write!(f, "get list of patch git info failed, {}", e)
+            }
+            Self::GetCommitDiffPatchIdFailed(e) => {
+                write!(f, "get commit diff patch id failed, {}", e)
+            }
+            Self::GetUpstreamPatchesFailed(e) => write!(f, "get upstream patches failed, {}", e),
+            Self::SyncFailed(e) => write!(f, "sync failed, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PatchStackError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::RepositoryNotFound(e) => Some(e.as_ref()),
+            Self::GetPatchStackFailed(e) => Some(e.as_ref()),
+            Self::GetPatchListFailed(e) => Some(e.as_ref()),
+            Self::PatchIndexNotFound(_) => None,
+            Self::GetRepoRootPathFailed(e) => Some(e.as_ref()),
+            Self::PathNotUtf8 => None,
+            Self::GetConfigFailed(e) => Some(e.as_ref()),
+            Self::RrBranchNamingInvalid(e) => Some(e.as_ref()),
+            Self::CurrentBranchNameMissing => None,
+            Self::GetUpstreamBranchNameFailed => None,
+            Self::GetBehindCountFailed(e) => Some(e.as_ref()),
+            Self::GetListPatchInfoFailed(e) => Some(e.as_ref()),
+            Self::GetCommitDiffPatchIdFailed(e) => Some(e.as_ref()),
+            Self::GetUpstreamPatchesFailed(e) => Some(e),
+            Self::SyncFailed(e) => Some(e),
+        }
+    }
+}
+
+/// A patch in the patch stack, index 0 being the patch closest to the base of the stack
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Patch {
+    pub index: usize,
+    pub oid: git2::Oid,
+    pub summary: String,
+    /// The patch stack id of the patch, once it has been given one, e.g. by request review
+    pub ps_id: Option<Uuid>,
+}
+
+/// The branch of the patch stack & how it relates to its upstream branch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StackStatus {
+    pub branch_name: String,
+    pub upstream_branch_name: String,
+    /// The number of patches in the stack
+    pub ahead: usize,
+    /// The number of commits on the upstream branch that aren't in the stack yet
+    pub behind: usize,
+    pub rebase_in_progress: bool,
+}
+
+/// The state of a patch on a branch it was branched to, e.g. its request review branch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BranchState {
+    pub name: String,
+    /// Whether it is a request review branch, named according to the request review branch naming
+    pub is_rr_branch: bool,
+    /// Whether the patch on the branch differs from the patch in the stack
+    pub differs: bool,
+    /// The state of the patch on the branch's remote tracking branch, if it was pushed there
+    pub remote: Option<RemoteBranchState>,
+}
+
+/// The state of a patch on the remote tracking branch of a branch it was branched to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteBranchState {
+    pub name: String,
+    /// Whether the patch on the remote branch differs from the patch in the stack
+    pub differs: bool,
+    /// Whether the remote branch has commits that aren't patches, e.g. ones a collaborator pushed
+    pub has_extra_commits: bool,
+}
+
+impl BranchState {
+    pub(crate) fn new(
+        branch: &state_computation::ListBranchInfo,
+        ps_id: Uuid,
+        commit_diff_id: Option<git2::Oid>,
+        rr_branch_naming: &rr_branch_name::RrBranchNaming,
+    ) -> Self {
+        let differs_in = |patches: &[state_computation::PatchInfo]| {
+            patches
+                .iter()
+                .find(|p| p.patch_id == ps_id)
+                .map(|p| commit_diff_id != Some(p.commit_diff_id))
+        };
+
+        Self {
+            name: branch.name.clone(),
+            is_rr_branch: rr_branch_naming.is_rr_branch_name(&branch.name),
+            differs: differs_in(&branch.patches).unwrap_or(true),
+            remote: branch.upstream.as_ref().and_then(|upstream| {
+                differs_in(&upstream.patches).map(|differs| RemoteBranchState {
+                    name: upstream.name.clone(),
+                    differs,
+                    has_extra_commits: upstream.patches.len() < upstream.commit_count,
+                })
+            }),
+        }
+    }
+
+    /// The compact form of the state shown by `gps list`, e.g. `l*r!`, where `l` is the local
+    /// branch, `r` the remote branch, `*` marks the patch differing on the preceding branch & `!`
+    /// marks the remote branch having commits that aren't patches
+    pub fn state_string(&self) -> String {
+        let mut state_string = String::from("l");
+        if self.differs {
+            state_string.push('*');
+        }
+        if let Some(ref remote) = self.remote {
+            state_string.push('r');
+            if remote.differs {
+                state_string.push('*');
+            }
+            if remote.has_extra_commits {
+                state_string.push('!');
+            }
+        }
+        state_string
+    }
+}
+
+/// A patch along with its state on each of the branches it was branched to
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatchState {
+    pub patch: Patch,
+    pub branches: Vec<BranchState>,
+}
+
+/// The result of syncing a patch or patch series up to its request review branch
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyncResult {
+    /// The name of the branch on the remote, e.g. ps/rr/some-patch
+    pub branch_name: String,
+    pub remote_name: String,
+}
+
+/// A handle on the patch stack checked out in a repository, e.g. the one containing the current
+/// working directory, the same patch stack the gps commands operate on
+pub struct PatchStack {
+    repo: git2::Repository,
+}
+
+impl PatchStack {
+    /// Open the patch stack of the repository containing the current working directory
+    pub fn open() -> Result<Self, PatchStackError> {
+        let repo =
+            git::create_cwd_repo().map_err(|e| PatchStackError::RepositoryNotFound(e.into()))?;
+        Ok(Self { repo })
+    }
+
+    /// The patch stack checked out in the given repository
+    pub fn from_repository(repo: git2::Repository) -> Self {
+        Self { repo }
+    }
+
+    /// The repository of the patch stack
+    pub fn repository(&self) -> &git2::Repository {
+        &self.repo
+    }
+
+    /// The patches in the stack, from the base of the stack to its head
+    pub fn patches(&self) -> Result<Vec<Patch>, PatchStackError> {
+        let patch_stack = ps::get_patch_stack(&self.repo)
+            .map_err(|e| PatchStackError::GetPatchStackFailed(e.into()))?;
+        let list_patches = ps::get_patch_list(&self.repo, &patch_stack)
+            .map_err(|e| PatchStackError::GetPatchListFailed(e.into()))?;
+        Ok(list_patches
+            .into_iter()
+            .map(|list_patch| Patch {
+                ps_id: self
+                    .repo
+                    .find_commit(list_patch.oid)
+                    .ok()
+                    .and_then(|c| ps::commit_ps_id(&c)),
+                index: list_patch.index,
+                oid: list_patch.oid,
+                summary: list_patch.summary,
+            })
+            .collect())
+    }
+
+    /// The patch at the given index of the stack
+    pub fn patch(&self, patch_index: usize) -> Result<Patch, PatchStackError> {
+        self.patches()?
+            .into_iter()
+            .nth(patch_index)
+            .ok_or(PatchStackError::PatchIndexNotFound(patch_index))
+    }
+
+    /// The branch of the stack & how far ahead & behind its upstream branch it is
+    pub fn status(&self) -> Result<StackStatus, PatchStackError> {
+        let (branch_name, upstream_branch_name) = self.branch_names()?;
+        let patch_stack = ps::get_patch_stack(&self.repo)
+            .map_err(|e| PatchStackError::GetPatchStackFailed(e.into()))?;
+        let ahead = ps::get_patch_list(&self.repo, &patch_stack)
+            .map_err(|e| PatchStackError::GetPatchListFailed(e.into()))?
+            .len();
+        let behind = behind_count(&self.repo, &patch_stack, &upstream_branch_name)
+            .map_err(PatchStackError::GetBehindCountFailed)?;
+        Ok(StackStatus {
+            branch_name,
+            upstream_branch_name,
+            ahead,
+            behind,
+            rebase_in_progress: git::in_rebase(self.repo.path()),
+        })
+    }
+
+    /// The patches in the stack along with their state on the branches they were branched to,
+    /// e.g. whether their request review branches are up to date, as shown by `gps list`
+    pub fn patch_states(&self) -> Result<Vec<PatchState>, PatchStackError> {
+        let repo_root_path = paths::repo_root_path(&self.repo)
+            .map_err(|e| PatchStackError::GetRepoRootPathFailed(e.into()))?;
+        let repo_root_str = repo_root_path
+            .to_str()
+            .ok_or(PatchStackError::PathNotUtf8)?;
+        let repo_gitdir_str = self
+            .repo
+            .path()
+            .to_str()
+            .ok_or(PatchStackError::PathNotUtf8)?;
+        let config = config::get_config(&self.repo, repo_root_str, repo_gitdir_str)
+            .map_err(|e| PatchStackError::GetConfigFailed(e.into()))?;
+        let rr_branch_naming = rr_branch_name::RrBranchNaming::new(&config.request_review)
+            .map_err(|e| PatchStackError::RrBranchNamingInvalid(e.into()))?;
+
+        let (branch_name, _) = self.branch_names()?;
+        let patch_stack = ps::get_patch_stack(&self.repo)
+            .map_err(|e| PatchStackError::GetPatchStackFailed(e.into()))?;
+        let base_oid = patch_stack
+            .base
+            .target()
+            .ok_or(PatchStackError::GetPatchStackFailed(
+                "patch stack base has no target".into(),
+            ))?;
+        let patch_info_collection = state_computation::get_list_patch_info(
+            &self.repo,
+            base_oid,
+            &branch_name,
+            &config.branch_scan,
+            &rr_branch_naming,
+        )
+        .map_err(|e| PatchStackError::GetListPatchInfoFailed(e.into()))?;
+
+        self.patches()?
+            .into_iter()
+            .map(|patch| {
+                let patch_info = patch
+                    .ps_id
+                    .and_then(|id| patch_info_collection.get(&id).map(|info| (id, info)));
+                let (ps_id, patch_info) = match patch_info {
+                    Some(patch_info) => patch_info,
+                    None => {
+                        return Ok(PatchState {
+                            patch,
+                            branches: vec![],
+                        })
+                    }
+                };
+                let commit = self
+                    .repo
+                    .find_commit(patch.oid)
+                    .map_err(|e| PatchStackError::GetCommitDiffPatchIdFailed(e.into()))?;
+                let commit_diff_id = commit_diff_id(&self.repo, &commit)
+                    .map_err(PatchStackError::GetCommitDiffPatchIdFailed)?;
+                let branches = patch_info
+                    .branches
+                    .iter()
+                    .map(|b| BranchState::new(b, ps_id, commit_diff_id, &rr_branch_naming))
+                    .collect();
+                Ok(PatchState { patch, branches })
+            })
+            .collect()
+    }
+
+    /// The commits on the upstream branch that aren't in the stack yet, newest first, e.g. to see
+    /// what a pull would bring in after fetching
+    pub fn upstream_patches(
+        &self,
+    ) -> Result<Vec<upstream_patches::UpstreamPatch>, PatchStackError> {
+        upstream_patches::find_upstream_patches(&self.repo)
+            .map_err(PatchStackError::GetUpstreamPatchesFailed)
+    }
+
+    /// Create or replace the request review branch of the patch or patch series & force push it up
+    /// to its remote, as `gps sync` does
+    pub fn sync(
+        &self,
+        start_patch_index: usize,
+        end_patch_index: Option<usize>,
+        given_branch_name: Option<String>,
+    ) -> Result<SyncResult, PatchStackError> {
+        let (branch_name, remote_name) = sync::sync_patches(
+            &self.repo,
+            start_patch_index,
+            end_patch_index,
+            given_branch_name,
+            false,
+        )
+        .map_err(PatchStackError::SyncFailed)?;
+        Ok(SyncResult {
+            branch_name,
+            remote_name,
+        })
+    }
+
+    /// The names of the branch of the stack & its upstream branch, e.g. main & origin/main, also
+    /// while rebasing
+    fn branch_names(&self) -> Result<(String, String), PatchStackError> {
+        let repo_gitdir_path = self.repo.path();
+        let branch_ref = match git::in_rebase(repo_gitdir_path) {
+            true => git::in_rebase_head_name(repo_gitdir_path)
+                .map_err(|_| PatchStackError::CurrentBranchNameMissing)?
+                .trim()
+                .to_string(),
+            false => git::get_current_branch(&self.repo)
+                .ok_or(PatchStackError::CurrentBranchNameMissing)?,
+        };
+        let upstream_ref = git::branch_upstream_name(&self.repo, &branch_ref)
+            .map_err(|_| PatchStackError::GetUpstreamBranchNameFailed)?;
+        Ok((
+            branch_ref.replace("refs/heads/", ""),
+            upstream_ref.replace("refs/remotes/", ""),
+        ))
+    }
+}
+
+/// Get the patch id of the commit's diff, none for merge commits
+pub(crate) fn commit_diff_id(
+    repo: &git2::Repository,
+    commit: &git2::Commit<'_>,
+) -> Result<Option<git2::Oid>, Box<dyn std::error::Error>> {
+    match git::commit_diff_patch_id(repo, commit) {
+        Ok(id) => Ok(Some(id)),
+        Err(git::CommitDiffPatchIdError::GetDiffFailed(git::CommitDiffError::MergeCommit)) => {
+            Ok(None)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Count the commits on the upstream tracking branch that aren't in the patch stack
+pub(crate) fn behind_count(
+    repo: &git2::Repository,
+    patch_stack: &ps::PatchStack,
+    upstream_tracking_branch_name: &str,
+) -> Result<usize, Box<dyn std::error::Error>> {
+    let upstream_oid = repo
+        .find_branch(upstream_tracking_branch_name, git2::BranchType::Remote)?
+        .into_reference()
+        .target()
+        .ok_or("upstream tracking branch has no target")?;
+    let head_oid = patch_stack
+        .head
+        .target()
+        .ok_or("patch stack head has no target")?;
+    let common_ancestor_oid = git::common_ancestor(repo, head_oid, upstream_oid)?;
+    Ok(git::count_commits(repo, upstream_oid, common_ancestor_oid)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PatchStack;
+    use crate::ps::private::git::test_utils::{commit_files, repo_init};
+    use tempfile::TempDir;
+
+    /// A patch stack of the given patches on main, tracking main on a bare origin remote, along
+    /// with the directories of the repository & the remote
+    fn stack_repo(patches: &[&str]) -> (TempDir, TempDir, PatchStack) {
+        let (td, repo) = repo_init();
+        let remote_td = TempDir::new().unwrap();
+        git2::Repository::init_bare(remote_td.path()).unwrap();
+        repo.remote("origin", remote_td.path().to_str().unwrap())
+            .unwrap()
+            .push(&["refs/heads/main:refs/heads/main"], None)
+            .unwrap();
+        repo.find_branch("main", git2::BranchType::Local)
+            .unwrap()
+            .set_upstream(Some("origin/main"))
+            .unwrap();
+        for patch in patches {
+            commit_files(&repo, &[(&format!("{}.txt", patch), patch)], patch);
+        }
+        (td, remote_td, PatchStack::from_repository(repo))
+    }
+
+    #[test]
+    fn test_patches_are_listed_from_the_base_of_the_stack() {
+        let (_td, _remote_td, patch_stack) = stack_repo(&["first", "second"]);
+
+        let patches = patch_stack.patches().unwrap();
+
+        let summaries: Vec<(usize, &str)> = patches
+            .iter()
+            .map(|p| (p.index, p.summary.as_str()))
+            .collect();
+        assert_eq!(summaries, vec![(0, "first"), (1, "second")]);
+        assert!(patches.iter().all(|p| p.ps_id.is_none()));
+        assert_eq!(patch_stack.patch(1).unwrap(), patches[1]);
+        assert!(patch_stack.patch(2).is_err());
+    }
+
+    #[test]
+    fn test_status_counts_the_commits_ahead_and_behind_the_upstream() {
+        let (_td, _remote_td, patch_stack) = stack_repo(&["first", "second"]);
+        let repo = patch_stack.repository();
+        let upstream_commit = repo
+            .find_reference("refs/remotes/origin/main")
+            .unwrap()
+            .peel_to_commit()
+            .unwrap();
+        let sig = repo.signature().unwrap();
+        repo.commit(
+            Some("refs/remotes/origin/main"),
+            &sig,
+            &sig,
+            "upstream",
+            &upstream_commit.tree().unwrap(),
+            &[&upstream_commit],
+        )
+        .unwrap();
+
+        let status = patch_stack.status().unwrap();
+
+        assert_eq!(status.branch_name, "main");
+        assert_eq!(status.upstream_branch_name, "origin/main");
+        assert_eq!(status.ahead, 2);
+        assert_eq!(status.behind, 1);
+        assert!(!status.rebase_in_progress);
+    }
+
+    #[test]
+    fn test_patch_states_have_no_branches_before_syncing() {
+        let (_td, _remote_td, patch_stack) = stack_repo(&["first"]);
+
+        let patch_states = patch_stack.patch_states().unwrap();
+
+        assert_eq!(patch_states.len(), 1);
+        assert!(patch_states[0].branches.is_empty());
+    }
+
+    #[test]
+    fn test_sync_pushes_the_request_review_branch_of_the_held_repository() {
+        let (_td, remote_td, patch_stack) = stack_repo(&["first", "second"]);
+
+        let sync_result = patch_stack
+            .sync(0, None, Some("first-patch".to_string()))
+            .unwrap();
+
+        assert_eq!(sync_result.remote_name, "origin");
+        assert_eq!(sync_result.branch_name, "first-patch");
+        let remote_repo = git2::Repository::open_bare(remote_td.path()).unwrap();
+        let pushed_commit = remote_repo
+            .find_reference("refs/heads/first-patch")
+            .unwrap()
+            .peel_to_commit()
+            .unwrap();
+        assert!(pushed_commit.summary().unwrap().starts_with("first"));
+
+        let patch_states = patch_stack.patch_states().unwrap();
+        let first_branches = &patch_states[0].branches;
+        assert_eq!(first_branches.len(), 1);
+        assert_eq!(first_branches[0].name, "first-patch");
+        assert_eq!(first_branches[0].state_string(), "lr");
+        assert!(patch_states[0].patch.ps_id.is_some());
+        assert!(patch_states[1].branches.is_empty());
+    }
+}
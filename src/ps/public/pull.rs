@@ -1,9 +1,14 @@
 use super::super::private::config;
+use super::super::private::config::pull::LandedBranchesCleanup;
+use super::super::private::conflicts;
 use super::super::private::git;
+use super::super::private::hooks;
+use super::super::private::in_memory_rebase;
 use super::super::private::paths;
 use super::super::private::utils;
 use super::super::public::fetch;
-use super::super::public::list;
+use super::super::public::gc;
+use super::super::public::upstream_patches::UpstreamPatch;
 
 #[derive(Debug)]
 pub enum PullError {
@@ -15,7 +20,18 @@ pub enum PullError {
     GetRepoRootPathFailed(paths::PathsError),
     PathNotUtf8,
     GetConfigFailed(config::GetConfigError),
-    ListFailed(list::ListError),
+    PrePullHookFailed(hooks::FindAndExecuteHookError),
+    PostPullHookFailed(hooks::FindAndExecuteHookError),
+    StoppedForConflictResolution(PullReport),
+    StopForConflictResolutionFailed(conflicts::ConflictsError),
+    OpenGitConfigFailed(git2::Error),
+    GetHeadOidFailed,
+    FindUpstreamBranchFailed(git2::Error),
+    GetCommonAncestorFailed(git::CommonAncestorError),
+    InMemoryRebaseFailed(in_memory_rebase::InMemoryRebaseError),
+    CheckoutFailed(git2::Error),
+    UpdateBranchFailed(git2::Error),
+    CleanupLandedBranchesFailed(gc::GcError),
 }
 
 impl std::fmt::Display for PullError {
@@ -29,7 +45,25 @@ impl std::fmt::Display for PullError {
             Self::GetRepoRootPathFailed(e) => write!(f, "get repository root path failed, {}", e),
             Self::PathNotUtf8 => write!(f, "path not utf-8"),
             Self::GetConfigFailed(e) => write!(f, "get config failed, {}", e),
-            Self::ListFailed(e) => write!(f, "get list failed, {}", e),
+            Self::PrePullHookFailed(e) => write!(f, "pre_pull hook failed, {}", e),
+            Self::PostPullHookFailed(e) => write!(f, "post_pull hook failed, {}", e),
+            Self::StoppedForConflictResolution(_) => write!(
+                f,
+                "stopped at conflicts in the rebase, resolve them, stage them with git add & run gps continue, or gps continue --abort"
+            ),
+            Self::StopForConflictResolutionFailed(e) => {
+                write!(f, "failed to stop for conflict resolution, {}", e)
+            }
+            Self::OpenGitConfigFailed(e) => write!(f, "open git config failed, {}", e),
+            Self::GetHeadOidFailed => write!(f, "get head oid failed"),
+            Self::FindUpstreamBranchFailed(e) => write!(f, "find upstream branch failed, {}", e),
+            Self::GetCommonAncestorFailed(e) => write!(f, "get common ancestor failed, {}", e),
+            Self::InMemoryRebaseFailed(e) => write!(f, "rebase failed, {}", e),
+            Self::CheckoutFailed(e) => write!(f, "checkout of the rebased patches failed, {}", e),
+            Self::UpdateBranchFailed(e) => write!(f, "update branch failed, {}", e),
+            Self::CleanupLandedBranchesFailed(e) => {
+                write!(f, "cleanup of landed branches failed, {}", e)
+            }
         }
     }
 }
@@ -45,20 +79,80 @@ impl std::error::Error for PullError {
             Self::GetRepoRootPathFailed(e) => Some(e),
             Self::PathNotUtf8 => None,
             Self::GetConfigFailed(e) => Some(e),
-            Self::ListFailed(e) => Some(e),
+            Self::PrePullHookFailed(e) => Some(e),
+            Self::PostPullHookFailed(e) => Some(e),
+            Self::StoppedForConflictResolution(_) => None,
+            Self::StopForConflictResolutionFailed(e) => Some(e),
+            Self::OpenGitConfigFailed(e) => Some(e),
+            Self::GetHeadOidFailed => None,
+            Self::FindUpstreamBranchFailed(e) => Some(e),
+            Self::GetCommonAncestorFailed(e) => Some(e),
+            Self::InMemoryRebaseFailed(e) => Some(e),
+            Self::CheckoutFailed(e) => Some(e),
+            Self::UpdateBranchFailed(e) => Some(e),
+            Self::CleanupLandedBranchesFailed(e) => Some(e),
         }
     }
 }
 
-pub fn pull(color: bool) -> Result<(), PullError> {
+/// A patch of the stack as it was before the pull
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PulledPatch {
+    pub oid: git2::Oid,
+    pub summary: String,
+}
+
+/// What a pull fetched & how the patches of the stack fared rebasing onto the upstream
+#[derive(Debug, Clone, Default)]
+pub struct PullReport {
+    /// The commits on the upstream branch that weren't in the patch stack, when
+    /// `fetch.show_upstream_patches_after_fetch` is enabled
+    pub upstream_patches: Option<Vec<UpstreamPatch>>,
+    /// The patches that landed upstream & were dropped from the stack
+    pub landed: Vec<PulledPatch>,
+    pub rebased: Vec<PulledPatch>,
+    /// The patch that conflicted, from which on the patches were rebased in the working copy
+    pub conflict: Option<PulledPatch>,
+}
+
+/// What followed the rebase of a pull, according to the pull config
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PullFollowUp {
+    /// The request review branches of landed patches that were deleted, when
+    /// `pull.cleanup_landed_branches` is auto
+    pub deleted_branches: Vec<String>,
+    /// The remote branches of the deleted branches that couldn't be deleted
+    pub remote_branches_not_deleted: Vec<gc::RemoteBranchNotDeleted>,
+    /// The request review branches of landed patches to delete with `gc::delete_branches` once
+    /// confirmed, when `pull.cleanup_landed_branches` is prompt
+    pub branches_to_confirm: Vec<String>,
+    /// Whether to list the patch stack, when `pull.show_list_post_pull` is enabled
+    pub list_patch_stack: bool,
+}
+
+/// The result of a pull
+#[derive(Debug, Clone)]
+pub struct PullResult {
+    pub report: PullReport,
+    pub follow_up: PullFollowUp,
+}
+
+/// Fetch & rebase the patch stack onto its upstream, reporting the patches that landed upstream &
+/// were dropped, the ones rebased, & the one conflicting, if any. When the rebase stops for the
+/// conflict to be resolved, the report comes with the `StoppedForConflictResolution` error.
+///
+/// The rebase happens in memory, recognizing landed patches by their ps-id or their diff, & only
+/// falls back to rebasing in the working copy from the first patch that conflicts, so the
+/// conflict can be resolved there.
+pub fn pull() -> Result<PullResult, PullError> {
     let repo = git::create_cwd_repo().map_err(|_| PullError::RepositoryMissing)?;
 
     let repo_root_path = paths::repo_root_path(&repo).map_err(PullError::GetRepoRootPathFailed)?;
     let repo_root_str = repo_root_path.to_str().ok_or(PullError::PathNotUtf8)?;
     let repo_gitdir_path = repo.path();
     let repo_gitdir_str = repo_gitdir_path.to_str().ok_or(PullError::PathNotUtf8)?;
-    let config =
-        config::get_config(repo_root_str, repo_gitdir_str).map_err(PullError::GetConfigFailed)?;
+    let config = config::get_config(&repo, repo_root_str, repo_gitdir_str)
+        .map_err(PullError::GetConfigFailed)?;
 
     let head_ref = repo
         .head()
@@ -71,29 +165,147 @@ pub fn pull(color: bool) -> Result<(), PullError> {
     let upstream_branch_name = git::branch_upstream_name(&repo, head_branch_name)
         .map_err(|_| PullError::GetUpstreamBranchNameFailed)?;
 
-    println!("Fetching upstream patches...");
-    fetch::fetch(color).map_err(PullError::FetchFailed)?;
-    println!();
+    hooks::find_and_execute_hook(
+        repo_root_str,
+        repo_gitdir_str,
+        &config.hooks,
+        &hooks::PRE_PULL_HOOK,
+        &[&upstream_branch_name],
+    )
+    .map_err(PullError::PrePullHookFailed)?;
+
+    let upstream_patches = fetch::fetch().map_err(PullError::FetchFailed)?;
 
-    println!("Rebasing...");
-    utils::execute(
+    let git_config = git2::Config::open_default().map_err(PullError::OpenGitConfigFailed)?;
+    let head_oid = head_ref.target().ok_or(PullError::GetHeadOidFailed)?;
+    let upstream_oid = repo
+        .refname_to_id(&upstream_branch_name)
+        .map_err(PullError::FindUpstreamBranchFailed)?;
+    let base_oid = git::common_ancestor(&repo, head_oid, upstream_oid)
+        .map_err(PullError::GetCommonAncestorFailed)?;
+    let rebase = in_memory_rebase::rebase_in_memory(
+        &repo,
+        &git_config,
+        &config.rewrite,
+        base_oid,
+        head_oid,
+        upstream_oid,
+    )
+    .map_err(PullError::InMemoryRebaseFailed)?;
+    let pulled_patch = |oid: &git2::Oid| PulledPatch {
+        oid: *oid,
+        summary: git::get_summary(&repo, oid).unwrap_or_default(),
+    };
+    let report = PullReport {
+        upstream_patches,
+        landed: rebase.landed.iter().map(pulled_patch).collect(),
+        rebased: rebase
+            .rebased
+            .iter()
+            .map(|(oid, _)| pulled_patch(oid))
+            .collect(),
+        conflict: rebase.stopped_at.as_ref().map(pulled_patch),
+    };
+
+    let conflicting_oid = match rebase.stopped_at {
+        Some(conflicting_oid) => conflicting_oid,
+        None => {
+            // only the files that differ between the old & new patch stack are touched, leaving
+            // any uncommitted changes to other files alone
+            let new_head_commit = repo
+                .find_commit(rebase.head_oid)
+                .map_err(PullError::CheckoutFailed)?;
+            repo.checkout_tree(
+                new_head_commit.as_object(),
+                Some(git2::build::CheckoutBuilder::new().safe()),
+            )
+            .map_err(PullError::CheckoutFailed)?;
+            repo.find_reference(head_branch_name)
+                .and_then(|mut r| r.set_target(rebase.head_oid, "gps pull: rebase onto upstream"))
+                .map_err(PullError::UpdateBranchFailed)?;
+
+            let follow_up = finish_pull(
+                repo_root_str,
+                repo_gitdir_str,
+                &config,
+                &upstream_branch_name,
+            )?;
+            return Ok(PullResult { report, follow_up });
+        }
+    };
+
+    // the conflict needs resolving in the working copy, so the patches from the conflicting one
+    // on are rebased there, on top of the ones already rebased in memory
+    let rebased_head_oid_str = rebase.head_oid.to_string();
+    let conflicting_parent_str = format!("{}^", conflicting_oid);
+    let rebase_result = utils::execute(
         "git",
         &[
             "rebase",
             "--no-reapply-cherry-picks",
             "--onto",
-            upstream_branch_name.as_str(),
-            upstream_branch_name.as_str(),
+            rebased_head_oid_str.as_str(),
+            conflicting_parent_str.as_str(),
             head_branch_shorthand,
         ],
+    );
+    match rebase_result {
+        Err(_) if config.conflicts.resolve_in_worktree && git::in_rebase(repo_gitdir_path) => {
+            conflicts::stop_in_working_copy(
+                &repo,
+                conflicts::InterruptedOperation::Pull {
+                    upstream_branch_name,
+                },
+            )
+            .map_err(PullError::StopForConflictResolutionFailed)?;
+            return Err(PullError::StoppedForConflictResolution(report));
+        }
+        result => result.map_err(PullError::RebaseFailed)?,
+    };
+
+    let follow_up = finish_pull(
+        repo_root_str,
+        repo_gitdir_str,
+        &config,
+        &upstream_branch_name,
+    )?;
+    Ok(PullResult { report, follow_up })
+}
+
+/// Run what follows the rebase of a pull, the post_pull hook & the optional cleanup of the request
+/// review branches of landed patches, leaving the ones to confirm & the optional listing of the
+/// patch stack to the caller
+pub fn finish_pull(
+    repo_root_str: &str,
+    repo_gitdir_str: &str,
+    config: &config::PsConfig,
+    upstream_branch_name: &str,
+) -> Result<PullFollowUp, PullError> {
+    hooks::find_and_execute_hook(
+        repo_root_str,
+        repo_gitdir_str,
+        &config.hooks,
+        &hooks::POST_PULL_HOOK,
+        &[upstream_branch_name],
     )
-    .map_err(PullError::RebaseFailed)?;
-    println!();
+    .map_err(PullError::PostPullHookFailed)?;
 
-    if config.pull.show_list_post_pull {
-        println!("Listing patch stack...");
-        list::list(color).map_err(PullError::ListFailed)?
+    let mut follow_up = PullFollowUp {
+        list_patch_stack: config.pull.show_list_post_pull,
+        ..PullFollowUp::default()
+    };
+    if config.pull.cleanup_landed_branches != LandedBranchesCleanup::Off {
+        let repo = git::create_cwd_repo().map_err(|_| PullError::RepositoryMissing)?;
+        let landed = gc::find_landed_branches(&repo, config)
+            .map_err(PullError::CleanupLandedBranchesFailed)?;
+        if config.pull.cleanup_landed_branches == LandedBranchesCleanup::Prompt {
+            follow_up.branches_to_confirm = landed;
+        } else {
+            follow_up.remote_branches_not_deleted = gc::delete_branches_in(&repo, &landed)
+                .map_err(PullError::CleanupLandedBranchesFailed)?;
+            follow_up.deleted_branches = landed;
+        }
     }
 
-    Ok(())
+    Ok(follow_up)
 }
@@ -1,3 +1,4 @@
+use super::super::private;
 use super::super::private::git;
 use std::result::Result;
 
@@ -86,7 +87,8 @@ pub fn push(branch_name: String) -> Result<(), PushError> {
 
     // e.g. origin/the-branch so it is <remote>/<branch-name-on-remote>
 
-    git::ext_push(
+    private::push::push(
+        &repo,
         false,
         remote_branch_remote,
         &branch_name,
@@ -1,4 +1,7 @@
+use super::super::private::config;
 use super::super::private::git;
+use super::super::private::hooks;
+use super::super::private::paths;
 use super::super::private::utils;
 
 #[derive(Debug)]
@@ -7,14 +10,58 @@ pub enum RebaseError {
     GetHeadBranchNameFailed,
     GetUpstreamBranchNameFailed,
     RebaseFailed(utils::ExecuteError),
+    GetRepoRootPathFailed(paths::PathsError),
+    PathNotUtf8,
+    GetConfigFailed(config::GetConfigError),
+    PreRebaseHookFailed(hooks::FindAndExecuteHookError),
+    PostRebaseHookFailed(hooks::FindAndExecuteHookError),
+}
+
+impl std::fmt::Display for RebaseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RepositoryMissing => write!(f, "repository missing"),
+            Self::GetHeadBranchNameFailed => write!(f, "get head branch name failed"),
+            Self::GetUpstreamBranchNameFailed => write!(f, "get upstream branch name failed"),
+            Self::RebaseFailed(e) => write!(f, "rebase failed, {}", e),
+            Self::GetRepoRootPathFailed(e) => write!(f, "get repository root path failed, {}", e),
+            Self::PathNotUtf8 => write!(f, "path not utf-8"),
+            Self::GetConfigFailed(e) => write!(f, "get config failed, {}", e),
+            Self::PreRebaseHookFailed(e) => write!(f, "pre rebase hook failed, {}", e),
+            Self::PostRebaseHookFailed(e) => write!(f, "post rebase hook failed, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for RebaseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::RepositoryMissing => None,
+            Self::GetHeadBranchNameFailed => None,
+            Self::GetUpstreamBranchNameFailed => None,
+            Self::RebaseFailed(e) => Some(e),
+            Self::GetRepoRootPathFailed(e) => Some(e),
+            Self::PathNotUtf8 => None,
+            Self::GetConfigFailed(e) => Some(e),
+            Self::PreRebaseHookFailed(e) => Some(e),
+            Self::PostRebaseHookFailed(e) => Some(e),
+        }
+    }
 }
 
 pub fn rebase(continue_rebase: bool) -> Result<(), RebaseError> {
+    let repo = git::create_cwd_repo().map_err(|_| RebaseError::RepositoryMissing)?;
+
+    let repo_root_path =
+        paths::repo_root_path(&repo).map_err(RebaseError::GetRepoRootPathFailed)?;
+    let repo_root_str = repo_root_path.to_str().ok_or(RebaseError::PathNotUtf8)?;
+    let repo_gitdir_str = repo.path().to_str().ok_or(RebaseError::PathNotUtf8)?;
+    let config = config::get_config(&repo, repo_root_str, repo_gitdir_str)
+        .map_err(RebaseError::GetConfigFailed)?;
+
     if continue_rebase {
-        utils::execute("git", &["rebase", "--continue"]).map_err(RebaseError::RebaseFailed)
+        utils::execute("git", &["rebase", "--continue"]).map_err(RebaseError::RebaseFailed)?;
     } else {
-        let repo = git::create_cwd_repo().map_err(|_| RebaseError::RepositoryMissing)?;
-
         let head_ref = repo
             .head()
             .map_err(|_| RebaseError::GetHeadBranchNameFailed)?;
@@ -28,6 +75,15 @@ pub fn rebase(continue_rebase: bool) -> Result<(), RebaseError> {
         let upstream_branch_name = git::branch_upstream_name(&repo, head_branch_name)
             .map_err(|_| RebaseError::GetUpstreamBranchNameFailed)?;
 
+        hooks::find_and_execute_hook(
+            repo_root_str,
+            repo_gitdir_str,
+            &config.hooks,
+            &hooks::PRE_REBASE_HOOK,
+            &[],
+        )
+        .map_err(RebaseError::PreRebaseHookFailed)?;
+
         utils::execute(
             "git",
             &[
@@ -39,6 +95,21 @@ pub fn rebase(continue_rebase: bool) -> Result<(), RebaseError> {
                 head_branch_shorthand,
             ],
         )
-        .map_err(RebaseError::RebaseFailed)
+        .map_err(RebaseError::RebaseFailed)?;
     }
+
+    // the rebase may have paused, e.g. to edit a patch, in which case it hasn't finished yet and
+    // the post_rebase hook will be executed once it is continued to completion
+    if !git::in_rebase(repo.path()) {
+        hooks::find_and_execute_hook(
+            repo_root_str,
+            repo_gitdir_str,
+            &config.hooks,
+            &hooks::POST_REBASE_HOOK,
+            &[],
+        )
+        .map_err(RebaseError::PostRebaseHookFailed)?;
+    }
+
+    Ok(())
 }
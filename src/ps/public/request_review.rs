@@ -3,7 +3,6 @@ use super::super::private::config;
 use super::super::private::git;
 use super::super::private::hooks;
 use super::super::private::paths;
-use super::super::private::utils;
 use super::sync;
 use super::verify_isolation;
 use std::fmt;
@@ -26,8 +25,12 @@ pub enum RequestReviewError {
     FindRemoteFailed(Box<dyn std::error::Error>),
     RemoteUrlNotUtf8,
     HookExecutionFailed(Box<dyn std::error::Error>),
+    GetPatchStackFailed(Box<dyn std::error::Error>),
+    GetPatchListFailed(Box<dyn std::error::Error>),
+    PreRequestReviewHookFailed(Box<dyn std::error::Error>),
     PostSyncHookNotExecutable(PathBuf),
     FindHookFailed(Box<dyn std::error::Error>),
+    StoppedForConflictResolution(String, PathBuf),
     Unhandled(Box<dyn std::error::Error>),
 }
 
@@ -38,6 +41,9 @@ impl From<sync::SyncError> for RequestReviewError {
             sync::SyncError::ConflictsExist(src_oid, dst_oid) => {
                 Self::ConflictsExist(src_oid, dst_oid)
             }
+            sync::SyncError::StoppedForConflictResolution(oid, path) => {
+                Self::StoppedForConflictResolution(oid, path)
+            }
             _ => Self::Unhandled(value.into()),
         }
     }
@@ -92,6 +98,13 @@ impl fmt::Display for RequestReviewError {
                 e
             ),
             Self::GetConfigFailed(e) => write!(f, "Failed to get Git Patch Stack config - {}", e),
+            Self::GetPatchStackFailed(e) => write!(f, "Failed to get patch stack - {}", e),
+            Self::GetPatchListFailed(e) => write!(f, "Failed to get patch list - {}", e),
+            Self::PreRequestReviewHookFailed(e) => write!(
+                f,
+                "The pre_request_review hook rejected the request for review - {}",
+                e
+            ),
             Self::IsolationVerificationFailed(e) => {
                 write!(f, "Isolation verification failed - {}", e)
             }
@@ -106,6 +119,12 @@ impl fmt::Display for RequestReviewError {
             Self::FindRemoteFailed(e) => {
                 write!(f, "Failed to find remote - {}", e)
             }
+            Self::StoppedForConflictResolution(oid, path) => write!(
+                f,
+                "Stopped at conflicts playing commit ({}), resolve them in the worktree at {}, stage them with git add & run gps continue, or gps continue --abort",
+                oid,
+                path.display()
+            ),
             Self::Unhandled(e) => write!(f, "{}", e),
         }
     }
@@ -128,8 +147,12 @@ impl std::error::Error for RequestReviewError {
             Self::FindRemoteFailed(e) => Some(e.as_ref()),
             Self::RemoteUrlNotUtf8 => None,
             Self::HookExecutionFailed(e) => Some(e.as_ref()),
+            Self::GetPatchStackFailed(e) => Some(e.as_ref()),
+            Self::GetPatchListFailed(e) => Some(e.as_ref()),
+            Self::PreRequestReviewHookFailed(e) => Some(e.as_ref()),
             Self::PostSyncHookNotExecutable(_) => None,
             Self::FindHookFailed(e) => Some(e.as_ref()),
+            Self::StoppedForConflictResolution(_, _) => None,
             Self::Unhandled(e) => Some(e.as_ref()),
         }
     }
@@ -139,7 +162,6 @@ pub fn request_review(
     start_patch_index: usize,
     end_patch_index: Option<usize>,
     given_branch_name: Option<String>,
-    color: bool,
     isolation_verification_hook: bool,
     post_sync_hook: bool,
 ) -> Result<(), RequestReviewError> {
@@ -157,38 +179,100 @@ pub fn request_review(
         .to_str()
         .ok_or(RequestReviewError::PathNotUtf8)?;
 
-    let mut post_sync_hook_path: Option<PathBuf> = None;
-    if post_sync_hook {
-        post_sync_hook_path =
-            match hooks::find_hook(repo_root_str, repo_gitdir_str, "request_review_post_sync") {
-                Ok(hook_path) => Some(hook_path),
-                Err(hooks::FindHookError::NotFound) => None,
-                Err(hooks::FindHookError::NotExecutable(p)) => {
-                    return Err(RequestReviewError::PostSyncHookNotExecutable(p));
-                }
-                Err(e) => {
-                    return Err(RequestReviewError::FindHookFailed(e.into()));
-                }
-            }
-    }
-
-    let config = config::get_config(repo_root_str, repo_gitdir_str)
+    let config = config::get_config(&repo, repo_root_str, repo_gitdir_str)
         .map_err(|e| RequestReviewError::GetConfigFailed(e.into()))?;
 
+    let post_sync_hooks = if post_sync_hook {
+        find_post_sync_hooks(repo_root_str, repo_gitdir_str, &config)?
+    } else {
+        None
+    };
+
+    // execute pre request review hook, giving it the chance to veto the request for review
+    let patch_stack = ps::get_patch_stack(&repo)
+        .map_err(|e| RequestReviewError::GetPatchStackFailed(e.into()))?;
+    let patches = ps::get_patch_list(&repo, &patch_stack)
+        .map_err(|e| RequestReviewError::GetPatchListFailed(e.into()))?;
+    let (first_patch_index, last_patch_index) = match end_patch_index {
+        Some(end_index) if end_index < start_patch_index => (end_index, start_patch_index),
+        Some(end_index) => (start_patch_index, end_index),
+        None => (start_patch_index, start_patch_index),
+    };
+    let patch_shas: Vec<String> = patches
+        .iter()
+        .filter(|p| p.index >= first_patch_index && p.index <= last_patch_index)
+        .map(|p| p.oid.to_string())
+        .collect();
+    let patch_sha_strs: Vec<&str> = patch_shas.iter().map(|sha| sha.as_str()).collect();
+    hooks::find_and_execute_hook(
+        repo_root_str,
+        repo_gitdir_str,
+        &config.hooks,
+        &hooks::PRE_REQUEST_REVIEW_HOOK,
+        &patch_sha_strs,
+    )
+    .map_err(|e| RequestReviewError::PreRequestReviewHookFailed(e.into()))?;
+
     // verify isolation
     if isolation_verification_hook && config.request_review.verify_isolation {
-        verify_isolation::verify_isolation(start_patch_index, end_patch_index, color)?;
+        verify_isolation::verify_isolation(start_patch_index, end_patch_index)?;
     }
 
     // sync patch up to remote
-    let (patch_upstream_branch_name, _patch_upstream_branch_remote_name) =
-        ps::public::sync::sync(start_patch_index, end_patch_index, given_branch_name)?;
+    let (patch_upstream_branch_name, _patch_upstream_branch_remote_name) = ps::public::sync::sync(
+        start_patch_index,
+        end_patch_index,
+        given_branch_name,
+        post_sync_hook,
+    )?;
 
-    // execute post sync hook
-    let cur_patch_stack_branch_name = git::get_current_branch(&repo)
+    if let Some(found_hooks) = post_sync_hooks {
+        execute_post_sync_hooks(
+            &repo,
+            repo_gitdir_str,
+            &config,
+            &found_hooks,
+            &patch_upstream_branch_name,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Find the request_review_post_sync hooks, None if there aren't any
+pub fn find_post_sync_hooks(
+    repo_root_str: &str,
+    repo_gitdir_str: &str,
+    config: &config::PsConfig,
+) -> Result<Option<Vec<(hooks::HookLocation, PathBuf)>>, RequestReviewError> {
+    match hooks::find_hooks(
+        repo_root_str,
+        repo_gitdir_str,
+        &config.hooks,
+        &hooks::REQUEST_REVIEW_POST_SYNC_HOOK,
+    ) {
+        Ok(found_hooks) => Ok(Some(found_hooks)),
+        Err(hooks::FindHookError::NotFound) => Ok(None),
+        Err(hooks::FindHookError::NotExecutable(p)) => {
+            Err(RequestReviewError::PostSyncHookNotExecutable(p))
+        }
+        Err(e) => Err(RequestReviewError::FindHookFailed(e.into())),
+    }
+}
+
+/// Execute the request_review_post_sync hooks for the patch branch synced up to the given
+/// upstream branch, relative to its remote
+pub fn execute_post_sync_hooks(
+    repo: &git2::Repository,
+    repo_gitdir_str: &str,
+    config: &config::PsConfig,
+    found_hooks: &[(hooks::HookLocation, PathBuf)],
+    patch_upstream_branch_name: &str,
+) -> Result<(), RequestReviewError> {
+    let cur_patch_stack_branch_name = git::get_current_branch(repo)
         .ok_or(RequestReviewError::CurrentPatchStackBranchNameMissing)?;
     let cur_patch_stack_upstream_branch_name =
-        git::branch_upstream_name(&repo, cur_patch_stack_branch_name.as_str())
+        git::branch_upstream_name(repo, cur_patch_stack_branch_name.as_str())
             .map_err(|_| RequestReviewError::GetCurrentPatchStackUpstreamBranchNameFailed)?;
     let cur_patch_stack_upstream_branch_remote_name = repo
         .branch_remote_name(&cur_patch_stack_upstream_branch_name)
@@ -211,18 +295,18 @@ pub fn request_review(
     let cur_patch_stack_upstream_branch_name_relative_to_remote =
         str::replace(&cur_patch_stack_upstream_branch_name, pattern.as_str(), "");
 
-    if let Some(hook_path) = post_sync_hook_path {
-        utils::execute(
-            hook_path.to_str().ok_or(RequestReviewError::PathNotUtf8)?,
-            &[
-                &patch_upstream_branch_name,
-                &cur_patch_stack_upstream_branch_name_relative_to_remote,
-                cur_patch_stack_upstream_branch_remote_name_str,
-                cur_patch_stack_upstream_branch_remote_url_str,
-            ],
-        )
-        .map_err(|e| RequestReviewError::HookExecutionFailed(e.into()))?;
-    }
-
+    hooks::execute_hooks(
+        repo_gitdir_str,
+        &config.hooks,
+        &hooks::REQUEST_REVIEW_POST_SYNC_HOOK,
+        found_hooks,
+        &[
+            patch_upstream_branch_name,
+            &cur_patch_stack_upstream_branch_name_relative_to_remote,
+            cur_patch_stack_upstream_branch_remote_name_str,
+            cur_patch_stack_upstream_branch_remote_url_str,
+        ],
+    )
+    .map_err(|e| RequestReviewError::HookExecutionFailed(e.into()))?;
     Ok(())
 }
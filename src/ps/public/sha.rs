@@ -25,7 +25,8 @@ impl std::error::Error for ShaError {
     }
 }
 
-pub fn sha(patch_index: usize, exclude_newline: bool) -> Result<(), ShaError> {
+/// Get the sha of the patch at the given index of the patch stack
+pub fn sha(patch_index: usize) -> Result<git2::Oid, ShaError> {
     let repo = git::create_cwd_repo().map_err(|e| ShaError::Unhandled(e.into()))?;
 
     let patch_stack = ps::get_patch_stack(&repo).map_err(|e| ShaError::Unhandled(e.into()))?;
@@ -37,11 +38,5 @@ pub fn sha(patch_index: usize, exclude_newline: bool) -> Result<(), ShaError> {
         .ok_or(ShaError::PatchIndexNotFound)?
         .oid;
 
-    if exclude_newline {
-        print!("{}", patch_oid);
-    } else {
-        println!("{}", patch_oid);
-    }
-
-    Ok(())
+    Ok(patch_oid)
 }
@@ -0,0 +1,446 @@
+use super::super::super::ps;
+use super::super::private::config;
+use super::super::private::events::{self, Event};
+use super::super::private::git;
+use super::super::private::paths;
+use super::super::private::rr_branch_name;
+use super::super::private::stacks;
+use super::super::private::state_computation;
+use super::super::private::utils;
+use std::collections::HashMap;
+use std::result::Result;
+
+#[derive(Debug)]
+pub enum StackError {
+    RepositoryNotFound,
+    GetRepoRootPathFailed(Box<dyn std::error::Error>),
+    PathNotUtf8,
+    GetConfigFailed(Box<dyn std::error::Error>),
+    RrBranchNamingInvalid(Box<dyn std::error::Error>),
+    ReadStacksFailed(Box<dyn std::error::Error>),
+    RegisterStackFailed(Box<dyn std::error::Error>),
+    StackAlreadyExists(String),
+    StackNotFound(String),
+    OntoMissing,
+    OntoNotFound(String),
+    CreateBranchFailed(Box<dyn std::error::Error>),
+    SetUpstreamFailed(Box<dyn std::error::Error>),
+    CheckoutFailed(Box<dyn std::error::Error>),
+    GetStackStateFailed(Box<dyn std::error::Error>),
+    StackIsCurrent(String),
+    StackHasPatches(String, usize),
+    DeleteRemoteBranchFailed(Box<dyn std::error::Error>),
+    DeleteLocalBranchFailed(Box<dyn std::error::Error>),
+}
+
+impl std::fmt::Display for StackError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RepositoryNotFound => write!(f, "repository not found"),
+            Self::GetRepoRootPathFailed(e) => write!(f, "get repository root path failed, {}", e),
+            Self::PathNotUtf8 => write!(f, "path not utf-8"),
+            Self::GetConfigFailed(e) => write!(f, "get config failed, {}", e),
+            Self::RrBranchNamingInvalid(e) => {
+                write!(f, "invalid request review branch name template, {}", e)
+            }
+            Self::ReadStacksFailed(e) => write!(f, "read patch stacks failed, {}", e),
+            Self::RegisterStackFailed(e) => write!(f, "register patch stack failed, {}", e),
+            Self::StackAlreadyExists(name) => write!(f, "branch {} already exists", name),
+            Self::StackNotFound(name) => write!(f, "patch stack {} not found", name),
+            Self::OntoMissing => write!(
+                f,
+                "the current branch has no upstream, specify the one to stack onto with --onto"
+            ),
+            Self::OntoNotFound(name) => write!(f, "remote tracking branch {} not found", name),
+            Self::CreateBranchFailed(e) => write!(f, "create branch failed, {}", e),
+            Self::SetUpstreamFailed(e) => write!(f, "set branch upstream failed, {}", e),
+            Self::CheckoutFailed(e) => write!(f, "checkout failed, {}", e),
+            Self::GetStackStateFailed(e) => write!(f, "get patch stack state failed, {}", e),
+            Self::StackIsCurrent(name) => {
+                write!(f, "patch stack {} is checked out, switch to another first", name)
+            }
+            Self::StackHasPatches(name, count) => write!(
+                f,
+                "patch stack {} has {} patch(es) that haven't been integrated, use --force to delete it anyway",
+                name, count
+            ),
+            Self::DeleteRemoteBranchFailed(e) => write!(f, "delete remote branch failed, {}", e),
+            Self::DeleteLocalBranchFailed(e) => write!(f, "delete local branch failed, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StackError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::RepositoryNotFound => None,
+            Self::GetRepoRootPathFailed(e) => Some(e.as_ref()),
+            Self::PathNotUtf8 => None,
+            Self::GetConfigFailed(e) => Some(e.as_ref()),
+            Self::RrBranchNamingInvalid(e) => Some(e.as_ref()),
+            Self::ReadStacksFailed(e) => Some(e.as_ref()),
+            Self::RegisterStackFailed(e) => Some(e.as_ref()),
+            Self::StackAlreadyExists(_) => None,
+            Self::StackNotFound(_) => None,
+            Self::OntoMissing => None,
+            Self::OntoNotFound(_) => None,
+            Self::CreateBranchFailed(e) => Some(e.as_ref()),
+            Self::SetUpstreamFailed(e) => Some(e.as_ref()),
+            Self::CheckoutFailed(e) => Some(e.as_ref()),
+            Self::GetStackStateFailed(e) => Some(e.as_ref()),
+            Self::StackIsCurrent(_) => None,
+            Self::StackHasPatches(_, _) => None,
+            Self::DeleteRemoteBranchFailed(e) => Some(e.as_ref()),
+            Self::DeleteLocalBranchFailed(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+/// How far along the review process the patches of a stack are
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct StackReviewCounts {
+    /// Patches not on any branch yet
+    pub new: usize,
+    /// Patches on a local branch that hasn't been pushed
+    pub branched: usize,
+    /// Patches pushed for review as they are in the stack
+    pub requested: usize,
+    /// Patches pushed for review that have changed in the stack since
+    pub changed: usize,
+}
+
+#[derive(Debug)]
+pub struct StackSummary {
+    pub name: String,
+    /// The remote tracking branch the stack is based on, e.g. `origin/main`
+    pub upstream: Option<String>,
+    pub is_current: bool,
+    pub ahead: usize,
+    pub behind: usize,
+    pub review: StackReviewCounts,
+}
+
+struct RepoContext {
+    repo: git2::Repository,
+    root: String,
+    gitdir: String,
+}
+
+fn repo_context() -> Result<RepoContext, StackError> {
+    let repo = git::create_cwd_repo().map_err(|_| StackError::RepositoryNotFound)?;
+    let repo_root_path =
+        paths::repo_root_path(&repo).map_err(|e| StackError::GetRepoRootPathFailed(e.into()))?;
+    let root = repo_root_path
+        .to_str()
+        .ok_or(StackError::PathNotUtf8)?
+        .to_string();
+    let gitdir = repo
+        .path()
+        .to_str()
+        .ok_or(StackError::PathNotUtf8)?
+        .to_string();
+    Ok(RepoContext { repo, root, gitdir })
+}
+
+fn current_branch_name(repo: &git2::Repository) -> Option<String> {
+    git::get_current_branch(repo).map(|r| r.replace("refs/heads/", ""))
+}
+
+/// The stacks of the repository, those created with `stack new` along with the current branch
+/// when it is based on an upstream, as it is the stack gps has always operated on
+fn stack_names(repo: &git2::Repository) -> Result<Vec<String>, StackError> {
+    let mut names =
+        stacks::registered_stacks(repo).map_err(|e| StackError::ReadStacksFailed(e.into()))?;
+    if let Some(current) = current_branch_name(repo) {
+        if !names.contains(&current) && stacks::stack_upstream_ref_name(repo, &current).is_some() {
+            names.push(current);
+            names.sort();
+        }
+    }
+    Ok(names)
+}
+
+/// Register the current branch as a stack, when it is based on an upstream, so it is still known
+/// about once switched away from
+fn register_current_stack(repo: &git2::Repository) -> Result<(), StackError> {
+    if let Some(current) = current_branch_name(repo) {
+        if stacks::stack_upstream_ref_name(repo, &current).is_some() {
+            stacks::register_stack(repo, &current)
+                .map_err(|e| StackError::RegisterStackFailed(e.into()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Create a new patch stack, a branch based on the given remote tracking branch, e.g.
+/// `origin/main`, or on the current stack's upstream, and switch to it. Returns the upstream the
+/// stack is based on.
+pub fn new(name: &str, onto: Option<String>) -> Result<String, StackError> {
+    let RepoContext { repo, .. } = repo_context()?;
+
+    if repo.find_branch(name, git2::BranchType::Local).is_ok() {
+        return Err(StackError::StackAlreadyExists(name.to_string()));
+    }
+
+    let onto = match onto {
+        Some(onto) => onto,
+        None => current_branch_name(&repo)
+            .and_then(|current| stacks::stack_upstream_ref_name(&repo, &current))
+            .map(|upstream| upstream.replace("refs/remotes/", ""))
+            .ok_or(StackError::OntoMissing)?,
+    };
+    let onto_commit = repo
+        .find_branch(&onto, git2::BranchType::Remote)
+        .map_err(|_| StackError::OntoNotFound(onto.clone()))?
+        .get()
+        .peel_to_commit()
+        .map_err(|_| StackError::OntoNotFound(onto.clone()))?;
+
+    let mut branch = repo
+        .branch(name, &onto_commit, false)
+        .map_err(|e| StackError::CreateBranchFailed(e.into()))?;
+    branch
+        .set_upstream(Some(&onto))
+        .map_err(|e| StackError::SetUpstreamFailed(e.into()))?;
+    stacks::register_stack(&repo, name).map_err(|e| StackError::RegisterStackFailed(e.into()))?;
+    register_current_stack(&repo)?;
+
+    utils::execute("git", &["checkout", name]).map_err(|e| StackError::CheckoutFailed(e.into()))?;
+
+    Ok(onto)
+}
+
+/// Get the ahead & behind counts along with the review state of each patch stack
+pub fn list() -> Result<Vec<StackSummary>, StackError> {
+    let context = repo_context()?;
+    let current = current_branch_name(&context.repo);
+    stack_names(&context.repo)?
+        .into_iter()
+        .map(|name| {
+            let is_current = current.as_deref() == Some(name.as_str());
+            stack_summary(&context, &name, is_current)
+        })
+        .collect()
+}
+
+/// The state of a patch stack along with the info of the branches its patches are on
+struct StackState<'a> {
+    patch_stack: ps::PatchStack<'a>,
+    patches: Vec<ps::ListPatch>,
+    patch_info_collection: HashMap<uuid::Uuid, state_computation::PatchGitInfo>,
+    rr_branch_naming: rr_branch_name::RrBranchNaming,
+}
+
+fn stack_state<'a>(
+    context: &'a RepoContext,
+    name: &str,
+    upstream_ref_name: &str,
+) -> Result<StackState<'a>, StackError> {
+    let repo = &context.repo;
+    let upstream_name = upstream_ref_name.replace("refs/remotes/", "");
+    let config =
+        config::get_config_for_upstream(&context.root, &context.gitdir, Some(&upstream_name))
+            .map_err(|e| StackError::GetConfigFailed(e.into()))?;
+    let rr_branch_naming = rr_branch_name::RrBranchNaming::new(&config.request_review)
+        .map_err(|e| StackError::RrBranchNamingInvalid(e.into()))?;
+
+    let patch_stack = ps::PatchStack {
+        head: repo
+            .find_reference(&format!("refs/heads/{}", name))
+            .map_err(|e| StackError::GetStackStateFailed(e.into()))?,
+        base: repo
+            .find_reference(upstream_ref_name)
+            .map_err(|e| StackError::GetStackStateFailed(e.into()))?,
+    };
+    let patches = ps::get_patch_list(repo, &patch_stack)
+        .map_err(|e| StackError::GetStackStateFailed(e.into()))?;
+    let base_oid = patch_stack
+        .base
+        .target()
+        .ok_or_else(|| StackError::StackNotFound(upstream_name.clone()))?;
+    let patch_info_collection = state_computation::get_list_patch_info(
+        repo,
+        base_oid,
+        name,
+        &config.branch_scan,
+        &rr_branch_naming,
+    )
+    .map_err(|e| StackError::GetStackStateFailed(e.into()))?;
+
+    Ok(StackState {
+        patch_stack,
+        patches,
+        patch_info_collection,
+        rr_branch_naming,
+    })
+}
+
+fn stack_summary(
+    context: &RepoContext,
+    name: &str,
+    is_current: bool,
+) -> Result<StackSummary, StackError> {
+    let repo = &context.repo;
+    let upstream_ref_name = match stacks::stack_upstream_ref_name(repo, name) {
+        Some(upstream_ref_name) => upstream_ref_name,
+        None => {
+            return Ok(StackSummary {
+                name: name.to_string(),
+                upstream: None,
+                is_current,
+                ahead: 0,
+                behind: 0,
+                review: StackReviewCounts::default(),
+            })
+        }
+    };
+    let state = stack_state(context, name, &upstream_ref_name)?;
+
+    let head_oid = state.patch_stack.head.target();
+    let base_oid = state.patch_stack.base.target();
+    let behind = match (head_oid, base_oid) {
+        (Some(head_oid), Some(base_oid)) => git::common_ancestor(repo, head_oid, base_oid)
+            .ok()
+            .and_then(|ancestor| git::count_commits(repo, base_oid, ancestor).ok())
+            .unwrap_or(0),
+        _ => 0,
+    };
+
+    let mut review = StackReviewCounts::default();
+    for patch in state.patches.iter() {
+        let commit = repo
+            .find_commit(patch.oid)
+            .map_err(|e| StackError::GetStackStateFailed(e.into()))?;
+        let commit_diff_id = git::commit_diff_patch_id(repo, &commit).ok();
+        let branches = ps::commit_ps_id(&commit)
+            .and_then(|ps_id| state.patch_info_collection.get(&ps_id).map(|i| (ps_id, i)))
+            .map(|(ps_id, info)| (ps_id, info.branches.clone()))
+            .filter(|(_, branches)| !branches.is_empty());
+
+        match branches {
+            None => review.new += 1,
+            Some((ps_id, branches)) => {
+                // the diffs of the patch as pushed to each of its branches' upstreams
+                let pushed_diff_ids: Vec<git2::Oid> = branches
+                    .iter()
+                    .filter_map(|b| b.upstream.as_ref())
+                    .filter_map(|u| u.patches.iter().find(|p| p.patch_id == ps_id))
+                    .map(|p| p.commit_diff_id)
+                    .collect();
+                if pushed_diff_ids.is_empty() {
+                    review.branched += 1;
+                } else if pushed_diff_ids.iter().any(|id| Some(*id) == commit_diff_id) {
+                    review.requested += 1;
+                } else {
+                    review.changed += 1;
+                }
+            }
+        }
+    }
+
+    Ok(StackSummary {
+        name: name.to_string(),
+        upstream: Some(upstream_ref_name.replace("refs/remotes/", "")),
+        is_current,
+        ahead: state.patches.len(),
+        behind,
+        review,
+    })
+}
+
+/// Switch to the given patch stack
+pub fn switch(name: &str) -> Result<(), StackError> {
+    let RepoContext { repo, .. } = repo_context()?;
+    if !stack_names(&repo)?.iter().any(|n| n == name) {
+        return Err(StackError::StackNotFound(name.to_string()));
+    }
+    register_current_stack(&repo)?;
+    utils::execute("git", &["checkout", name]).map_err(|e| StackError::CheckoutFailed(e.into()))
+}
+
+/// Delete the given patch stack along with the request review branches of its patches, local &
+/// remote. A stack with patches is only deleted when forced as they would otherwise be lost.
+/// Returns the names of the request review branches that were deleted.
+pub fn delete(name: &str, force: bool) -> Result<Vec<String>, StackError> {
+    let context = repo_context()?;
+    let repo = &context.repo;
+    if !stack_names(repo)?.iter().any(|n| n == name) {
+        return Err(StackError::StackNotFound(name.to_string()));
+    }
+    if current_branch_name(repo).as_deref() == Some(name) {
+        return Err(StackError::StackIsCurrent(name.to_string()));
+    }
+
+    let mut rr_branch_names: Vec<String> = vec![];
+    if let Some(upstream_ref_name) = stacks::stack_upstream_ref_name(repo, name) {
+        let state = stack_state(&context, name, &upstream_ref_name)?;
+        if !state.patches.is_empty() && !force {
+            return Err(StackError::StackHasPatches(
+                name.to_string(),
+                state.patches.len(),
+            ));
+        }
+        for patch in state.patches.iter() {
+            let commit = repo
+                .find_commit(patch.oid)
+                .map_err(|e| StackError::GetStackStateFailed(e.into()))?;
+            let info =
+                ps::commit_ps_id(&commit).and_then(|ps_id| state.patch_info_collection.get(&ps_id));
+            for branch in info.iter().flat_map(|i| i.branches.iter()) {
+                if state.rr_branch_naming.is_rr_branch_name(&branch.name)
+                    && !rr_branch_names.contains(&branch.name)
+                {
+                    rr_branch_names.push(branch.name.clone());
+                }
+            }
+        }
+    }
+
+    for rr_branch_name in rr_branch_names.iter() {
+        delete_branch_and_its_remote(repo, rr_branch_name)?;
+    }
+
+    let mut stack_branch = repo
+        .find_branch(name, git2::BranchType::Local)
+        .map_err(|_| StackError::StackNotFound(name.to_string()))?;
+    stacks::unregister_stack(repo, name).map_err(|e| StackError::RegisterStackFailed(e.into()))?;
+    stack_branch
+        .delete()
+        .map_err(|e| StackError::DeleteLocalBranchFailed(e.into()))?;
+    events::emit(Event::BranchDeleted {
+        name: name.to_string(),
+        remote: None,
+    });
+
+    Ok(rr_branch_names)
+}
+
+fn delete_branch_and_its_remote(repo: &git2::Repository, name: &str) -> Result<(), StackError> {
+    let mut local_branch = repo
+        .find_branch(name, git2::BranchType::Local)
+        .map_err(|e| StackError::DeleteLocalBranchFailed(e.into()))?;
+
+    if let Ok(remote_branch) = local_branch.upstream() {
+        let remote = repo
+            .branch_upstream_remote(&format!("refs/heads/{}", name))
+            .map_err(|e| StackError::DeleteRemoteBranchFailed(e.into()))?;
+        let remote_str = remote.as_str().ok_or(StackError::PathNotUtf8)?;
+        let remote_branch_name = remote_branch
+            .name()
+            .map_err(|e| StackError::DeleteRemoteBranchFailed(e.into()))?
+            .ok_or(StackError::PathNotUtf8)?;
+        let remote_branch_name_relative_to_remote =
+            str::replace(remote_branch_name, &format!("{}/", remote_str), "");
+        git::ext_delete_remote_branch(remote_str, &remote_branch_name_relative_to_remote)
+            .map_err(|e| StackError::DeleteRemoteBranchFailed(e.into()))?;
+    }
+
+    local_branch
+        .delete()
+        .map_err(|e| StackError::DeleteLocalBranchFailed(e.into()))?;
+    events::emit(Event::BranchDeleted {
+        name: name.to_string(),
+        remote: None,
+    });
+    Ok(())
+}
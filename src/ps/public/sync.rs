@@ -1,5 +1,7 @@
 use super::super::super::ps;
+use super::super::private::conflicts;
 use super::super::private::git;
+use std::path::PathBuf;
 
 #[derive(Debug)]
 pub enum SyncError {
@@ -16,6 +18,7 @@ pub enum SyncError {
     ForcePushFailed(Box<dyn std::error::Error>),
     GetBranchUpstreamRemoteName(Box<dyn std::error::Error>),
     PatchBranchRefMissing,
+    StoppedForConflictResolution(String, PathBuf),
     Unhandled(Box<dyn std::error::Error>),
 }
 
@@ -28,6 +31,9 @@ impl From<ps::private::branch::BranchError> for SyncError {
             ps::private::branch::BranchError::ConflictsExist(src_oid, dst_oid) => {
                 Self::ConflictsExist(src_oid, dst_oid)
             }
+            ps::private::branch::BranchError::StoppedForConflictResolution(oid, path) => {
+                Self::StoppedForConflictResolution(oid, path)
+            }
             _ => Self::Unhandled(value.into()),
         }
     }
@@ -59,6 +65,12 @@ impl std::fmt::Display for SyncError {
                 write!(f, "failed to get branch upstream remote name, {}", e)
             }
             Self::PatchBranchRefMissing => write!(f, "patch branch ref missing"),
+            Self::StoppedForConflictResolution(oid, path) => write!(
+                f,
+                "stopped at conflicts playing {}, resolve them in the worktree at {}, stage them with git add & run gps continue, or gps continue --abort",
+                oid,
+                path.display()
+            ),
             Self::Unhandled(e) => write!(f, "{}", e),
         }
     }
@@ -80,23 +92,61 @@ impl std::error::Error for SyncError {
             Self::ForcePushFailed(e) => Some(e.as_ref()),
             Self::GetBranchUpstreamRemoteName(e) => Some(e.as_ref()),
             Self::PatchBranchRefMissing => None,
+            Self::StoppedForConflictResolution(_, _) => None,
             Self::Unhandled(e) => Some(e.as_ref()),
         }
     }
 }
 
+/// Create or replace the request review branch for the patch or patch series & force push it up
+/// to its remote. With `post_sync_hook` the caller runs the request_review_post_sync hook after
+/// syncing, which `gps continue` then does too when the sync stops at a conflict.
 pub fn sync(
     start_patch_index: usize,
     end_patch_index: Option<usize>,
     given_branch_name: Option<String>,
+    post_sync_hook: bool,
 ) -> Result<(String, String), SyncError> {
     let repo = git::create_cwd_repo().map_err(|_| SyncError::RepositoryNotFound)?;
+    sync_patches(
+        &repo,
+        start_patch_index,
+        end_patch_index,
+        given_branch_name,
+        post_sync_hook,
+    )
+}
 
+/// Sync the patch or patch series of the patch stack checked out in the given repository, see
+/// [`sync`]
+pub(crate) fn sync_patches(
+    repo: &git2::Repository,
+    start_patch_index: usize,
+    end_patch_index: Option<usize>,
+    given_branch_name: Option<String>,
+    post_sync_hook: bool,
+) -> Result<(String, String), SyncError> {
+    let cur_patch_stack_remote_name = patch_stack_remote_name(repo)?;
+
+    // create request review branch for patch
+    let (mut patch_branch, _new_commit_oid) = ps::private::branch::branch(
+        repo,
+        start_patch_index,
+        end_patch_index,
+        given_branch_name,
+        Some(conflicts::InterruptedOperation::RequestReview { post_sync_hook }),
+    )?;
+
+    push_patch_branch(repo, &mut patch_branch, &cur_patch_stack_remote_name)
+}
+
+/// Get the name of the remote of the current patch stack's upstream branch, e.g. origin
+pub fn patch_stack_remote_name(repo: &git2::Repository) -> Result<String, SyncError> {
     // get remote name of current branch
     let cur_patch_stack_branch_name =
-        git::get_current_branch(&repo).ok_or(SyncError::CurrentBranchNameMissing)?;
+        git::get_current_branch(repo).ok_or(SyncError::CurrentBranchNameMissing)?;
     let cur_patch_stack_branch_upstream_name =
-        git::branch_upstream_name(&repo, cur_patch_stack_branch_name.as_str())
+        git::branch_upstream_name(repo, cur_patch_stack_branch_name.as_str())
             .map_err(|_| SyncError::GetUpstreamBranchNameFailed)?;
     let cur_patch_stack_remote_name = repo
         .branch_remote_name(&cur_patch_stack_branch_upstream_name)
@@ -105,10 +155,17 @@ pub fn sync(
         .as_str()
         .ok_or(SyncError::BranchRemoteNameNotUtf8)?;
 
-    // create request review branch for patch
-    let (mut patch_branch, _new_commit_oid) =
-        ps::private::branch::branch(&repo, start_patch_index, end_patch_index, given_branch_name)?;
+    Ok(cur_patch_stack_remote_name_str.to_string())
+}
 
+/// Force push the patch branch up to its upstream branch, or to a branch of the same name on the
+/// patch stack's remote if it doesn't have one yet, returning the upstream branch name relative to
+/// the remote along with the remote name
+pub fn push_patch_branch(
+    repo: &git2::Repository,
+    patch_branch: &mut git2::Branch,
+    cur_patch_stack_remote_name_str: &str,
+) -> Result<(String, String), SyncError> {
     // get upstream branch name & remote of patch branch or fallback to using patch branch name &
     // cur patch stack remote.
     let patch_branch_name: String = patch_branch
@@ -144,7 +201,8 @@ pub fn sync(
             let upstream_branch_name_relative_to_remote =
                 str::replace(&upstream_branch_name, pattern.as_str(), "");
 
-            git::ext_push(
+            ps::private::push::push(
+                repo,
                 true,
                 &remote_name,
                 &patch_branch_name,
@@ -155,7 +213,8 @@ pub fn sync(
             (upstream_branch_name_relative_to_remote, remote_name)
         }
         Err(_e) => {
-            git::ext_push(
+            ps::private::push::push(
+                repo,
                 true,
                 cur_patch_stack_remote_name_str,
                 &patch_branch_name,
@@ -0,0 +1,451 @@
+use super::super::super::ps;
+use super::super::private::cherry_picking;
+use super::super::private::config;
+use super::super::private::git;
+use super::super::private::paths;
+use std::result::Result;
+
+const TRANSFER_TARGET_BRANCH_NAME: &str = "ps/tmp/transfer_target";
+const TRANSFER_SOURCE_BRANCH_NAME: &str = "ps/tmp/transfer_source";
+
+#[derive(Debug)]
+pub enum TransferError {
+    OpenRepositoryFailed(Box<dyn std::error::Error>),
+    OpenGitConfigFailed(Box<dyn std::error::Error>),
+    GetConfigFailed(Box<dyn std::error::Error>),
+    UncommittedChangesExistFailure(Box<dyn std::error::Error>),
+    UncommittedChangesExist,
+    CurrentBranchNameMissing,
+    TargetStackNotFound(String),
+    TargetStackIsCurrent(String),
+    AddPatchIdsFailed(Box<dyn std::error::Error>),
+    GetPatchStackFailed(Box<dyn std::error::Error>),
+    GetPatchListFailed(Box<dyn std::error::Error>),
+    FailedToMapIndexesForCherryPick(Box<dyn std::error::Error>),
+    CreateTemporaryBranchFailed(Box<dyn std::error::Error>),
+    TargetConflictsExist(String, String),
+    SourceConflictsExist(String, String),
+    MergeCommitDetected(String),
+    CherryPickFailed(Box<dyn std::error::Error>),
+    UpdateBranchFailed(Box<dyn std::error::Error>),
+    CheckoutFailed(Box<dyn std::error::Error>),
+    DeleteTemporaryBranchFailed(Box<dyn std::error::Error>),
+}
+
+impl std::fmt::Display for TransferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::OpenRepositoryFailed(e) => write!(f, "failed to open repository, {}", e),
+            Self::OpenGitConfigFailed(e) => write!(f, "failed to open git config, {}", e),
+            Self::GetConfigFailed(e) => write!(f, "failed to get config, {}", e),
+            Self::UncommittedChangesExistFailure(e) => {
+                write!(f, "failed to check for uncommitted changes, {}", e)
+            }
+            Self::UncommittedChangesExist => write!(f, "uncommited changes exist"),
+            Self::CurrentBranchNameMissing => write!(f, "current branch name missing"),
+            Self::TargetStackNotFound(name) => write!(f, "target stack {} not found", name),
+            Self::TargetStackIsCurrent(name) => {
+                write!(f, "target stack {} is the current stack", name)
+            }
+            Self::AddPatchIdsFailed(e) => write!(f, "failed to add patch ids, {}", e),
+            Self::GetPatchStackFailed(e) => write!(f, "failed to get patch stack, {}", e),
+            Self::GetPatchListFailed(e) => write!(f, "failed to get patch list, {}", e),
+            Self::FailedToMapIndexesForCherryPick(e) => {
+                write!(f, "failed to map indexes for cherry pick {}", e)
+            }
+            Self::CreateTemporaryBranchFailed(e) => {
+                write!(f, "failed to create temporary branch, {}", e)
+            }
+            Self::TargetConflictsExist(oid_a, oid_b) => write!(
+                f,
+                "conflicts exist between shas {} and {} in the target stack, nothing was changed",
+                oid_a, oid_b
+            ),
+            Self::SourceConflictsExist(oid_a, oid_b) => write!(
+                f,
+                "conflicts exist between shas {} and {} in the current stack once the patches are removed, nothing was changed",
+                oid_a, oid_b
+            ),
+            Self::MergeCommitDetected(oid) => write!(f, "merge commit detected with sha {}", oid),
+            Self::CherryPickFailed(e) => write!(f, "failed to cherry pick, {}", e),
+            Self::UpdateBranchFailed(e) => write!(f, "failed to update branch, {}", e),
+            Self::CheckoutFailed(e) => write!(f, "failed to checkout the updated stack, {}", e),
+            Self::DeleteTemporaryBranchFailed(e) => {
+                write!(f, "failed to delete temporary branch, {}", e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TransferError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::OpenRepositoryFailed(e) => Some(e.as_ref()),
+            Self::OpenGitConfigFailed(e) => Some(e.as_ref()),
+            Self::GetConfigFailed(e) => Some(e.as_ref()),
+            Self::UncommittedChangesExistFailure(e) => Some(e.as_ref()),
+            Self::UncommittedChangesExist => None,
+            Self::CurrentBranchNameMissing => None,
+            Self::TargetStackNotFound(_) => None,
+            Self::TargetStackIsCurrent(_) => None,
+            Self::AddPatchIdsFailed(e) => Some(e.as_ref()),
+            Self::GetPatchStackFailed(e) => Some(e.as_ref()),
+            Self::GetPatchListFailed(e) => Some(e.as_ref()),
+            Self::FailedToMapIndexesForCherryPick(e) => Some(e.as_ref()),
+            Self::CreateTemporaryBranchFailed(e) => Some(e.as_ref()),
+            Self::TargetConflictsExist(_, _) => None,
+            Self::SourceConflictsExist(_, _) => None,
+            Self::MergeCommitDetected(_) => None,
+            Self::CherryPickFailed(e) => Some(e.as_ref()),
+            Self::UpdateBranchFailed(e) => Some(e.as_ref()),
+            Self::CheckoutFailed(e) => Some(e.as_ref()),
+            Self::DeleteTemporaryBranchFailed(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+/// Map a cherry pick error, distinguishing conflicts in the target stack from those in the
+/// current stack
+fn map_cherry_pick_error(e: cherry_picking::CherryPickError, into_target: bool) -> TransferError {
+    match e {
+        cherry_picking::CherryPickError::ConflictsExist(oid_a, oid_b) if into_target => {
+            TransferError::TargetConflictsExist(oid_a, oid_b)
+        }
+        cherry_picking::CherryPickError::ConflictsExist(oid_a, oid_b) => {
+            TransferError::SourceConflictsExist(oid_a, oid_b)
+        }
+        cherry_picking::CherryPickError::MergeCommitDetected(oid) => {
+            TransferError::MergeCommitDetected(oid)
+        }
+        _ => TransferError::CherryPickFailed(e.into()),
+    }
+}
+
+fn delete_temporary_branches(repo: &git2::Repository) -> Result<(), TransferError> {
+    for name in [TRANSFER_TARGET_BRANCH_NAME, TRANSFER_SOURCE_BRANCH_NAME] {
+        if let Ok(mut branch) = repo.find_branch(name, git2::BranchType::Local) {
+            branch
+                .delete()
+                .map_err(|e| TransferError::DeleteTemporaryBranchFailed(e.into()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Move the patches in the given range from the current patch stack to the end of the target
+/// stack, a local branch, keeping their patch ids so any request review branches stay associated
+/// with them.
+///
+/// Both stacks are rebuilt on temporary branches first & only updated once that succeeded for
+/// both, so when the patches conflict with the target stack, or the remaining patches conflict
+/// once the moved ones are removed, neither stack is changed.
+pub fn transfer(
+    start_patch_index: usize,
+    end_patch_index: Option<usize>,
+    target_stack_name: &str,
+) -> Result<(), TransferError> {
+    let repo = git::create_cwd_repo().map_err(|e| TransferError::OpenRepositoryFailed(e.into()))?;
+    let config =
+        git2::Config::open_default().map_err(|e| TransferError::OpenGitConfigFailed(e.into()))?;
+    let repo_root_str = paths::repo_root_path(&repo)
+        .map_err(|e| TransferError::GetConfigFailed(e.into()))?
+        .to_str()
+        .ok_or_else(|| TransferError::GetConfigFailed("repository path not utf-8".into()))?;
+    let repo_gitdir_str = repo
+        .path()
+        .to_str()
+        .ok_or_else(|| TransferError::GetConfigFailed("repository gitdir not utf-8".into()))?;
+    let ps_config = config::get_config(&repo, repo_root_str, repo_gitdir_str)
+        .map_err(|e| TransferError::GetConfigFailed(e.into()))?;
+
+    transfer_patches(
+        &repo,
+        &config,
+        &ps_config.rewrite,
+        start_patch_index,
+        end_patch_index,
+        target_stack_name,
+    )
+}
+
+/// Move the patches, as `transfer` does, in an already opened repository
+fn transfer_patches(
+    repo: &git2::Repository,
+    config: &git2::Config,
+    rewrite_config: &config::PsRewriteConfig,
+    start_patch_index: usize,
+    end_patch_index: Option<usize>,
+    target_stack_name: &str,
+) -> Result<(), TransferError> {
+    if git::uncommitted_changes_exist(repo)
+        .map_err(|e| TransferError::UncommittedChangesExistFailure(e.into()))?
+    {
+        return Err(TransferError::UncommittedChangesExist);
+    }
+
+    let current_branch_ref_name =
+        git::get_current_branch(repo).ok_or(TransferError::CurrentBranchNameMissing)?;
+    let target_branch = repo
+        .find_branch(target_stack_name, git2::BranchType::Local)
+        .map_err(|_| TransferError::TargetStackNotFound(target_stack_name.to_string()))?;
+    let target_branch_ref_name = target_branch
+        .get()
+        .name()
+        .ok_or_else(|| TransferError::TargetStackNotFound(target_stack_name.to_string()))?
+        .to_string();
+    if target_branch_ref_name == current_branch_ref_name {
+        return Err(TransferError::TargetStackIsCurrent(
+            target_stack_name.to_string(),
+        ));
+    }
+    let target_commit = target_branch
+        .get()
+        .peel_to_commit()
+        .map_err(|_| TransferError::TargetStackNotFound(target_stack_name.to_string()))?;
+
+    ps::add_patch_ids(repo, config, rewrite_config)
+        .map_err(|e| TransferError::AddPatchIdsFailed(e.into()))?;
+
+    let patch_stack =
+        ps::get_patch_stack(repo).map_err(|e| TransferError::GetPatchStackFailed(e.into()))?;
+    let patches_vec = ps::get_patch_list(repo, &patch_stack)
+        .map_err(|e| TransferError::GetPatchListFailed(e.into()))?;
+
+    let cherry_pick_range =
+        cherry_picking::map_range_for_cherry_pick(&patches_vec, start_patch_index, end_patch_index)
+            .map_err(|e| TransferError::FailedToMapIndexesForCherryPick(e.into()))?;
+    let range_end_oid = cherry_pick_range
+        .leaf_oid
+        .unwrap_or(cherry_pick_range.root_oid);
+
+    // the current stack without the patches is rebuilt from the parent of the first of them
+    let range_parent_commit = repo
+        .find_commit(cherry_pick_range.root_oid)
+        .and_then(|c| c.parent(0))
+        .map_err(|e| TransferError::CherryPickFailed(e.into()))?;
+
+    delete_temporary_branches(repo)?;
+    repo.branch(TRANSFER_TARGET_BRANCH_NAME, &target_commit, true)
+        .and_then(|_| repo.branch(TRANSFER_SOURCE_BRANCH_NAME, &range_parent_commit, true))
+        .map_err(|e| TransferError::CreateTemporaryBranchFailed(e.into()))?;
+    let target_tmp_ref_name = format!("refs/heads/{}", TRANSFER_TARGET_BRANCH_NAME);
+    let source_tmp_ref_name = format!("refs/heads/{}", TRANSFER_SOURCE_BRANCH_NAME);
+
+    let rebuilt = cherry_picking::cherry_pick(
+        repo,
+        config,
+        rewrite_config,
+        cherry_pick_range.root_oid,
+        cherry_pick_range.leaf_oid,
+        &target_tmp_ref_name,
+        0,
+        false,
+        true,
+    )
+    .map_err(|e| map_cherry_pick_error(e, true))
+    .and_then(|_| match patches_vec.last() {
+        Some(last_patch) if last_patch.oid != range_end_oid => cherry_picking::cherry_pick(
+            repo,
+            config,
+            rewrite_config,
+            range_end_oid,
+            Some(last_patch.oid),
+            &source_tmp_ref_name,
+            0,
+            false,
+            false,
+        )
+        .map(|_| ())
+        .map_err(|e| map_cherry_pick_error(e, false)),
+        _ => Ok(()),
+    });
+    if let Err(e) = rebuilt {
+        delete_temporary_branches(repo)?;
+        return Err(e);
+    }
+
+    // both stacks were rebuilt cleanly, so swap them in
+    let rebuilt_oid = |ref_name: &str| -> Result<git2::Oid, TransferError> {
+        repo.refname_to_id(ref_name)
+            .map_err(|e| TransferError::UpdateBranchFailed(e.into()))
+    };
+    let target_oid = rebuilt_oid(&target_tmp_ref_name)?;
+    let source_oid = rebuilt_oid(&source_tmp_ref_name)?;
+    repo.find_reference(&target_branch_ref_name)
+        .and_then(|mut r| r.set_target(target_oid, "transfer patches into stack"))
+        .map_err(|e| TransferError::UpdateBranchFailed(e.into()))?;
+    repo.find_reference(&current_branch_ref_name)
+        .and_then(|mut r| r.set_target(source_oid, "transfer patches out of stack"))
+        .map_err(|e| TransferError::UpdateBranchFailed(e.into()))?;
+    repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+        .map_err(|e| TransferError::CheckoutFailed(e.into()))?;
+
+    delete_temporary_branches(repo)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::super::private::git::test_utils;
+    use super::*;
+
+    /// A repository with the patch stack on main, whose upstream is the local base branch, & a
+    /// target stack, other, with a patch of its own on the same base
+    fn stacks_repo(
+        patches: &[(&str, &str)],
+        target_patch: (&str, &str),
+    ) -> (tempfile::TempDir, git2::Repository) {
+        let (dir, repo) = test_utils::repo_init();
+        let base_oid = test_utils::commit_files(&repo, &[("base.txt", "base\n")], "base");
+        let base_commit = repo.find_commit(base_oid).unwrap();
+        repo.branch("base", &base_commit, false).unwrap();
+        repo.branch("other", &base_commit, false).unwrap();
+        repo.find_branch("main", git2::BranchType::Local)
+            .unwrap()
+            .set_upstream(Some("base"))
+            .unwrap();
+
+        repo.set_head("refs/heads/other").unwrap();
+        commit_patch(&repo, target_patch, "x");
+        repo.set_head("refs/heads/main").unwrap();
+        for (i, patch) in patches.iter().enumerate() {
+            commit_patch(&repo, *patch, &format!("p{}", i));
+        }
+        repo.checkout_head(Some(git2::build::CheckoutBuilder::new().force()))
+            .unwrap();
+        drop(base_commit);
+        (dir, repo)
+    }
+
+    /// Commit the patch with a ps-id, so adding the missing patch ids leaves the stack alone
+    fn commit_patch(repo: &git2::Repository, (path, content): (&str, &str), summary: &str) {
+        test_utils::commit_files(
+            repo,
+            &[(path, content)],
+            &format!("{}\n\n<!-- ps-id: {} -->", summary, uuid::Uuid::new_v4()),
+        );
+    }
+
+    /// The summaries of the commits of the branch on top of base, from the bottom up
+    fn summaries(repo: &git2::Repository, branch_name: &str) -> Vec<String> {
+        let mut revs = repo.revwalk().unwrap();
+        revs.push_ref(&format!("refs/heads/{}", branch_name))
+            .unwrap();
+        revs.hide_ref("refs/heads/base").unwrap();
+        revs.set_sorting(git2::Sort::TOPOLOGICAL | git2::Sort::REVERSE)
+            .unwrap();
+        revs.map(|oid| {
+            let commit = repo.find_commit(oid.unwrap()).unwrap();
+            commit.summary().unwrap().to_string()
+        })
+        .collect()
+    }
+
+    fn branch_oid(repo: &git2::Repository, branch_name: &str) -> git2::Oid {
+        repo.refname_to_id(&format!("refs/heads/{}", branch_name))
+            .unwrap()
+    }
+
+    fn assert_no_temporary_branches(repo: &git2::Repository) {
+        for name in [TRANSFER_TARGET_BRANCH_NAME, TRANSFER_SOURCE_BRANCH_NAME] {
+            assert!(repo.find_branch(name, git2::BranchType::Local).is_err());
+        }
+    }
+
+    const PATCHES: [(&str, &str); 4] = [
+        ("a.txt", "a\n"),
+        ("b.txt", "b\n"),
+        ("c.txt", "c\n"),
+        ("d.txt", "d\n"),
+    ];
+
+    #[test]
+    fn test_transfer_moves_the_patches_to_the_end_of_the_target_stack() {
+        let (_dir, repo) = stacks_repo(&PATCHES, ("x.txt", "x\n"));
+        let config = repo.config().unwrap();
+
+        transfer_patches(
+            &repo,
+            &config,
+            &test_utils::rewrite_config(),
+            1,
+            Some(2),
+            "other",
+        )
+        .unwrap();
+
+        assert_eq!(summaries(&repo, "other"), vec!["x", "p1", "p2"]);
+        assert_eq!(summaries(&repo, "main"), vec!["p0", "p3"]);
+        assert_no_temporary_branches(&repo);
+    }
+
+    #[test]
+    fn test_transfer_moves_a_reversed_range() {
+        let (_dir, repo) = stacks_repo(&PATCHES, ("x.txt", "x\n"));
+        let config = repo.config().unwrap();
+
+        transfer_patches(
+            &repo,
+            &config,
+            &test_utils::rewrite_config(),
+            2,
+            Some(1),
+            "other",
+        )
+        .unwrap();
+
+        assert_eq!(summaries(&repo, "other"), vec!["x", "p1", "p2"]);
+        assert_eq!(summaries(&repo, "main"), vec!["p0", "p3"]);
+    }
+
+    #[test]
+    fn test_transfer_conflicting_with_the_target_stack_changes_neither_stack() {
+        let (_dir, repo) = stacks_repo(&PATCHES, ("b.txt", "b other\n"));
+        let config = repo.config().unwrap();
+        let main_oid = branch_oid(&repo, "main");
+        let other_oid = branch_oid(&repo, "other");
+
+        let result = transfer_patches(
+            &repo,
+            &config,
+            &test_utils::rewrite_config(),
+            1,
+            None,
+            "other",
+        );
+
+        assert!(matches!(
+            result,
+            Err(TransferError::TargetConflictsExist(_, _))
+        ));
+        assert_eq!(branch_oid(&repo, "main"), main_oid);
+        assert_eq!(branch_oid(&repo, "other"), other_oid);
+        assert_no_temporary_branches(&repo);
+    }
+
+    #[test]
+    fn test_transfer_conflicting_with_the_remaining_patches_changes_neither_stack() {
+        // the patch after the moved one changes the file the moved one adds
+        let patches = [("a.txt", "a\n"), ("b.txt", "b\n"), ("b.txt", "b changed\n")];
+        let (_dir, repo) = stacks_repo(&patches, ("x.txt", "x\n"));
+        let config = repo.config().unwrap();
+        let main_oid = branch_oid(&repo, "main");
+        let other_oid = branch_oid(&repo, "other");
+
+        let result = transfer_patches(
+            &repo,
+            &config,
+            &test_utils::rewrite_config(),
+            1,
+            None,
+            "other",
+        );
+
+        assert!(matches!(
+            result,
+            Err(TransferError::SourceConflictsExist(_, _))
+        ));
+        assert_eq!(branch_oid(&repo, "main"), main_oid);
+        assert_eq!(branch_oid(&repo, "other"), other_oid);
+        assert_no_temporary_branches(&repo);
+    }
+}
@@ -1,7 +1,6 @@
 use super::super::private::config;
 use super::super::private::git;
 use super::super::private::paths;
-use ansi_term::Colour::{Cyan, Yellow};
 
 #[derive(Debug)]
 pub enum UpstreamPatchesError {
@@ -15,6 +14,7 @@ pub enum UpstreamPatchesError {
     GetUpstreamBranchNameFailed,
     FindUpstreamBranchReferenceFailed(git2::Error),
     GetUpstreamBranchOidFailed,
+    GetUpstreamCommitsFailed(Box<dyn std::error::Error>),
 }
 
 impl std::fmt::Display for UpstreamPatchesError {
@@ -32,6 +32,7 @@ impl std::fmt::Display for UpstreamPatchesError {
                 write!(f, "find upstream branch reference failed, {}", e)
             }
             Self::GetUpstreamBranchOidFailed => write!(f, "get upstream branch oid failed"),
+            Self::GetUpstreamCommitsFailed(e) => write!(f, "get upstream commits failed, {}", e),
         }
     }
 }
@@ -49,13 +50,33 @@ impl std::error::Error for UpstreamPatchesError {
             Self::GetUpstreamBranchNameFailed => None,
             Self::FindUpstreamBranchReferenceFailed(e) => Some(e),
             Self::GetUpstreamBranchOidFailed => None,
+            Self::GetUpstreamCommitsFailed(e) => Some(e.as_ref()),
         }
     }
 }
 
-pub fn upstream_patches(color: bool) -> Result<(), UpstreamPatchesError> {
+/// A commit on the upstream branch of the patch stack that isn't in the patch stack yet, e.g. one
+/// another developer integrated
+#[derive(Debug, Clone)]
+pub struct UpstreamPatch {
+    pub oid: git2::Oid,
+    pub summary: String,
+    pub author_name: Option<String>,
+    pub author_email: Option<String>,
+}
+
+/// Get the commits on the upstream branch of the current patch stack that aren't in it yet, newest
+/// first, e.g. to see what a pull would bring in after a fetch
+pub fn upstream_patches() -> Result<Vec<UpstreamPatch>, UpstreamPatchesError> {
     let repo = git::create_cwd_repo().map_err(|_| UpstreamPatchesError::RepositoryMissing)?;
+    find_upstream_patches(&repo)
+}
 
+/// Get the commits on the upstream branch of the patch stack checked out in the given repository
+/// that aren't in it yet, newest first
+pub fn find_upstream_patches(
+    repo: &git2::Repository,
+) -> Result<Vec<UpstreamPatch>, UpstreamPatchesError> {
     // get the start & end oids - e.g. origin/main & main
     let head_ref = repo
         .head()
@@ -67,7 +88,7 @@ pub fn upstream_patches(color: bool) -> Result<(), UpstreamPatchesError> {
     let head_branch_name = head_ref
         .name()
         .ok_or(UpstreamPatchesError::GetHeadBranchNameFailed)?;
-    let upstream_branch_name = git::branch_upstream_name(&repo, head_branch_name)
+    let upstream_branch_name = git::branch_upstream_name(repo, head_branch_name)
         .map_err(|_| UpstreamPatchesError::GetUpstreamBranchNameFailed)?;
 
     let upstream_branch_ref = repo
@@ -77,40 +98,21 @@ pub fn upstream_patches(color: bool) -> Result<(), UpstreamPatchesError> {
         .target()
         .ok_or(UpstreamPatchesError::GetUpstreamBranchOidFailed)?;
 
-    let mut rev_walk = git::get_revs(
-        &repo,
-        head_oid,
-        upstream_branch_oid,
-        git2::Sort::TOPOLOGICAL,
-    )
-    .unwrap()
-    .peekable();
-    println!();
-    if rev_walk.peek().is_none() {
-        // empty
-        println!("None. You are already up to date!");
-    }
-    rev_walk.for_each(|oid| {
-        let sha = oid.unwrap();
-        let patch_oid_str = format!("{:.6}", sha);
-        let commit = repo.find_commit(sha).unwrap();
-        let summary = commit.summary().unwrap_or("").to_string();
-        let signature = commit.author();
-        let display_name = signature
-            .name()
-            .unwrap_or_else(|| signature.email().unwrap_or("Unknown"));
-
-        if color {
-            println!(
-                "* {:.6} {} {}",
-                Yellow.paint(patch_oid_str),
-                summary,
-                Cyan.paint(display_name)
-            );
-        } else {
-            println!("* {:.6} {} {}", patch_oid_str, summary, signature);
-        }
-    });
-
-    Ok(())
+    git::get_revs(repo, head_oid, upstream_branch_oid, git2::Sort::TOPOLOGICAL)
+        .map_err(|e| UpstreamPatchesError::GetUpstreamCommitsFailed(e.into()))?
+        .map(|oid_result| {
+            let oid =
+                oid_result.map_err(|e| UpstreamPatchesError::GetUpstreamCommitsFailed(e.into()))?;
+            let commit = repo
+                .find_commit(oid)
+                .map_err(|e| UpstreamPatchesError::GetUpstreamCommitsFailed(e.into()))?;
+            let author = commit.author();
+            Ok(UpstreamPatch {
+                oid,
+                summary: commit.summary().unwrap_or("").to_string(),
+                author_name: author.name().map(|n| n.to_string()),
+                author_email: author.email().map(|e| e.to_string()),
+            })
+        })
+        .collect()
 }
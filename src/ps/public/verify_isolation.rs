@@ -66,11 +66,11 @@ impl std::error::Error for VerifyIsolationError {
 pub fn verify_isolation(
     patch_index: usize,
     end_patch_index_optional: Option<usize>,
-    color: bool,
 ) -> Result<(), VerifyIsolationError> {
-    match isolate::isolate(Some(patch_index), end_patch_index_optional, color) {
-        Ok(_) => Ok(isolate::isolate(None, None, color)
-            .map_err(VerifyIsolationError::IsolateResetFailed)?),
+    match isolate::isolate(Some(patch_index), end_patch_index_optional) {
+        Ok(_) => {
+            Ok(isolate::isolate(None, None).map_err(VerifyIsolationError::IsolateResetFailed)?)
+        }
         Err(e) => match e {
             // pre-checkout errors
             IsolateError::OpenGitRepositoryFailed(_)
@@ -85,12 +85,17 @@ pub fn verify_isolation(
             | IsolateError::BranchNameNotUtf8
             | IsolateError::MergeCommitDetected(_)
             | IsolateError::ConflictsExist(_, _)
+            | IsolateError::GetRepoRootPathFailed(_)
+            | IsolateError::PathNotUtf8
+            | IsolateError::GetConfigFailed(_)
+            | IsolateError::DependenciesMissing(_)
+            | IsolateError::DependenciesFailed(_)
             | IsolateError::GetCurrentBranchFailed
             | IsolateError::StoreLastBranchFailed(_)
             | IsolateError::FailedToCheckout(_) => Err(isolate_failed_err_map(e)),
             // post-checkout errors
             _ => {
-                isolate::isolate(None, None, color).map_err(isolate_reset_failed_err_map)?;
+                isolate::isolate(None, None).map_err(isolate_reset_failed_err_map)?;
                 Err(isolate_failed_err_map(e))
             }
         },
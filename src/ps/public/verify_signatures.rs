@@ -0,0 +1,238 @@
+use super::super::super::ps;
+use super::super::private::config;
+use super::super::private::git;
+use super::super::private::paths;
+use super::super::private::rr_branch_name;
+use super::super::private::signatures;
+use super::super::private::state_computation;
+use std::collections::{HashMap, HashSet};
+use uuid::Uuid;
+
+pub use super::super::private::git::SignatureStatus;
+
+#[derive(Debug)]
+pub enum VerifySignaturesError {
+    RepositoryNotFound,
+    GetPatchStackFailed(Box<dyn std::error::Error>),
+    GetPatchListFailed(Box<dyn std::error::Error>),
+    PatchIndexRangeOutOfBounds(Box<dyn std::error::Error>),
+    GetRepoRootPathFailed(Box<dyn std::error::Error>),
+    PathNotUtf8,
+    GetConfigFailed(Box<dyn std::error::Error>),
+    RrBranchNamingInvalid(Box<dyn std::error::Error>),
+    PatchStackBaseNotFound,
+    PatchStackHeadNoName,
+    GetListPatchInfoFailed(Box<dyn std::error::Error>),
+    FindReviewBranchFailed(Box<dyn std::error::Error>),
+    VerifySignaturesFailed(Box<dyn std::error::Error>),
+}
+
+impl std::fmt::Display for VerifySignaturesError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::RepositoryNotFound => write!(f, "repository not found"),
+            Self::GetPatchStackFailed(e) => write!(f, "get patch stack failed, {}", e),
+            Self::GetPatchListFailed(e) => write!(f, "get patch list failed, {}", e),
+            Self::PatchIndexRangeOutOfBounds(e) => {
+                write!(f, "patch index range out of bounds, {}", e)
+            }
+            Self::GetRepoRootPathFailed(e) => write!(f, "get repository root path failed, {}", e),
+            Self::PathNotUtf8 => write!(f, "path not utf-8"),
+            Self::GetConfigFailed(e) => write!(f, "get config failed, {}", e),
+            Self::RrBranchNamingInvalid(e) => {
+                write!(f, "request review branch naming invalid, {}", e)
+            }
+            Self::PatchStackBaseNotFound => write!(f, "patch stack base not found"),
+            Self::PatchStackHeadNoName => write!(f, "patch stack head has no name"),
+            Self::GetListPatchInfoFailed(e) => write!(f, "get list patch info failed, {}", e),
+            Self::FindReviewBranchFailed(e) => write!(f, "find review branch failed, {}", e),
+            Self::VerifySignaturesFailed(e) => write!(f, "verify signatures failed, {}", e),
+        }
+    }
+}
+
+impl std::error::Error for VerifySignaturesError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::RepositoryNotFound => None,
+            Self::GetPatchStackFailed(e) => Some(e.as_ref()),
+            Self::GetPatchListFailed(e) => Some(e.as_ref()),
+            Self::PatchIndexRangeOutOfBounds(e) => Some(e.as_ref()),
+            Self::GetRepoRootPathFailed(e) => Some(e.as_ref()),
+            Self::PathNotUtf8 => None,
+            Self::GetConfigFailed(e) => Some(e.as_ref()),
+            Self::RrBranchNamingInvalid(e) => Some(e.as_ref()),
+            Self::PatchStackBaseNotFound => None,
+            Self::PatchStackHeadNoName => None,
+            Self::GetListPatchInfoFailed(e) => Some(e.as_ref()),
+            Self::FindReviewBranchFailed(e) => Some(e.as_ref()),
+            Self::VerifySignaturesFailed(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+/// Where a verified commit was found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignedCommitLocation {
+    /// The patch at the given index of the patch stack
+    Patch(usize),
+    /// The given remote review branch, e.g. a commit a collaborator pushed to it
+    ReviewBranch(String),
+}
+
+#[derive(Debug, Clone)]
+pub struct VerifiedCommit {
+    pub oid: git2::Oid,
+    pub summary: String,
+    pub location: SignedCommitLocation,
+    pub status: SignatureStatus,
+    pub signer: Option<String>,
+    pub key: Option<String>,
+}
+
+impl VerifiedCommit {
+    /// Whether the commit has a good signature by someone trusted
+    pub fn is_trusted(&self) -> bool {
+        self.status.is_trusted()
+    }
+}
+
+/// Verify the signatures of the patches in the given range, all of the patches in the stack by
+/// default, as well as of the commits on the remote review branches of those patches that aren't
+/// in the stack, e.g. ones a collaborator pushed to them.
+pub fn verify_signatures(
+    start_patch_index: Option<usize>,
+    end_patch_index: Option<usize>,
+) -> Result<Vec<VerifiedCommit>, VerifySignaturesError> {
+    let repo = git::create_cwd_repo().map_err(|_| VerifySignaturesError::RepositoryNotFound)?;
+
+    let patch_stack = ps::get_patch_stack(&repo)
+        .map_err(|e| VerifySignaturesError::GetPatchStackFailed(e.into()))?;
+    let patches_vec = ps::get_patch_list(&repo, &patch_stack)
+        .map_err(|e| VerifySignaturesError::GetPatchListFailed(e.into()))?;
+
+    let patch_indexes: Vec<usize> = match (start_patch_index, end_patch_index) {
+        (Some(start_index), end_index) => {
+            ps::patch_range_within_stack_bounds(start_index, end_index, &patches_vec)
+                .map_err(|e| VerifySignaturesError::PatchIndexRangeOutOfBounds(e.into()))?;
+            (start_index..=end_index.unwrap_or(start_index)).collect()
+        }
+        (None, _) => (0..patches_vec.len()).collect(),
+    };
+
+    let repo_root_path = paths::repo_root_path(&repo)
+        .map_err(|e| VerifySignaturesError::GetRepoRootPathFailed(e.into()))?;
+    let repo_root_str = repo_root_path
+        .to_str()
+        .ok_or(VerifySignaturesError::PathNotUtf8)?;
+    let repo_gitdir_str = repo
+        .path()
+        .to_str()
+        .ok_or(VerifySignaturesError::PathNotUtf8)?;
+    let config = config::get_config(&repo, repo_root_str, repo_gitdir_str)
+        .map_err(|e| VerifySignaturesError::GetConfigFailed(e.into()))?;
+    let rr_branch_naming = rr_branch_name::RrBranchNaming::new(&config.request_review)
+        .map_err(|e| VerifySignaturesError::RrBranchNamingInvalid(e.into()))?;
+
+    let patch_stack_base_oid = patch_stack
+        .base
+        .peel_to_commit()
+        .map_err(|_| VerifySignaturesError::PatchStackBaseNotFound)?
+        .id();
+    let head_ref_name = patch_stack
+        .head
+        .shorthand()
+        .ok_or(VerifySignaturesError::PatchStackHeadNoName)?;
+    let patch_info_collection: HashMap<Uuid, state_computation::PatchGitInfo> =
+        state_computation::get_list_patch_info(
+            &repo,
+            patch_stack_base_oid,
+            head_ref_name,
+            &config.branch_scan,
+            &rr_branch_naming,
+        )
+        .map_err(|e| VerifySignaturesError::GetListPatchInfoFailed(e.into()))?;
+
+    let patch_oids: Vec<git2::Oid> = patch_indexes
+        .iter()
+        .map(|i| patches_vec.get(*i).unwrap().oid)
+        .collect();
+    let patch_signatures = git::ext_commit_signatures(&repo, &patch_oids)
+        .map_err(|e| VerifySignaturesError::VerifySignaturesFailed(e.into()))?;
+
+    let mut verified_commits: Vec<VerifiedCommit> = vec![];
+    let mut seen_oids: HashSet<git2::Oid> = HashSet::new();
+    for (patch_index, signature) in patch_indexes.iter().zip(patch_signatures) {
+        seen_oids.insert(signature.oid);
+        verified_commits.push(verified_commit(
+            &repo,
+            signature,
+            SignedCommitLocation::Patch(*patch_index),
+        ));
+    }
+
+    // the remote review branches of the patches, in the order of the patches
+    let mut review_branch_names: Vec<String> = vec![];
+    for patch_index in patch_indexes.iter() {
+        let patch = patches_vec.get(*patch_index).unwrap();
+        let patch_info = repo
+            .find_commit(patch.oid)
+            .ok()
+            .and_then(|c| ps::commit_ps_id(&c))
+            .and_then(|ps_id| patch_info_collection.get(&ps_id));
+        if let Some(patch_info) = patch_info {
+            for branch in patch_info.branches.iter() {
+                if let Some(ref upstream) = branch.upstream {
+                    if !review_branch_names.contains(&upstream.name) {
+                        review_branch_names.push(upstream.name.clone());
+                    }
+                }
+            }
+        }
+    }
+
+    for review_branch_name in review_branch_names.iter() {
+        let review_branch_head_oid = repo
+            .find_branch(review_branch_name, git2::BranchType::Remote)
+            .and_then(|b| b.get().peel_to_commit())
+            .map_err(|e| VerifySignaturesError::FindReviewBranchFailed(e.into()))?
+            .id();
+        let signatures = signatures::branch_commit_signatures(
+            &repo,
+            patch_stack_base_oid,
+            review_branch_head_oid,
+        )
+        .map_err(|e| VerifySignaturesError::VerifySignaturesFailed(e.into()))?;
+        for signature in signatures.into_iter() {
+            if seen_oids.insert(signature.oid) {
+                verified_commits.push(verified_commit(
+                    &repo,
+                    signature,
+                    SignedCommitLocation::ReviewBranch(review_branch_name.clone()),
+                ));
+            }
+        }
+    }
+
+    Ok(verified_commits)
+}
+
+fn verified_commit(
+    repo: &git2::Repository,
+    signature: git::CommitSignature,
+    location: SignedCommitLocation,
+) -> VerifiedCommit {
+    let summary = repo
+        .find_commit(signature.oid)
+        .ok()
+        .and_then(|c| c.summary().map(|s| s.to_string()))
+        .unwrap_or_default();
+    VerifiedCommit {
+        oid: signature.oid,
+        summary,
+        location,
+        status: signature.status,
+        signer: signature.signer,
+        key: signature.key,
+    }
+}